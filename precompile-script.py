@@ -31,6 +31,7 @@ import sys
 import subprocess
 import argparse
 import os
+import shutil
 import struct
 
 TARGET_ENV = "vulkan1.0"
@@ -38,6 +39,9 @@ TARGET_ENV = "vulkan1.0"
 SECTION_RE = re.compile(r'^\[([^]]+)\]\s*$')
 VERSION_RE = re.compile(r'^(\s*vulkan\s*\d+\.\d+)(\.\d+\s*)$')
 TRIM_RE = re.compile(r'\s+')
+FROMFILE_RE = re.compile(r'\bfromfile\s+"([^"]*)"')
+SPIRV_FILE_RE = re.compile(r'^(.+) shader spirv file\s+"([^"]+)"\s*$')
+STAGE_TARGET_ENV_RE = re.compile(r'^\s*([a-z ]+?)\s+targetenv\s+(\S+)\s*$')
 
 STAGE_MAP = {
     'vertex shader': 'vert',
@@ -46,8 +50,43 @@ STAGE_MAP = {
     'geometry shader': 'geom',
     'fragment shader': 'frag',
     'compute shader': 'comp',
+    'task shader': 'task',
+    'mesh shader': 'mesh',
 }
 
+def write_spirv_hex(data, source, fout):
+    if len(data) < 4 or len(data) % 4 != 0:
+        print("{} has an invalid size".format(source), file=sys.stderr)
+        sys.exit(1)
+
+    magic_header = struct.unpack(">I", data[0:4])[0]
+
+    if magic_header == 0x07230203:
+        byte_order = ">I"
+    elif magic_header == 0x03022307:
+        byte_order = "<I"
+    else:
+        print("{} has an invalid magic number".format(source),
+              file=sys.stderr)
+        sys.exit(1)
+
+    line_pos = 0
+    for offset in range(0, len(data), 4):
+        value = struct.unpack(byte_order, data[offset:offset+4])[0]
+        hex_str = "{:x}".format(value)
+        if len(hex_str) + line_pos + 1 > 80:
+            line_pos = 0
+            print(file=fout)
+        elif line_pos > 0:
+            print(' ', file=fout, end='')
+            line_pos += 1
+        print(hex_str, file=fout, end='')
+        line_pos += len(hex_str)
+
+    if line_pos > 0:
+        print(file=fout)
+    print(file=fout)
+
 class Converter:
     def __init__(self, type, stage, fout, binary, version):
         self._type = type
@@ -82,43 +121,42 @@ class Converter:
 
         self._tempfile.close()
 
-        if len(data) < 4 or len(data) % 4 != 0:
-            print("Resulting binary SPIR-V file has an invalid size",
-                  file=sys.stderr)
-            sys.exit(1)
+        write_spirv_hex(data, "Resulting binary SPIR-V", self._fout)
 
-        magic_header = struct.unpack(">I", data[0:4])[0]
+def copy_data_file(filename, input_dir, output_dir):
+    # Files referenced with “fromfile” are looked up relative to the
+    # script so they need to be next to the precompiled script too
+    if os.path.isabs(filename):
+        return
 
-        if magic_header == 0x07230203:
-            byte_order = ">I"
-        elif magic_header == 0x03022307:
-            byte_order = "<I"
-        else:
-            print("Resulting binary SPIR-V has an invalid magic number",
-                  file=sys.stderr)
-            sys.exit(1)
-
-        line_pos = 0
-        for offset in range(0, len(data), 4):
-            value = struct.unpack(byte_order, data[offset:offset+4])[0]
-            hex_str = "{:x}".format(value)
-            if len(hex_str) + line_pos + 1 > 80:
-                line_pos = 0
-                print(file=self._fout)
-            elif line_pos > 0:
-                print(' ', file=self._fout, end='')
-                line_pos += 1
-            print(hex_str, file=self._fout, end='')
-            line_pos += len(hex_str)
-
-        if line_pos > 0:
-            print(file=self._fout)
-        print(file=self._fout)
-
-def convert_stream(fin, fout, glslang, spirv_as):
+    source = os.path.join(input_dir, filename)
+    dest = os.path.join(output_dir, filename)
+
+    if os.path.exists(dest) and os.path.samefile(source, dest):
+        return
+
+    dest_dir = os.path.dirname(dest)
+    if dest_dir and not os.path.isdir(dest_dir):
+        os.makedirs(dest_dir)
+
+    shutil.copyfile(source, dest)
+
+def inline_spirv_file(filename, input_dir, fout):
+    # The file is relative to the original script so it is loaded
+    # here and written as a hex dump instead
+    path = os.path.join(input_dir, filename)
+
+    with open(path, 'rb') as f:
+        data = f.read()
+
+    write_spirv_hex(data, path, fout)
+
+def convert_stream(fin, fout, glslang, spirv_as, input_dir, output_dir):
     section_name = None
     converter = None
     version = TARGET_ENV
+    # Target environments overridden with “<stage> targetenv” lines
+    stage_envs = {}
 
     for line in fin:
         md = SECTION_RE.match(line)
@@ -128,19 +166,26 @@ def convert_stream(fin, fout, glslang, spirv_as):
                 converter = None
 
             section_name = md.group(1)
+            fmd = SPIRV_FILE_RE.match(section_name)
 
-            if section_name.endswith(' spirv'):
+            if fmd:
+                print("[{} shader binary]".format(fmd.group(1)), file=fout)
+                inline_spirv_file(fmd.group(2), input_dir, fout)
+            elif section_name.endswith(' spirv'):
                 stage_name = section_name[:-6]
+                stage = STAGE_MAP[stage_name]
                 converter = Converter('spirv',
-                                      STAGE_MAP[stage_name],
+                                      stage,
                                       fout,
                                       spirv_as,
-                                      version)
+                                      stage_envs.get(stage, version))
                 print("[{} binary]".format(stage_name), file=fout)
             elif section_name in STAGE_MAP:
+                stage = STAGE_MAP[section_name]
                 converter = Converter('glsl',
-                                      STAGE_MAP[section_name],
-                                      fout, glslang, version)
+                                      stage,
+                                      fout, glslang,
+                                      stage_envs.get(stage, version))
                 print("[{} binary]".format(section_name), file=fout)
             else:
                 fout.write(line)
@@ -152,6 +197,14 @@ def convert_stream(fin, fout, glslang, spirv_as):
                 if vmd:
                     version = vmd.group(1)
                     version = TRIM_RE.sub('', version)
+                emd = STAGE_TARGET_ENV_RE.match(line)
+                if emd and emd.group(1) + ' shader' in STAGE_MAP:
+                    stage_envs[STAGE_MAP[emd.group(1) + ' shader']] = \
+                        emd.group(2)
+            elif section_name == 'test':
+                fmd = FROMFILE_RE.search(line)
+                if fmd:
+                    copy_data_file(fmd.group(1), input_dir, output_dir)
             fout.write(line)
 
     if converter:
@@ -187,4 +240,6 @@ for input in args.inputs:
 
     with open(input, 'r') as fin:
         with open(output, 'w') as fout:
-            convert_stream(fin, fout, args.glslang, args.spirv_as)
+            convert_stream(fin, fout, args.glslang, args.spirv_as,
+                           os.path.dirname(input),
+                           os.path.dirname(output))
@@ -33,6 +33,22 @@
 #include <stdint.h>
 #include <math.h>
 #include <limits.h>
+#include <ctype.h>
+#include <time.h>
+
+#ifdef _WIN32
+#include <direct.h>
+#else
+#include <sys/stat.h>
+#endif
+
+#ifdef HAVE_PTHREADS
+#include <pthread.h>
+#endif
+
+#ifdef HAVE_ZLIB
+#include <zlib.h>
+#endif
 
 #include <vkrunner/vkrunner.h>
 
@@ -42,16 +58,76 @@ struct string_array {
         size_t size;
 };
 
-struct main_data {
+enum buffer_format {
+        BUFFER_FORMAT_RAW,
+        BUFFER_FORMAT_HEX
+};
+
+struct main_data;
+
+struct worker {
+        struct main_data *data;
         struct vr_executor *executor;
+        /* Error messages reported while running the current script */
+        struct string_array script_errors;
+#ifdef HAVE_PTHREADS
+        pthread_t thread;
+#endif
+};
+
+struct script_record {
+        char *filename;
+        enum vr_result result;
+        /* Error messages reported while running the script separated
+         * by newlines */
+        char *errors;
+        /* Wall-clock time taken to run the script in seconds */
+        double duration;
+};
+
+struct main_data {
         struct vr_config *config;
         const char *image_filename;
         const char *buffer_filename;
+        const char *buffer_dirname;
+        /* Directory to write the color buffer to after every draw,
+         * the prefix for the images of the current script and the
+         * number of images written for it */
+        const char *capture_dirname;
+        char *capture_prefix;
+        const char *pipeline_cache_filename;
+        int n_captured_draws;
+        enum buffer_format buffer_format;
+        const char *result_filename;
+        FILE *result_file;
+        const char *junit_filename;
+        const char *json_filename;
+        /* Records of each script to write to the JUnit or JSON
+         * report */
+        struct script_record *records;
+        size_t n_records;
+        size_t records_size;
+        int n_jobs;
+        /* One worker per job. The first one runs on the main thread */
+        struct worker *workers;
+        /* Index of the next script to give to a worker */
+        size_t next_script;
+        enum vr_result overall_result;
+#ifdef HAVE_PTHREADS
+        /* Protects the state shared between the workers */
+        pthread_mutex_t mutex;
+#endif
         struct string_array filenames;
         struct string_array token_replacements;
+        /* Pairs of script filenames and expected results */
+        struct string_array expectations;
         int binding;
         bool inspect_failed;
         bool quiet;
+        bool dry_run;
+        bool print_script;
+        bool print_version;
+        bool list_formats;
 };
 
 typedef bool (* option_cb_t) (struct main_data *data,
@@ -119,6 +195,87 @@ opt_buffer(struct main_data *data,
         return true;
 }
 
+static bool
+opt_buffer_format(struct main_data *data,
+                  const char *arg)
+{
+        if (!strcmp(arg, "raw")) {
+                data->buffer_format = BUFFER_FORMAT_RAW;
+        } else if (!strcmp(arg, "hex")) {
+                data->buffer_format = BUFFER_FORMAT_HEX;
+        } else {
+                fprintf(stderr, "Invalid buffer format: %s\n", arg);
+                return false;
+        }
+
+        return true;
+}
+
+static bool
+opt_result_file(struct main_data *data,
+                const char *arg)
+{
+        data->result_filename = arg;
+        return true;
+}
+
+static bool
+opt_junit(struct main_data *data,
+          const char *arg)
+{
+        data->junit_filename = arg;
+        return true;
+}
+
+static bool
+opt_results_json(struct main_data *data,
+                 const char *arg)
+{
+        data->json_filename = arg;
+        return true;
+}
+
+static bool
+opt_jobs(struct main_data *data,
+         const char *arg)
+{
+        char *tail;
+        unsigned long n_jobs = strtoul(arg, &tail, 10);
+
+        if (*arg == '\0' || *tail != '\0' || n_jobs < 1 || n_jobs > INT_MAX) {
+                fprintf(stderr, "Invalid number of jobs: %s\n", arg);
+                return false;
+        }
+
+#ifndef HAVE_PTHREADS
+        if (n_jobs > 1) {
+                fprintf(stderr,
+                        "Running multiple jobs is not supported on this "
+                        "platform\n");
+                return false;
+        }
+#endif
+
+        data->n_jobs = n_jobs;
+        return true;
+}
+
+static bool
+opt_dump_all_buffers(struct main_data *data,
+                     const char *arg)
+{
+        data->buffer_dirname = arg;
+        return true;
+}
+
+static bool
+opt_capture_each_draw(struct main_data *data,
+                      const char *arg)
+{
+        data->capture_dirname = arg;
+        return true;
+}
+
 static bool
 opt_binding(struct main_data *data,
             const char *arg)
@@ -136,17 +293,13 @@ opt_disassembly(struct main_data *data,
 }
 
 static bool
-opt_token_replacement(struct main_data *data,
+add_token_replacement(struct main_data *data,
                       const char *arg)
 {
         const char *equals = strchr(arg, '=');
 
-        if (equals == NULL || equals == arg) {
-                fprintf(stderr,
-                        "invalid token replacement “%s”\n",
-                        arg);
+        if (equals == NULL || equals == arg)
                 return false;
-        }
 
         char *token = malloc(equals - arg + 1);
         memcpy(token, arg, equals - arg);
@@ -161,197 +314,978 @@ opt_token_replacement(struct main_data *data,
 }
 
 static bool
-opt_quiet(struct main_data *data,
-          const char *arg)
-{
-        data->quiet = true;
-
-        return true;
-}
-
-static bool
-opt_device_id(struct main_data *data,
-              const char *arg)
+opt_token_replacement(struct main_data *data,
+                      const char *arg)
 {
-        char *endp;
-        long id = strtol(arg, &endp, 0) - 1;
-        if (id < 0 || *endp) {
+        if (!add_token_replacement(data, arg)) {
                 fprintf(stderr,
-                        "Invalid device id.\n");
+                        "invalid token replacement “%s”\n",
+                        arg);
                 return false;
         }
 
-        vr_config_set_device_id(data->config, id);
         return true;
 }
 
-/* Use unique negative numbers to denote options without a short option.
- * Don't choose -1, it's used in the getopt_long error check.
+/* Reads a line of any length from the file. The newline is not
+ * included. Returns a newly allocated string that should be freed
+ * with free(), or NULL at the end of the file.
  */
-static const struct vr_option
-options[] = {
-        { 'h', "help", "Show this help message", NULL, opt_help },
-        { 'i', "image", "Write the final rendering to IMG as a PPM image", "IMG",
-          opt_image },
-        { 'b', "buffer", "Dump contents of a UBO or SSBO to BUF", "BUF",
-          opt_buffer },
-        { 'B', "binding", "Select which buffer to dump using the -b option. "
-          "Defaults to first buffer", "BINDING",
-          opt_binding },
-        { 'd', "disasm", "Show the SPIR-V disassembly", NULL, opt_disassembly },
-        { 'D', "replace", "Replace occurences of TOK with REPL in the scripts",
-          "TOK=REPL", opt_token_replacement },
-        { 'q', "quiet", "Don’t print any non-error information to stdout", NULL,
-          opt_quiet },
-        { -2, "device-id", "Select the Vulkan device", "DEVID", opt_device_id }
-};
+static char *
+read_line(FILE *in)
+{
+        size_t size = 128, length = 0;
+        char *line = malloc(size);
+        int c;
+
+        while ((c = fgetc(in)) != EOF && c != '\n') {
+                if (length + 1 >= size) {
+                        size *= 2;
+                        line = realloc(line, size);
+                }
+                line[length++] = c;
+        }
 
-#define N_OPTIONS (sizeof options / sizeof options[0])
+        if (c == EOF && length == 0) {
+                free(line);
+                return NULL;
+        }
+
+        line[length] = '\0';
+
+        return line;
+}
 
 static bool
-opt_help(struct main_data *data,
-         const char *arg)
+opt_replace_file(struct main_data *data,
+                 const char *arg)
 {
-        printf("usage: vkrunner [OPTION]... SCRIPT...\n"
-               "Runs the shader test script SCRIPT\n"
-               "\n"
-               "Options:\n");
+        FILE *in = fopen(arg, "r");
 
-        for (int i = 0; i < N_OPTIONS; i++) {
-                char fmt[] = "  -%c,--%-10s %-10s %s\n";
-                char shortopt = options[i].short_opt;
+        if (in == NULL) {
+                fprintf(stderr, "%s: %s\n", arg, strerror(errno));
+                return false;
+        }
 
-                if (shortopt < 0) {
-                        fmt[2] = fmt[5] = shortopt = ' ';
+        char *line;
+        int line_num = 0;
+        bool ret = true;
+
+        while ((line = read_line(in))) {
+                line_num++;
+
+                char *start = line;
+                char *end = line + strlen(line);
+
+                while (*start == ' ' || *start == '\t')
+                        start++;
+                while (end > start && end[-1] == '\r')
+                        end--;
+                *end = '\0';
+
+                if (*start == '#' || *start == '\0') {
+                        free(line);
+                        continue;
                 }
 
-                printf(fmt,
-                       shortopt,
-                       options[i].long_opt,
-                       options[i].argument_name ?
-                       options[i].argument_name :
-                       "",
-                       options[i].description);
+                if (!add_token_replacement(data, start)) {
+                        fprintf(stderr,
+                                "%s:%i: invalid token replacement “%s”\n",
+                                arg,
+                                line_num,
+                                start);
+                        free(line);
+                        ret = false;
+                        break;
+                }
 
+                free(line);
         }
 
-        return false;
+        fclose(in);
+
+        return ret;
 }
 
 static bool
-process_argv(struct main_data *data,
-             int argc, char **argv)
+opt_quiet(struct main_data *data,
+          const char *arg)
 {
-        int num_sopts = 0;
+        data->quiet = true;
 
-        struct option opts[N_OPTIONS + 1];
-        char sopts[2 * N_OPTIONS + 1];
+        return true;
+}
 
-        for (int i = 0; i < N_OPTIONS; i++) {
-                if (options[i].short_opt > 0) {
-                        sopts[num_sopts++] = options[i].short_opt;
-                        if (options[i].argument_name) {
-                                sopts[num_sopts++] = ':';
-                        }
-                }
+static bool
+opt_reflect(struct main_data *data,
+            const char *arg)
+{
+        vr_config_set_show_reflection(data->config, true);
 
-                opts[i].name = options[i].long_opt;
-                opts[i].has_arg = options[i].argument_name ?
-                                  required_argument : no_argument;
-                opts[i].flag = 0;
-                opts[i].val = options[i].short_opt;
-        }
+        return true;
+}
 
-        memset(opts + N_OPTIONS, 0, sizeof *opts);
-        sopts[num_sopts] = 0;
+static bool
+opt_pipeline_stats(struct main_data *data,
+                   const char *arg)
+{
+        vr_config_set_show_pipeline_stats(data->config, true);
 
-        int opt;
-        while ((opt = getopt_long(argc, argv, sopts, opts, 0)) != -1) {
-                if (opt == '?')
-                        return false;
+        return true;
+}
 
-                for (int i = 0; i < N_OPTIONS; i++) {
-                        if (opt == options[i].short_opt) {
-                                if (!options[i].cb(data, optarg))
-                                        return false;
-                        }
-                }
-        }
+static bool
+opt_measure_readback(struct main_data *data,
+                     const char *arg)
+{
+        vr_config_set_measure_readback(data->config, true);
 
-        for (int i = optind; i < argc; i++) {
-                string_array_add(&data->filenames, argv[i]);
-        }
+        return true;
+}
 
-        if (data->filenames.length <= 0) {
-                fprintf(stderr, "no script specified\n");
-                opt_help(data, NULL);
-                return false;
-        }
+static bool
+opt_dry_run(struct main_data *data,
+            const char *arg)
+{
+        data->dry_run = true;
 
         return true;
 }
 
 static bool
-write_ppm(const struct vr_inspect_image *image,
-          const char *filename)
+opt_print_script(struct main_data *data,
+                 const char *arg)
 {
-        const struct vr_format *format = image->format;
-        int format_size = vr_format_get_size(image->format);
-        FILE *out = fopen(filename, "wb");
+        data->print_script = true;
 
-        if (out == NULL) {
+        return true;
+}
+
+static bool
+opt_device_id(struct main_data *data,
+              const char *arg)
+{
+        char *endp;
+        long id = strtol(arg, &endp, 0) - 1;
+        if (id < 0 || *endp) {
                 fprintf(stderr,
-                        "%s: %s",
-                        filename,
-                        strerror(errno));
+                        "Invalid device id.\n");
                 return false;
         }
 
-        fprintf(out,
-                "P6\n"
-                "%i %i\n"
-                "255\n",
-                image->width,
-                image->height);
+        vr_config_set_device_id(data->config, id);
+        return true;
+}
 
-        for (int y = 0; y < image->height; y++) {
-                const uint8_t *p = (uint8_t *) image->data + y * image->stride;
+static bool
+opt_device_name(struct main_data *data,
+                const char *arg)
+{
+        vr_config_set_device_name(data->config, arg);
+        return true;
+}
 
-                for (int x = 0; x < image->width; x++) {
-                        double pixel[4];
+static int
+hex_digit_value(char ch)
+{
+        if (ch >= '0' && ch <= '9')
+                return ch - '0';
+        if (ch >= 'a' && ch <= 'f')
+                return ch - 'a' + 10;
+        if (ch >= 'A' && ch <= 'F')
+                return ch - 'A' + 10;
+        return -1;
+}
 
-                        vr_format_load_pixel(format, p, pixel);
+static bool
+opt_device_uuid(struct main_data *data,
+                const char *arg)
+{
+        uint8_t uuid[16];
+        const char *p = arg;
 
-                        for (int i = 0; i < 3; i++) {
-                                double v = pixel[i];
+        /* Accept the UUID as 32 hex digits with optional dashes
+         * between the bytes, as printed by vulkaninfo */
+        for (int i = 0; i < sizeof uuid; i++) {
+                if (*p == '-' && i > 0)
+                        p++;
 
-                                if (v < 0.0)
-                                        v = 0.0;
-                                else if (v > 1.0)
-                                        v = 1.0;
+                int high = hex_digit_value(p[0]);
+                int low = high == -1 ? -1 : hex_digit_value(p[1]);
 
-                                fputc(round(v * 255.0), out);
-                        }
-                        p += format_size;
+                if (low == -1) {
+                        fprintf(stderr, "Invalid device UUID: %s\n", arg);
+                        return false;
                 }
+
+                uuid[i] = (high << 4) | low;
+                p += 2;
         }
 
-        fclose(out);
+        if (*p) {
+                fprintf(stderr, "Invalid device UUID: %s\n", arg);
+                return false;
+        }
 
+        vr_config_set_device_uuid(data->config, uuid);
         return true;
 }
 
 static bool
-write_buffer(const struct vr_inspect_data *data,
-             int binding,
-             const char *filename)
+opt_memory_type(struct main_data *data,
+                const char *arg)
 {
-        const struct vr_inspect_buffer *buffer;
-
-        if (data->n_buffers < 1) {
+        char *endp;
+        long index = strtol(arg, &endp, 0);
+        if (index < 0 || index >= 32 || *endp) {
                 fprintf(stderr,
-                        "%s: no buffers are used in the test script\n",
-                        filename);
+                        "Invalid memory type index.\n");
+                return false;
+        }
+
+        vr_config_set_memory_type_index(data->config, index);
+        return true;
+}
+
+static bool
+opt_pipeline_cache(struct main_data *data,
+                   const char *arg)
+{
+        vr_config_set_pipeline_cache_file(data->config, arg);
+        data->pipeline_cache_filename = arg;
+        return true;
+}
+
+static bool
+opt_print_version(struct main_data *data,
+                  const char *arg)
+{
+        data->print_version = true;
+
+        return true;
+}
+
+static bool
+opt_list_formats(struct main_data *data,
+                 const char *arg)
+{
+        data->list_formats = true;
+
+        return true;
+}
+
+static bool
+opt_warmup(struct main_data *data,
+           const char *arg)
+{
+        char *tail;
+        unsigned long warmup_runs = strtoul(arg, &tail, 10);
+
+        if (*arg == '\0' || *tail != '\0' || warmup_runs > INT_MAX) {
+                fprintf(stderr, "Invalid number of warmup runs: %s\n", arg);
+                return false;
+        }
+
+        vr_config_set_warmup_runs(data->config, warmup_runs);
+        return true;
+}
+
+static bool
+opt_timeout(struct main_data *data,
+            const char *arg)
+{
+        char *tail;
+        unsigned long timeout = strtoul(arg, &tail, 10);
+
+        if (*arg == '\0' || *tail != '\0' || timeout > INT_MAX) {
+                fprintf(stderr, "Invalid timeout: %s\n", arg);
+                return false;
+        }
+
+        vr_config_set_timeout(data->config, timeout);
+        return true;
+}
+
+static bool
+opt_wait_mode(struct main_data *data,
+              const char *arg)
+{
+        if (!strcmp(arg, "block")) {
+                vr_config_set_wait_mode(data->config,
+                                        VR_CONFIG_WAIT_MODE_BLOCK);
+        } else if (!strcmp(arg, "spin")) {
+                vr_config_set_wait_mode(data->config,
+                                        VR_CONFIG_WAIT_MODE_SPIN);
+        } else {
+                fprintf(stderr,
+                        "Invalid wait mode “%s”. It should be “block” or "
+                        "“spin”\n",
+                        arg);
+                return false;
+        }
+
+        return true;
+}
+
+static bool
+is_valid_result_name(const char *name)
+{
+        static const enum vr_result results[] = {
+                VR_RESULT_PASS,
+                VR_RESULT_FAIL,
+                VR_RESULT_SKIP,
+        };
+
+        for (int i = 0; i < sizeof results / sizeof results[0]; i++) {
+                if (!strcmp(name, vr_result_to_string(results[i])))
+                        return true;
+        }
+
+        return false;
+}
+
+static bool
+opt_expect(struct main_data *data,
+           const char *arg)
+{
+        FILE *in = fopen(arg, "r");
+
+        if (in == NULL) {
+                fprintf(stderr, "%s: %s\n", arg, strerror(errno));
+                return false;
+        }
+
+        char *line;
+        int line_num = 0;
+        bool ret = true;
+
+        while ((line = read_line(in))) {
+                line_num++;
+
+                char *start = line;
+                char *end = line + strlen(line);
+
+                while (*start == ' ' || *start == '\t')
+                        start++;
+                while (end > start && strchr(" \t\r\n", end[-1]))
+                        end--;
+                *end = '\0';
+
+                if (*start == '#' || *start == '\0') {
+                        free(line);
+                        continue;
+                }
+
+                char *result = end;
+
+                while (result > start && !strchr(" \t", result[-1]))
+                        result--;
+
+                char *filename_end = result;
+
+                while (filename_end > start &&
+                       strchr(" \t", filename_end[-1]))
+                        filename_end--;
+
+                if (filename_end <= start || !is_valid_result_name(result)) {
+                        fprintf(stderr,
+                                "%s:%i: invalid expectation\n",
+                                arg,
+                                line_num);
+                        free(line);
+                        ret = false;
+                        break;
+                }
+
+                *filename_end = '\0';
+
+                string_array_add(&data->expectations, start);
+                string_array_add(&data->expectations, result);
+
+                free(line);
+        }
+
+        fclose(in);
+
+        return ret;
+}
+
+/* Use unique negative numbers to denote options without a short option.
+ * Don't choose -1, it's used in the getopt_long error check.
+ */
+static const struct vr_option
+options[] = {
+        { 'h', "help", "Show this help message", NULL, opt_help },
+        { 'i', "image", "Write the final rendering to IMG as a PPM or PNG "
+          "image", "IMG", opt_image },
+        { 'b', "buffer", "Dump contents of a UBO or SSBO to BUF", "BUF",
+          opt_buffer },
+        { -7, "buffer-format", "Write the buffer from -b as “raw” bytes or "
+          "as a “hex” dump", "FMT", opt_buffer_format },
+        { -13, "dump-all-buffers", "Write the contents of every UBO and "
+          "SSBO to files in DIR", "DIR", opt_dump_all_buffers },
+        { -18, "capture-each-draw", "Write the color buffer to an image in "
+          "DIR after every draw", "DIR", opt_capture_each_draw },
+        { 'B', "binding", "Select which buffer to dump using the -b option. "
+          "Defaults to first buffer", "BINDING",
+          opt_binding },
+        { 'd', "disasm", "Show the SPIR-V disassembly", NULL, opt_disassembly },
+        { 'D', "replace", "Replace occurences of TOK with REPL in the scripts",
+          "TOK=REPL", opt_token_replacement },
+        { -15, "replace-file", "Read TOK=REPL replacements from each line of "
+          "FILE", "FILE", opt_replace_file },
+        { 'q', "quiet", "Don’t print any non-error information to stdout", NULL,
+          opt_quiet },
+        { -2, "device-id", "Select the Vulkan device", "DEVID", opt_device_id },
+        { -19, "device-name", "Select the Vulkan device with the name NAME",
+          "NAME", opt_device_name },
+        { -20, "device-uuid", "Select the Vulkan device with the UUID UUID",
+          "UUID", opt_device_uuid },
+        { -3, "expect", "Read the expected result of each script from FILE",
+          "FILE", opt_expect },
+        { -4, "dry-run", "Only load the scripts and report what would be "
+          "created", NULL, opt_dry_run },
+        { -24, "print-script", "Print each script as it was parsed instead "
+          "of running it", NULL, opt_print_script },
+        { -5, "reflect", "Show the bindings and entry points of the SPIR-V",
+          NULL, opt_reflect },
+        { -16, "pipeline-stats", "Show the driver’s statistics for pipelines "
+          "created with capture_statistics", NULL, opt_pipeline_stats },
+        { -22, "measure-readback", "Report the bandwidth of reading back "
+          "the color buffer", NULL, opt_measure_readback },
+        { -6, "memory-type", "Use the memory type with INDEX for all "
+          "allocations", "INDEX", opt_memory_type },
+        { -21, "pipeline-cache", "Load the pipeline cache from FILE and save "
+          "it back when finished", "FILE", opt_pipeline_cache },
+        { -8, "result-file", "Also write the result of each script to FILE",
+          "FILE", opt_result_file },
+        { -9, "junit", "Write a JUnit XML report of the results to FILE",
+          "FILE", opt_junit },
+        { -12, "results-json", "Write the result of each script to FILE as "
+          "JSON", "FILE", opt_results_json },
+        { 'j', "jobs", "Run up to N scripts at the same time", "N",
+          opt_jobs },
+        { -10, "warmup", "Run the test commands N times without the probes "
+          "before the real run", "N", opt_warmup },
+        { -14, "timeout", "Fail a script if its commands take longer than "
+          "SECS seconds to complete", "SECS", opt_timeout },
+        { -17, "wait-mode", "Wait for the commands by blocking or spinning",
+          "MODE", opt_wait_mode },
+        { -11, "print-version", "Print the Vulkan API version of the "
+          "instance and device and exit", NULL, opt_print_version },
+        { -23, "list-formats", "List the format names that can be used in "
+          "scripts and exit", NULL, opt_list_formats },
+};
+
+#define N_OPTIONS (sizeof options / sizeof options[0])
+
+static bool
+opt_help(struct main_data *data,
+         const char *arg)
+{
+        printf("usage: vkrunner [OPTION]... SCRIPT...\n"
+               "Runs the shader test script SCRIPT\n"
+               "\n"
+               "Options:\n");
+
+        for (int i = 0; i < N_OPTIONS; i++) {
+                char fmt[] = "  -%c,--%-10s %-10s %s\n";
+                char shortopt = options[i].short_opt;
+
+                if (shortopt < 0) {
+                        fmt[2] = fmt[5] = shortopt = ' ';
+                }
+
+                printf(fmt,
+                       shortopt,
+                       options[i].long_opt,
+                       options[i].argument_name ?
+                       options[i].argument_name :
+                       "",
+                       options[i].description);
+
+        }
+
+        return false;
+}
+
+static bool
+process_argv(struct main_data *data,
+             int argc, char **argv)
+{
+        int num_sopts = 0;
+
+        struct option opts[N_OPTIONS + 1];
+        char sopts[2 * N_OPTIONS + 1];
+
+        for (int i = 0; i < N_OPTIONS; i++) {
+                if (options[i].short_opt > 0) {
+                        sopts[num_sopts++] = options[i].short_opt;
+                        if (options[i].argument_name) {
+                                sopts[num_sopts++] = ':';
+                        }
+                }
+
+                opts[i].name = options[i].long_opt;
+                opts[i].has_arg = options[i].argument_name ?
+                                  required_argument : no_argument;
+                opts[i].flag = 0;
+                opts[i].val = options[i].short_opt;
+        }
+
+        memset(opts + N_OPTIONS, 0, sizeof *opts);
+        sopts[num_sopts] = 0;
+
+        int opt;
+        while ((opt = getopt_long(argc, argv, sopts, opts, 0)) != -1) {
+                if (opt == '?')
+                        return false;
+
+                for (int i = 0; i < N_OPTIONS; i++) {
+                        if (opt == options[i].short_opt) {
+                                if (!options[i].cb(data, optarg))
+                                        return false;
+                        }
+                }
+        }
+
+        for (int i = optind; i < argc; i++) {
+                string_array_add(&data->filenames, argv[i]);
+        }
+
+        if (data->print_version || data->list_formats)
+                return true;
+
+        if (data->filenames.length <= 0) {
+                fprintf(stderr, "no script specified\n");
+                opt_help(data, NULL);
+                return false;
+        }
+
+        if (data->n_jobs > 1 &&
+            (data->image_filename ||
+             data->buffer_filename ||
+             data->buffer_dirname ||
+             data->capture_dirname ||
+             data->pipeline_cache_filename)) {
+                fprintf(stderr,
+                        "The -i, -b, --dump-all-buffers, "
+                        "--capture-each-draw and --pipeline-cache options "
+                        "can’t be used with multiple jobs\n");
+                return false;
+        }
+
+        if ((size_t) data->n_jobs > data->filenames.length)
+                data->n_jobs = data->filenames.length;
+
+        return true;
+}
+
+static void
+print_api_version(const char *name,
+                  uint32_t version)
+{
+        printf("%s: %u.%u.%u\n",
+               name,
+               (version >> 22) & 0x7f,
+               (version >> 12) & 0x3ff,
+               version & 0xfff);
+}
+
+static double
+get_time(void)
+{
+#ifdef HAVE_CLOCK_GETTIME
+        struct timespec ts;
+
+        clock_gettime(CLOCK_MONOTONIC, &ts);
+
+        return ts.tv_sec + ts.tv_nsec / 1e9;
+#else
+        return time(NULL);
+#endif
+}
+
+static enum vr_result
+print_version(struct main_data *data)
+{
+        struct vr_executor *executor = vr_executor_new(data->config);
+        uint32_t instance_version, device_version;
+        enum vr_result result =
+                vr_executor_get_api_versions(executor,
+                                             &instance_version,
+                                             &device_version);
+
+        if (result == VR_RESULT_PASS) {
+                print_api_version("Instance API version", instance_version);
+                print_api_version("Device API version", device_version);
+        }
+
+        vr_executor_free(executor);
+
+        return result;
+}
+
+static void
+list_formats(void)
+{
+        int n_formats = vr_format_get_n_formats();
+
+        for (int i = 0; i < n_formats; i++) {
+                const struct vr_format *format = vr_format_get(i);
+
+                printf("%-28s %2i %s\n",
+                       vr_format_get_name(format),
+                       vr_format_get_size(format),
+                       vr_format_is_depth_stencil(format) ?
+                       "depth/stencil" :
+                       "color");
+        }
+}
+
+/* Converts a row of the image to 8-bit components. Only the first
+ * n_components of each pixel are stored.
+ */
+static void
+load_image_row(const struct vr_inspect_image *image,
+               int y,
+               int n_components,
+               uint8_t *out)
+{
+        const struct vr_format *format = image->format;
+        int format_size = vr_format_get_size(image->format);
+        const uint8_t *p = (uint8_t *) image->data + y * image->stride;
+
+        for (int x = 0; x < image->width; x++) {
+                double pixel[4];
+
+                vr_format_load_pixel(format, p, pixel);
+
+                for (int i = 0; i < n_components; i++) {
+                        double v = pixel[i];
+
+                        if (v < 0.0)
+                                v = 0.0;
+                        else if (v > 1.0)
+                                v = 1.0;
+
+                        *(out++) = round(v * 255.0);
+                }
+                p += format_size;
+        }
+}
+
+static bool
+write_ppm(const struct vr_inspect_image *image,
+          const char *filename)
+{
+        FILE *out = fopen(filename, "wb");
+
+        if (out == NULL) {
+                fprintf(stderr,
+                        "%s: %s",
+                        filename,
+                        strerror(errno));
+                return false;
+        }
+
+        fprintf(out,
+                "P6\n"
+                "%i %i\n"
+                "255\n",
+                image->width,
+                image->height);
+
+        uint8_t *row = malloc(image->width * 3);
+
+        for (int y = 0; y < image->height; y++) {
+                load_image_row(image, y, 3, row);
+                fwrite(row, 3, image->width, out);
+        }
+
+        free(row);
+
+        fclose(out);
+
+        return true;
+}
+
+static void
+write_uint32_be(uint8_t *p,
+                uint32_t value)
+{
+        p[0] = value >> 24;
+        p[1] = value >> 16;
+        p[2] = value >> 8;
+        p[3] = value;
+}
+
+static uint32_t
+png_crc(const uint8_t *data,
+        size_t length,
+        uint32_t crc)
+{
+        for (size_t i = 0; i < length; i++) {
+                crc ^= data[i];
+
+                for (int bit = 0; bit < 8; bit++)
+                        crc = (crc >> 1) ^ (0xedb88320 & -(crc & 1));
+        }
+
+        return crc;
+}
+
+static void
+write_png_chunk(FILE *out,
+                const char *type,
+                const uint8_t *data,
+                size_t length)
+{
+        uint8_t header[8];
+
+        write_uint32_be(header, length);
+        memcpy(header + 4, type, 4);
+
+        uint32_t crc = png_crc(header + 4, 4, UINT32_MAX);
+        crc = png_crc(data, length, crc) ^ UINT32_MAX;
+
+        uint8_t footer[4];
+        write_uint32_be(footer, crc);
+
+        fwrite(header, 1, sizeof header, out);
+        fwrite(data, 1, length, out);
+        fwrite(footer, 1, sizeof footer, out);
+}
+
+#ifndef HAVE_ZLIB
+/* Wraps the data in a zlib stream using uncompressed deflate blocks.
+ * This is only used if VkRunner is built without zlib.
+ */
+static uint8_t *
+deflate_stored(const uint8_t *data,
+               size_t length,
+               size_t *length_out)
+{
+        size_t n_blocks = length / 65535 + 1;
+        uint8_t *buf = malloc(2 + n_blocks * 5 + length + 4);
+        uint8_t *p = buf;
+        uint32_t a = 1, b = 0;
+
+        /* zlib header for deflate with a 32K window. The window
+         * size doesn’t matter for stored blocks. The second byte
+         * makes the header a multiple of 31 as required. */
+        *(p++) = 0x78;
+        *(p++) = 0x01;
+
+        for (size_t i = 0; i < n_blocks; i++) {
+                size_t block_length = length - i * 65535;
+
+                if (block_length > 65535)
+                        block_length = 65535;
+
+                *(p++) = i == n_blocks - 1;
+                *(p++) = block_length;
+                *(p++) = block_length >> 8;
+                *(p++) = ~block_length;
+                *(p++) = ~block_length >> 8;
+                memcpy(p, data + i * 65535, block_length);
+                p += block_length;
+        }
+
+        for (size_t i = 0; i < length; i++) {
+                a = (a + data[i]) % 65521;
+                b = (b + a) % 65521;
+        }
+
+        write_uint32_be(p, (b << 16) | a);
+        p += 4;
+
+        *length_out = p - buf;
+
+        return buf;
+}
+#endif
+
+static bool
+write_png(const struct vr_inspect_image *image,
+          const char *filename)
+{
+        int n_components = vr_format_has_alpha(image->format) ? 4 : 3;
+        size_t row_size = image->width * n_components + 1;
+        size_t raw_size = row_size * image->height;
+        uint8_t *raw = malloc(raw_size);
+
+        for (int y = 0; y < image->height; y++) {
+                /* No filter */
+                raw[y * row_size] = 0;
+                load_image_row(image, y, n_components, raw + y * row_size + 1);
+        }
+
+        uint8_t *compressed;
+        size_t compressed_size;
+
+#ifdef HAVE_ZLIB
+        uLongf dest_len = compressBound(raw_size);
+        compressed = malloc(dest_len);
+        int zres = compress(compressed, &dest_len, raw, raw_size);
+        compressed_size = dest_len;
+#else
+        compressed = deflate_stored(raw, raw_size, &compressed_size);
+#endif
+
+        free(raw);
+
+#ifdef HAVE_ZLIB
+        if (zres != Z_OK) {
+                fprintf(stderr,
+                        "%s: error compressing the image\n",
+                        filename);
+                free(compressed);
+                return false;
+        }
+#endif
+
+        /* The file is only opened once the data is ready so that a
+         * failure doesn’t leave a truncated file behind */
+        FILE *out = fopen(filename, "wb");
+
+        if (out == NULL) {
+                fprintf(stderr,
+                        "%s: %s",
+                        filename,
+                        strerror(errno));
+                free(compressed);
+                return false;
+        }
+
+        static const uint8_t signature[] = {
+                0x89, 'P', 'N', 'G', '\r', '\n', 0x1a, '\n'
+        };
+        fwrite(signature, 1, sizeof signature, out);
+
+        uint8_t ihdr[13];
+        write_uint32_be(ihdr, image->width);
+        write_uint32_be(ihdr + 4, image->height);
+        ihdr[8] = 8; /* bit depth */
+        ihdr[9] = n_components == 4 ? 6 : 2; /* RGBA or RGB */
+        ihdr[10] = 0; /* compression method */
+        ihdr[11] = 0; /* filter method */
+        ihdr[12] = 0; /* interlace method */
+        write_png_chunk(out, "IHDR", ihdr, sizeof ihdr);
+
+        write_png_chunk(out, "IDAT", compressed, compressed_size);
+        write_png_chunk(out, "IEND", NULL, 0);
+
+        free(compressed);
+
+        fclose(out);
+
+        return true;
+}
+
+static bool
+is_png_filename(const char *filename)
+{
+        static const char extension[] = ".png";
+        size_t length = strlen(filename);
+        size_t ext_length = sizeof extension - 1;
+
+        if (length < ext_length)
+                return false;
+
+        for (size_t i = 0; i < ext_length; i++) {
+                if (tolower(filename[length - ext_length + i]) != extension[i])
+                        return false;
+        }
+
+        return true;
+}
+
+static bool
+write_image(const struct vr_inspect_image *image,
+            const char *filename)
+{
+        if (is_png_filename(filename))
+                return write_png(image, filename);
+        else
+                return write_ppm(image, filename);
+}
+
+static void
+write_hex_dump(FILE *out,
+               const uint8_t *data,
+               size_t size)
+{
+        for (size_t offset = 0; offset < size; offset += 16) {
+                size_t line_size = size - offset;
+
+                if (line_size > 16)
+                        line_size = 16;
+
+                fprintf(out, "%08lx:", (unsigned long) offset);
+
+                for (size_t i = 0; i < 16; i++) {
+                        if (i % 2 == 0)
+                                fputc(' ', out);
+                        if (i < line_size)
+                                fprintf(out, "%02x", data[offset + i]);
+                        else
+                                fputs("  ", out);
+                }
+
+                fputs("  ", out);
+
+                for (size_t i = 0; i < line_size; i++) {
+                        uint8_t ch = data[offset + i];
+                        fputc(ch >= ' ' && ch <= '~' ? ch : '.', out);
+                }
+
+                fputc('\n', out);
+        }
+}
+
+static bool
+write_buffer_contents(const struct vr_inspect_buffer *buffer,
+                      enum buffer_format format,
+                      const char *filename)
+{
+        FILE *out = fopen(filename,
+                          format == BUFFER_FORMAT_HEX ? "w" : "wb");
+
+        if (out == NULL) {
+                fprintf(stderr,
+                        "%s: %s",
+                        filename,
+                        strerror(errno));
+                return false;
+        }
+
+        switch (format) {
+        case BUFFER_FORMAT_RAW:
+                fwrite(buffer->data, 1, buffer->size, out);
+                break;
+        case BUFFER_FORMAT_HEX:
+                write_hex_dump(out, buffer->data, buffer->size);
+                break;
+        }
+
+        fclose(out);
+
+        return true;
+}
+
+static bool
+write_buffer(const struct vr_inspect_data *data,
+             int binding,
+             enum buffer_format format,
+             const char *filename)
+{
+        const struct vr_inspect_buffer *buffer;
+
+        if (data->n_buffers < 1) {
+                fprintf(stderr,
+                        "%s: no buffers are used in the test script\n",
+                        filename);
                 return false;
         }
 
@@ -366,85 +1300,728 @@ write_buffer(const struct vr_inspect_data *data,
                 }
 
                 fprintf(stderr,
-                        "%s: no buffer with binding %i was found\n",
-                        filename,
-                        binding);
+                        "%s: no buffer with binding %i was found\n",
+                        filename,
+                        binding);
+                return false;
+
+        found_buffer:
+                (void) 0;
+        }
+
+        return write_buffer_contents(buffer, format, filename);
+}
+
+static bool
+make_directory(const char *dirname)
+{
+#ifdef _WIN32
+        int res = _mkdir(dirname);
+#else
+        int res = mkdir(dirname, 0777);
+#endif
+
+        if (res != 0 && errno != EEXIST) {
+                fprintf(stderr,
+                        "%s: %s\n",
+                        dirname,
+                        strerror(errno));
+                return false;
+        }
+
+        return true;
+}
+
+static bool
+write_all_buffers(const struct vr_inspect_data *data,
+                  enum buffer_format format,
+                  const char *dirname)
+{
+        if (!make_directory(dirname))
+                return false;
+
+        size_t filename_size = strlen(dirname) + 64;
+        char *filename = malloc(filename_size);
+        bool ret = true;
+
+        for (int i = 0; i < data->n_buffers; i++) {
+                const struct vr_inspect_buffer *buffer = data->buffers + i;
+
+                snprintf(filename,
+                         filename_size,
+                         "%s/%i_%i.%s",
+                         dirname,
+                         buffer->desc_set,
+                         buffer->binding,
+                         format == BUFFER_FORMAT_HEX ? "hex" : "raw");
+
+                if (!write_buffer_contents(buffer, format, filename))
+                        ret = false;
+        }
+
+        free(filename);
+
+        return ret;
+}
+
+static void
+inspect_cb(const struct vr_inspect_data *inspect_data,
+           void *user_data)
+{
+        struct main_data *data = user_data;
+
+        if (data->image_filename) {
+                if (!write_image(&inspect_data->color_buffer,
+                                 data->image_filename))
+                        data->inspect_failed = true;
+        }
+
+        if (data->buffer_filename) {
+                if (!write_buffer(inspect_data,
+                                  data->binding,
+                                  data->buffer_format,
+                                  data->buffer_filename))
+                        data->inspect_failed = true;
+        }
+
+        if (data->buffer_dirname) {
+                if (!write_all_buffers(inspect_data,
+                                       data->buffer_format,
+                                       data->buffer_dirname))
+                        data->inspect_failed = true;
+        }
+}
+
+/* Returns the basename of the script without the extension so that
+ * the images captured for different scripts don’t overwrite each
+ * other */
+static char *
+get_capture_prefix(const char *filename)
+{
+        if (!strcmp(filename, "-"))
+                return strdup("stdin");
+
+        const char *slash = strrchr(filename, '/');
+
+        if (slash)
+                filename = slash + 1;
+
+        const char *dot = strrchr(filename, '.');
+        size_t length = (dot && dot > filename ?
+                         dot - filename :
+                         strlen(filename));
+
+        char *prefix = malloc(length + 1);
+
+        memcpy(prefix, filename, length);
+        prefix[length] = '\0';
+
+        return prefix;
+}
+
+static void
+draw_inspect_cb(const struct vr_inspect_data *inspect_data,
+                void *user_data)
+{
+        struct main_data *data = user_data;
+
+        if (!make_directory(data->capture_dirname)) {
+                data->inspect_failed = true;
+                return;
+        }
+
+        size_t filename_size = (strlen(data->capture_dirname) +
+                                strlen(data->capture_prefix) +
+                                64);
+        char *filename = malloc(filename_size);
+
+        snprintf(filename,
+                 filename_size,
+                 "%s/%s_draw_%03i.ppm",
+                 data->capture_dirname,
+                 data->capture_prefix,
+                 data->n_captured_draws++);
+
+        if (!write_image(&inspect_data->color_buffer, filename))
+                data->inspect_failed = true;
+
+        free(filename);
+}
+
+static void
+add_token_replacements(struct main_data *data,
+                       struct vr_source *source)
+{
+        for (size_t i = 0; i < data->token_replacements.length; i += 2) {
+                const char *token = data->token_replacements.data[i];
+                const char *val = data->token_replacements.data[i + 1];
+                vr_source_add_token_replacement(source, token, val);
+        }
+}
+
+static const char *
+get_expected_result(struct main_data *data,
+                    const char *filename)
+{
+        for (size_t i = 0; i < data->expectations.length; i += 2) {
+                if (!strcmp(data->expectations.data[i], filename))
+                        return data->expectations.data[i + 1];
+        }
+
+        return NULL;
+}
+
+static void
+print_dry_run_requirements(const struct vr_script *script)
+{
+        unsigned major, minor, patch;
+
+        vr_script_get_required_version(script, &major, &minor, &patch);
+
+        printf("required version: %u.%u.%u\n", major, minor, patch);
+
+        size_t n_extensions = vr_script_get_n_required_extensions(script);
+
+        printf("required extensions: %lu\n", (unsigned long) n_extensions);
+
+        for (size_t i = 0; i < n_extensions; i++)
+                printf("  %s\n", vr_script_get_required_extension(script, i));
+
+        size_t n_features = vr_script_get_n_required_features(script);
+
+        printf("required features: %lu\n", (unsigned long) n_features);
+
+        for (size_t i = 0; i < n_features; i++)
+                printf("  %s\n", vr_script_get_required_feature(script, i));
+}
+
+static enum vr_result
+dry_run_script(struct main_data *data,
+               const struct vr_source *source)
+{
+        struct vr_script *script = vr_script_load(data->config, source);
+
+        if (script == NULL)
+                return VR_RESULT_FAIL;
+
+        size_t n_buffers = vr_script_get_n_buffers(script);
+        unsigned n_desc_sets = 0;
+        size_t total_size = 0;
+
+        /* The report is the whole point of a dry run so it is
+         * printed even with --quiet */
+        printf("pipelines: %lu\n"
+               "shaders: %i\n"
+               "buffers: %lu\n",
+               (unsigned long) vr_script_get_n_pipelines(script),
+               vr_script_get_num_shaders(script),
+               (unsigned long) n_buffers);
+
+        for (size_t i = 0; i < n_buffers; i++) {
+                struct vr_script_buffer_info info;
+
+                vr_script_get_buffer_info(script, i, &info);
+
+                printf("  %u:%u %lu bytes\n",
+                       info.desc_set,
+                       info.binding,
+                       (unsigned long) info.size);
+
+                if (info.desc_set + 1 > n_desc_sets)
+                        n_desc_sets = info.desc_set + 1;
+                total_size += info.size;
+        }
+
+        printf("descriptor sets: %u\n"
+               "estimated buffer memory: %lu bytes\n",
+               n_desc_sets,
+               (unsigned long) total_size);
+        print_dry_run_requirements(script);
+
+        vr_script_free(script);
+
+        return VR_RESULT_PASS;
+}
+
+static enum vr_result
+print_script(struct main_data *data,
+             const struct vr_source *source)
+{
+        struct vr_script *script = vr_script_load(data->config, source);
+
+        if (script == NULL)
+                return VR_RESULT_FAIL;
+
+        char *str = vr_script_to_string(script);
+
+        fputs(str, stdout);
+
+        free(str);
+        vr_script_free(script);
+
+        return VR_RESULT_PASS;
+}
+
+static void
+lock_data(struct main_data *data)
+{
+#ifdef HAVE_PTHREADS
+        pthread_mutex_lock(&data->mutex);
+#endif
+}
+
+static void
+unlock_data(struct main_data *data)
+{
+#ifdef HAVE_PTHREADS
+        pthread_mutex_unlock(&data->mutex);
+#endif
+}
+
+static struct worker *
+get_current_worker(struct main_data *data)
+{
+#ifdef HAVE_PTHREADS
+        pthread_t self = pthread_self();
+
+        for (int i = 1; i < data->n_jobs; i++) {
+                if (pthread_equal(data->workers[i].thread, self))
+                        return data->workers + i;
+        }
+#endif
+
+        return data->workers;
+}
+
+static void
+error_cb(const char *message,
+         void *user_data)
+{
+        struct main_data *data = user_data;
+        struct worker *worker = get_current_worker(data);
+
+        fputs(message, stderr);
+        fputc('\n', stderr);
+
+        string_array_add(&worker->script_errors, message);
+}
+
+/* Informational output such as the reflection report isn’t an error
+ * so it is only printed and not added to the script record.
+ */
+static void
+info_cb(const char *message,
+        void *user_data)
+{
+        fputs(message, stderr);
+        fputc('\n', stderr);
+}
+
+static void
+add_script_record(struct main_data *data,
+                  struct worker *worker,
+                  const char *filename,
+                  enum vr_result result,
+                  double duration)
+{
+        const struct string_array *script_errors = &worker->script_errors;
+        size_t length = 0;
+
+        for (size_t i = 0; i < script_errors->length; i++)
+                length += strlen(script_errors->data[i]) + 1;
+
+        char *errors = malloc(length + 1);
+        char *p = errors;
+
+        for (size_t i = 0; i < script_errors->length; i++) {
+                size_t len = strlen(script_errors->data[i]);
+                memcpy(p, script_errors->data[i], len);
+                p[len] = '\n';
+                p += len + 1;
+        }
+
+        *p = '\0';
+
+        if (data->n_records >= data->records_size) {
+                data->records_size = (data->records_size == 0 ?
+                                      4 :
+                                      data->records_size * 2);
+                data->records = realloc(data->records,
+                                        data->records_size *
+                                        sizeof *data->records);
+        }
+
+        struct script_record *record = data->records + data->n_records++;
+        size_t filename_size = strlen(filename) + 1;
+
+        record->filename = malloc(filename_size);
+        memcpy(record->filename, filename, filename_size);
+        record->result = result;
+        record->errors = errors;
+        record->duration = duration;
+}
+
+static void
+write_xml_string(FILE *out,
+                 const char *str)
+{
+        for (const char *p = str; *p; p++) {
+                switch (*p) {
+                case '&':
+                        fputs("&amp;", out);
+                        break;
+                case '<':
+                        fputs("&lt;", out);
+                        break;
+                case '>':
+                        fputs("&gt;", out);
+                        break;
+                case '"':
+                        fputs("&quot;", out);
+                        break;
+                default:
+                        fputc(*p, out);
+                        break;
+                }
+        }
+}
+
+static bool
+write_junit(struct main_data *data)
+{
+        FILE *out = fopen(data->junit_filename, "w");
+
+        if (out == NULL) {
+                fprintf(stderr,
+                        "%s: %s\n",
+                        data->junit_filename,
+                        strerror(errno));
                 return false;
+        }
 
-        found_buffer:
-                (void) 0;
+        size_t n_failures = 0, n_skipped = 0;
+
+        for (size_t i = 0; i < data->n_records; i++) {
+                switch (data->records[i].result) {
+                case VR_RESULT_PASS:
+                        break;
+                case VR_RESULT_SKIP:
+                        n_skipped++;
+                        break;
+                default:
+                        n_failures++;
+                        break;
+                }
         }
 
-        FILE *out = fopen(filename, "wb");
+        fprintf(out,
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"
+                "<testsuite name=\"vkrunner\" tests=\"%lu\" "
+                "failures=\"%lu\" skipped=\"%lu\">\n",
+                (unsigned long) data->n_records,
+                (unsigned long) n_failures,
+                (unsigned long) n_skipped);
 
-        if (out == NULL) {
+        for (size_t i = 0; i < data->n_records; i++) {
+                const struct script_record *record = data->records + i;
+
+                fputs("  <testcase classname=\"vkrunner\" name=\"", out);
+                write_xml_string(out, record->filename);
+                fputs("\">\n", out);
+
+                switch (record->result) {
+                case VR_RESULT_PASS:
+                        break;
+                case VR_RESULT_SKIP:
+                        fputs("    <skipped/>\n", out);
+                        break;
+                default:
+                        fprintf(out,
+                                "    <failure message=\"%s\">",
+                                vr_result_to_string(record->result));
+                        write_xml_string(out, record->errors);
+                        fputs("</failure>\n", out);
+                        break;
+                }
+
+                fputs("  </testcase>\n", out);
+        }
+
+        fputs("</testsuite>\n", out);
+
+        if (fclose(out) != 0) {
                 fprintf(stderr,
-                        "%s: %s",
-                        filename,
+                        "%s: %s\n",
+                        data->junit_filename,
                         strerror(errno));
                 return false;
         }
 
-        fwrite(buffer->data, 1, buffer->size, out);
-        fclose(out);
-
         return true;
 }
 
 static void
-inspect_cb(const struct vr_inspect_data *inspect_data,
-           void *user_data)
+write_json_string(FILE *out,
+                  const char *str)
 {
-        struct main_data *data = user_data;
+        fputc('"', out);
 
-        if (data->image_filename) {
-                if (!write_ppm(&inspect_data->color_buffer,
-                               data->image_filename))
-                        data->inspect_failed = true;
+        for (const char *p = str; *p; p++) {
+                switch (*p) {
+                case '"':
+                        fputs("\\\"", out);
+                        break;
+                case '\\':
+                        fputs("\\\\", out);
+                        break;
+                case '\n':
+                        fputs("\\n", out);
+                        break;
+                case '\r':
+                        fputs("\\r", out);
+                        break;
+                case '\t':
+                        fputs("\\t", out);
+                        break;
+                default:
+                        if ((unsigned char) *p < ' ')
+                                fprintf(out, "\\u%04x", *p);
+                        else
+                                fputc(*p, out);
+                        break;
+                }
         }
 
-        if (data->buffer_filename) {
-                if (!write_buffer(inspect_data,
-                                  data->binding,
-                                  data->buffer_filename))
-                        data->inspect_failed = true;
+        fputc('"', out);
+}
+
+static bool
+write_json(struct main_data *data)
+{
+        FILE *out = fopen(data->json_filename, "w");
+
+        if (out == NULL) {
+                fprintf(stderr,
+                        "%s: %s\n",
+                        data->json_filename,
+                        strerror(errno));
+                return false;
+        }
+
+        fputc('[', out);
+
+        for (size_t i = 0; i < data->n_records; i++) {
+                const struct script_record *record = data->records + i;
+
+                fputs(i == 0 ? "\n" : ",\n", out);
+                fputs("  {\n    \"filename\": ", out);
+                write_json_string(out, record->filename);
+                fprintf(out,
+                        ",\n"
+                        "    \"result\": \"%s\",\n"
+                        "    \"duration\": %.6f,\n"
+                        "    \"errors\": ",
+                        vr_result_to_string(record->result),
+                        record->duration);
+                write_json_string(out, record->errors);
+                fputs("\n  }", out);
+        }
+
+        fputs("\n]\n", out);
+
+        if (fclose(out) != 0) {
+                fprintf(stderr,
+                        "%s: %s\n",
+                        data->json_filename,
+                        strerror(errno));
+                return false;
         }
+
+        return true;
 }
 
 static void
-add_token_replacements(struct main_data *data,
-                       struct vr_source *source)
+print_filename(struct main_data *data,
+               const char *filename)
 {
-        for (size_t i = 0; i < data->token_replacements.length; i += 2) {
-                const char *token = data->token_replacements.data[i];
-                const char *val = data->token_replacements.data[i + 1];
-                vr_source_add_token_replacement(source, token, val);
-        }
+        if (data->filenames.length > 1 && (!data->quiet || data->dry_run))
+                printf("%s\n", filename);
 }
 
 static enum vr_result
-run_scripts(struct main_data *data)
+run_script(struct worker *worker,
+           const char *filename)
 {
-        enum vr_result overall_result = VR_RESULT_SKIP;
+        struct main_data *data = worker->data;
+        double start_time = get_time();
+
+        if (data->capture_dirname) {
+                free(data->capture_prefix);
+                data->capture_prefix = get_capture_prefix(filename);
+                data->n_captured_draws = 0;
+        }
+
+        struct vr_source *source;
+
+        if (!strcmp(filename, "-"))
+                source = vr_source_from_stream(stdin, "(stdin)");
+        else
+                source = vr_source_from_file(filename);
+
+        add_token_replacements(data, source);
+
+        enum vr_result result;
 
-        for (size_t i = 0; i < data->filenames.length; i++) {
-                const char *filename = data->filenames.data[i];
+        if (data->print_script || data->dry_run) {
+                /* These only parse the script so the lock can be
+                 * held for the whole report to keep it together
+                 * when running multiple jobs */
+                lock_data(data);
+                print_filename(data, filename);
 
-                if (data->filenames.length > 1 && !data->quiet)
-                        printf("%s\n", filename);
+                if (data->print_script)
+                        result = print_script(data, source);
+                else
+                        result = dry_run_script(data, source);
 
-                struct vr_source *source = vr_source_from_file(filename);
+                unlock_data(data);
+        } else {
+                lock_data(data);
+                print_filename(data, filename);
+                unlock_data(data);
+
+                result = vr_executor_execute(worker->executor, source);
+        }
+
+        vr_source_free(source);
+
+        const char *expected = get_expected_result(data, filename);
+
+        /* If the script has an expected result then it is only
+         * considered a failure if it doesn’t match.
+         */
+        if (expected) {
+                const char *actual = vr_result_to_string(result);
+
+                if (strcmp(expected, actual)) {
+                        fprintf(stderr,
+                                "%s: expected result “%s” but got “%s”\n",
+                                filename,
+                                expected,
+                                actual);
+
+                        char message[64];
+                        snprintf(message, sizeof message,
+                                 "expected result “%s” but got “%s”",
+                                 expected,
+                                 actual);
+                        string_array_add(&worker->script_errors, message);
+
+                        result = VR_RESULT_FAIL;
+                } else {
+                        result = VR_RESULT_PASS;
+                }
+        }
+
+        lock_data(data);
+
+        if (data->result_file) {
+                fprintf(data->result_file,
+                        "%s: %s\n",
+                        filename,
+                        vr_result_to_string(result));
+        }
+
+        if (data->junit_filename || data->json_filename) {
+                add_script_record(data,
+                                  worker,
+                                  filename,
+                                  result,
+                                  get_time() - start_time);
+        }
 
-                add_token_replacements(data, source);
+        data->overall_result = vr_result_merge(result, data->overall_result);
 
-                enum vr_result result = vr_executor_execute(data->executor,
-                                                            source);
-                vr_source_free(source);
+        unlock_data(data);
 
-                overall_result = vr_result_merge(result, overall_result);
+        string_array_destroy(&worker->script_errors);
+        memset(&worker->script_errors, 0, sizeof worker->script_errors);
+
+        return result;
+}
+
+static void
+run_worker(struct worker *worker)
+{
+        struct main_data *data = worker->data;
+
+        while (true) {
+                lock_data(data);
+                size_t script_num = data->next_script++;
+                unlock_data(data);
+
+                if (script_num >= data->filenames.length)
+                        break;
+
+                run_script(worker, data->filenames.data[script_num]);
+        }
+}
+
+#ifdef HAVE_PTHREADS
+static void *
+worker_thread_cb(void *user_data)
+{
+        run_worker(user_data);
+        return NULL;
+}
+#endif
+
+static enum vr_result
+run_scripts(struct main_data *data)
+{
+        data->overall_result = VR_RESULT_SKIP;
+        data->next_script = 0;
+
+#ifdef HAVE_PTHREADS
+        /* Keep the lock while creating the threads so that they can’t
+         * start running a script until all of the thread IDs are
+         * stored. These are used to find the worker in the error
+         * callback.
+         */
+        pthread_mutex_lock(&data->mutex);
+
+        for (int i = 1; i < data->n_jobs; i++) {
+                int err = pthread_create(&data->workers[i].thread,
+                                         NULL, /* attr */
+                                         worker_thread_cb,
+                                         data->workers + i);
+                if (err) {
+                        fprintf(stderr,
+                                "Error creating thread: %s\n",
+                                strerror(err));
+
+                        /* The remaining workers will never run so
+                         * their executors have to be freed here
+                         * before they are forgotten */
+                        for (int j = i; j < data->n_jobs; j++)
+                                vr_executor_free(data->workers[j].executor);
+
+                        data->n_jobs = i;
+                        break;
+                }
         }
 
-        return overall_result;
+        pthread_mutex_unlock(&data->mutex);
+#endif
+
+        run_worker(data->workers);
+
+#ifdef HAVE_PTHREADS
+        for (int i = 1; i < data->n_jobs; i++)
+                pthread_join(data->workers[i].thread, NULL);
+#endif
+
+        return data->overall_result;
 }
 
 int
@@ -453,28 +2030,85 @@ main(int argc, char **argv)
         int return_value = EXIT_SUCCESS;
         struct vr_config *config = vr_config_new();
         struct main_data data = {
-                .executor = vr_executor_new(config),
                 .config = config,
                 .filenames = { .data = NULL },
                 .token_replacements = { .data = NULL },
+                .expectations = { .data = NULL },
+                .n_jobs = 1,
+                .buffer_format = BUFFER_FORMAT_RAW,
                 .binding = -1,
-                .quiet = false
+                .quiet = false,
+                .dry_run = false,
+                .print_script = false
         };
 
+#ifdef HAVE_PTHREADS
+        pthread_mutex_init(&data.mutex, NULL);
+#endif
+
         vr_config_set_user_data(config, &data);
         vr_config_set_inspect_cb(config, inspect_cb);
 
-        if (process_argv(&data, argc, argv)) {
+        if (!process_argv(&data, argc, argv)) {
+                return_value = EXIT_FAILURE;
+        } else if (data.print_version) {
+                if (print_version(&data) != VR_RESULT_PASS)
+                        return_value = EXIT_FAILURE;
+        } else if (data.list_formats) {
+                list_formats();
+        } else if (data.result_filename &&
+                   (data.result_file = fopen(data.result_filename,
+                                             "w")) == NULL) {
+                fprintf(stderr,
+                        "%s: %s\n",
+                        data.result_filename,
+                        strerror(errno));
+                return_value = EXIT_FAILURE;
+        } else {
+                if (data.junit_filename || data.json_filename) {
+                        vr_config_set_error_cb(config, error_cb);
+                        vr_config_set_info_cb(config, info_cb);
+                }
+
+                if (data.capture_dirname)
+                        vr_config_set_draw_inspect_cb(config, draw_inspect_cb);
+
+                data.workers = calloc(data.n_jobs, sizeof *data.workers);
+
+                for (int i = 0; i < data.n_jobs; i++) {
+                        data.workers[i].data = &data;
+                        data.workers[i].executor = vr_executor_new(config);
+                }
+
                 enum vr_result result = run_scripts(&data);
 
                 if (data.inspect_failed)
                         result = vr_result_merge(result, VR_RESULT_FAIL);
 
+                if (data.junit_filename && !write_junit(&data))
+                        result = VR_RESULT_FAIL;
+
+                if (data.json_filename && !write_json(&data))
+                        result = VR_RESULT_FAIL;
+
                 if (!data.quiet || result != VR_RESULT_PASS) {
                         printf("PIGLIT: {\"result\": \"%s\" }\n",
                                vr_result_to_string(result));
                 }
 
+                if (data.result_file) {
+                        fprintf(data.result_file,
+                                "PIGLIT: {\"result\": \"%s\" }\n",
+                                vr_result_to_string(result));
+                        if (fclose(data.result_file) != 0) {
+                                fprintf(stderr,
+                                        "%s: %s\n",
+                                        data.result_filename,
+                                        strerror(errno));
+                                result = VR_RESULT_FAIL;
+                        }
+                }
+
                 switch (result) {
                 case VR_RESULT_PASS:
                 case VR_RESULT_SKIP:
@@ -483,14 +2117,28 @@ main(int argc, char **argv)
                         return_value = EXIT_FAILURE;
                         break;
                 }
-        } else {
-                return_value = EXIT_FAILURE;
+        }
+
+        if (data.workers) {
+                for (int i = 0; i < data.n_jobs; i++)
+                        vr_executor_free(data.workers[i].executor);
+                free(data.workers);
         }
 
         vr_config_free(config);
-        vr_executor_free(data.executor);
         string_array_destroy(&data.filenames);
         string_array_destroy(&data.token_replacements);
+        string_array_destroy(&data.expectations);
+        for (size_t i = 0; i < data.n_records; i++) {
+                free(data.records[i].filename);
+                free(data.records[i].errors);
+        }
+        free(data.records);
+        free(data.capture_prefix);
+
+#ifdef HAVE_PTHREADS
+        pthread_mutex_destroy(&data.mutex);
+#endif
 
         return return_value;
 }
@@ -46,6 +46,267 @@ fi
 "$install_dir/bin/vkrunner" $device_id \
     "$build_dir/precompiled-examples/"*.shader_test
 
+# Check that --expect turns a result that doesn’t match into a
+# failure. The long comment checks that lines aren’t split up.
+expect_file="$build_dir/expectations.txt"
+expected_script="$src_dir/examples/tricolore.shader_test"
+printf '#%02000d\n%s pass\n' 0 "$expected_script" > "$expect_file"
+"$install_dir/bin/vkrunner" $device_id -q --expect "$expect_file" \
+                            "$expected_script"
+printf '#%02000d\n%s fail\n' 0 "$expected_script" > "$expect_file"
+
+if "$install_dir/bin/vkrunner" $device_id -q --expect "$expect_file" \
+                               "$expected_script"; then
+    echo "FAIL --expect didn’t report a result that doesn’t match"
+    exit 1
+fi
+
+# Check that the lines of a --replace-file aren’t split up either.
+# The script only passes if the token is replaced.
+replace_file="$build_dir/replacements.txt"
+replaced_script="$build_dir/replaced.shader_test"
+printf '#%02000d\nRED=(1, 0, 0, 1)\n' 0 > "$replace_file"
+sed -e 's/(1, 0, 0, 1)$/RED/' \
+    < "$src_dir/examples/tricolore.shader_test" \
+    > "$replaced_script"
+"$install_dir/bin/vkrunner" $device_id -q --replace-file "$replace_file" \
+                            "$replaced_script"
+
+# Check that --list-formats works without a device and lists a
+# common color format with its size
+if ! "$install_dir/bin/vkrunner" --list-formats \
+        | grep -q '^R8G8B8A8_UNORM  *4 color$'; then
+    echo "FAIL --list-formats didn’t list R8G8B8A8_UNORM"
+    exit 1
+fi
+
+# Check the report from --dry-run for a script with a known set of
+# buffers
+dry_run_report="$("$install_dir/bin/vkrunner" -q --dry-run \
+    "$src_dir/examples/ubo.shader_test" | head -n 6)"
+expected_report="pipelines: 1
+shaders: 2
+buffers: 1
+  0:3 100 bytes
+descriptor sets: 1
+estimated buffer memory: 100 bytes"
+
+if [ "$dry_run_report" != "$expected_report" ]; then
+    echo "FAIL --dry-run reported:"
+    echo "$dry_run_report"
+    exit 1
+fi
+
+# The viewport and scissor state of each draw is part of the pipeline
+# so the two draws with different viewports should need two pipelines
+viewport_pipelines="$("$install_dir/bin/vkrunner" -q --dry-run \
+    "$src_dir/examples/viewport-scissor.shader_test" | head -n 1)"
+
+if [ "$viewport_pipelines" != "pipelines: 2" ]; then
+    echo "FAIL --dry-run of viewport-scissor.shader_test reported:"
+    echo "$viewport_pipelines"
+    exit 1
+fi
+
+# A memory type that the device doesn’t have should make the script
+# fail with an error instead of falling back to another type
+memory_type_output="$build_dir/memory-type.txt"
+
+if "$install_dir/bin/vkrunner" $device_id -q --memory-type 31 \
+                               "$src_dir/examples/tricolore.shader_test" \
+                               > "$memory_type_output" 2>&1; then
+    echo "FAIL --memory-type 31 didn’t make the script fail"
+    exit 1
+fi
+
+if ! grep -q '^Memory type 31 does not exist$' "$memory_type_output"; then
+    echo "FAIL --memory-type 31 didn’t report the missing memory type"
+    exit 1
+fi
+
+# Dump the SSBO of a script as hex. The first line has the color that
+# the script sets and the last line has the value that the shader
+# writes.
+hex_dump="$build_dir/ssbo.hex"
+"$install_dir/bin/vkrunner" $device_id -q -b "$hex_dump" -B 3 \
+                            --buffer-format hex \
+                            "$src_dir/examples/ssbo.shader_test"
+
+if ! grep -qxF '00000000: 0000 0000 0000 803f 0000 0000 0000 803f  .......?.......?' \
+     "$hex_dump" ||
+   ! tail -n 1 "$hex_dump" | grep -qx '00000030: 0300 0000 *\.\.\.\.'; then
+    echo "FAIL --buffer-format hex wrote:"
+    cat "$hex_dump"
+    exit 1
+fi
+
+# Check that all of the warmup runs are done before the real run
+warmup_output="$("$install_dir/bin/vkrunner" $device_id -q --warmup 3 \
+    "$src_dir/examples/tricolore.shader_test" 2>&1)"
+
+if [ "$warmup_output" != "Warmup: 3 of 3 runs completed" ]; then
+    echo "FAIL --warmup reported:"
+    echo "$warmup_output"
+    exit 1
+fi
+
+# Check that --measure-readback reports the size of the color buffer
+readback_output="$("$install_dir/bin/vkrunner" $device_id -q \
+    --measure-readback "$src_dir/examples/tricolore.shader_test" 2>&1)"
+
+if ! echo "$readback_output" | grep -q '^Readback: [0-9][0-9]* bytes '; then
+    echo "FAIL --measure-readback reported:"
+    echo "$readback_output"
+    exit 1
+fi
+
+# Run a passing script and one that fails to load to check the
+# reports of the results
+broken_script="$build_dir/broken.shader_test"
+printf '[test]\nnot a command\n' > "$broken_script"
+result_scripts=("$src_dir/examples/tricolore.shader_test" "$broken_script")
+
+result_file="$build_dir/results.txt"
+
+if "$install_dir/bin/vkrunner" $device_id -q --result-file "$result_file" \
+                               "${result_scripts[@]}" > /dev/null 2>&1; then
+    echo "FAIL VkRunner passed with a broken script"
+    exit 1
+fi
+
+expected_results="${result_scripts[0]}: pass
+${result_scripts[1]}: fail
+PIGLIT: {\"result\": \"fail\" }"
+
+if [ "$(cat "$result_file")" != "$expected_results" ]; then
+    echo "FAIL --result-file wrote:"
+    cat "$result_file"
+    exit 1
+fi
+
+# The JUnit report should be valid XML with a failure for the broken
+# script
+junit_file="$build_dir/junit.xml"
+"$install_dir/bin/vkrunner" $device_id -q --junit "$junit_file" \
+                            "${result_scripts[@]}" > /dev/null 2>&1 || true
+
+if ! python3 - "$junit_file" "${result_scripts[@]}" <<'EOF'
+import sys
+import xml.etree.ElementTree as ET
+
+suite = ET.parse(sys.argv[1]).getroot()
+cases = suite.findall('testcase')
+assert suite.tag == 'testsuite'
+assert suite.get('tests') == '2' and suite.get('failures') == '1'
+assert [case.get('name') for case in cases] == sys.argv[2:]
+assert cases[0].find('failure') is None
+assert 'Invalid test command' in cases[1].find('failure').text
+EOF
+then
+    echo "FAIL --junit wrote:"
+    cat "$junit_file"
+    exit 1
+fi
+
+# The JSON results should have an object for each script
+json_file="$build_dir/results.json"
+"$install_dir/bin/vkrunner" $device_id -q --results-json "$json_file" \
+                            "${result_scripts[@]}" > /dev/null 2>&1 || true
+
+if ! python3 - "$json_file" "${result_scripts[@]}" <<'EOF'
+import sys
+import json
+
+with open(sys.argv[1]) as f:
+    results = json.load(f)
+
+assert [r['filename'] for r in results] == sys.argv[2:]
+assert [r['result'] for r in results] == ['pass', 'fail']
+assert all(r['duration'] >= 0 for r in results)
+assert results[0]['errors'] == ''
+assert 'Invalid test command' in results[1]['errors']
+EOF
+then
+    echo "FAIL --results-json wrote:"
+    cat "$json_file"
+    exit 1
+fi
+
+# Write each example back out with --print-script. Printing the
+# result again should give the same script and it should still pass.
+round_trip_dir="$build_dir/round-trip"
+mkdir -p "$round_trip_dir"
+
+for script in "$src_dir/examples/"*.shader_test; do
+    printed="$round_trip_dir/$(basename "$script")"
+    "$install_dir/bin/vkrunner" -q --print-script "$script" > "$printed"
+    "$install_dir/bin/vkrunner" -q --print-script "$printed" \
+                                > "$printed.again"
+
+    if ! cmp -s "$printed" "$printed.again"; then
+        echo "FAIL --print-script output of $script doesn’t round trip"
+        exit 1
+    fi
+done
+
+"$install_dir/bin/vkrunner" $device_id -q "$round_trip_dir/"*.shader_test
+
+# Check that a “targetenv” line overrides the environment from the
+# Vulkan version when precompiling. The version is lowered so that
+# the override is distinguishable from the default.
+target_env_dir="$build_dir/target-env"
+glslang_log="$target_env_dir/glslang-args.txt"
+glslang_wrapper="$target_env_dir/glslang-wrapper"
+mkdir -p "$target_env_dir"
+printf '#!/bin/sh\necho "$@" >> "%s"\nexec glslangValidator "$@"\n' \
+       "$glslang_log" > "$glslang_wrapper"
+chmod +x "$glslang_wrapper"
+sed -e 's/^vulkan 1\.1\.0$/vulkan 1.0.0/' \
+    < "$src_dir/examples/target-env.shader_test" \
+    > "$target_env_dir/target-env.shader_test"
+"$src_dir"/precompile-script.py -g "$glslang_wrapper" \
+          -o "$target_env_dir/precompiled.shader_test" \
+          "$target_env_dir/target-env.shader_test"
+
+if ! grep -q -- '^-S frag .*--target-env vulkan1\.1 ' "$glslang_log"; then
+    echo "FAIL precompile-script.py ignored the targetenv line"
+    exit 1
+fi
+
+# Check that --reflect reports the descriptor bindings declared in
+# the fragment shader of a known script
+reflected_bindings="$("$install_dir/bin/vkrunner" $device_id -q --reflect \
+    "$src_dir/examples/desc_set_and_binding.shader_test" 2>&1 \
+    | sed -n 's/^  set \([0-9]*\) binding \([0-9]*\): .* (\(.*\))$/\1:\2 \3/p' \
+    | sort)"
+expected_bindings="$(printf '%s storage buffer\n' \
+                            0:3 0:4 0:5 1:3 1:5 1:6 2:0 2:3 2:4 2:7
+                     printf '%s uniform buffer\n' 3:2 3:6 3:10)"
+expected_bindings="$(echo "$expected_bindings" | sort)"
+
+if [ "$reflected_bindings" != "$expected_bindings" ]; then
+    echo "FAIL --reflect reported the bindings:"
+    echo "$reflected_bindings"
+    exit 1
+fi
+
+# Capture each draw of two scripts into the same directory. There
+# should be one image per draw named after each script.
+capture_dir="$build_dir/capture"
+"$install_dir/bin/vkrunner" $device_id -q \
+    --capture-each-draw "$capture_dir" \
+    "$src_dir/examples/cull-mode.shader_test" \
+    "$src_dir/examples/first-instance.shader_test"
+
+captured_images="$(cd "$capture_dir" && echo *)"
+expected_images="cull-mode_draw_000.ppm cull-mode_draw_001.ppm \
+cull-mode_draw_002.ppm first-instance_draw_000.ppm"
+
+if [ "$captured_images" != "$expected_images" ]; then
+    echo "FAIL --capture-each-draw wrote: $captured_images"
+    exit 1
+fi
+
 # Extract the example from the README. This will test both that the
 # example is still correct and that all of the necessary public
 # headers are properly installed.
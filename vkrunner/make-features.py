@@ -63,16 +63,26 @@ EXTENSIONS = [
     },
     "EXT_FRAGMENT_DENSITY_MAP",
     "NV_FRAGMENT_SHADER_BARYCENTRIC",
+    "EXT_HOST_QUERY_RESET",
     "EXT_INLINE_UNIFORM_BLOCK",
     "EXT_MEMORY_PRIORITY",
     "NV_MESH_SHADER",
+    "EXT_MESH_SHADER",
     "KHR_MULTIVIEW",
+    "KHR_PIPELINE_EXECUTABLE_PROPERTIES",
     "NV_REPRESENTATIVE_FRAGMENT_TEST",
+    "EXT_ROBUSTNESS_2",
     "KHR_SAMPLER_YCBCR_CONVERSION",
     "EXT_SCALAR_BLOCK_LAYOUT",
     "KHR_SHADER_ATOMIC_INT64",
+    {
+        "name": "KHR_SHADER_DRAW_PARAMETERS",
+        "struct": "VkPhysicalDeviceShaderDrawParametersFeatures",
+        "struct_type": "SHADER_DRAW_PARAMETERS_FEATURES"
+    },
     "NV_SHADER_IMAGE_FOOTPRINT",
     "NV_SHADING_RATE_IMAGE",
+    "KHR_SYNCHRONIZATION_2",
     "EXT_TRANSFORM_FEEDBACK",
     {
         "name": "KHR_VARIABLE_POINTERS",
@@ -184,7 +194,7 @@ def extension_to_struct_name(ext):
 
 def struct_to_regexp(struct):
     # Make the vendor in the struct name optional
-    md = re.match(r'(.*?)([A-Z]+)$', struct)
+    md = re.match(r'(.*?)([A-Z]*)$', struct)
     return re.compile(r'^typedef\s+struct\s+' +
                       re.escape(md.group(1)) +
                       r'(?:' + re.escape(md.group(2)) + ')?\s+' +
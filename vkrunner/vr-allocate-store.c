@@ -29,6 +29,42 @@
 
 #include "vr-allocate-store.h"
 #include "vr-util.h"
+#include "vr-config-private.h"
+#include "vr-error-message.h"
+
+static int
+get_forced_memory_type(struct vr_context *context,
+                       uint32_t usable_memory_types,
+                       uint32_t memory_type_flags)
+{
+        int i = context->config->memory_type_index;
+
+        if (i >= context->memory_properties.memoryTypeCount) {
+                vr_error_message(context->config,
+                                 "Memory type %i does not exist",
+                                 i);
+                return -1;
+        }
+
+        if ((usable_memory_types & (1u << i)) == 0) {
+                vr_error_message(context->config,
+                                 "Memory type %i can not be used for this "
+                                 "resource",
+                                 i);
+                return -1;
+        }
+
+        if ((context->memory_properties.memoryTypes[i].propertyFlags &
+             memory_type_flags) != memory_type_flags) {
+                vr_error_message(context->config,
+                                 "Memory type %i does not have the "
+                                 "properties needed for this resource",
+                                 i);
+                return -1;
+        }
+
+        return i;
+}
 
 static int
 find_memory_type(struct vr_context *context,
@@ -37,6 +73,12 @@ find_memory_type(struct vr_context *context,
 {
         int i;
 
+        if (context->config->memory_type_index >= 0) {
+                return get_forced_memory_type(context,
+                                              usable_memory_types,
+                                              memory_type_flags);
+        }
+
         while (usable_memory_types) {
                 i = vr_util_ffs(usable_memory_types) - 1;
 
@@ -44,7 +86,7 @@ find_memory_type(struct vr_context *context,
                      memory_type_flags) == memory_type_flags)
                         return i;
 
-                usable_memory_types &= ~(1 << i);
+                usable_memory_types &= ~(1u << i);
         }
 
         return -1;
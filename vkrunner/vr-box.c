@@ -310,16 +310,20 @@ compare_unsigned(enum vr_box_comparison comparison,
 static bool
 compare_double(const struct compare_closure *data,
                double a,
-               double b)
+               double b,
+               enum vr_tolerance_precision precision)
 {
+        int component = data->index % data->index_max;
+
         switch (data->comparison) {
         case VR_BOX_COMPARISON_EQUAL:
                 return a == b;
         case VR_BOX_COMPARISON_FUZZY_EQUAL:
-                return vr_tolerance_equal(data->tolerance,
-                                          data->index % data->index_max,
-                                          a,
-                                          b);
+                return vr_tolerance_equal_precision(data->tolerance,
+                                                    precision,
+                                                    component,
+                                                    a,
+                                                    b);
         case VR_BOX_COMPARISON_NOT_EQUAL:
                 return a != b;
         case VR_BOX_COMPARISON_LESS:
@@ -379,16 +383,19 @@ compare_value(const struct compare_closure *data,
                 uint16_t bi = *(const uint16_t *) b;
                 return compare_double(data,
                                       vr_half_float_to_double(ai),
-                                      vr_half_float_to_double(bi));
+                                      vr_half_float_to_double(bi),
+                                      VR_TOLERANCE_PRECISION_HALF);
         }
         case VR_BOX_BASE_TYPE_FLOAT:
                 return compare_double(data,
                                       *(const float *) a,
-                                      *(const float *) b);
+                                      *(const float *) b,
+                                      VR_TOLERANCE_PRECISION_SINGLE);
         case VR_BOX_BASE_TYPE_DOUBLE:
                 return compare_double(data,
                                       *(const double *) a,
-                                      *(const double *) b);
+                                      *(const double *) b,
+                                      VR_TOLERANCE_PRECISION_DOUBLE);
         }
 
         vr_fatal("Unexpected base type");
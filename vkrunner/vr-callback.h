@@ -33,9 +33,26 @@ typedef void
 (* vr_callback_error)(const char *message,
                       void *user_data);
 
+typedef void
+(* vr_callback_info)(const char *message,
+                     void *user_data);
+
 
 typedef void
 (* vr_callback_inspect)(const struct vr_inspect_data *inspect_data,
                         void *user_data);
 
+struct vr_command_result {
+        /* The line number of the command in the script */
+        int line_num;
+        /* The kind of command, such as “draw rect” or “probe ssbo” */
+        const char *op;
+        /* Either VR_RESULT_PASS or VR_RESULT_FAIL */
+        enum vr_result result;
+};
+
+typedef void
+(* vr_callback_command)(const struct vr_command_result *command_result,
+                        void *user_data);
+
 #endif /* VR_CALLBACK_H */
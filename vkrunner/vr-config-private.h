@@ -33,10 +33,42 @@
 
 struct vr_config {
         bool show_disassembly;
+        bool show_reflection;
+        bool show_pipeline_stats;
+        bool measure_readback;
+        bool stop_on_probe_failure;
+        /* Number of times to run the test commands without probes
+         * before the real run */
+        int warmup_runs;
+        /* Maximum number of seconds to wait for each run of the test
+         * commands or zero to wait forever */
+        int timeout;
+        enum vr_config_wait_mode wait_mode;
         int device_id;
+        /* Name of the device to use or NULL to not select by name */
+        char *device_name;
+        /* UUID of the device to use if has_device_uuid is true */
+        bool has_device_uuid;
+        uint8_t device_uuid[16];
+        /* Memory type to use for all allocations or -1 to pick one
+         * automatically */
+        int memory_type_index;
+        /* File to load the pipeline cache from and save it to, or
+         * NULL to use a new cache for each script */
+        char *pipeline_cache_file;
+
+        /* Names and versions for the VkApplicationInfo. The names
+         * can be NULL. */
+        char *application_name;
+        uint32_t application_version;
+        char *engine_name;
+        uint32_t engine_version;
 
         vr_callback_error error_cb;
+        vr_callback_info info_cb;
         vr_callback_inspect inspect_cb;
+        vr_callback_inspect draw_inspect_cb;
+        vr_callback_command command_cb;
         void *user_data;
 
         struct vr_strtof_data strtof_data;
@@ -33,6 +33,8 @@ vr_config_new(void)
         struct vr_config *config = vr_calloc(sizeof(struct vr_config));
         vr_strtof_init(&config->strtof_data);
         config->device_id = -1;
+        config->memory_type_index = -1;
+        config->application_name = vr_strdup("vkrunner");
         return config;
 }
 
@@ -41,6 +43,10 @@ void
 vr_config_free(struct vr_config *config)
 {
         vr_strtof_destroy(&config->strtof_data);
+        vr_free(config->application_name);
+        vr_free(config->engine_name);
+        vr_free(config->device_name);
+        vr_free(config->pipeline_cache_file);
         vr_free(config);
 }
 
@@ -51,6 +57,55 @@ vr_config_set_show_disassembly(struct vr_config *config,
         config->show_disassembly = show_disassembly;
 }
 
+void
+vr_config_set_show_reflection(struct vr_config *config,
+                              bool show_reflection)
+{
+        config->show_reflection = show_reflection;
+}
+
+void
+vr_config_set_show_pipeline_stats(struct vr_config *config,
+                                  bool show_pipeline_stats)
+{
+        config->show_pipeline_stats = show_pipeline_stats;
+}
+
+void
+vr_config_set_measure_readback(struct vr_config *config,
+                               bool measure_readback)
+{
+        config->measure_readback = measure_readback;
+}
+
+void
+vr_config_set_stop_on_probe_failure(struct vr_config *config,
+                                    bool stop_on_probe_failure)
+{
+        config->stop_on_probe_failure = stop_on_probe_failure;
+}
+
+void
+vr_config_set_warmup_runs(struct vr_config *config,
+                          int warmup_runs)
+{
+        config->warmup_runs = warmup_runs;
+}
+
+void
+vr_config_set_timeout(struct vr_config *config,
+                      int timeout)
+{
+        config->timeout = timeout;
+}
+
+void
+vr_config_set_wait_mode(struct vr_config *config,
+                        enum vr_config_wait_mode wait_mode)
+{
+        config->wait_mode = wait_mode;
+}
+
 void
 vr_config_set_user_data(struct vr_config *config,
                         void *user_data)
@@ -65,6 +120,13 @@ vr_config_set_error_cb(struct vr_config *config,
         config->error_cb = error_cb;
 }
 
+void
+vr_config_set_info_cb(struct vr_config *config,
+                      vr_callback_info info_cb)
+{
+        config->info_cb = info_cb;
+}
+
 void
 vr_config_set_inspect_cb(struct vr_config *config,
                          vr_callback_inspect inspect_cb)
@@ -72,9 +134,76 @@ vr_config_set_inspect_cb(struct vr_config *config,
         config->inspect_cb = inspect_cb;
 }
 
+void
+vr_config_set_draw_inspect_cb(struct vr_config *config,
+                              vr_callback_inspect draw_inspect_cb)
+{
+        config->draw_inspect_cb = draw_inspect_cb;
+}
+
+void
+vr_config_set_command_cb(struct vr_config *config,
+                         vr_callback_command command_cb)
+{
+        config->command_cb = command_cb;
+}
+
 void
 vr_config_set_device_id(struct vr_config *config,
                         int device_id)
 {
         config->device_id = device_id;
 }
+
+void
+vr_config_set_device_name(struct vr_config *config,
+                          const char *device_name)
+{
+        vr_free(config->device_name);
+        config->device_name = device_name ? vr_strdup(device_name) : NULL;
+}
+
+void
+vr_config_set_device_uuid(struct vr_config *config,
+                          const uint8_t *device_uuid)
+{
+        config->has_device_uuid = device_uuid != NULL;
+
+        if (device_uuid) {
+                memcpy(config->device_uuid,
+                       device_uuid,
+                       sizeof config->device_uuid);
+        }
+}
+
+void
+vr_config_set_memory_type_index(struct vr_config *config,
+                                int memory_type_index)
+{
+        config->memory_type_index = memory_type_index;
+}
+
+void
+vr_config_set_pipeline_cache_file(struct vr_config *config,
+                                  const char *filename)
+{
+        vr_free(config->pipeline_cache_file);
+        config->pipeline_cache_file = filename ? vr_strdup(filename) : NULL;
+}
+
+void
+vr_config_set_application_info(struct vr_config *config,
+                               const char *application_name,
+                               uint32_t application_version,
+                               const char *engine_name,
+                               uint32_t engine_version)
+{
+        vr_free(config->application_name);
+        vr_free(config->engine_name);
+
+        config->application_name =
+                application_name ? vr_strdup(application_name) : NULL;
+        config->application_version = application_version;
+        config->engine_name = engine_name ? vr_strdup(engine_name) : NULL;
+        config->engine_version = engine_version;
+}
@@ -27,6 +27,7 @@
 #define VR_CONFIG_H
 
 #include <stdbool.h>
+#include <stdint.h>
 #include <vkrunner/vr-callback.h>
 
 #ifdef  __cplusplus
@@ -35,6 +36,14 @@ extern "C" {
 
 struct vr_config;
 
+enum vr_config_wait_mode {
+        /* Block in vkWaitForFences until the fence is signaled */
+        VR_CONFIG_WAIT_MODE_BLOCK,
+        /* Busy-loop calling vkGetFenceStatus until the fence is
+         * signaled */
+        VR_CONFIG_WAIT_MODE_SPIN
+};
+
 /* Create a new vr_config object */
 struct vr_config *
 vr_config_new(void);
@@ -47,6 +56,76 @@ void
 vr_config_set_show_disassembly(struct vr_config *config,
                                bool show_disassembly);
 
+/* Sets whether to report the entry points, descriptor bindings and
+ * push constant ranges of each SPIR-V module. The report is sent via
+ * the info callback.
+ */
+void
+vr_config_set_show_reflection(struct vr_config *config,
+                              bool show_reflection);
+
+/* Sets whether the statistics reported by the driver with
+ * VK_KHR_pipeline_executable_properties should be shown for each
+ * pipeline that is created with the capture_statistics flag. They
+ * are reported via the info callback.
+ */
+void
+vr_config_set_show_pipeline_stats(struct vr_config *config,
+                                  bool show_pipeline_stats);
+
+/* Sets whether to measure how long it takes to copy the color buffer
+ * to host memory and read it back at the end of each script. The
+ * bandwidth is reported via the info callback. The probes see the
+ * same results as without it but the script fails if the extra
+ * readback itself fails.
+ */
+void
+vr_config_set_measure_readback(struct vr_config *config,
+                               bool measure_readback);
+
+/* Sets whether the remaining commands of a script should be skipped
+ * after the first probe fails. By default all of the commands are
+ * run so that every failure is reported.
+ */
+void
+vr_config_set_stop_on_probe_failure(struct vr_config *config,
+                                    bool stop_on_probe_failure);
+
+/* Sets the number of times to run the commands of the test section
+ * before the real run. The warmup runs skip the probes and don’t
+ * invoke the inspect callback so their results are discarded. This
+ * can be used to make sure that the driver has already created any
+ * internal caches before the real run. The remaining warmup runs
+ * are skipped if one of them fails. The number of warmup runs that
+ * were done is reported with the info callback. The default is zero.
+ */
+void
+vr_config_set_warmup_runs(struct vr_config *config,
+                          int warmup_runs);
+
+/* Sets the maximum number of seconds that a run of the test
+ * commands can take before it is aborted. This is checked whenever
+ * VkRunner waits for the commands to complete so it can be used to
+ * detect a driver that never signals the fence. If the timeout
+ * elapses the test fails and the Vulkan device is recreated for the
+ * next script. The objects from the timed out run, including the old
+ * device, are leaked rather than destroyed because the driver might
+ * still be using them. The default is zero which means there is no
+ * timeout.
+ */
+void
+vr_config_set_timeout(struct vr_config *config,
+                      int timeout);
+
+/* Sets how VkRunner waits for the commands to complete after each
+ * submission. Spinning keeps the CPU busy so it can be used to
+ * compare the power usage and latency with blocking. The default is
+ * VR_CONFIG_WAIT_MODE_BLOCK.
+ */
+void
+vr_config_set_wait_mode(struct vr_config *config,
+                        enum vr_config_wait_mode wait_mode);
+
 /* Sets a pointer to be passed back to the caller in all of the
  * callback fuctions below.
  */
@@ -62,6 +141,15 @@ void
 vr_config_set_error_cb(struct vr_config *config,
                        vr_callback_error error_cb);
 
+/* Sets a callback that will be invoked for informational output that
+ * isn’t an error, such as the SPIR-V reflection report or the
+ * pipeline statistics. If this isn’t set then the messages are sent
+ * to the error callback instead, or to stderr if neither is set.
+ */
+void
+vr_config_set_info_cb(struct vr_config *config,
+                      vr_callback_info info_cb);
+
 /* Sets a callback to invoke after the commands in the test section
  * have run. It is not invoked if the test fails before the test
  * section is reached. The application can use the inspect struct to
@@ -71,10 +159,88 @@ void
 vr_config_set_inspect_cb(struct vr_config *config,
                          vr_callback_inspect inspect_cb);
 
+/* Sets a callback to invoke after every draw command in the test
+ * section. The commands are submitted and waited for before each call
+ * so that the color buffer contains the result of the draw. This
+ * splits the test into many more submissions so it is slow and it
+ * will break any expect submissions command. It is not invoked
+ * during the warmup runs.
+ */
+void
+vr_config_set_draw_inspect_cb(struct vr_config *config,
+                              vr_callback_inspect draw_inspect_cb);
+
+/* Sets a callback to invoke after each command in the test section
+ * has run with its line number, its kind and whether it passed. Any
+ * error messages for a failed command are reported with the error
+ * callback before this is invoked, so the application can use it to
+ * find out which commands failed without parsing the messages. It is
+ * not invoked during the warmup runs.
+ */
+void
+vr_config_set_command_cb(struct vr_config *config,
+                         vr_callback_command command_cb);
+
 void
 vr_config_set_device_id(struct vr_config *config,
                         int device_id);
 
+/* Selects the physical device whose deviceName is exactly the given
+ * string. It is an error if no device or more than one device has the
+ * name. Set it to NULL to stop selecting by name.
+ */
+void
+vr_config_set_device_name(struct vr_config *config,
+                          const char *device_name);
+
+/* Selects the physical device whose deviceUUID from
+ * VkPhysicalDeviceIDProperties matches the given 16 bytes. This needs
+ * the VK_KHR_get_physical_device_properties2 instance extension and
+ * either Vulkan 1.1 or VK_KHR_external_memory_capabilities. It is an
+ * error if no device or more than one device matches. Set it to NULL
+ * to stop selecting by UUID.
+ */
+void
+vr_config_set_device_uuid(struct vr_config *config,
+                          const uint8_t *device_uuid);
+
+/* Forces all memory allocations to use the memory type with the given
+ * index instead of picking one automatically. Any allocation for a
+ * resource that can’t use the memory type will fail. Set it to -1 to
+ * go back to the automatic selection.
+ */
+void
+vr_config_set_memory_type_index(struct vr_config *config,
+                                int memory_type_index);
+
+/* Loads the initial data for a pipeline cache from the given file
+ * when the Vulkan device is created and writes the cache back to the
+ * file when the device is destroyed. The cache is shared by all of the
+ * scripts that use the same device. If the file doesn’t exist or its
+ * data can’t be used with the device then an empty cache is used
+ * instead. The file is replaced atomically by writing a temporary
+ * file next to it and renaming it, so if several executors share the
+ * file it ends up with the cache of whichever one was destroyed last.
+ * Set it to NULL to go back to using a new cache for each script.
+ */
+void
+vr_config_set_pipeline_cache_file(struct vr_config *config,
+                                  const char *filename);
+
+/* Sets the application and engine names and versions that will be
+ * passed to the driver in the VkApplicationInfo when the instance is
+ * created. Some drivers enable workarounds based on these so this can
+ * be used to reproduce problems that only happen with a particular
+ * application. Either name can be NULL. By default the application
+ * name is “vkrunner” and there is no engine name.
+ */
+void
+vr_config_set_application_info(struct vr_config *config,
+                               const char *application_name,
+                               uint32_t application_version,
+                               const char *engine_name,
+                               uint32_t engine_version);
+
 #ifdef  __cplusplus
 }
 #endif
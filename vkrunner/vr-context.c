@@ -29,12 +29,16 @@
 #include <stdbool.h>
 #include <assert.h>
 #include <string.h>
+#include <stdio.h>
+#include <errno.h>
 
 #include "vr-config-private.h"
 #include "vr-context.h"
 #include "vr-util.h"
+#include "vr-buffer.h"
 #include "vr-error-message.h"
 #include "vr-allocate-store.h"
+#include "vr-temp-file.h"
 
 static int
 find_queue_family(struct vr_context *context,
@@ -69,11 +73,193 @@ find_queue_family(struct vr_context *context,
                 return i;
 }
 
+/* Returns the contents of the pipeline cache file or NULL if it can’t
+ * be read */
+static uint8_t *
+read_pipeline_cache_file(const char *filename,
+                         size_t *size_out)
+{
+        FILE *f = fopen(filename, "rb");
+
+        if (f == NULL)
+                return NULL;
+
+        uint8_t *contents = NULL;
+        long size;
+
+        if (fseek(f, 0, SEEK_END) == 0 &&
+            (size = ftell(f)) > 0 &&
+            fseek(f, 0, SEEK_SET) == 0) {
+                contents = vr_alloc(size);
+
+                if (fread(contents, 1, size, f) == (size_t) size) {
+                        *size_out = size;
+                } else {
+                        vr_free(contents);
+                        contents = NULL;
+                }
+        }
+
+        fclose(f);
+
+        return contents;
+}
+
+/* Checks that the data starts with a pipeline cache header that was
+ * written for the same device so that the driver never sees the data
+ * from another device or a truncated file */
+static bool
+pipeline_cache_header_matches(const struct vr_context *context,
+                              const uint8_t *data,
+                              size_t size)
+{
+        const VkPhysicalDeviceProperties *props = &context->device_properties;
+        uint32_t header[4];
+
+        if (size < sizeof header + VK_UUID_SIZE)
+                return false;
+
+        memcpy(header, data, sizeof header);
+
+        return (header[0] >= sizeof header + VK_UUID_SIZE &&
+                header[0] <= size &&
+                header[1] == VK_PIPELINE_CACHE_HEADER_VERSION_ONE &&
+                header[2] == props->vendorID &&
+                header[3] == props->deviceID &&
+                !memcmp(data + sizeof header,
+                        props->pipelineCacheUUID,
+                        VK_UUID_SIZE));
+}
+
+static bool
+create_pipeline_cache(struct vr_context *context)
+{
+        struct vr_vk *vkfn = &context->vkfn;
+        const char *filename = context->config->pipeline_cache_file;
+        size_t size = 0;
+        uint8_t *data = read_pipeline_cache_file(filename, &size);
+        VkResult res;
+
+        if (data && !pipeline_cache_header_matches(context, data, size)) {
+                vr_free(data);
+                data = NULL;
+                size = 0;
+        }
+
+        VkPipelineCacheCreateInfo pipeline_cache_create_info = {
+                .sType = VK_STRUCTURE_TYPE_PIPELINE_CACHE_CREATE_INFO,
+                .initialDataSize = size,
+                .pInitialData = data
+        };
+        res = vkfn->vkCreatePipelineCache(context->device,
+                                          &pipeline_cache_create_info,
+                                          NULL, /* allocator */
+                                          &context->pipeline_cache);
+
+        /* If the driver doesn’t like the data then start again with
+         * an empty cache */
+        if (res != VK_SUCCESS && data) {
+                pipeline_cache_create_info.initialDataSize = 0;
+                pipeline_cache_create_info.pInitialData = NULL;
+                res = vkfn->vkCreatePipelineCache(context->device,
+                                                  &pipeline_cache_create_info,
+                                                  NULL, /* allocator */
+                                                  &context->pipeline_cache);
+        }
+
+        vr_free(data);
+
+        if (res != VK_SUCCESS) {
+                context->pipeline_cache = VK_NULL_HANDLE;
+                vr_error_message(context->config,
+                                 "Error creating pipeline cache");
+                return false;
+        }
+
+        return true;
+}
+
+static void
+save_pipeline_cache(struct vr_context *context)
+{
+        struct vr_vk *vkfn = &context->vkfn;
+        const char *filename = context->config->pipeline_cache_file;
+        size_t size;
+        VkResult res;
+
+        res = vkfn->vkGetPipelineCacheData(context->device,
+                                           context->pipeline_cache,
+                                           &size,
+                                           NULL /* pData */);
+        if (res != VK_SUCCESS) {
+                vr_error_message(context->config,
+                                 "Error getting the pipeline cache data");
+                return;
+        }
+
+        uint8_t *data = vr_alloc(MAX(size, 1));
+
+        res = vkfn->vkGetPipelineCacheData(context->device,
+                                           context->pipeline_cache,
+                                           &size,
+                                           data);
+        if (res != VK_SUCCESS) {
+                vr_error_message(context->config,
+                                 "Error getting the pipeline cache data");
+                goto out;
+        }
+
+        /* The data is written to a temporary file first and then
+         * moved into place so that another process reading or
+         * writing the cache at the same time never sees a partially
+         * written file */
+        FILE *f;
+        char *temp_filename;
+
+        if (!vr_temp_file_create_beside(context->config,
+                                        filename,
+                                        &f,
+                                        &temp_filename))
+                goto out;
+
+        bool write_ok = fwrite(data, 1, size, f) == size;
+
+        if (fclose(f) != 0)
+                write_ok = false;
+
+        if (write_ok) {
+                vr_temp_file_replace(context->config,
+                                     temp_filename,
+                                     filename);
+        } else {
+                vr_error_message(context->config,
+                                 "%s: error writing the pipeline cache",
+                                 temp_filename);
+                remove(temp_filename);
+        }
+
+        vr_free(temp_filename);
+
+out:
+        vr_free(data);
+}
+
 static void
 deinit_vk(struct vr_context *context)
 {
         struct vr_vk *vkfn = &context->vkfn;
 
+        if (context->pipeline_cache) {
+                /* After a timeout the device is in an unknown
+                 * state so the cache data can’t be trusted */
+                if (!context->timed_out)
+                        save_pipeline_cache(context);
+                vkfn->vkDestroyPipelineCache(context->device,
+                                             context->pipeline_cache,
+                                             NULL /* allocator */);
+                context->pipeline_cache = VK_NULL_HANDLE;
+        }
+
         if (context->vk_fence) {
                 vkfn->vkDestroyFence(context->device,
                                      context->vk_fence,
@@ -107,6 +293,131 @@ deinit_vk(struct vr_context *context)
         }
 }
 
+static void
+add_skip_reason(struct vr_context *context,
+                struct vr_buffer *buffer,
+                VkPhysicalDevice physical_device,
+                const char *reason)
+{
+        VkPhysicalDeviceProperties props;
+
+        context->vkfn.vkGetPhysicalDeviceProperties(physical_device, &props);
+
+        if (buffer->length > 0)
+                vr_buffer_append_c(buffer, '\n');
+
+        vr_buffer_append_printf(buffer,
+                                "Skipping device “%s”: %s",
+                                props.deviceName,
+                                reason);
+}
+
+static bool
+device_uuid_matches(struct vr_context *context,
+                    VkPhysicalDevice physical_device)
+{
+        const char *get_properties_name = "vkGetPhysicalDeviceProperties2KHR";
+        PFN_vkGetPhysicalDeviceProperties2 get_properties =
+                (void *) context->vkfn.vkGetInstanceProcAddr(
+                        context->vk_instance,
+                        get_properties_name);
+
+        if (get_properties == NULL)
+                return false;
+
+        /* VkPhysicalDeviceIDProperties can only be chained if the
+         * device supports Vulkan 1.1 or the instance has the
+         * extension that originally added it */
+        VkPhysicalDeviceProperties device_props;
+
+        context->vkfn.vkGetPhysicalDeviceProperties(physical_device,
+                                                    &device_props);
+
+        if (device_props.apiVersion < VK_MAKE_VERSION(1, 1, 0) &&
+            !context->has_external_memory_capabilities) {
+                vr_error_message(context->config,
+                                 "The UUID of %s can’t be queried because "
+                                 "it doesn’t support Vulkan 1.1 or %s",
+                                 device_props.deviceName,
+                                 VK_KHR_EXTERNAL_MEMORY_CAPABILITIES_EXTENSION_NAME);
+                return false;
+        }
+
+        VkPhysicalDeviceIDProperties id_props = {
+                .sType = VK_STRUCTURE_TYPE_PHYSICAL_DEVICE_ID_PROPERTIES,
+        };
+        VkPhysicalDeviceProperties2 props = {
+                .sType = VK_STRUCTURE_TYPE_PHYSICAL_DEVICE_PROPERTIES_2,
+                .pNext = &id_props,
+        };
+
+        get_properties(physical_device, &props);
+
+        return !memcmp(id_props.deviceUUID,
+                       context->config->device_uuid,
+                       VK_UUID_SIZE);
+}
+
+static bool
+device_matches(struct vr_context *context,
+               VkPhysicalDevice physical_device)
+{
+        const struct vr_config *config = context->config;
+
+        if (config->device_name) {
+                VkPhysicalDeviceProperties props;
+
+                context->vkfn.vkGetPhysicalDeviceProperties(physical_device,
+                                                            &props);
+
+                if (strcmp(props.deviceName, config->device_name))
+                        return false;
+        }
+
+        if (config->has_device_uuid &&
+            !device_uuid_matches(context, physical_device))
+                return false;
+
+        return true;
+}
+
+/* Finds the only device in the range that matches the name or UUID
+ * from the config */
+static enum vr_result
+find_matching_device(struct vr_context *context,
+                     const VkPhysicalDevice *devices,
+                     int first_dev,
+                     int count,
+                     int *dev_out)
+{
+        int match = -1;
+
+        for (int i = first_dev; i < count; i++) {
+                if (!device_matches(context, devices[i]))
+                        continue;
+
+                if (match != -1) {
+                        vr_error_message(context->config,
+                                         "More than one device matches the "
+                                         "requested device name or UUID");
+                        return VR_RESULT_FAIL;
+                }
+
+                match = i;
+        }
+
+        if (match == -1) {
+                vr_error_message(context->config,
+                                 "No device matches the requested device "
+                                 "name or UUID");
+                return VR_RESULT_FAIL;
+        }
+
+        *dev_out = match;
+
+        return VR_RESULT_PASS;
+}
+
 static enum vr_result
 find_physical_device(struct vr_context *context,
                      const struct vr_requirements *reqs)
@@ -150,27 +461,69 @@ find_physical_device(struct vr_context *context,
                 first_dev = dev_id;
                 count = first_dev + 1;
         }
+
+        if (context->config->device_name ||
+            context->config->has_device_uuid) {
+                enum vr_result match_res =
+                        find_matching_device(context,
+                                             devices,
+                                             first_dev,
+                                             count,
+                                             &dev_id);
+                if (match_res != VR_RESULT_PASS)
+                        return match_res;
+
+                first_dev = dev_id;
+                count = first_dev + 1;
+        }
+
+        struct vr_buffer reason = VR_BUFFER_STATIC_INIT;
+        struct vr_buffer skipped = VR_BUFFER_STATIC_INIT;
+        enum vr_result ret = VR_RESULT_SKIP;
+
         for (i = first_dev; i < count; i++) {
                 if (!vr_requirements_check(reqs,
                                            vkfn,
                                            context->vk_instance,
-                                           devices[i]))
+                                           devices[i],
+                                           &reason)) {
+                        add_skip_reason(context,
+                                        &skipped,
+                                        devices[i],
+                                        (const char *) reason.data);
                         continue;
+                }
 
                 queue_family = find_queue_family(context, devices[i]);
-                if (queue_family == -1)
+                if (queue_family == -1) {
+                        add_skip_reason(context,
+                                        &skipped,
+                                        devices[i],
+                                        "no graphics queue family is "
+                                        "available");
                         continue;
+                }
 
                 context->physical_device = devices[i];
                 context->queue_family = queue_family;
 
-                return VR_RESULT_PASS;
+                ret = VR_RESULT_PASS;
+                goto out;
         }
 
         vr_error_message(context->config,
                          "No suitable device and queue family found");
 
-        return VR_RESULT_SKIP;
+        if (skipped.length > 0) {
+                vr_error_message_string(context->config,
+                                        (const char *) skipped.data);
+        }
+
+out:
+        vr_buffer_destroy(&skipped);
+        vr_buffer_destroy(&reason);
+
+        return ret;
 }
 
 static void *
@@ -216,7 +569,7 @@ check_instance_extension(struct vr_vk *vkfn,
         res = vkfn->vkEnumerateInstanceExtensionProperties(NULL, /* layerName */
                                                            &count,
                                                            props);
-        if (res != VK_SUCCESS && !find_extension(count, props, ext))
+        if (res != VK_SUCCESS || !find_extension(count, props, ext))
                 ret = false;
 
         vr_free(props);
@@ -235,15 +588,24 @@ init_vk_device(struct vr_context *context,
                 .sType = VK_STRUCTURE_TYPE_INSTANCE_CREATE_INFO,
                 .pApplicationInfo = &(VkApplicationInfo) {
                         .sType = VK_STRUCTURE_TYPE_APPLICATION_INFO,
-                        .pApplicationName = "vkrunner",
+                        .pApplicationName = context->config->application_name,
+                        .applicationVersion =
+                        context->config->application_version,
+                        .pEngineName = context->config->engine_name,
+                        .engineVersion = context->config->engine_version,
                         .apiVersion = vr_requirements_get_version(reqs)
                 },
         };
 
+        const char *exts[2];
         const char *ext =
                 VK_KHR_GET_PHYSICAL_DEVICE_PROPERTIES_2_EXTENSION_NAME;
+        int n_exts = 0;
 
-        if (vr_requirements_get_structures(reqs)) {
+        /* The extension is needed to check the features in the
+         * structures and to get the UUID of the devices */
+        if (vr_requirements_get_structures(reqs) ||
+            context->config->has_device_uuid) {
                 if (!check_instance_extension(vkfn, ext)) {
                         vr_error_message(context->config,
                                          "Driver is missing the %s extension",
@@ -251,10 +613,22 @@ init_vk_device(struct vr_context *context,
                         return VR_RESULT_SKIP;
                 }
 
-                instance_create_info.enabledExtensionCount = 1;
-                instance_create_info.ppEnabledExtensionNames = &ext;
+                exts[n_exts++] = ext;
         }
 
+        /* The UUID of devices that only support Vulkan 1.0 can
+         * still be queried if the instance has this extension */
+        ext = VK_KHR_EXTERNAL_MEMORY_CAPABILITIES_EXTENSION_NAME;
+
+        if (context->config->has_device_uuid &&
+            check_instance_extension(vkfn, ext)) {
+                exts[n_exts++] = ext;
+                context->has_external_memory_capabilities = true;
+        }
+
+        instance_create_info.enabledExtensionCount = n_exts;
+        instance_create_info.ppEnabledExtensionNames = n_exts ? exts : NULL;
+
         res = vkfn->vkCreateInstance(&instance_create_info,
                                      NULL, /* allocator */
                                      &context->vk_instance);
@@ -380,6 +754,12 @@ init_vk(struct vr_context *context)
                 goto error;
         }
 
+        if (context->config->pipeline_cache_file &&
+            !create_pipeline_cache(context)) {
+                vres = VR_RESULT_FAIL;
+                goto error;
+        }
+
         return VR_RESULT_PASS;
 
 error:
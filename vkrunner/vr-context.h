@@ -49,9 +49,21 @@ struct vr_context {
         int queue_family;
         VkInstance vk_instance;
         VkFence vk_fence;
+        /* Pipeline cache loaded from the file in the config or
+         * VK_NULL_HANDLE if the config doesn’t have one */
+        VkPipelineCache pipeline_cache;
 
         bool always_flush_memory;
 
+        /* Whether VK_KHR_external_memory_capabilities was enabled on
+         * the instance. This is needed to query the UUID of a device
+         * that doesn’t support Vulkan 1.1. */
+        bool has_external_memory_capabilities;
+
+        /* Set if waiting for a fence timed out. The device might
+         * still be busy so the context can’t be used again. */
+        bool timed_out;
+
         struct vr_vk vkfn;
 };
 
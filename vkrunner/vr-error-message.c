@@ -65,3 +65,29 @@ vr_error_message(const struct vr_config *config,
 
         va_end(ap);
 }
+
+void
+vr_info_message_string(const struct vr_config *config,
+                       const char *str)
+{
+        if (config->info_cb)
+                config->info_cb(str, config->user_data);
+        else
+                vr_error_message_string(config, str);
+}
+
+void
+vr_info_message(const struct vr_config *config,
+                const char *format, ...)
+{
+        struct vr_buffer buf = VR_BUFFER_STATIC_INIT;
+        va_list ap;
+
+        va_start(ap, format);
+        vr_buffer_append_vprintf(&buf, format, ap);
+        va_end(ap);
+
+        vr_info_message_string(config, (const char *) buf.data);
+
+        vr_buffer_destroy(&buf);
+}
@@ -38,4 +38,13 @@ void
 vr_error_message_string(const struct vr_config *config,
                         const char *str);
 
+VR_PRINTF_FORMAT(2, 3)
+void
+vr_info_message(const struct vr_config *config,
+                const char *format, ...);
+
+void
+vr_info_message_string(const struct vr_config *config,
+                       const char *str);
+
 #endif /* VR_ERROR_MESSAGE_H */
@@ -83,6 +83,20 @@ free_context(struct vr_executor *executor)
                 vr_requirements_free(executor->requirements);
 }
 
+/* Forgets about the context without destroying any of its objects.
+ * This is used after a timeout because the device might still be
+ * using them and destroying them would be undefined behaviour. The
+ * objects are leaked instead. */
+static void
+abandon_context(struct vr_executor *executor)
+{
+        executor->window = NULL;
+        executor->context = NULL;
+
+        if (!executor->use_external)
+                vr_requirements_free(executor->requirements);
+}
+
 static bool
 context_is_compatible(struct vr_executor *executor,
                       const struct vr_script *script)
@@ -115,6 +129,27 @@ create_external_context(struct vr_executor *executor)
                                           &executor->context);
 }
 
+static enum vr_result
+create_context(struct vr_executor *executor,
+               const struct vr_requirements *requirements)
+{
+        enum vr_result res;
+
+        if (executor->use_external)
+                return create_external_context(executor);
+
+        res = vr_context_new(executor->config,
+                             requirements,
+                             &executor->context);
+
+        if (res != VR_RESULT_PASS)
+                return res;
+
+        executor->requirements = vr_requirements_copy(requirements);
+
+        return VR_RESULT_PASS;
+}
+
 struct vr_executor *
 vr_executor_new(struct vr_config *config)
 {
@@ -162,37 +197,32 @@ vr_executor_execute_script(struct vr_executor *executor,
                 free_window(executor);
 
         if (executor->context == NULL) {
-                if (executor->use_external) {
-                        res = create_external_context(executor);
-                        if (res != VR_RESULT_PASS)
-                                goto out;
-                } else {
-                        res = vr_context_new(executor->config,
-                                             script->requirements,
-                                             &executor->context);
-
-                        if (res != VR_RESULT_PASS)
-                                goto out;
-
-                        executor->requirements =
-                                vr_requirements_copy(script->requirements);
-                }
+                res = create_context(executor, script->requirements);
+                if (res != VR_RESULT_PASS)
+                        goto out;
         }
 
         if (executor->use_external) {
                 struct vr_context *context = executor->context;
 
+                struct vr_buffer reason = VR_BUFFER_STATIC_INIT;
+
                 if (!vr_requirements_check(script->requirements,
                                            &context->vkfn,
                                            context->vk_instance,
-                                           context->physical_device)) {
+                                           context->physical_device,
+                                           &reason)) {
                         vr_error_message(executor->config,
                                          "%s: A required feature or extension "
-                                         "is missing",
-                                         script->filename);
+                                         "is missing: %s",
+                                         script->filename,
+                                         (const char *) reason.data);
+                        vr_buffer_destroy(&reason);
                         res = VR_RESULT_SKIP;
                         goto out;
                 }
+
+                vr_buffer_destroy(&reason);
         }
 
         if (executor->window == NULL) {
@@ -212,11 +242,44 @@ vr_executor_execute_script(struct vr_executor *executor,
                 goto out;
         }
 
-        if (!vr_test_run(executor->window, pipeline, script))
+        /* The results of the warmup runs are ignored. Any errors
+         * will be reported again by the real run anyway so the
+         * remaining warmup runs are skipped after the first failure
+         * to avoid repeating them.
+         */
+        int warmup_runs = executor->config->warmup_runs;
+        int n_warmup_runs = 0;
+
+        while (n_warmup_runs < warmup_runs) {
+                bool warmup_passed = vr_test_run(executor->window,
+                                                 pipeline,
+                                                 script,
+                                                 true);
+
+                n_warmup_runs++;
+
+                if (!warmup_passed || executor->context->timed_out)
+                        break;
+        }
+
+        if (warmup_runs > 0) {
+                vr_info_message(executor->config,
+                                "Warmup: %i of %i runs completed",
+                                n_warmup_runs,
+                                warmup_runs);
+        }
+
+        if (executor->context->timed_out ||
+            !vr_test_run(executor->window, pipeline, script, false))
                 res = VR_RESULT_FAIL;
 
 out:
-        if (pipeline)
+        /* The device might still be busy after a timeout so the
+         * pipeline and the context are leaked and a new device is
+         * created for the next script */
+        if (executor->context && executor->context->timed_out)
+                abandon_context(executor);
+        else if (pipeline)
                 vr_pipeline_free(pipeline);
 
         return res;
@@ -243,6 +306,35 @@ out:
         return res;
 }
 
+enum vr_result
+vr_executor_get_api_versions(struct vr_executor *executor,
+                             uint32_t *instance_version,
+                             uint32_t *device_version)
+{
+        if (executor->context == NULL) {
+                struct vr_requirements *reqs = vr_requirements_new();
+                enum vr_result res = create_context(executor, reqs);
+                vr_requirements_free(reqs);
+
+                if (res != VR_RESULT_PASS)
+                        return res;
+        }
+
+        struct vr_context *context = executor->context;
+        PFN_vkEnumerateInstanceVersion enum_instance_version =
+                (void *) context->vkfn.vkGetInstanceProcAddr(
+                        NULL,
+                        "vkEnumerateInstanceVersion");
+
+        if (enum_instance_version == NULL ||
+            enum_instance_version(instance_version) != VK_SUCCESS)
+                *instance_version = VK_MAKE_VERSION(1, 0, 0);
+
+        *device_version = context->device_properties.apiVersion;
+
+        return VR_RESULT_PASS;
+}
+
 void
 vr_executor_free(struct vr_executor *executor)
 {
@@ -27,6 +27,7 @@
 #define VR_EXECUTOR_H
 
 #include <stdbool.h>
+#include <stdint.h>
 #include <vkrunner/vr-result.h>
 #include <vkrunner/vr-source.h>
 #include <vkrunner/vr-callback.h>
@@ -78,6 +79,17 @@ enum vr_result
 vr_executor_execute_script(struct vr_executor *executor,
                            const struct vr_script *script);
 
+/* Creates the Vulkan device if it hasn’t been created yet and reports
+ * the API version of the instance and of the selected physical device
+ * without running any scripts. The instance version comes from
+ * vkEnumerateInstanceVersion or is 1.0 if that function isn’t
+ * available. The versions are encoded as with VK_MAKE_VERSION.
+ */
+enum vr_result
+vr_executor_get_api_versions(struct vr_executor *executor,
+                             uint32_t *instance_version,
+                             uint32_t *device_version);
+
 void
 vr_executor_free(struct vr_executor *executor);
 
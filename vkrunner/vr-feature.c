@@ -312,6 +312,20 @@ offsets_NV_FRAGMENT_SHADER_BARYCENTRIC[] = {
 #warning "The vulkan headers are missing VK_NV_FRAGMENT_SHADER_BARYCENTRIC_EXTENSION_NAME"
 #endif
 
+#ifdef VK_EXT_HOST_QUERY_RESET_EXTENSION_NAME
+#define have_EXT_HOST_QUERY_RESET
+static const struct vr_feature_offset
+offsets_EXT_HOST_QUERY_RESET[] = {
+        {
+                .name = "hostQueryReset",
+                .offset = offsetof(VkPhysicalDeviceHostQueryResetFeaturesEXT, hostQueryReset)
+        },
+        { .name = NULL }
+};
+#else /* VK_EXT_HOST_QUERY_RESET_EXTENSION_NAME */
+#warning "The vulkan headers are missing VK_EXT_HOST_QUERY_RESET_EXTENSION_NAME"
+#endif
+
 #ifdef VK_EXT_INLINE_UNIFORM_BLOCK_EXTENSION_NAME
 #define have_EXT_INLINE_UNIFORM_BLOCK
 static const struct vr_feature_offset
@@ -362,6 +376,36 @@ offsets_NV_MESH_SHADER[] = {
 #warning "The vulkan headers are missing VK_NV_MESH_SHADER_EXTENSION_NAME"
 #endif
 
+#ifdef VK_EXT_MESH_SHADER_EXTENSION_NAME
+#define have_EXT_MESH_SHADER
+static const struct vr_feature_offset
+offsets_EXT_MESH_SHADER[] = {
+        {
+                .name = "taskShader",
+                .offset = offsetof(VkPhysicalDeviceMeshShaderFeaturesEXT, taskShader)
+        },
+        {
+                .name = "meshShader",
+                .offset = offsetof(VkPhysicalDeviceMeshShaderFeaturesEXT, meshShader)
+        },
+        {
+                .name = "multiviewMeshShader",
+                .offset = offsetof(VkPhysicalDeviceMeshShaderFeaturesEXT, multiviewMeshShader)
+        },
+        {
+                .name = "primitiveFragmentShadingRateMeshShader",
+                .offset = offsetof(VkPhysicalDeviceMeshShaderFeaturesEXT, primitiveFragmentShadingRateMeshShader)
+        },
+        {
+                .name = "meshShaderQueries",
+                .offset = offsetof(VkPhysicalDeviceMeshShaderFeaturesEXT, meshShaderQueries)
+        },
+        { .name = NULL }
+};
+#else /* VK_EXT_MESH_SHADER_EXTENSION_NAME */
+#warning "The vulkan headers are missing VK_EXT_MESH_SHADER_EXTENSION_NAME"
+#endif
+
 #ifdef VK_KHR_MULTIVIEW_EXTENSION_NAME
 #define have_KHR_MULTIVIEW
 static const struct vr_feature_offset
@@ -384,6 +428,20 @@ offsets_KHR_MULTIVIEW[] = {
 #warning "The vulkan headers are missing VK_KHR_MULTIVIEW_EXTENSION_NAME"
 #endif
 
+#ifdef VK_KHR_PIPELINE_EXECUTABLE_PROPERTIES_EXTENSION_NAME
+#define have_KHR_PIPELINE_EXECUTABLE_PROPERTIES
+static const struct vr_feature_offset
+offsets_KHR_PIPELINE_EXECUTABLE_PROPERTIES[] = {
+        {
+                .name = "pipelineExecutableInfo",
+                .offset = offsetof(VkPhysicalDevicePipelineExecutablePropertiesFeaturesKHR, pipelineExecutableInfo)
+        },
+        { .name = NULL }
+};
+#else /* VK_KHR_PIPELINE_EXECUTABLE_PROPERTIES_EXTENSION_NAME */
+#warning "The vulkan headers are missing VK_KHR_PIPELINE_EXECUTABLE_PROPERTIES_EXTENSION_NAME"
+#endif
+
 #ifdef VK_NV_REPRESENTATIVE_FRAGMENT_TEST_EXTENSION_NAME
 #define have_NV_REPRESENTATIVE_FRAGMENT_TEST
 static const struct vr_feature_offset
@@ -398,6 +456,28 @@ offsets_NV_REPRESENTATIVE_FRAGMENT_TEST[] = {
 #warning "The vulkan headers are missing VK_NV_REPRESENTATIVE_FRAGMENT_TEST_EXTENSION_NAME"
 #endif
 
+#ifdef VK_EXT_ROBUSTNESS_2_EXTENSION_NAME
+#define have_EXT_ROBUSTNESS_2
+static const struct vr_feature_offset
+offsets_EXT_ROBUSTNESS_2[] = {
+        {
+                .name = "robustBufferAccess2",
+                .offset = offsetof(VkPhysicalDeviceRobustness2FeaturesEXT, robustBufferAccess2)
+        },
+        {
+                .name = "robustImageAccess2",
+                .offset = offsetof(VkPhysicalDeviceRobustness2FeaturesEXT, robustImageAccess2)
+        },
+        {
+                .name = "nullDescriptor",
+                .offset = offsetof(VkPhysicalDeviceRobustness2FeaturesEXT, nullDescriptor)
+        },
+        { .name = NULL }
+};
+#else /* VK_EXT_ROBUSTNESS_2_EXTENSION_NAME */
+#warning "The vulkan headers are missing VK_EXT_ROBUSTNESS_2_EXTENSION_NAME"
+#endif
+
 #ifdef VK_KHR_SAMPLER_YCBCR_CONVERSION_EXTENSION_NAME
 #define have_KHR_SAMPLER_YCBCR_CONVERSION
 static const struct vr_feature_offset
@@ -444,6 +524,20 @@ offsets_KHR_SHADER_ATOMIC_INT64[] = {
 #warning "The vulkan headers are missing VK_KHR_SHADER_ATOMIC_INT64_EXTENSION_NAME"
 #endif
 
+#ifdef VK_KHR_SHADER_DRAW_PARAMETERS_EXTENSION_NAME
+#define have_KHR_SHADER_DRAW_PARAMETERS
+static const struct vr_feature_offset
+offsets_KHR_SHADER_DRAW_PARAMETERS[] = {
+        {
+                .name = "shaderDrawParameters",
+                .offset = offsetof(VkPhysicalDeviceShaderDrawParametersFeatures, shaderDrawParameters)
+        },
+        { .name = NULL }
+};
+#else /* VK_KHR_SHADER_DRAW_PARAMETERS_EXTENSION_NAME */
+#warning "The vulkan headers are missing VK_KHR_SHADER_DRAW_PARAMETERS_EXTENSION_NAME"
+#endif
+
 #ifdef VK_NV_SHADER_IMAGE_FOOTPRINT_EXTENSION_NAME
 #define have_NV_SHADER_IMAGE_FOOTPRINT
 static const struct vr_feature_offset
@@ -476,6 +570,20 @@ offsets_NV_SHADING_RATE_IMAGE[] = {
 #warning "The vulkan headers are missing VK_NV_SHADING_RATE_IMAGE_EXTENSION_NAME"
 #endif
 
+#ifdef VK_KHR_SYNCHRONIZATION_2_EXTENSION_NAME
+#define have_KHR_SYNCHRONIZATION_2
+static const struct vr_feature_offset
+offsets_KHR_SYNCHRONIZATION_2[] = {
+        {
+                .name = "synchronization2",
+                .offset = offsetof(VkPhysicalDeviceSynchronization2FeaturesKHR, synchronization2)
+        },
+        { .name = NULL }
+};
+#else /* VK_KHR_SYNCHRONIZATION_2_EXTENSION_NAME */
+#warning "The vulkan headers are missing VK_KHR_SYNCHRONIZATION_2_EXTENSION_NAME"
+#endif
+
 #ifdef VK_EXT_TRANSFORM_FEEDBACK_EXTENSION_NAME
 #define have_EXT_TRANSFORM_FEEDBACK
 static const struct vr_feature_offset
@@ -883,6 +991,14 @@ vr_feature_extensions[] = {
                 .offsets = offsets_NV_FRAGMENT_SHADER_BARYCENTRIC
         },
 #endif
+#ifdef have_EXT_HOST_QUERY_RESET
+        {
+                .name = VK_EXT_HOST_QUERY_RESET_EXTENSION_NAME,
+                .struct_size = sizeof(VkPhysicalDeviceHostQueryResetFeaturesEXT),
+                .struct_type = VK_STRUCTURE_TYPE_PHYSICAL_DEVICE_HOST_QUERY_RESET_FEATURES_EXT,
+                .offsets = offsets_EXT_HOST_QUERY_RESET
+        },
+#endif
 #ifdef have_EXT_INLINE_UNIFORM_BLOCK
         {
                 .name = VK_EXT_INLINE_UNIFORM_BLOCK_EXTENSION_NAME,
@@ -907,6 +1023,14 @@ vr_feature_extensions[] = {
                 .offsets = offsets_NV_MESH_SHADER
         },
 #endif
+#ifdef have_EXT_MESH_SHADER
+        {
+                .name = VK_EXT_MESH_SHADER_EXTENSION_NAME,
+                .struct_size = sizeof(VkPhysicalDeviceMeshShaderFeaturesEXT),
+                .struct_type = VK_STRUCTURE_TYPE_PHYSICAL_DEVICE_MESH_SHADER_FEATURES_EXT,
+                .offsets = offsets_EXT_MESH_SHADER
+        },
+#endif
 #ifdef have_KHR_MULTIVIEW
         {
                 .name = VK_KHR_MULTIVIEW_EXTENSION_NAME,
@@ -915,6 +1039,14 @@ vr_feature_extensions[] = {
                 .offsets = offsets_KHR_MULTIVIEW
         },
 #endif
+#ifdef have_KHR_PIPELINE_EXECUTABLE_PROPERTIES
+        {
+                .name = VK_KHR_PIPELINE_EXECUTABLE_PROPERTIES_EXTENSION_NAME,
+                .struct_size = sizeof(VkPhysicalDevicePipelineExecutablePropertiesFeaturesKHR),
+                .struct_type = VK_STRUCTURE_TYPE_PHYSICAL_DEVICE_PIPELINE_EXECUTABLE_PROPERTIES_FEATURES_KHR,
+                .offsets = offsets_KHR_PIPELINE_EXECUTABLE_PROPERTIES
+        },
+#endif
 #ifdef have_NV_REPRESENTATIVE_FRAGMENT_TEST
         {
                 .name = VK_NV_REPRESENTATIVE_FRAGMENT_TEST_EXTENSION_NAME,
@@ -923,6 +1055,14 @@ vr_feature_extensions[] = {
                 .offsets = offsets_NV_REPRESENTATIVE_FRAGMENT_TEST
         },
 #endif
+#ifdef have_EXT_ROBUSTNESS_2
+        {
+                .name = VK_EXT_ROBUSTNESS_2_EXTENSION_NAME,
+                .struct_size = sizeof(VkPhysicalDeviceRobustness2FeaturesEXT),
+                .struct_type = VK_STRUCTURE_TYPE_PHYSICAL_DEVICE_ROBUSTNESS_2_FEATURES_EXT,
+                .offsets = offsets_EXT_ROBUSTNESS_2
+        },
+#endif
 #ifdef have_KHR_SAMPLER_YCBCR_CONVERSION
         {
                 .name = VK_KHR_SAMPLER_YCBCR_CONVERSION_EXTENSION_NAME,
@@ -947,6 +1087,14 @@ vr_feature_extensions[] = {
                 .offsets = offsets_KHR_SHADER_ATOMIC_INT64
         },
 #endif
+#ifdef have_KHR_SHADER_DRAW_PARAMETERS
+        {
+                .name = VK_KHR_SHADER_DRAW_PARAMETERS_EXTENSION_NAME,
+                .struct_size = sizeof(VkPhysicalDeviceShaderDrawParametersFeatures),
+                .struct_type = VK_STRUCTURE_TYPE_PHYSICAL_DEVICE_SHADER_DRAW_PARAMETERS_FEATURES,
+                .offsets = offsets_KHR_SHADER_DRAW_PARAMETERS
+        },
+#endif
 #ifdef have_NV_SHADER_IMAGE_FOOTPRINT
         {
                 .name = VK_NV_SHADER_IMAGE_FOOTPRINT_EXTENSION_NAME,
@@ -963,6 +1111,14 @@ vr_feature_extensions[] = {
                 .offsets = offsets_NV_SHADING_RATE_IMAGE
         },
 #endif
+#ifdef have_KHR_SYNCHRONIZATION_2
+        {
+                .name = VK_KHR_SYNCHRONIZATION_2_EXTENSION_NAME,
+                .struct_size = sizeof(VkPhysicalDeviceSynchronization2FeaturesKHR),
+                .struct_type = VK_STRUCTURE_TYPE_PHYSICAL_DEVICE_SYNCHRONIZATION_2_FEATURES_KHR,
+                .offsets = offsets_KHR_SYNCHRONIZATION_2
+        },
+#endif
 #ifdef have_EXT_TRANSFORM_FEEDBACK
         {
                 .name = VK_EXT_TRANSFORM_FEEDBACK_EXTENSION_NAME,
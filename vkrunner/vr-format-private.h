@@ -79,4 +79,10 @@ vr_format_lookup_by_details(int bit_size,
                             enum vr_format_mode mode,
                             int n_components);
 
+/* Returns the size in bits of the components if they are all signed
+ * floats of the same size. Otherwise returns zero.
+ */
+int
+vr_format_get_float_bits(const struct vr_format *format);
+
 #endif /* VR_FORMAT_PRIVATE_H */
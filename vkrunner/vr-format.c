@@ -103,6 +103,64 @@ vr_format_get_size(const struct vr_format *format)
         return total_size / 8;
 }
 
+int
+vr_format_get_float_bits(const struct vr_format *format)
+{
+        if (format->packed_size != 0 || format->n_parts < 1)
+                return 0;
+
+        for (int i = 0; i < format->n_parts; i++) {
+                if (format->parts[i].mode != VR_FORMAT_MODE_SFLOAT ||
+                    format->parts[i].bits != format->parts[0].bits)
+                        return 0;
+        }
+
+        return format->parts[0].bits;
+}
+
+bool
+vr_format_has_alpha(const struct vr_format *format)
+{
+        for (int i = 0; i < format->n_parts; i++) {
+                if (format->parts[i].component == VR_FORMAT_COMPONENT_A)
+                        return true;
+        }
+
+        return false;
+}
+
+bool
+vr_format_is_depth_stencil(const struct vr_format *format)
+{
+        for (int i = 0; i < format->n_parts; i++) {
+                if (format->parts[i].component == VR_FORMAT_COMPONENT_D ||
+                    format->parts[i].component == VR_FORMAT_COMPONENT_S)
+                        return true;
+        }
+
+        return false;
+}
+
+const char *
+vr_format_get_name(const struct vr_format *format)
+{
+        return format->name;
+}
+
+int
+vr_format_get_n_formats(void)
+{
+        return VR_N_ELEMENTS(formats);
+}
+
+const struct vr_format *
+vr_format_get(int index)
+{
+        assert(index >= 0 && index < VR_N_ELEMENTS(formats));
+
+        return formats + index;
+}
+
 static int32_t
 sign_extend(uint32_t part, int bits)
 {
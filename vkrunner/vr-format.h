@@ -26,6 +26,8 @@
 #ifndef VR_FORMAT_H
 #define VR_FORMAT_H
 
+#include <stdbool.h>
+
 struct vr_format;
 
 #ifdef  __cplusplus
@@ -40,6 +42,28 @@ vr_format_load_pixel(const struct vr_format *format,
                      const void *source,
                      double *pixel);
 
+/* Returns whether the format has an alpha component */
+bool
+vr_format_has_alpha(const struct vr_format *format);
+
+/* Returns whether the format has a depth or stencil component */
+bool
+vr_format_is_depth_stencil(const struct vr_format *format);
+
+/* Returns the name of the format as used in scripts. This is the
+ * name of the VkFormat without the VK_FORMAT prefix.
+ */
+const char *
+vr_format_get_name(const struct vr_format *format);
+
+/* Returns the number of formats that can be used in scripts */
+int
+vr_format_get_n_formats(void);
+
+/* Returns the format at index. The formats are sorted by name. */
+const struct vr_format *
+vr_format_get(int index);
+
 #ifdef  __cplusplus
 }
 #endif
@@ -43,6 +43,8 @@ struct vr_inspect_image {
 };
 
 struct vr_inspect_buffer {
+        /* The descriptor set of the buffer */
+        int desc_set;
         /* The binding number of the buffer */
         int binding;
         /* Size in bytes of the buffer */
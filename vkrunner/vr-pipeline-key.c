@@ -35,6 +35,7 @@
 static const struct vr_pipeline_key
 base_key = {
         .type = VR_PIPELINE_KEY_TYPE_GRAPHICS,
+        .n_viewports = 1,
         .topology = { .i = VK_PRIMITIVE_TOPOLOGY_TRIANGLE_STRIP },
         .polygonMode = { .i = VK_POLYGON_MODE_FILL },
         .cullMode = { .i = VK_CULL_MODE_NONE },
@@ -72,6 +73,24 @@ struct vr_enum {
 
 #include "vr-enum-table.h"
 
+struct vr_property_enum {
+        const char *property;
+        const char *name;
+        int value;
+};
+
+/* Shorter names that can be used for the values of some properties
+ * instead of the full Vulkan enum name */
+static const struct vr_property_enum
+property_enums[] = {
+        { "cullMode", "none", VK_CULL_MODE_NONE },
+        { "cullMode", "front", VK_CULL_MODE_FRONT_BIT },
+        { "cullMode", "back", VK_CULL_MODE_BACK_BIT },
+        { "cullMode", "front_and_back", VK_CULL_MODE_FRONT_AND_BACK },
+        { "frontFace", "counter_clockwise", VK_FRONT_FACE_COUNTER_CLOCKWISE },
+        { "frontFace", "clockwise", VK_FRONT_FACE_CLOCKWISE },
+};
+
 struct vr_pipeline_key_prop {
         const char *name;
         size_t member_offset;
@@ -139,6 +158,31 @@ vr_pipeline_key_copy(struct vr_pipeline_key *dest,
                 if (src->entrypoints[i])
                         dest->entrypoints[i] = vr_strdup(src->entrypoints[i]);
         }
+
+        if (src->n_specializations > 0) {
+                size_t size = (sizeof src->specializations[0] *
+                               src->n_specializations);
+                dest->specializations = vr_alloc(size);
+                memcpy(dest->specializations, src->specializations, size);
+        }
+}
+
+static bool
+specializations_equal(const struct vr_pipeline_key *a,
+                      const struct vr_pipeline_key *b)
+{
+        if (a->n_specializations != b->n_specializations)
+                return false;
+
+        for (int i = 0; i < a->n_specializations; i++) {
+                if (a->specializations[i].constant_id !=
+                    b->specializations[i].constant_id ||
+                    a->specializations[i].value !=
+                    b->specializations[i].value)
+                        return false;
+        }
+
+        return true;
 }
 
 bool
@@ -161,15 +205,18 @@ vr_pipeline_key_equal(const struct vr_pipeline_key *a,
                                 return false;
                 }
 
-                return true;
+                return specializations_equal(a, b);
 
         case VR_PIPELINE_KEY_TYPE_COMPUTE: {
                 const enum vr_shader_stage stage = VR_SHADER_STAGE_COMPUTE;
 
+                if (a->flags != b->flags)
+                        return false;
+
                 if (strcmp(vr_pipeline_key_get_entrypoint(a, stage),
                            vr_pipeline_key_get_entrypoint(b, stage)))
                         return false;
-                return true;
+                return specializations_equal(a, b);
         }
         }
 
@@ -218,6 +265,39 @@ vr_pipeline_key_get_entrypoint(const struct vr_pipeline_key *key,
                 return "main";
 }
 
+void
+vr_pipeline_key_set_specialization(struct vr_pipeline_key *key,
+                                   uint32_t constant_id,
+                                   uint32_t value)
+{
+        int pos;
+
+        for (pos = 0; pos < key->n_specializations; pos++) {
+                struct vr_pipeline_key_specialization *spec =
+                        key->specializations + pos;
+
+                if (spec->constant_id == constant_id) {
+                        spec->value = value;
+                        return;
+                }
+
+                if (spec->constant_id > constant_id)
+                        break;
+        }
+
+        key->specializations =
+                vr_realloc(key->specializations,
+                           sizeof key->specializations[0] *
+                           (key->n_specializations + 1));
+        memmove(key->specializations + pos + 1,
+                key->specializations + pos,
+                sizeof key->specializations[0] *
+                (key->n_specializations - pos));
+        key->specializations[pos].constant_id = constant_id;
+        key->specializations[pos].value = value;
+        key->n_specializations++;
+}
+
 static size_t
 type_size(enum vr_pipeline_key_value_type type)
 {
@@ -257,6 +337,8 @@ vr_pipeline_key_to_create_info(const struct vr_pipeline_key *key,
                                type_size(prop->type));
                 }
         }
+
+        create_info->flags |= key->flags;
 }
 
 bool
@@ -282,9 +364,26 @@ vr_pipeline_key_lookup_enum(const char *name,
         return false;
 }
 
+bool
+vr_pipeline_key_lookup_property_enum(const char *property,
+                                     const char *name,
+                                     int *value)
+{
+        for (int i = 0; i < VR_N_ELEMENTS(property_enums); i++) {
+                if (!strcmp(property_enums[i].property, property) &&
+                    !strcmp(property_enums[i].name, name)) {
+                        *value = property_enums[i].value;
+                        return true;
+                }
+        }
+
+        return vr_pipeline_key_lookup_enum(name, value);
+}
+
 void
 vr_pipeline_key_destroy(struct vr_pipeline_key *key)
 {
         for (int i = 0; i < VR_SHADER_STAGE_N_STAGES; i++)
                 vr_free(key->entrypoints[i]);
+        vr_free(key->specializations);
 }
@@ -51,9 +51,27 @@ enum vr_pipeline_key_source {
         VR_PIPELINE_KEY_SOURCE_VERTEX_DATA
 };
 
+/* A value for a specialization constant. All of the supported types
+ * are 32-bit so the value is just stored as the raw bits. */
+struct vr_pipeline_key_specialization {
+        uint32_t constant_id;
+        uint32_t value;
+};
+
 struct vr_pipeline_key {
         enum vr_pipeline_key_type type;
         enum vr_pipeline_key_source source;
+        /* Number of viewports and scissors. They all use the same
+         * rectangles below. */
+        int n_viewports;
+        /* The viewport and scissor set with the viewport and scissor
+         * commands. If the width is zero then they cover the whole
+         * framebuffer. */
+        VkViewport viewport;
+        VkRect2D scissor;
+        /* Extra VkPipelineCreateFlags set with the pipelineFlags
+         * command */
+        int flags;
 
 #define VR_PIPELINE_STRUCT_BEGIN(m)
 #define VR_PIPELINE_STRUCT_BEGIN2(m1, s2, m2)
@@ -67,9 +85,13 @@ struct vr_pipeline_key {
 #undef VR_PIPELINE_PROP_NAME
 #undef VR_PIPELINE_STRUCT_END
 
-        /* This must be the last entry so that the rest can be
+        /* These must be the last entries so that the rest can be
          * compared with a simple memcmp in vr_pipeline_key_equal */
         char *entrypoints[VR_SHADER_STAGE_N_STAGES];
+        /* Specialization constants sorted by constant ID. They are
+         * used for every stage. */
+        struct vr_pipeline_key_specialization *specializations;
+        int n_specializations;
 };
 
 void
@@ -96,6 +118,11 @@ const char *
 vr_pipeline_key_get_entrypoint(const struct vr_pipeline_key *key,
                                enum vr_shader_stage stage);
 
+void
+vr_pipeline_key_set_specialization(struct vr_pipeline_key *key,
+                                   uint32_t constant_id,
+                                   uint32_t value);
+
 union vr_pipeline_key_value *
 vr_pipeline_key_lookup(struct vr_pipeline_key *key,
                        const char *name,
@@ -105,6 +132,14 @@ bool
 vr_pipeline_key_lookup_enum(const char *name,
                             int *value);
 
+/* Looks up an enum value for the given property. This accepts the
+ * short names of the property’s values, such as “back” for cullMode,
+ * as well as any Vulkan enum name. */
+bool
+vr_pipeline_key_lookup_property_enum(const char *property,
+                                     const char *name,
+                                     int *value);
+
 void
 vr_pipeline_key_to_create_info(const struct vr_pipeline_key *key,
                                VkGraphicsPipelineCreateInfo *create_info);
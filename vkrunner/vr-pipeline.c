@@ -33,6 +33,7 @@
 #include "vr-buffer.h"
 #include "vr-temp-file.h"
 #include "vr-format-private.h"
+#include "vr-reflect.h"
 
 #include <stddef.h>
 #include <stdio.h>
@@ -40,6 +41,7 @@
 #include <assert.h>
 #include <string.h>
 #include <limits.h>
+#include <inttypes.h>
 #ifndef WIN32
 #include <unistd.h>
 #endif
@@ -58,6 +60,8 @@ stage_names[VR_SHADER_STAGE_N_STAGES] = {
         [VR_SHADER_STAGE_GEOMETRY] = "geom",
         [VR_SHADER_STAGE_FRAGMENT] = "frag",
         [VR_SHADER_STAGE_COMPUTE] = "comp",
+        [VR_SHADER_STAGE_TASK] = "task",
+        [VR_SHADER_STAGE_MESH] = "mesh",
 };
 
 static const VkPipelineMultisampleStateCreateInfo
@@ -130,7 +134,7 @@ show_disassembly(const struct vr_config *config,
         if (args[0] == NULL)
                 args[0] = "spirv-dis";
 
-        return vr_subprocess_command(config, args);
+        return vr_subprocess_command_info(config, args);
 }
 
 static VkShaderModule
@@ -157,6 +161,11 @@ compile_stage(const struct vr_config *config,
         sprintf(version_str, "vulkan%u.%u", VK_VERSION_MAJOR(version),
                 VK_VERSION_MINOR(version));
 
+        const char *target_env = script->target_envs[stage];
+
+        if (target_env == NULL)
+                target_env = version_str;
+
         memset(args + n_base_args, 0, (n_shaders + 1) * sizeof args[0]);
 
         if (!vr_temp_file_create_named(config,
@@ -170,7 +179,7 @@ compile_stage(const struct vr_config *config,
 
         args[1] = "-V";
         args[2] = "--target-env";
-        args[3] = version_str;
+        args[3] = (char *) target_env;
         args[4] = "-S";
         args[5] = (char *) stage_names[stage];
         args[6] = "-o";
@@ -199,6 +208,12 @@ compile_stage(const struct vr_config *config,
                                   &module_size))
                 goto out;
 
+        if (config->show_reflection) {
+                vr_reflect_show(config,
+                                (const uint32_t *) module_binary,
+                                module_size);
+        }
+
         VkShaderModuleCreateInfo shader_module_create_info = {
                         .sType = VK_STRUCTURE_TYPE_SHADER_MODULE_CREATE_INFO,
                         .codeSize = module_size,
@@ -238,6 +253,7 @@ static VkShaderModule
 assemble_stage(const struct vr_config *config,
                struct vr_window *window,
                const struct vr_script *script,
+               enum vr_shader_stage stage,
                const struct vr_script_shader *shader)
 {
         struct vr_vk *vkfn = &window->vkfn;
@@ -254,6 +270,11 @@ assemble_stage(const struct vr_config *config,
         sprintf(version_str, "vulkan%u.%u", VK_VERSION_MAJOR(version),
                 VK_VERSION_MINOR(version));
 
+        const char *target_env = script->target_envs[stage];
+
+        if (target_env == NULL)
+                target_env = version_str;
+
         if (!vr_temp_file_create_named(config,
                                        &module_stream,
                                        &module_filename))
@@ -265,7 +286,7 @@ assemble_stage(const struct vr_config *config,
 
         char *args[] = {
                 getenv("PIGLIT_SPIRV_AS_BINARY"),
-                "--target-env", version_str,
+                "--target-env", (char *) target_env,
                 "-o", module_filename,
                 source_filename,
                 NULL
@@ -289,6 +310,12 @@ assemble_stage(const struct vr_config *config,
                                   &module_size))
                 goto out;
 
+        if (config->show_reflection) {
+                vr_reflect_show(config,
+                                (const uint32_t *) module_binary,
+                                module_size);
+        }
+
         VkShaderModuleCreateInfo shader_module_create_info = {
                         .sType = VK_STRUCTURE_TYPE_SHADER_MODULE_CREATE_INFO,
                         .codeSize = module_size,
@@ -350,6 +377,12 @@ load_binary_stage(const struct vr_config *config,
                 }
         }
 
+        if (config->show_reflection) {
+                vr_reflect_show(config,
+                                (const uint32_t *) shader->source,
+                                shader->length);
+        }
+
         VkShaderModuleCreateInfo shader_module_create_info = {
                         .sType = VK_STRUCTURE_TYPE_SHADER_MODULE_CREATE_INFO,
                         .codeSize = shader->length,
@@ -382,7 +415,7 @@ build_stage(const struct vr_config *config,
         case VR_SCRIPT_SOURCE_TYPE_GLSL:
                 return compile_stage(config, window, script, stage);
         case VR_SCRIPT_SOURCE_TYPE_SPIRV:
-                return assemble_stage(config, window, script, shader);
+                return assemble_stage(config, window, script, stage, shader);
         case VR_SCRIPT_SOURCE_TYPE_BINARY:
                 return load_binary_stage(config, window, shader);
         }
@@ -390,6 +423,11 @@ build_stage(const struct vr_config *config,
         vr_fatal("should not be reached");
 }
 
+struct vertex_divisor_state {
+        VkPipelineVertexInputDivisorStateCreateInfoEXT info;
+        VkVertexInputBindingDivisorDescriptionEXT description;
+};
+
 static void
 set_vertex_input_state(const struct vr_script *script,
                        VkPipelineVertexInputStateCreateInfo *state,
@@ -430,6 +468,27 @@ set_vertex_input_state(const struct vr_script *script,
                 return;
         }
 
+        if (script->vertex_data_instanced) {
+                input_binding[0].inputRate = VK_VERTEX_INPUT_RATE_INSTANCE;
+
+                if (script->vertex_data_divisor != 1) {
+                        struct vertex_divisor_state *divisor_state =
+                                vr_calloc(sizeof *divisor_state);
+
+                        divisor_state->description.binding = 0;
+                        divisor_state->description.divisor =
+                                script->vertex_data_divisor;
+
+                        divisor_state->info.sType =
+                                VK_STRUCTURE_TYPE_PIPELINE_VERTEX_INPUT_DIVISOR_STATE_CREATE_INFO_EXT;
+                        divisor_state->info.vertexBindingDivisorCount = 1;
+                        divisor_state->info.pVertexBindingDivisors =
+                                &divisor_state->description;
+
+                        state->pNext = &divisor_state->info;
+                }
+        }
+
         int n_attribs = vr_list_length(&script->vertex_data->attribs);
         VkVertexInputAttributeDescription *attrib_desc =
                 vr_calloc((sizeof *attrib_desc) * n_attribs);
@@ -448,6 +507,43 @@ set_vertex_input_state(const struct vr_script *script,
         };
 }
 
+/* Creates a VkSpecializationInfo for the specialization constants in
+ * the key or returns NULL if there aren’t any. The info and its
+ * arrays are allocated in one block so it can be freed with vr_free.
+ */
+static VkSpecializationInfo *
+create_specialization_info(const struct vr_pipeline_key *key)
+{
+        int n_specs = key->n_specializations;
+
+        if (n_specs == 0)
+                return NULL;
+
+        VkSpecializationInfo *info;
+        VkSpecializationMapEntry *entries;
+        uint32_t *data;
+
+        info = vr_alloc(sizeof *info +
+                        sizeof *entries * n_specs +
+                        sizeof *data * n_specs);
+        entries = (VkSpecializationMapEntry *) (info + 1);
+        data = (uint32_t *) (entries + n_specs);
+
+        for (int i = 0; i < n_specs; i++) {
+                entries[i].constantID = key->specializations[i].constant_id;
+                entries[i].offset = i * sizeof *data;
+                entries[i].size = sizeof *data;
+                data[i] = key->specializations[i].value;
+        }
+
+        info->mapEntryCount = n_specs;
+        info->pMapEntries = entries;
+        info->dataSize = sizeof *data * n_specs;
+        info->pData = data;
+
+        return info;
+}
+
 static VkPipeline
 create_vk_pipeline(struct vr_pipeline *pipeline,
                    const struct vr_script *script,
@@ -463,6 +559,9 @@ create_vk_pipeline(struct vr_pipeline *pipeline,
         VkPipelineShaderStageCreateInfo stages[VR_SHADER_STAGE_N_STAGES];
         memset(&stages, 0, sizeof stages);
 
+        VkSpecializationInfo *specialization_info =
+                create_specialization_info(key);
+
         for (int i = 0; i < VR_SHADER_STAGE_N_STAGES; i++) {
                 if (i == VR_SHADER_STAGE_COMPUTE ||
                     pipeline->modules[i] == VK_NULL_HANDLE)
@@ -473,6 +572,7 @@ create_vk_pipeline(struct vr_pipeline *pipeline,
                 stages[num_stages].module = pipeline->modules[i];
                 stages[num_stages].pName =
                         vr_pipeline_key_get_entrypoint(key, i);
+                stages[num_stages].pSpecializationInfo = specialization_info;
                 num_stages++;
         }
 
@@ -481,25 +581,37 @@ create_vk_pipeline(struct vr_pipeline *pipeline,
                 VK_STRUCTURE_TYPE_PIPELINE_INPUT_ASSEMBLY_STATE_CREATE_INFO
         };
 
-        VkViewport viewports[] = {
-                {
-                        .width = window->format.width,
-                        .height = window->format.height,
-                        .minDepth = 0.0f,
-                        .maxDepth = 1.0f
+        VkViewport *viewports = vr_alloc(sizeof *viewports *
+                                         key->n_viewports);
+        VkRect2D *scissors = vr_alloc(sizeof *scissors * key->n_viewports);
+
+        for (int i = 0; i < key->n_viewports; i++) {
+                if (key->viewport.width > 0.0f) {
+                        viewports[i] = key->viewport;
+                } else {
+                        viewports[i] = (VkViewport) {
+                                .width = window->format.width,
+                                .height = window->format.height,
+                                .minDepth = 0.0f,
+                                .maxDepth = 1.0f
+                        };
                 }
-        };
-        VkRect2D scissors[] = {
-                {
-                        .extent = { window->format.width,
-                                    window->format.height }
+
+                if (key->scissor.extent.width > 0) {
+                        scissors[i] = key->scissor;
+                } else {
+                        scissors[i] = (VkRect2D) {
+                                .extent = { window->format.width,
+                                            window->format.height }
+                        };
                 }
-        };
+        }
+
         VkPipelineViewportStateCreateInfo viewport_state = {
                 .sType = VK_STRUCTURE_TYPE_PIPELINE_VIEWPORT_STATE_CREATE_INFO,
-                .viewportCount = VR_N_ELEMENTS(viewports),
+                .viewportCount = key->n_viewports,
                 .pViewports = viewports,
-                .scissorCount = VR_N_ELEMENTS(scissors),
+                .scissorCount = key->n_viewports,
                 .pScissors = scissors
         };
 
@@ -516,7 +628,8 @@ create_vk_pipeline(struct vr_pipeline *pipeline,
                 VK_STRUCTURE_TYPE_PIPELINE_TESSELLATION_STATE_CREATE_INFO
         };
 
-        VkPipelineColorBlendAttachmentState blend_attachments[] = {
+        VkPipelineColorBlendAttachmentState
+                blend_attachments[VR_WINDOW_MAX_COLOR_ATTACHMENTS] = {
                 {
                         .blendEnable = false,
                 }
@@ -530,7 +643,7 @@ create_vk_pipeline(struct vr_pipeline *pipeline,
         VkPipelineColorBlendStateCreateInfo color_blend_state = {
                 .sType =
                 VK_STRUCTURE_TYPE_PIPELINE_COLOR_BLEND_STATE_CREATE_INFO,
-                .attachmentCount = VR_N_ELEMENTS(blend_attachments),
+                .attachmentCount = window->format.n_color_attachments,
                 .pAttachments = blend_attachments
         };
 
@@ -556,6 +669,11 @@ create_vk_pipeline(struct vr_pipeline *pipeline,
 
         vr_pipeline_key_to_create_info(key, &info);
 
+        /* The key only describes the blend state of the first
+         * attachment so use the same state for all of them */
+        for (int i = 1; i < window->format.n_color_attachments; i++)
+                blend_attachments[i] = blend_attachments[0];
+
         if (allow_derivatives)
                 info.flags |= VK_PIPELINE_CREATE_ALLOW_DERIVATIVES_BIT;
         if (parent_pipeline)
@@ -565,6 +683,13 @@ create_vk_pipeline(struct vr_pipeline *pipeline,
                                   VK_SHADER_STAGE_TESSELLATION_EVALUATION_BIT)))
                 info.pTessellationState = NULL;
 
+        /* Mesh pipelines don’t use the vertex input stage */
+        if ((pipeline->stages &
+             (VK_SHADER_STAGE_VERTEX_BIT << VR_SHADER_STAGE_MESH))) {
+                info.pVertexInputState = NULL;
+                info.pInputAssemblyState = NULL;
+        }
+
         VkPipeline vk_pipeline;
 
         res = vkfn->vkCreateGraphicsPipelines(window->device,
@@ -576,6 +701,10 @@ create_vk_pipeline(struct vr_pipeline *pipeline,
 
         vr_free((void *) vertex_input_state.pVertexBindingDescriptions);
         vr_free((void *) vertex_input_state.pVertexAttributeDescriptions);
+        vr_free((void *) vertex_input_state.pNext);
+        vr_free(viewports);
+        vr_free(scissors);
+        vr_free(specialization_info);
 
         if (res != VK_SUCCESS) {
                 vr_error_message(window->config, "Error creating VkPipeline");
@@ -593,6 +722,8 @@ create_compute_pipeline(struct vr_pipeline *pipeline,
         struct vr_vk *vkfn = &window->vkfn;
         const char *entrypoint =
                 vr_pipeline_key_get_entrypoint(key, VR_SHADER_STAGE_COMPUTE);
+        VkSpecializationInfo *specialization_info =
+                create_specialization_info(key);
         VkResult res;
 
         VkComputePipelineCreateInfo info = {
@@ -604,8 +735,10 @@ create_compute_pipeline(struct vr_pipeline *pipeline,
                         .stage = VK_SHADER_STAGE_COMPUTE_BIT,
                         .module =
                         pipeline->modules[VR_SHADER_STAGE_COMPUTE],
-                        .pName = entrypoint
+                        .pName = entrypoint,
+                        .pSpecializationInfo = specialization_info
                 },
+                .flags = key->flags,
                 .layout = pipeline->layout,
                 .basePipelineHandle = VK_NULL_HANDLE,
                 .basePipelineIndex = -1,
@@ -620,6 +753,8 @@ create_compute_pipeline(struct vr_pipeline *pipeline,
                                              NULL, /* allocator */
                                              &vk_pipeline);
 
+        vr_free(specialization_info);
+
         if (res != VK_SUCCESS) {
                 vr_error_message(window->config, "Error creating VkPipeline");
                 return VK_NULL_HANDLE;
@@ -839,6 +974,242 @@ error:
         return ret;
 }
 
+static bool
+check_vertex_attribs(const struct vr_config *config,
+                     struct vr_window *window,
+                     const struct vr_script *script)
+{
+        if (script->vertex_data == NULL)
+                return true;
+
+        const VkPhysicalDeviceLimits *limits =
+                &window->context->device_properties.limits;
+        int n_attribs = vr_list_length(&script->vertex_data->attribs);
+
+        if ((uint32_t) n_attribs > limits->maxVertexInputAttributes) {
+                vr_error_message(config,
+                                 "The vertex data has %i attributes but the "
+                                 "device only supports %u "
+                                 "(maxVertexInputAttributes)",
+                                 n_attribs,
+                                 (unsigned) limits->maxVertexInputAttributes);
+                return false;
+        }
+
+        return true;
+}
+
+static bool
+check_ubo_sizes(const struct vr_config *config,
+                struct vr_window *window,
+                const struct vr_script *script)
+{
+        const VkPhysicalDeviceLimits *limits =
+                &window->context->device_properties.limits;
+
+        for (size_t i = 0; i < script->n_buffers; i++) {
+                const struct vr_script_buffer *buffer = script->buffers + i;
+
+                if (buffer->type != VR_SCRIPT_BUFFER_TYPE_UBO ||
+                    buffer->size <= limits->maxUniformBufferRange)
+                        continue;
+
+                vr_error_message(config,
+                                 "The UBO at binding %u:%u is %lu bytes but "
+                                 "the device only supports %u "
+                                 "(maxUniformBufferRange). Consider using "
+                                 "an SSBO instead.",
+                                 buffer->desc_set,
+                                 buffer->binding,
+                                 (unsigned long) buffer->size,
+                                 (unsigned) limits->maxUniformBufferRange);
+                return false;
+        }
+
+        return true;
+}
+
+static bool
+check_ssbo_count(const struct vr_config *config,
+                 struct vr_window *window,
+                 const struct vr_script *script)
+{
+        const VkPhysicalDeviceLimits *limits =
+                &window->context->device_properties.limits;
+        unsigned n_ssbos = 0;
+
+        for (size_t i = 0; i < script->n_buffers; i++) {
+                if (script->buffers[i].type == VR_SCRIPT_BUFFER_TYPE_SSBO)
+                        n_ssbos++;
+        }
+
+        /* Every binding is visible to all of the stages in the
+         * pipeline so each stage uses all of the SSBOs */
+        if (n_ssbos <= limits->maxPerStageDescriptorStorageBuffers)
+                return true;
+
+        vr_error_message(config,
+                         "The script uses %u SSBOs but the device only "
+                         "supports %u per stage "
+                         "(maxPerStageDescriptorStorageBuffers). Use "
+                         "“maxstoragebuffers” in the [require] section "
+                         "to skip the test instead.",
+                         n_ssbos,
+                         (unsigned) limits->maxPerStageDescriptorStorageBuffers);
+
+        return false;
+}
+
+#ifdef VK_KHR_PIPELINE_EXECUTABLE_PROPERTIES_EXTENSION_NAME
+
+static void
+append_statistic(struct vr_buffer *report,
+                 const VkPipelineExecutableStatisticKHR *stat)
+{
+        vr_buffer_append_printf(report, "    %s: ", stat->name);
+
+        switch (stat->format) {
+        case VK_PIPELINE_EXECUTABLE_STATISTIC_FORMAT_BOOL32_KHR:
+                vr_buffer_append_string(report,
+                                        stat->value.b32 ? "true" : "false");
+                break;
+        case VK_PIPELINE_EXECUTABLE_STATISTIC_FORMAT_INT64_KHR:
+                vr_buffer_append_printf(report,
+                                        "%" PRIi64,
+                                        stat->value.i64);
+                break;
+        case VK_PIPELINE_EXECUTABLE_STATISTIC_FORMAT_UINT64_KHR:
+                vr_buffer_append_printf(report,
+                                        "%" PRIu64,
+                                        stat->value.u64);
+                break;
+        case VK_PIPELINE_EXECUTABLE_STATISTIC_FORMAT_FLOAT64_KHR:
+                vr_buffer_append_printf(report, "%g", stat->value.f64);
+                break;
+        default:
+                vr_buffer_append_string(report, "?");
+                break;
+        }
+
+        vr_buffer_append_c(report, '\n');
+}
+
+static void
+append_executable_stats(struct vr_window *window,
+                        struct vr_buffer *report,
+                        VkPipeline vk_pipeline,
+                        uint32_t executable_index,
+                        const VkPipelineExecutablePropertiesKHR *props)
+{
+        struct vr_vk *vkfn = &window->vkfn;
+        VkResult res;
+
+        vr_buffer_append_printf(report, "  %s:\n", props->name);
+
+        VkPipelineExecutableInfoKHR info = {
+                .sType = VK_STRUCTURE_TYPE_PIPELINE_EXECUTABLE_INFO_KHR,
+                .pipeline = vk_pipeline,
+                .executableIndex = executable_index,
+        };
+        uint32_t n_stats = 0;
+
+        res = vkfn->vkGetPipelineExecutableStatisticsKHR(window->device,
+                                                         &info,
+                                                         &n_stats,
+                                                         NULL);
+        if (res != VK_SUCCESS)
+                return;
+
+        VkPipelineExecutableStatisticKHR *stats =
+                vr_calloc(n_stats * sizeof *stats);
+
+        for (uint32_t i = 0; i < n_stats; i++) {
+                stats[i].sType =
+                        VK_STRUCTURE_TYPE_PIPELINE_EXECUTABLE_STATISTIC_KHR;
+        }
+
+        res = vkfn->vkGetPipelineExecutableStatisticsKHR(window->device,
+                                                         &info,
+                                                         &n_stats,
+                                                         stats);
+        if (res == VK_SUCCESS || res == VK_INCOMPLETE) {
+                for (uint32_t i = 0; i < n_stats; i++)
+                        append_statistic(report, stats + i);
+        }
+
+        vr_free(stats);
+}
+
+static void
+show_pipeline_stats(const struct vr_config *config,
+                    struct vr_window *window,
+                    int pipeline_num,
+                    VkPipeline vk_pipeline)
+{
+        struct vr_vk *vkfn = &window->vkfn;
+        VkResult res;
+
+        if (vkfn->vkGetPipelineExecutablePropertiesKHR == NULL ||
+            vkfn->vkGetPipelineExecutableStatisticsKHR == NULL)
+                return;
+
+        VkPipelineInfoKHR pipeline_info = {
+                .sType = VK_STRUCTURE_TYPE_PIPELINE_INFO_KHR,
+                .pipeline = vk_pipeline,
+        };
+        uint32_t n_executables = 0;
+
+        res = vkfn->vkGetPipelineExecutablePropertiesKHR(window->device,
+                                                         &pipeline_info,
+                                                         &n_executables,
+                                                         NULL);
+        if (res != VK_SUCCESS)
+                return;
+
+        VkPipelineExecutablePropertiesKHR *props =
+                vr_calloc(n_executables * sizeof *props);
+
+        for (uint32_t i = 0; i < n_executables; i++) {
+                props[i].sType =
+                        VK_STRUCTURE_TYPE_PIPELINE_EXECUTABLE_PROPERTIES_KHR;
+        }
+
+        res = vkfn->vkGetPipelineExecutablePropertiesKHR(window->device,
+                                                         &pipeline_info,
+                                                         &n_executables,
+                                                         props);
+
+        if (res == VK_SUCCESS || res == VK_INCOMPLETE) {
+                struct vr_buffer report = VR_BUFFER_STATIC_INIT;
+
+                vr_buffer_append_printf(&report,
+                                        "Pipeline %i statistics:",
+                                        pipeline_num);
+
+                for (uint32_t i = 0; i < n_executables; i++) {
+                        vr_buffer_append_c(&report, '\n');
+                        append_executable_stats(window,
+                                                &report,
+                                                vk_pipeline,
+                                                i,
+                                                props + i);
+                        /* Remove the trailing newline */
+                        report.length--;
+                }
+
+                vr_buffer_append_c(&report, '\0');
+
+                vr_info_message_string(config,
+                                       (const char *) report.data);
+
+                vr_buffer_destroy(&report);
+        }
+
+        vr_free(props);
+}
+
+#endif /* VK_KHR_PIPELINE_EXECUTABLE_PROPERTIES_EXTENSION_NAME */
+
 struct vr_pipeline *
 vr_pipeline_create(const struct vr_config *config,
                    struct vr_window *window,
@@ -850,6 +1221,15 @@ vr_pipeline_create(const struct vr_config *config,
 
         pipeline->window = window;
 
+        if (!check_vertex_attribs(config, window, script))
+                goto error;
+
+        if (!check_ubo_sizes(config, window, script))
+                goto error;
+
+        if (!check_ssbo_count(config, window, script))
+                goto error;
+
         for (int i = 0; i < VR_SHADER_STAGE_N_STAGES; i++) {
                 if (vr_list_empty(&script->stages[i]))
                         continue;
@@ -859,16 +1239,22 @@ vr_pipeline_create(const struct vr_config *config,
                         goto error;
         }
 
-        VkPipelineCacheCreateInfo pipeline_cache_create_info = {
-                .sType = VK_STRUCTURE_TYPE_PIPELINE_CACHE_CREATE_INFO
-        };
-        res = vkfn->vkCreatePipelineCache(window->device,
-                                          &pipeline_cache_create_info,
-                                          NULL, /* allocator */
-                                          &pipeline->pipeline_cache);
-        if (res != VK_SUCCESS) {
-                vr_error_message(config, "Error creating pipeline cache");
-                goto error;
+        if (window->context->pipeline_cache) {
+                /* Use the cache that is persisted to a file */
+                pipeline->pipeline_cache = window->context->pipeline_cache;
+        } else {
+                VkPipelineCacheCreateInfo pipeline_cache_create_info = {
+                        .sType = VK_STRUCTURE_TYPE_PIPELINE_CACHE_CREATE_INFO
+                };
+                res = vkfn->vkCreatePipelineCache(window->device,
+                                                  &pipeline_cache_create_info,
+                                                  NULL, /* allocator */
+                                                  &pipeline->pipeline_cache);
+                if (res != VK_SUCCESS) {
+                        vr_error_message(config,
+                                         "Error creating pipeline cache");
+                        goto error;
+                }
         }
 
         pipeline->stages = get_script_stages(script);
@@ -916,6 +1302,17 @@ vr_pipeline_create(const struct vr_config *config,
 
                 if (pipeline->pipelines[i] == VK_NULL_HANDLE)
                         goto error;
+
+#ifdef VK_KHR_PIPELINE_EXECUTABLE_PROPERTIES_EXTENSION_NAME
+                if (config->show_pipeline_stats &&
+                    (keys[i].flags &
+                     VK_PIPELINE_CREATE_CAPTURE_STATISTICS_BIT_KHR)) {
+                        show_pipeline_stats(config,
+                                            window,
+                                            i,
+                                            pipeline->pipelines[i]);
+                }
+#endif
         }
 
         return pipeline;
@@ -940,7 +1337,8 @@ vr_pipeline_free(struct vr_pipeline *pipeline)
         }
         vr_free(pipeline->pipelines);
 
-        if (pipeline->pipeline_cache) {
+        if (pipeline->pipeline_cache &&
+            pipeline->pipeline_cache != window->context->pipeline_cache) {
                 vkfn->vkDestroyPipelineCache(window->device,
                                              pipeline->pipeline_cache,
                                              NULL /* allocator */);
@@ -0,0 +1,578 @@
+/*
+ * vkrunner
+ *
+ * Copyright (C) 2019 Intel Corporation
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a
+ * copy of this software and associated documentation files (the "Software"),
+ * to deal in the Software without restriction, including without limitation
+ * the rights to use, copy, modify, merge, publish, distribute, sublicense,
+ * and/or sell copies of the Software, and to permit persons to whom the
+ * Software is furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice (including the next
+ * paragraph) shall be included in all copies or substantial portions of the
+ * Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.  IN NO EVENT SHALL
+ * THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+ * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+ * DEALINGS IN THE SOFTWARE.
+ */
+
+#include "config.h"
+
+#include <string.h>
+
+#include "vr-reflect.h"
+#include "vr-buffer.h"
+#include "vr-error-message.h"
+#include "vr-util.h"
+
+#define SPIRV_MAGIC 0x07230203
+#define HEADER_SIZE 5
+
+/* Structs can’t be recursive in SPIR-V but a broken module could
+ * still make the size calculation loop forever so give up after
+ * this many levels.
+ */
+#define MAX_TYPE_DEPTH 32
+
+enum spirv_op {
+        OP_NAME = 5,
+        OP_ENTRY_POINT = 15,
+        OP_TYPE_INT = 21,
+        OP_TYPE_FLOAT = 22,
+        OP_TYPE_VECTOR = 23,
+        OP_TYPE_MATRIX = 24,
+        OP_TYPE_ARRAY = 28,
+        OP_TYPE_RUNTIME_ARRAY = 29,
+        OP_TYPE_STRUCT = 30,
+        OP_TYPE_POINTER = 32,
+        OP_CONSTANT = 43,
+        OP_VARIABLE = 59,
+        OP_DECORATE = 71,
+        OP_MEMBER_DECORATE = 72,
+};
+
+enum spirv_decoration {
+        DECORATION_BUFFER_BLOCK = 3,
+        DECORATION_ROW_MAJOR = 4,
+        DECORATION_ARRAY_STRIDE = 6,
+        DECORATION_MATRIX_STRIDE = 7,
+        DECORATION_BINDING = 33,
+        DECORATION_DESCRIPTOR_SET = 34,
+        DECORATION_OFFSET = 35,
+};
+
+enum spirv_storage_class {
+        STORAGE_CLASS_UNIFORM_CONSTANT = 0,
+        STORAGE_CLASS_UNIFORM = 2,
+        STORAGE_CLASS_PUSH_CONSTANT = 9,
+        STORAGE_CLASS_STORAGE_BUFFER = 12,
+};
+
+struct id_info {
+        /* The instruction that defines the id, if it is a type,
+         * constant or variable */
+        const uint32_t *insn;
+        const char *name;
+        bool has_binding;
+        bool has_desc_set;
+        bool is_buffer_block;
+        uint32_t binding;
+        uint32_t desc_set;
+        uint32_t array_stride;
+};
+
+struct reflect_data {
+        const uint32_t *words;
+        size_t n_words;
+        uint32_t bound;
+        struct id_info *ids;
+        struct vr_buffer report;
+};
+
+static const struct {
+        uint32_t model;
+        const char *name;
+} execution_models[] = {
+        { 0, "vertex" },
+        { 1, "tessellation control" },
+        { 2, "tessellation evaluation" },
+        { 3, "geometry" },
+        { 4, "fragment" },
+        { 5, "compute" },
+        { 5267, "task (NV)" },
+        { 5268, "mesh (NV)" },
+        { 5364, "task" },
+        { 5365, "mesh" },
+};
+
+static struct id_info *
+get_id(struct reflect_data *data,
+       uint32_t id)
+{
+        if (id >= data->bound)
+                return NULL;
+
+        return data->ids + id;
+}
+
+static const uint32_t *
+get_type(struct reflect_data *data,
+         uint32_t id)
+{
+        struct id_info *info = get_id(data, id);
+
+        if (info == NULL)
+                return NULL;
+
+        return info->insn;
+}
+
+static const char *
+get_string(const uint32_t *insn,
+           int word)
+{
+        int n_words = insn[0] >> 16;
+
+        if (word >= n_words)
+                return NULL;
+
+        const char *str = (const char *) (insn + word);
+
+        /* Make sure the string is terminated within the instruction */
+        if (memchr(str, '\0', (n_words - word) * sizeof (uint32_t)) == NULL)
+                return NULL;
+
+        return str;
+}
+
+static bool
+get_type_size(struct reflect_data *data,
+              uint32_t type_id,
+              int depth,
+              uint32_t *size_out);
+
+static bool
+get_member_size(struct reflect_data *data,
+                uint32_t struct_id,
+                uint32_t member,
+                uint32_t type_id,
+                int depth,
+                uint32_t *size_out)
+{
+        const uint32_t *type = get_type(data, type_id);
+
+        if (type == NULL)
+                return false;
+
+        if ((type[0] & 0xffff) != OP_TYPE_MATRIX)
+                return get_type_size(data, type_id, depth + 1, size_out);
+
+        /* Matrices use the stride from the member decoration */
+        uint32_t stride = 0;
+        bool row_major = false;
+
+        for (size_t pos = HEADER_SIZE;
+             pos < data->n_words;
+             pos += data->words[pos] >> 16) {
+                const uint32_t *insn = data->words + pos;
+
+                if ((insn[0] & 0xffff) != OP_MEMBER_DECORATE ||
+                    (insn[0] >> 16) < 4 ||
+                    insn[1] != struct_id ||
+                    insn[2] != member)
+                        continue;
+
+                if (insn[3] == DECORATION_MATRIX_STRIDE &&
+                    (insn[0] >> 16) >= 5)
+                        stride = insn[4];
+                else if (insn[3] == DECORATION_ROW_MAJOR)
+                        row_major = true;
+        }
+
+        if (stride == 0)
+                return get_type_size(data, type_id, depth + 1, size_out);
+
+        if (row_major) {
+                const uint32_t *column_type = get_type(data, type[2]);
+
+                if (column_type == NULL ||
+                    (column_type[0] & 0xffff) != OP_TYPE_VECTOR)
+                        return false;
+
+                *size_out = column_type[3] * stride;
+        } else {
+                *size_out = type[3] * stride;
+        }
+
+        return true;
+}
+
+static bool
+get_struct_size(struct reflect_data *data,
+                const uint32_t *type,
+                int depth,
+                uint32_t *offset_out,
+                uint32_t *size_out)
+{
+        uint32_t struct_id = type[1];
+        uint32_t n_members = (type[0] >> 16) - 2;
+        uint32_t start = UINT32_MAX, end = 0;
+
+        for (size_t pos = HEADER_SIZE;
+             pos < data->n_words;
+             pos += data->words[pos] >> 16) {
+                const uint32_t *insn = data->words + pos;
+
+                if ((insn[0] & 0xffff) != OP_MEMBER_DECORATE ||
+                    (insn[0] >> 16) < 5 ||
+                    insn[1] != struct_id ||
+                    insn[3] != DECORATION_OFFSET ||
+                    insn[2] >= n_members)
+                        continue;
+
+                uint32_t member_size;
+
+                if (!get_member_size(data,
+                                     struct_id,
+                                     insn[2],
+                                     type[insn[2] + 2],
+                                     depth,
+                                     &member_size))
+                        return false;
+
+                start = MIN(start, insn[4]);
+                end = MAX(end, insn[4] + member_size);
+        }
+
+        if (start > end)
+                start = end;
+
+        if (offset_out)
+                *offset_out = start;
+        *size_out = end;
+
+        return true;
+}
+
+static bool
+get_type_size(struct reflect_data *data,
+              uint32_t type_id,
+              int depth,
+              uint32_t *size_out)
+{
+        const uint32_t *type = get_type(data, type_id);
+
+        if (type == NULL || depth > MAX_TYPE_DEPTH)
+                return false;
+
+        uint32_t element_size;
+
+        switch (type[0] & 0xffff) {
+        case OP_TYPE_INT:
+        case OP_TYPE_FLOAT:
+                *size_out = type[2] / 8;
+                return true;
+        case OP_TYPE_VECTOR:
+        case OP_TYPE_MATRIX:
+                if (!get_type_size(data, type[2], depth + 1, &element_size))
+                        return false;
+                *size_out = element_size * type[3];
+                return true;
+        case OP_TYPE_ARRAY: {
+                const uint32_t *length = get_type(data, type[3]);
+
+                if (length == NULL ||
+                    (length[0] & 0xffff) != OP_CONSTANT ||
+                    (length[0] >> 16) < 4)
+                        return false;
+
+                uint32_t stride = get_id(data, type_id)->array_stride;
+
+                if (stride == 0 &&
+                    !get_type_size(data, type[2], depth + 1, &stride))
+                        return false;
+
+                *size_out = stride * length[3];
+                return true;
+        }
+        case OP_TYPE_RUNTIME_ARRAY:
+                *size_out = 0;
+                return true;
+        case OP_TYPE_STRUCT:
+                return get_struct_size(data, type, depth, NULL, size_out);
+        }
+
+        return false;
+}
+
+static bool
+scan_instructions(struct reflect_data *data)
+{
+        size_t pos = HEADER_SIZE;
+
+        while (pos < data->n_words) {
+                const uint32_t *insn = data->words + pos;
+                uint32_t n_words = insn[0] >> 16;
+                struct id_info *info;
+
+                if (n_words < 1 || pos + n_words > data->n_words)
+                        return false;
+
+                switch (insn[0] & 0xffff) {
+                case OP_NAME:
+                        if (n_words < 3 || !(info = get_id(data, insn[1])))
+                                break;
+                        info->name = get_string(insn, 2);
+                        break;
+                case OP_TYPE_STRUCT:
+                        if (n_words < 2 || !(info = get_id(data, insn[1])))
+                                break;
+                        info->insn = insn;
+                        break;
+                case OP_TYPE_INT:
+                case OP_TYPE_FLOAT:
+                case OP_TYPE_RUNTIME_ARRAY:
+                        if (n_words < 3 || !(info = get_id(data, insn[1])))
+                                break;
+                        info->insn = insn;
+                        break;
+                case OP_TYPE_VECTOR:
+                case OP_TYPE_MATRIX:
+                case OP_TYPE_ARRAY:
+                case OP_TYPE_POINTER:
+                        if (n_words < 4 || !(info = get_id(data, insn[1])))
+                                break;
+                        info->insn = insn;
+                        break;
+                case OP_CONSTANT:
+                case OP_VARIABLE:
+                        if (n_words < 4 || !(info = get_id(data, insn[2])))
+                                break;
+                        info->insn = insn;
+                        break;
+                case OP_DECORATE:
+                        if (n_words < 3 || !(info = get_id(data, insn[1])))
+                                break;
+                        switch (insn[2]) {
+                        case DECORATION_BUFFER_BLOCK:
+                                info->is_buffer_block = true;
+                                break;
+                        case DECORATION_ARRAY_STRIDE:
+                                if (n_words >= 4)
+                                        info->array_stride = insn[3];
+                                break;
+                        case DECORATION_BINDING:
+                                if (n_words >= 4) {
+                                        info->has_binding = true;
+                                        info->binding = insn[3];
+                                }
+                                break;
+                        case DECORATION_DESCRIPTOR_SET:
+                                if (n_words >= 4) {
+                                        info->has_desc_set = true;
+                                        info->desc_set = insn[3];
+                                }
+                                break;
+                        }
+                        break;
+                }
+
+                pos += n_words;
+        }
+
+        return true;
+}
+
+static void
+report_entry_points(struct reflect_data *data)
+{
+        for (size_t pos = HEADER_SIZE;
+             pos < data->n_words;
+             pos += data->words[pos] >> 16) {
+                const uint32_t *insn = data->words + pos;
+
+                if ((insn[0] & 0xffff) != OP_ENTRY_POINT ||
+                    (insn[0] >> 16) < 4)
+                        continue;
+
+                const char *name = get_string(insn, 3);
+                const char *model = "unknown";
+
+                for (int i = 0; i < VR_N_ELEMENTS(execution_models); i++) {
+                        if (execution_models[i].model == insn[1]) {
+                                model = execution_models[i].name;
+                                break;
+                        }
+                }
+
+                vr_buffer_append_printf(&data->report,
+                                        "  entry point: %s (%s)\n",
+                                        name ? name : "?",
+                                        model);
+        }
+}
+
+static const char *
+get_variable_name(struct reflect_data *data,
+                  const uint32_t *variable)
+{
+        const char *name = get_id(data, variable[2])->name;
+
+        if (name && *name)
+                return name;
+
+        /* Buffer blocks without an instance name can use the name of
+         * the block type instead */
+        const uint32_t *pointer = get_type(data, variable[1]);
+
+        if (pointer && (pointer[0] & 0xffff) == OP_TYPE_POINTER) {
+                struct id_info *type = get_id(data, pointer[3]);
+
+                if (type && type->name && *type->name)
+                        return type->name;
+        }
+
+        return "?";
+}
+
+static const char *
+get_descriptor_type_name(struct reflect_data *data,
+                         const uint32_t *variable)
+{
+        switch (variable[3]) {
+        case STORAGE_CLASS_UNIFORM_CONSTANT:
+                return "uniform constant";
+        case STORAGE_CLASS_STORAGE_BUFFER:
+                return "storage buffer";
+        case STORAGE_CLASS_UNIFORM:
+                break;
+        default:
+                return "unknown";
+        }
+
+        /* Old-style SSBOs use the Uniform storage class with a
+         * BufferBlock decoration on the struct */
+        const uint32_t *pointer = get_type(data, variable[1]);
+
+        if (pointer && (pointer[0] & 0xffff) == OP_TYPE_POINTER) {
+                struct id_info *type = get_id(data, pointer[3]);
+
+                if (type && type->is_buffer_block)
+                        return "storage buffer";
+        }
+
+        return "uniform buffer";
+}
+
+static void
+report_push_constants(struct reflect_data *data,
+                      const uint32_t *variable)
+{
+        const uint32_t *pointer = get_type(data, variable[1]);
+        const uint32_t *type = NULL;
+        uint32_t offset, end;
+
+        if (pointer && (pointer[0] & 0xffff) == OP_TYPE_POINTER)
+                type = get_type(data, pointer[3]);
+
+        vr_buffer_append_printf(&data->report,
+                                "  push constants: %s",
+                                get_variable_name(data, variable));
+
+        if (type &&
+            (type[0] & 0xffff) == OP_TYPE_STRUCT &&
+            get_struct_size(data, type, 0, &offset, &end)) {
+                vr_buffer_append_printf(&data->report,
+                                        ", offset %u, size %u\n",
+                                        offset,
+                                        end - offset);
+        } else {
+                vr_buffer_append_string(&data->report,
+                                        ", unknown size\n");
+        }
+}
+
+static void
+report_variables(struct reflect_data *data)
+{
+        for (uint32_t id = 0; id < data->bound; id++) {
+                const struct id_info *info = data->ids + id;
+                const uint32_t *insn = info->insn;
+
+                if (insn == NULL || (insn[0] & 0xffff) != OP_VARIABLE)
+                        continue;
+
+                if (insn[3] == STORAGE_CLASS_PUSH_CONSTANT) {
+                        report_push_constants(data, insn);
+                        continue;
+                }
+
+                if (!info->has_binding)
+                        continue;
+
+                vr_buffer_append_printf(&data->report,
+                                        "  set %u binding %u: %s (%s)\n",
+                                        info->desc_set,
+                                        info->binding,
+                                        get_variable_name(data, insn),
+                                        get_descriptor_type_name(data, insn));
+        }
+}
+
+void
+vr_reflect_show(const struct vr_config *config,
+                const uint32_t *words,
+                size_t size)
+{
+        struct reflect_data data = {
+                .words = words,
+                .n_words = size / sizeof (uint32_t),
+                .report = VR_BUFFER_STATIC_INIT,
+        };
+
+        if (data.n_words < HEADER_SIZE || words[0] != SPIRV_MAGIC) {
+                vr_error_message(config,
+                                 "SPIR-V reflection: invalid module header");
+                return;
+        }
+
+        data.bound = words[3];
+
+        /* Every ID has to be the result of an instruction so a valid
+         * module can’t have a bound larger than its size. Checking
+         * this avoids a huge allocation for a corrupt header. */
+        if (data.bound > data.n_words) {
+                vr_error_message(config,
+                                 "SPIR-V reflection: invalid ID bound");
+                return;
+        }
+
+        data.ids = vr_calloc(data.bound * sizeof *data.ids);
+
+        if (scan_instructions(&data)) {
+                vr_buffer_append_string(&data.report, "SPIR-V reflection:\n");
+                report_entry_points(&data);
+                report_variables(&data);
+
+                /* Remove the trailing newline because the info
+                 * message will add its own */
+                data.report.length--;
+                vr_buffer_append_c(&data.report, '\0');
+
+                vr_info_message_string(config,
+                                       (const char *) data.report.data);
+        } else {
+                vr_error_message(config,
+                                 "SPIR-V reflection: invalid instruction");
+        }
+
+        vr_buffer_destroy(&data.report);
+        vr_free(data.ids);
+}
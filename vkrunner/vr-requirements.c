@@ -30,6 +30,8 @@
 #include <stdbool.h>
 #include <assert.h>
 #include <string.h>
+#include <stdarg.h>
+#include <inttypes.h>
 
 #include "vr-util.h"
 #include "vr-feature.h"
@@ -50,6 +52,38 @@ struct full_structure {
 
 struct vr_requirements {
         uint32_t version;
+        /* Minimum value for maxComputeSharedMemorySize or 0 if
+         * there is no requirement */
+        uint32_t max_compute_shared_memory_size;
+        /* Minimum value for maxVertexInputAttributes or 0 if there is
+         * no requirement */
+        uint32_t max_vertex_input_attributes;
+        /* Minimum value for maxDrawIndirectCount or 0 if there is no
+         * requirement */
+        uint32_t max_draw_indirect_count;
+        /* Minimum value for maxViewports or 0 if there is no
+         * requirement */
+        uint32_t max_viewports;
+        /* Minimum value for maxColorAttachments or 0 if there is no
+         * requirement */
+        uint32_t max_color_attachments;
+        /* Minimum value for maxUniformBufferRange or 0 if there is
+         * no requirement */
+        uint32_t max_uniform_buffer_range;
+        /* Minimum value for maxPerStageDescriptorStorageBuffers or 0
+         * if there is no requirement */
+        uint32_t max_storage_buffers;
+        /* Range of point sizes that must be within pointSizeRange.
+         * This defaults to [1, 1] which every device supports. */
+        float point_size_range[2];
+        /* Minimum number of physical devices in the device group of
+         * the device or 0 if there is no requirement */
+        uint32_t device_group_size;
+        /* Whether shaderDrawParameters is required. This is kept
+         * separately because the feature struct only exists from
+         * Vulkan 1.1. On Vulkan 1.0 the feature is enabled with the
+         * VK_KHR_shader_draw_parameters extension instead. */
+        bool shader_draw_parameters;
         struct vr_buffer extensions;
         struct vr_list structures;
         VkPhysicalDeviceFeatures features;
@@ -64,6 +98,8 @@ vr_requirements_new(void)
         vr_list_init(&reqs->structures);
 
         reqs->version = VK_MAKE_VERSION(1, 0, 0);
+        reqs->point_size_range[0] = 1.0f;
+        reqs->point_size_range[1] = 1.0f;
 
         return reqs;
 }
@@ -74,13 +110,141 @@ vr_requirements_get_version(const struct vr_requirements *reqs)
         return reqs->version;
 }
 
+uint32_t
+vr_requirements_get_max_compute_shared_memory_size(
+        const struct vr_requirements *reqs)
+{
+        return reqs->max_compute_shared_memory_size;
+}
+
 void
-vr_requirements_add_version(struct vr_requirements *reqs,
-                            unsigned int major,
-                            unsigned int minor,
-                            unsigned int patch)
+vr_requirements_add_max_compute_shared_memory_size(
+        struct vr_requirements *reqs,
+        uint32_t size)
+{
+        if (size > reqs->max_compute_shared_memory_size)
+                reqs->max_compute_shared_memory_size = size;
+}
+
+uint32_t
+vr_requirements_get_device_group_size(const struct vr_requirements *reqs)
+{
+        return reqs->device_group_size;
+}
+
+uint32_t
+vr_requirements_get_max_vertex_input_attributes(
+        const struct vr_requirements *reqs)
+{
+        return reqs->max_vertex_input_attributes;
+}
+
+void
+vr_requirements_add_max_vertex_input_attributes(
+        struct vr_requirements *reqs,
+        uint32_t n_attributes)
+{
+        if (n_attributes > reqs->max_vertex_input_attributes)
+                reqs->max_vertex_input_attributes = n_attributes;
+}
+
+uint32_t
+vr_requirements_get_max_draw_indirect_count(
+        const struct vr_requirements *reqs)
+{
+        return reqs->max_draw_indirect_count;
+}
+
+void
+vr_requirements_add_max_draw_indirect_count(struct vr_requirements *reqs,
+                                            uint32_t count)
 {
-        reqs->version = VK_MAKE_VERSION(major, minor, patch);
+        if (count > reqs->max_draw_indirect_count)
+                reqs->max_draw_indirect_count = count;
+
+        /* Without multiDrawIndirect the limit is always 1 */
+        if (count > 1)
+                reqs->features.multiDrawIndirect = true;
+}
+
+uint32_t
+vr_requirements_get_max_uniform_buffer_range(
+        const struct vr_requirements *reqs)
+{
+        return reqs->max_uniform_buffer_range;
+}
+
+void
+vr_requirements_add_max_uniform_buffer_range(struct vr_requirements *reqs,
+                                             uint32_t range)
+{
+        if (range > reqs->max_uniform_buffer_range)
+                reqs->max_uniform_buffer_range = range;
+}
+
+uint32_t
+vr_requirements_get_max_storage_buffers(const struct vr_requirements *reqs)
+{
+        return reqs->max_storage_buffers;
+}
+
+void
+vr_requirements_add_max_storage_buffers(struct vr_requirements *reqs,
+                                        uint32_t n_buffers)
+{
+        if (n_buffers > reqs->max_storage_buffers)
+                reqs->max_storage_buffers = n_buffers;
+}
+
+void
+vr_requirements_get_point_size_range(const struct vr_requirements *reqs,
+                                     float range_out[2])
+{
+        range_out[0] = reqs->point_size_range[0];
+        range_out[1] = reqs->point_size_range[1];
+}
+
+void
+vr_requirements_add_point_size_range(struct vr_requirements *reqs,
+                                     float min_size,
+                                     float max_size)
+{
+        if (min_size < reqs->point_size_range[0])
+                reqs->point_size_range[0] = min_size;
+        if (max_size > reqs->point_size_range[1])
+                reqs->point_size_range[1] = max_size;
+}
+
+uint32_t
+vr_requirements_get_max_viewports(const struct vr_requirements *reqs)
+{
+        return reqs->max_viewports;
+}
+
+void
+vr_requirements_add_max_viewports(struct vr_requirements *reqs,
+                                  uint32_t n_viewports)
+{
+        if (n_viewports > reqs->max_viewports)
+                reqs->max_viewports = n_viewports;
+
+        /* Without multiViewport the limit is always 1 */
+        if (n_viewports > 1)
+                reqs->features.multiViewport = true;
+}
+
+uint32_t
+vr_requirements_get_max_color_attachments(const struct vr_requirements *reqs)
+{
+        return reqs->max_color_attachments;
+}
+
+void
+vr_requirements_add_max_color_attachments(struct vr_requirements *reqs,
+                                          uint32_t n_attachments)
+{
+        if (n_attachments > reqs->max_color_attachments)
+                reqs->max_color_attachments = n_attachments;
 }
 
 const char* const*
@@ -167,14 +331,36 @@ add_structure_to_list(struct vr_list *list,
         return structure;
 }
 
+/* Looks up a feature by name. If several extensions provide a
+ * feature with the same name then the first one in the table wins.
+ * The name can be prefixed with an extension name and a dot, such as
+ * “VK_EXT_mesh_shader.meshShader”, to pick the feature from a
+ * specific extension.
+ */
 static bool
 find_feature(const char *name,
              const struct vr_feature_extension **extension_out,
              const struct vr_feature_offset **offset_out)
 {
+        const char *dot = strchr(name, '.');
+        const char *ext_name = NULL;
+        size_t ext_name_len = 0;
+
+        if (dot) {
+                ext_name = name;
+                ext_name_len = dot - name;
+                name = dot + 1;
+        }
+
         for (const struct vr_feature_extension *ext = vr_feature_extensions;
              ext->struct_size > 0;
              ext++) {
+                if (ext_name &&
+                    (ext->name == NULL ||
+                     strlen(ext->name) != ext_name_len ||
+                     memcmp(ext->name, ext_name, ext_name_len)))
+                        continue;
+
                 for (const struct vr_feature_offset *offset = ext->offsets;
                      offset->name;
                      offset++) {
@@ -205,6 +391,79 @@ get_structure(struct vr_requirements *reqs,
         return (uint8_t *) &structure->base;
 }
 
+bool
+vr_requirements_is_feature(const char *name)
+{
+        const struct vr_feature_extension *extension;
+        const struct vr_feature_offset *offset;
+
+        return find_feature(name, &extension, &offset);
+}
+
+static void
+set_feature(struct vr_requirements *reqs,
+            const struct vr_feature_extension *extension,
+            const struct vr_feature_offset *offset)
+{
+        uint8_t *structure;
+
+        if (extension->name)
+                structure = get_structure(reqs, extension);
+        else
+                structure = (uint8_t *) &reqs->features;
+
+        *(VkBool32 *) (structure + offset->offset) = true;
+}
+
+static void
+add_shader_draw_parameters_structure(struct vr_requirements *reqs)
+{
+        const struct vr_feature_extension *extension;
+        const struct vr_feature_offset *offset;
+
+        if (find_feature("shaderDrawParameters", &extension, &offset))
+                set_feature(reqs, extension, offset);
+}
+
+static void
+raise_version(struct vr_requirements *reqs,
+              uint32_t version)
+{
+        /* Other requirements may have already bumped the version so
+         * only ever raise it */
+        if (version <= reqs->version)
+                return;
+
+        reqs->version = version;
+
+        /* The feature struct for shaderDrawParameters can be used
+         * now that the version is high enough */
+        if (reqs->shader_draw_parameters &&
+            version >= VK_MAKE_VERSION(1, 1, 0))
+                add_shader_draw_parameters_structure(reqs);
+}
+
+void
+vr_requirements_add_version(struct vr_requirements *reqs,
+                            unsigned int major,
+                            unsigned int minor,
+                            unsigned int patch)
+{
+        raise_version(reqs, VK_MAKE_VERSION(major, minor, patch));
+}
+
+void
+vr_requirements_add_device_group_size(struct vr_requirements *reqs,
+                                      uint32_t size)
+{
+        if (size > reqs->device_group_size)
+                reqs->device_group_size = size;
+
+        /* vkEnumeratePhysicalDeviceGroups is only available in
+         * Vulkan 1.1 */
+        raise_version(reqs, VK_MAKE_VERSION(1, 1, 0));
+}
+
 void
 vr_requirements_add(struct vr_requirements *reqs,
                     const char *name)
@@ -213,20 +472,184 @@ vr_requirements_add(struct vr_requirements *reqs,
         const struct vr_feature_offset *offset;
         bool is_feature = find_feature(name, &extension, &offset);
 
-        if (is_feature) {
-                uint8_t *structure;
+        if (!is_feature) {
+                add_extension_name(reqs, name);
+                return;
+        }
+
+        if (extension->name)
+                add_extension_name(reqs, extension->name);
+
+        if (!strcmp(offset->name, "shaderDrawParameters")) {
+                reqs->shader_draw_parameters = true;
 
-                if (extension->name) {
-                        add_extension_name(reqs, extension->name);
-                        structure = get_structure(reqs, extension);
-                } else {
-                        structure = (uint8_t *) &reqs->features;
+                /* VkPhysicalDeviceShaderDrawParametersFeatures only
+                 * exists from Vulkan 1.1. Before that enabling
+                 * VK_KHR_shader_draw_parameters is enough. If the
+                 * version is raised later then the structure will
+                 * be added then. */
+                if (reqs->version < VK_MAKE_VERSION(1, 1, 0))
+                        return;
+        }
+
+        set_feature(reqs, extension, offset);
+
+        /* VK_EXT_robustness2 requires the base feature to be enabled
+         * too */
+        if (!strcmp(offset->name, "robustBufferAccess2"))
+                vr_requirements_add(reqs, "robustBufferAccess");
+}
+
+static const uint8_t *
+find_structure(const struct vr_requirements *reqs,
+               const struct vr_feature_extension *extension)
+{
+        const struct full_structure *full_structure;
+
+        vr_list_for_each(full_structure, &reqs->structures, link) {
+                if (full_structure->extension == extension)
+                        return (const uint8_t *) &full_structure->base;
+        }
+
+        return NULL;
+}
+
+bool
+vr_requirements_has(const struct vr_requirements *reqs,
+                    const char *name)
+{
+        const struct vr_feature_extension *extension;
+        const struct vr_feature_offset *offset;
+
+        if (!find_feature(name, &extension, &offset)) {
+                size_t n_exts = vr_requirements_get_n_extensions(reqs);
+                const char *const *exts = vr_requirements_get_extensions(reqs);
+
+                for (size_t i = 0; i < n_exts; i++) {
+                        if (!strcmp(exts[i], name))
+                                return true;
                 }
 
-                *(VkBool32 *) (structure + offset->offset) = true;
+                return false;
+        }
+
+        /* On Vulkan 1.0 this isn’t stored in the structure */
+        if (!strcmp(offset->name, "shaderDrawParameters") &&
+            reqs->shader_draw_parameters)
+                return true;
+
+        const uint8_t *structure;
+
+        if (extension->name) {
+                structure = find_structure(reqs, extension);
+                if (structure == NULL)
+                        return false;
         } else {
-                add_extension_name(reqs, name);
+                structure = (const uint8_t *) &reqs->features;
+        }
+
+        return *(const VkBool32 *) (structure + offset->offset);
+}
+
+/* Returns the name of the feature at index in the list of required
+ * features or NULL if there are fewer features than that. Either way
+ * the number of required features up to the returned one is stored
+ * in n_features_out. */
+static const char *
+get_nth_feature(const struct vr_requirements *reqs,
+                size_t index,
+                size_t *n_features_out,
+                const struct vr_feature_extension **extension_out)
+{
+        const struct vr_feature_extension *extension = NULL;
+        const struct vr_feature_offset *offset;
+        size_t n_features = 0;
+
+        for (offset = vr_feature_base_offsets; offset->name; offset++) {
+                const uint8_t *structure = (const uint8_t *) &reqs->features;
+
+                if (!*(const VkBool32 *) (structure + offset->offset))
+                        continue;
+
+                if (n_features++ == index)
+                        goto found;
+        }
+
+        for (extension = vr_feature_extensions;
+             extension->struct_size;
+             extension++) {
+                const uint8_t *structure = find_structure(reqs, extension);
+
+                if (structure == NULL)
+                        continue;
+
+                for (offset = extension->offsets; offset->name; offset++) {
+                        if (!*(const VkBool32 *) (structure + offset->offset))
+                                continue;
+
+                        if (n_features++ == index)
+                                goto found;
+                }
         }
+
+        if (reqs->shader_draw_parameters &&
+            reqs->version < VK_MAKE_VERSION(1, 1, 0) &&
+            n_features++ == index &&
+            find_feature("shaderDrawParameters", &extension, &offset))
+                goto found;
+
+        *n_features_out = n_features;
+        return NULL;
+
+found:
+        *n_features_out = n_features;
+        if (extension_out)
+                *extension_out = extension;
+        return offset->name;
+}
+
+size_t
+vr_requirements_get_n_features(const struct vr_requirements *reqs)
+{
+        size_t n_features;
+
+        get_nth_feature(reqs, SIZE_MAX, &n_features, NULL);
+
+        return n_features;
+}
+
+const char *
+vr_requirements_get_feature(const struct vr_requirements *reqs,
+                            size_t index)
+{
+        size_t n_features;
+        const char *name = get_nth_feature(reqs, index, &n_features, NULL);
+
+        assert(name != NULL);
+
+        return name;
+}
+
+const char *
+vr_requirements_get_feature_extension(const struct vr_requirements *reqs,
+                                      size_t index)
+{
+        const struct vr_feature_extension *extension, *found_extension;
+        const struct vr_feature_offset *offset;
+        size_t n_features;
+        const char *name = get_nth_feature(reqs,
+                                           index,
+                                           &n_features,
+                                           &extension);
+
+        assert(name != NULL);
+
+        if (extension == NULL ||
+            !find_feature(name, &found_extension, &offset) ||
+            found_extension == extension)
+                return NULL;
+
+        return extension->name;
 }
 
 bool
@@ -290,6 +713,41 @@ vr_requirements_equal(const struct vr_requirements *reqs_a,
                 return false;
         }
 
+        if (reqs_a->max_compute_shared_memory_size !=
+            reqs_b->max_compute_shared_memory_size)
+                return false;
+
+        if (reqs_a->max_vertex_input_attributes !=
+            reqs_b->max_vertex_input_attributes)
+                return false;
+
+        if (reqs_a->max_draw_indirect_count !=
+            reqs_b->max_draw_indirect_count)
+                return false;
+
+        if (reqs_a->max_viewports != reqs_b->max_viewports)
+                return false;
+
+        if (reqs_a->max_color_attachments != reqs_b->max_color_attachments)
+                return false;
+
+        if (reqs_a->max_uniform_buffer_range !=
+            reqs_b->max_uniform_buffer_range)
+                return false;
+
+        if (reqs_a->max_storage_buffers != reqs_b->max_storage_buffers)
+                return false;
+
+        if (reqs_a->point_size_range[0] != reqs_b->point_size_range[0] ||
+            reqs_a->point_size_range[1] != reqs_b->point_size_range[1])
+                return false;
+
+        if (reqs_a->device_group_size != reqs_b->device_group_size)
+                return false;
+
+        if (reqs_a->shader_draw_parameters != reqs_b->shader_draw_parameters)
+                return false;
+
         return true;
 }
 
@@ -335,6 +793,19 @@ vr_requirements_copy(const struct vr_requirements *reqs)
 
         reqs_copy->features = reqs->features;
         reqs_copy->version = reqs->version;
+        reqs_copy->max_compute_shared_memory_size =
+                reqs->max_compute_shared_memory_size;
+        reqs_copy->max_vertex_input_attributes =
+                reqs->max_vertex_input_attributes;
+        reqs_copy->max_draw_indirect_count = reqs->max_draw_indirect_count;
+        reqs_copy->max_viewports = reqs->max_viewports;
+        reqs_copy->max_color_attachments = reqs->max_color_attachments;
+        reqs_copy->max_uniform_buffer_range = reqs->max_uniform_buffer_range;
+        reqs_copy->max_storage_buffers = reqs->max_storage_buffers;
+        reqs_copy->point_size_range[0] = reqs->point_size_range[0];
+        reqs_copy->point_size_range[1] = reqs->point_size_range[1];
+        reqs_copy->device_group_size = reqs->device_group_size;
+        reqs_copy->shader_draw_parameters = reqs->shader_draw_parameters;
 
         assert(vr_requirements_equal(reqs, reqs_copy));
 
@@ -349,15 +820,38 @@ get_feature_value(const void *structure,
         return *(const VkBool32 *) (buf + offset->offset);
 }
 
+VR_PRINTF_FORMAT(2, 3)
+static void
+set_reason(struct vr_buffer *reason,
+           const char *format,
+           ...)
+{
+        if (reason == NULL)
+                return;
+
+        va_list ap;
+
+        vr_buffer_set_length(reason, 0);
+
+        va_start(ap, format);
+        vr_buffer_append_vprintf(reason, format, ap);
+        va_end(ap);
+}
+
 static bool
 check_features(const struct vr_feature_offset *offsets,
                const void *requested,
-               const void *actual)
+               const void *actual,
+               struct vr_buffer *reason)
 {
         for (int i = 0; offsets[i].name; i++) {
                 if (get_feature_value(requested, offsets + i) &&
-                    !get_feature_value(actual, offsets + i))
+                    !get_feature_value(actual, offsets + i)) {
+                        set_reason(reason,
+                                   "the feature %s is not supported",
+                                   offsets[i].name);
                         return false;
+                }
         }
 
         return true;
@@ -379,7 +873,8 @@ find_extension(uint32_t property_count,
 static bool
 check_extensions(const struct vr_requirements *reqs,
                  struct vr_vk *vkfn,
-                 VkPhysicalDevice device)
+                 VkPhysicalDevice device,
+                 struct vr_buffer *reason)
 {
         size_t n_exts = vr_requirements_get_n_extensions(reqs);
 
@@ -395,8 +890,10 @@ check_extensions(const struct vr_requirements *reqs,
                                                          NULL, /* layerName */
                                                          &property_count,
                                                          NULL /* properties */);
-        if (res != VK_SUCCESS)
+        if (res != VK_SUCCESS) {
+                set_reason(reason, "the device extensions can’t be queried");
                 return false;
+        }
 
         VkExtensionProperties *props = vr_alloc(property_count * sizeof *props);
         bool ret = true;
@@ -408,11 +905,16 @@ check_extensions(const struct vr_requirements *reqs,
         if (res == VK_SUCCESS) {
                 for (size_t i = 0; i < n_exts; i++) {
                         if (!find_extension(property_count, props, exts[i])) {
+                                set_reason(reason,
+                                           "the extension %s is not "
+                                           "supported",
+                                           exts[i]);
                                 ret = false;
                                 break;
                         }
                 }
         } else {
+                set_reason(reason, "the device extensions can’t be queried");
                 ret = false;
         }
 
@@ -435,7 +937,8 @@ static bool
 check_structures(const struct vr_requirements *reqs,
                  struct vr_vk *vkfn,
                  VkInstance instance,
-                 VkPhysicalDevice device)
+                 VkPhysicalDevice device,
+                 struct vr_buffer *reason)
 {
         if (vr_list_empty(&reqs->structures))
                 return true;
@@ -445,8 +948,10 @@ check_structures(const struct vr_requirements *reqs,
                 (void *) vkfn->vkGetInstanceProcAddr(instance,
                                                      get_features_name);
 
-        if (get_features == NULL)
+        if (get_features == NULL) {
+                set_reason(reason, "%s is not available", get_features_name);
                 return false;
+        }
 
         struct vr_list structures;
         vr_list_init(&structures);
@@ -474,7 +979,8 @@ check_structures(const struct vr_requirements *reqs,
         vr_list_for_each(requested, &reqs->structures, link) {
                 if (!check_features(requested->extension->offsets,
                                     &requested->base,
-                                    &actual->base)) {
+                                    &actual->base,
+                                    reason)) {
                         ret = false;
                         break;
                 }
@@ -493,7 +999,8 @@ static bool
 check_version(const struct vr_requirements *reqs,
                   struct vr_vk *vkfn,
                   VkInstance instance,
-                  VkPhysicalDevice device)
+                  VkPhysicalDevice device,
+                  struct vr_buffer *reason)
 {
         uint32_t rversion = vr_requirements_get_version(reqs);
 
@@ -503,23 +1010,191 @@ check_version(const struct vr_requirements *reqs,
                         (void *) vkfn->vkGetInstanceProcAddr(instance,
                                                              enum_instance_version_name);
 
-                if (!enum_instance_version)
+                if (!enum_instance_version) {
+                        set_reason(reason,
+                                   "%s is not available",
+                                   enum_instance_version_name);
                         return false;
+                }
         }
 
         VkPhysicalDeviceProperties props;
         vkfn->vkGetPhysicalDeviceProperties(device, &props);
-        if (props.apiVersion < rversion)
-            return false;
+        if (props.apiVersion < rversion) {
+                set_reason(reason,
+                           "Vulkan %u.%u.%u is required but the device "
+                           "only supports %u.%u.%u",
+                           VK_VERSION_MAJOR(rversion),
+                           VK_VERSION_MINOR(rversion),
+                           VK_VERSION_PATCH(rversion),
+                           VK_VERSION_MAJOR(props.apiVersion),
+                           VK_VERSION_MINOR(props.apiVersion),
+                           VK_VERSION_PATCH(props.apiVersion));
+                return false;
+        }
+
+        return true;
+}
+
+static bool
+check_limit(const char *name,
+            uint32_t actual,
+            uint32_t required,
+            struct vr_buffer *reason)
+{
+        if (actual >= required)
+                return true;
+
+        set_reason(reason,
+                   "%s is %" PRIu32 " but %" PRIu32 " is required",
+                   name,
+                   actual,
+                   required);
+
+        return false;
+}
+
+static bool
+check_limits(const struct vr_requirements *reqs,
+             struct vr_vk *vkfn,
+             VkPhysicalDevice device,
+             struct vr_buffer *reason)
+{
+        VkPhysicalDeviceProperties props;
+        vkfn->vkGetPhysicalDeviceProperties(device, &props);
+
+        if (!check_limit("maxComputeSharedMemorySize",
+                         props.limits.maxComputeSharedMemorySize,
+                         reqs->max_compute_shared_memory_size,
+                         reason))
+                return false;
+
+        if (!check_limit("maxVertexInputAttributes",
+                         props.limits.maxVertexInputAttributes,
+                         reqs->max_vertex_input_attributes,
+                         reason))
+                return false;
+
+        if (!check_limit("maxDrawIndirectCount",
+                         props.limits.maxDrawIndirectCount,
+                         reqs->max_draw_indirect_count,
+                         reason))
+                return false;
+
+        if (!check_limit("maxViewports",
+                         props.limits.maxViewports,
+                         reqs->max_viewports,
+                         reason))
+                return false;
+
+        if (!check_limit("maxColorAttachments",
+                         props.limits.maxColorAttachments,
+                         reqs->max_color_attachments,
+                         reason))
+                return false;
+
+        if (!check_limit("maxUniformBufferRange",
+                         props.limits.maxUniformBufferRange,
+                         reqs->max_uniform_buffer_range,
+                         reason))
+                return false;
+
+        if (!check_limit("maxPerStageDescriptorStorageBuffers",
+                         props.limits.maxPerStageDescriptorStorageBuffers,
+                         reqs->max_storage_buffers,
+                         reason))
+                return false;
+
+        const float *point_size_range = props.limits.pointSizeRange;
+
+        if (reqs->point_size_range[0] < point_size_range[0] ||
+            reqs->point_size_range[1] > point_size_range[1]) {
+                set_reason(reason,
+                           "pointSizeRange is [%g, %g] but [%g, %g] is "
+                           "required",
+                           point_size_range[0],
+                           point_size_range[1],
+                           reqs->point_size_range[0],
+                           reqs->point_size_range[1]);
+                return false;
+        }
 
         return true;
 }
 
+static bool
+check_device_group(const struct vr_requirements *reqs,
+                   struct vr_vk *vkfn,
+                   VkInstance instance,
+                   VkPhysicalDevice device,
+                   struct vr_buffer *reason)
+{
+        if (reqs->device_group_size <= 1)
+                return true;
+
+        const char *enum_groups_name = "vkEnumeratePhysicalDeviceGroups";
+        PFN_vkEnumeratePhysicalDeviceGroups enum_groups =
+                (void *) vkfn->vkGetInstanceProcAddr(instance,
+                                                     enum_groups_name);
+
+        if (enum_groups == NULL) {
+                set_reason(reason, "%s is not available", enum_groups_name);
+                return false;
+        }
+
+        VkResult res;
+        uint32_t n_groups;
+
+        res = enum_groups(instance, &n_groups, NULL);
+        if (res != VK_SUCCESS) {
+                set_reason(reason, "the device groups can’t be queried");
+                return false;
+        }
+
+        VkPhysicalDeviceGroupProperties *groups =
+                vr_calloc(n_groups * sizeof *groups);
+
+        for (uint32_t i = 0; i < n_groups; i++) {
+                groups[i].sType =
+                        VK_STRUCTURE_TYPE_PHYSICAL_DEVICE_GROUP_PROPERTIES;
+        }
+
+        bool ret = false;
+
+        res = enum_groups(instance, &n_groups, groups);
+        if (res != VK_SUCCESS)
+                goto out;
+
+        for (uint32_t i = 0; i < n_groups; i++) {
+                for (uint32_t j = 0; j < groups[i].physicalDeviceCount; j++) {
+                        if (groups[i].physicalDevices[j] != device)
+                                continue;
+
+                        ret = (groups[i].physicalDeviceCount >=
+                               reqs->device_group_size);
+                        goto out;
+                }
+        }
+
+out:
+        if (!ret) {
+                set_reason(reason,
+                           "the device is not in a device group of size "
+                           "%" PRIu32,
+                           reqs->device_group_size);
+        }
+
+        vr_free(groups);
+
+        return ret;
+}
+
 bool
 vr_requirements_check(const struct vr_requirements *reqs,
                       struct vr_vk *vkfn,
                       VkInstance instance,
-                      VkPhysicalDevice device)
+                      VkPhysicalDevice device,
+                      struct vr_buffer *reason)
 {
         VkPhysicalDeviceFeatures features;
 
@@ -527,16 +1202,23 @@ vr_requirements_check(const struct vr_requirements *reqs,
 
         if (!check_features(vr_feature_base_offsets,
                             &reqs->features,
-                            &features))
+                            &features,
+                            reason))
+                return false;
+
+        if (!check_extensions(reqs, vkfn, device, reason))
+                return false;
+
+        if (!check_structures(reqs, vkfn, instance, device, reason))
                 return false;
 
-        if (!check_extensions(reqs, vkfn, device))
+        if (!check_version(reqs, vkfn, instance, device, reason))
                 return false;
 
-        if (!check_structures(reqs, vkfn, instance, device))
+        if (!check_limits(reqs, vkfn, device, reason))
                 return false;
 
-        if (!check_version(reqs, vkfn, instance, device))
+        if (!check_device_group(reqs, vkfn, instance, device, reason))
                 return false;
 
         return true;
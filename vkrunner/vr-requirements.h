@@ -30,6 +30,7 @@
 #include <stdbool.h>
 
 #include "vr-vk.h"
+#include "vr-buffer.h"
 
 struct vr_requirements;
 
@@ -45,22 +46,123 @@ vr_requirements_add_version(struct vr_requirements *reqs,
                             unsigned int minor,
                             unsigned int patch);
 
+uint32_t
+vr_requirements_get_max_compute_shared_memory_size(
+        const struct vr_requirements *reqs);
+
+void
+vr_requirements_add_max_compute_shared_memory_size(
+        struct vr_requirements *reqs,
+        uint32_t size);
+
+uint32_t
+vr_requirements_get_device_group_size(const struct vr_requirements *reqs);
+
+void
+vr_requirements_add_device_group_size(struct vr_requirements *reqs,
+                                      uint32_t size);
+
+uint32_t
+vr_requirements_get_max_vertex_input_attributes(
+        const struct vr_requirements *reqs);
+
+void
+vr_requirements_add_max_vertex_input_attributes(
+        struct vr_requirements *reqs,
+        uint32_t n_attributes);
+
+uint32_t
+vr_requirements_get_max_draw_indirect_count(
+        const struct vr_requirements *reqs);
+
+void
+vr_requirements_add_max_draw_indirect_count(struct vr_requirements *reqs,
+                                            uint32_t count);
+
+uint32_t
+vr_requirements_get_max_uniform_buffer_range(
+        const struct vr_requirements *reqs);
+
+void
+vr_requirements_add_max_uniform_buffer_range(struct vr_requirements *reqs,
+                                             uint32_t range);
+
+uint32_t
+vr_requirements_get_max_storage_buffers(const struct vr_requirements *reqs);
+
+void
+vr_requirements_add_max_storage_buffers(struct vr_requirements *reqs,
+                                        uint32_t n_buffers);
+
+void
+vr_requirements_get_point_size_range(const struct vr_requirements *reqs,
+                                     float range_out[2]);
+
+void
+vr_requirements_add_point_size_range(struct vr_requirements *reqs,
+                                     float min_size,
+                                     float max_size);
+
+uint32_t
+vr_requirements_get_max_viewports(const struct vr_requirements *reqs);
+
+void
+vr_requirements_add_max_viewports(struct vr_requirements *reqs,
+                                  uint32_t n_viewports);
+
+uint32_t
+vr_requirements_get_max_color_attachments(const struct vr_requirements *reqs);
+
+void
+vr_requirements_add_max_color_attachments(struct vr_requirements *reqs,
+                                          uint32_t n_attachments);
+
 const char* const*
 vr_requirements_get_extensions(const struct vr_requirements *reqs);
 
 size_t
 vr_requirements_get_n_extensions(const struct vr_requirements *reqs);
 
+/* Returns the number of features that are required, including the
+ * ones from VkPhysicalDeviceFeatures and from the extension
+ * structures */
+size_t
+vr_requirements_get_n_features(const struct vr_requirements *reqs);
+
+const char *
+vr_requirements_get_feature(const struct vr_requirements *reqs,
+                            size_t index);
+
+/* Returns the name of the extension that the feature at index comes
+ * from if the bare feature name would pick the feature from a
+ * different extension, or NULL otherwise. In that case the feature
+ * needs to be written as the extension name, a dot and the feature
+ * name. */
+const char *
+vr_requirements_get_feature_extension(const struct vr_requirements *reqs,
+                                      size_t index);
+
 const void *
 vr_requirements_get_structures(const struct vr_requirements *reqs);
 
 const VkPhysicalDeviceFeatures *
 vr_requirements_get_base_features(const struct vr_requirements *reqs);
 
+/* Returns whether the name is a feature that VkRunner knows about,
+ * either on its own or qualified with an extension name */
+bool
+vr_requirements_is_feature(const char *name);
+
 void
 vr_requirements_add(struct vr_requirements *reqs,
                     const char *name);
 
+/* Returns whether the feature or extension with the given name has
+ * been added to the requirements */
+bool
+vr_requirements_has(const struct vr_requirements *reqs,
+                    const char *name);
+
 bool
 vr_requirements_equal(const struct vr_requirements *reqs_a,
                       const struct vr_requirements *reqs_b);
@@ -72,7 +174,8 @@ bool
 vr_requirements_check(const struct vr_requirements *reqs,
                       struct vr_vk *vkfn,
                       VkInstance instance,
-                      VkPhysicalDevice device);
+                      VkPhysicalDevice device,
+                      struct vr_buffer *reason);
 
 void
 vr_requirements_free(struct vr_requirements *reqs);
@@ -43,12 +43,32 @@
 enum vr_script_op {
         VR_SCRIPT_OP_DRAW_RECT,
         VR_SCRIPT_OP_DRAW_ARRAYS,
+        VR_SCRIPT_OP_DRAW_INDIRECT_COUNT,
+        VR_SCRIPT_OP_DRAW_MESH,
         VR_SCRIPT_OP_DISPATCH_COMPUTE,
         VR_SCRIPT_OP_PROBE_RECT,
         VR_SCRIPT_OP_PROBE_SSBO,
+        VR_SCRIPT_OP_PROBE_SSBO_EQUAL,
+        VR_SCRIPT_OP_PROBE_SSBO_HISTOGRAM,
         VR_SCRIPT_OP_SET_PUSH_CONSTANT,
         VR_SCRIPT_OP_SET_BUFFER_SUBDATA,
-        VR_SCRIPT_OP_CLEAR
+        VR_SCRIPT_OP_CLEAR,
+        VR_SCRIPT_OP_CLEAR_CHECKERBOARD,
+        VR_SCRIPT_OP_IMAGE_TRANSITION,
+        VR_SCRIPT_OP_BEGIN_OCCLUSION_QUERY,
+        VR_SCRIPT_OP_END_OCCLUSION_QUERY,
+        VR_SCRIPT_OP_PROBE_OCCLUSION
+};
+
+enum vr_script_buffer_type {
+        VR_SCRIPT_BUFFER_TYPE_UBO,
+        VR_SCRIPT_BUFFER_TYPE_SSBO,
+};
+
+enum vr_script_probe_comparison {
+        VR_SCRIPT_PROBE_COMPARISON_EQUAL,
+        VR_SCRIPT_PROBE_COMPARISON_GREATER,
+        VR_SCRIPT_PROBE_COMPARISON_LESS
 };
 
 struct vr_script_shader {
@@ -74,13 +94,24 @@ struct vr_script_command {
                 } dispatch_compute;
 
                 struct {
+                        unsigned x, y, z;
+                        unsigned pipeline_key;
+                } draw_mesh;
+
+                struct {
+                        /* Index of the color attachment to probe */
+                        int attachment;
                         int n_components;
                         int x, y, w, h;
                         double color[4];
                         struct vr_tolerance tolerance;
+                        enum vr_script_probe_comparison comparison;
                 } probe_rect;
 
                 struct {
+                        /* Type of the probed buffer. This is used
+                         * for both probe ssbo and probe ubo. */
+                        enum vr_script_buffer_type buffer_type;
                         unsigned desc_set;
                         unsigned binding;
                         enum vr_box_comparison comparison;
@@ -90,8 +121,47 @@ struct vr_script_command {
                         size_t n_values;
                         void *value;
                         struct vr_tolerance tolerance;
+                        /* If true then the result of the comparison
+                         * is written to the result buffer as a uint
+                         * instead of failing the test. */
+                        bool write_result;
+                        unsigned result_desc_set;
+                        unsigned result_binding;
+                        size_t result_offset;
+                        /* If true then the values were loaded from a
+                         * file with the same stride as the buffer
+                         * and a failure only reports the first
+                         * differing value. */
+                        bool from_file;
+                        /* If true then value is a single value
+                         * that every one of the n_values in the
+                         * buffer is compared against */
+                        bool each;
                 } probe_ssbo;
 
+                struct {
+                        struct {
+                                unsigned desc_set;
+                                unsigned binding;
+                        } buffers[2];
+                        size_t size;
+                } probe_ssbo_equal;
+
+                struct {
+                        unsigned desc_set;
+                        unsigned binding;
+                        size_t offset;
+                        enum vr_box_type type;
+                        struct vr_box_layout layout;
+                        size_t n_values;
+                        /* The values are binned into n_buckets
+                         * buckets of equal width spanning from the
+                         * smallest to the largest value */
+                        unsigned n_buckets;
+                        unsigned *expected;
+                        struct vr_tolerance tolerance;
+                } probe_ssbo_histogram;
+
                 struct {
                         unsigned desc_set;
                         unsigned binding;
@@ -107,11 +177,27 @@ struct vr_script_command {
                 } set_push_constant;
 
                 struct {
-                        float color[4];
+                        /* Color for each attachment */
+                        float colors[VR_WINDOW_MAX_COLOR_ATTACHMENTS][4];
                         float depth;
                         uint32_t stencil;
                 } clear;
 
+                struct {
+                        float colors[2][4];
+                        unsigned size;
+                } clear_checkerboard;
+
+                struct {
+                        VkImageLayout old_layout;
+                        VkImageLayout new_layout;
+                } image_transition;
+
+                struct {
+                        enum vr_box_comparison comparison;
+                        uint64_t value;
+                } probe_occlusion;
+
                 struct {
                         VkPrimitiveTopology topology;
                         bool indexed;
@@ -121,12 +207,19 @@ struct vr_script_command {
                         uint32_t first_instance;
                         unsigned pipeline_key;
                 } draw_arrays;
-        };
-};
 
-enum vr_script_buffer_type {
-        VR_SCRIPT_BUFFER_TYPE_UBO,
-        VR_SCRIPT_BUFFER_TYPE_SSBO,
+                struct {
+                        bool indexed;
+                        unsigned desc_set;
+                        unsigned binding;
+                        size_t offset;
+                        unsigned count_desc_set;
+                        unsigned count_binding;
+                        size_t count_offset;
+                        uint32_t max_draw_count;
+                        unsigned pipeline_key;
+                } draw_indirect_count;
+        };
 };
 
 struct vr_script_buffer {
@@ -134,11 +227,25 @@ struct vr_script_buffer {
         unsigned binding;
         enum vr_script_buffer_type type;
         size_t size;
+        /* If true, the binding gets a null descriptor instead of a
+         * buffer. This needs the nullDescriptor feature. */
+        bool is_null;
+        /* If true, the binding gets a copy of the descriptor for
+         * another binding so that both refer to the same buffer. */
+        bool is_copy;
+        /* If true as well as is_copy, the descriptor is written
+         * with the same buffer instead of being copied. */
+        bool is_alias;
+        unsigned copy_desc_set;
+        unsigned copy_binding;
 };
 
 struct vr_script {
         char *filename;
         struct vr_list stages[VR_SHADER_STAGE_N_STAGES];
+        /* The --target-env to pass to the compiler for each stage
+         * or NULL to use the one for the required Vulkan version */
+        char *target_envs[VR_SHADER_STAGE_N_STAGES];
         size_t n_commands;
         struct vr_script_command *commands;
         size_t n_pipeline_keys;
@@ -146,10 +253,21 @@ struct vr_script {
         struct vr_requirements *requirements;
         struct vr_window_format window_format;
         struct vr_vbo *vertex_data;
-        uint16_t *indices;
+        /* Whether the vertex data advances per instance instead of
+         * per vertex and the number of instances per element */
+        bool vertex_data_instanced;
+        uint32_t vertex_data_divisor;
+        /* An array of either uint16_t or uint32_t depending on
+         * index_type */
+        void *indices;
         size_t n_indices;
+        VkIndexType index_type;
         struct vr_script_buffer *buffers;
         size_t n_buffers;
+        /* The number of times the test is expected to call
+         * vkQueueSubmit or -1 if it shouldn’t be checked.
+         */
+        int expected_submissions;
 };
 
 #endif /* VR_SCRIPT_PRIVATE_H */
@@ -32,6 +32,9 @@
 #include <limits.h>
 #include <assert.h>
 #include <stdlib.h>
+#include <stddef.h>
+#include <inttypes.h>
+#include <math.h>
 
 #include "vr-script-private.h"
 #include "vr-list.h"
@@ -45,6 +48,7 @@
 #include "vr-stream.h"
 #include "vr-char.h"
 #include "vr-hex.h"
+#include "vr-half-float.h"
 
 #define DEFAULT_TOLERANCE 0.01
 
@@ -71,6 +75,12 @@ enum parse_result {
         PARSE_RESULT_NON_MATCHED,
 };
 
+struct persisted_buffer {
+        unsigned desc_set;
+        unsigned binding;
+        int line_num;
+};
+
 struct load_state {
         const struct vr_config *config;
         const struct vr_source *source;
@@ -85,15 +95,26 @@ struct load_state {
         struct vr_buffer commands;
         struct vr_buffer pipeline_keys;
         struct vr_buffer buffers;
+        /* Array of struct persisted_buffer for each persist command
+         * so that a buffer that never gets any data can be reported
+         * at the end of the script */
+        struct vr_buffer persisted_buffers;
         struct vr_pipeline_key current_key;
         struct vr_buffer indices;
-        float clear_color[4];
+        /* The clear color of each attachment. Attachments that
+         * haven’t been given their own color follow the color of
+         * attachment 0. */
+        float clear_colors[VR_WINDOW_MAX_COLOR_ATTACHMENTS][4];
+        bool clear_color_set[VR_WINDOW_MAX_COLOR_ATTACHMENTS];
         float clear_depth;
         unsigned clear_stencil;
         struct vr_tolerance tolerance;
         struct vr_box_layout push_layout;
         struct vr_box_layout ubo_layout;
         struct vr_box_layout ssbo_layout;
+        /* Line number of the occlusion begin command of the query
+         * that is currently active, or zero if there isn’t one */
+        int occlusion_query_line;
         int had_sections;
 };
 
@@ -108,6 +129,8 @@ stage_names[VR_SHADER_STAGE_N_STAGES] = {
         "geometry",
         "fragment",
         "compute",
+        "task",
+        "mesh",
 };
 
 static uint32_t
@@ -189,6 +212,20 @@ end_vertex_data(struct load_state *data)
         return data->script->vertex_data != NULL;
 }
 
+static bool
+end_test(struct load_state *data)
+{
+        if (data->occlusion_query_line) {
+                vr_error_message(data->config,
+                                 "%s:%i: Occlusion query is never ended",
+                                 data->filename,
+                                 data->occlusion_query_line);
+                return false;
+        }
+
+        return true;
+}
+
 static bool
 end_section(struct load_state *data)
 {
@@ -212,7 +249,7 @@ end_section(struct load_state *data)
                 break;
 
         case SECTION_TEST:
-                break;
+                return end_test(data);
         }
 
         data->current_section = SECTION_NONE;
@@ -649,87 +686,87 @@ parse_desc_set_and_binding(const char **p,
         return true;
 }
 
+static const struct {
+        const char *name;
+        enum vr_box_type type;
+} value_types[] = {
+        { "int ", VR_BOX_TYPE_INT },
+        { "uint ", VR_BOX_TYPE_UINT },
+        { "int8_t ", VR_BOX_TYPE_INT8 },
+        { "uint8_t ", VR_BOX_TYPE_UINT8 },
+        { "int16_t ", VR_BOX_TYPE_INT16 },
+        { "uint16_t ", VR_BOX_TYPE_UINT16 },
+        { "int64_t ", VR_BOX_TYPE_INT64 },
+        { "uint64_t ", VR_BOX_TYPE_UINT64 },
+        { "float16_t ", VR_BOX_TYPE_FLOAT16 },
+        { "float ", VR_BOX_TYPE_FLOAT },
+        { "double ", VR_BOX_TYPE_DOUBLE },
+        { "f16vec2 ", VR_BOX_TYPE_F16VEC2 },
+        { "f16vec3 ", VR_BOX_TYPE_F16VEC3 },
+        { "f16vec4 ", VR_BOX_TYPE_F16VEC4 },
+        { "vec2 ", VR_BOX_TYPE_VEC2 },
+        { "vec3 ", VR_BOX_TYPE_VEC3 },
+        { "vec4 ", VR_BOX_TYPE_VEC4 },
+        { "dvec2 ", VR_BOX_TYPE_DVEC2 },
+        { "dvec3 ", VR_BOX_TYPE_DVEC3 },
+        { "dvec4 ", VR_BOX_TYPE_DVEC4 },
+        { "ivec2 ", VR_BOX_TYPE_IVEC2 },
+        { "ivec3 ", VR_BOX_TYPE_IVEC3 },
+        { "ivec4 ", VR_BOX_TYPE_IVEC4 },
+        { "uvec2 ", VR_BOX_TYPE_UVEC2 },
+        { "uvec3 ", VR_BOX_TYPE_UVEC3 },
+        { "uvec4 ", VR_BOX_TYPE_UVEC4 },
+        { "i8vec2 ", VR_BOX_TYPE_I8VEC2 },
+        { "i8vec3 ", VR_BOX_TYPE_I8VEC3 },
+        { "i8vec4 ", VR_BOX_TYPE_I8VEC4 },
+        { "u8vec2 ", VR_BOX_TYPE_U8VEC2 },
+        { "u8vec3 ", VR_BOX_TYPE_U8VEC3 },
+        { "u8vec4 ", VR_BOX_TYPE_U8VEC4 },
+        { "i16vec2 ", VR_BOX_TYPE_I16VEC2 },
+        { "i16vec3 ", VR_BOX_TYPE_I16VEC3 },
+        { "i16vec4 ", VR_BOX_TYPE_I16VEC4 },
+        { "u16vec2 ", VR_BOX_TYPE_U16VEC2 },
+        { "u16vec3 ", VR_BOX_TYPE_U16VEC3 },
+        { "u16vec4 ", VR_BOX_TYPE_U16VEC4 },
+        { "i64vec2 ", VR_BOX_TYPE_I64VEC2 },
+        { "i64vec3 ", VR_BOX_TYPE_I64VEC3 },
+        { "i64vec4 ", VR_BOX_TYPE_I64VEC4 },
+        { "u64vec2 ", VR_BOX_TYPE_U64VEC2 },
+        { "u64vec3 ", VR_BOX_TYPE_U64VEC3 },
+        { "u64vec4 ", VR_BOX_TYPE_U64VEC4 },
+        { "mat2 ", VR_BOX_TYPE_MAT2 },
+        { "mat2x2 ", VR_BOX_TYPE_MAT2 },
+        { "mat2x3 ", VR_BOX_TYPE_MAT2X3 },
+        { "mat2x4 ", VR_BOX_TYPE_MAT2X4 },
+        { "mat3x2 ", VR_BOX_TYPE_MAT3X2 },
+        { "mat3 ", VR_BOX_TYPE_MAT3 },
+        { "mat3x3 ", VR_BOX_TYPE_MAT3 },
+        { "mat3x4 ", VR_BOX_TYPE_MAT3X4 },
+        { "mat4x2 ", VR_BOX_TYPE_MAT4X2 },
+        { "mat4x3 ", VR_BOX_TYPE_MAT4X3 },
+        { "mat4 ", VR_BOX_TYPE_MAT4 },
+        { "mat4x4 ", VR_BOX_TYPE_MAT4 },
+        { "dmat2 ", VR_BOX_TYPE_DMAT2 },
+        { "dmat2x2 ", VR_BOX_TYPE_DMAT2 },
+        { "dmat2x3 ", VR_BOX_TYPE_DMAT2X3 },
+        { "dmat2x4 ", VR_BOX_TYPE_DMAT2X4 },
+        { "dmat3x2 ", VR_BOX_TYPE_DMAT3X2 },
+        { "dmat3 ", VR_BOX_TYPE_DMAT3 },
+        { "dmat3x3 ", VR_BOX_TYPE_DMAT3 },
+        { "dmat3x4 ", VR_BOX_TYPE_DMAT3X4 },
+        { "dmat4x2 ", VR_BOX_TYPE_DMAT4X2 },
+        { "dmat4x3 ", VR_BOX_TYPE_DMAT4X3 },
+        { "dmat4 ", VR_BOX_TYPE_DMAT4 },
+        { "dmat4x4 ", VR_BOX_TYPE_DMAT4 },
+};
+
 static bool
 parse_value_type(const char **p,
                  enum vr_box_type *type)
 {
-        static const struct {
-                const char *name;
-                enum vr_box_type type;
-        } types[] = {
-                { "int ", VR_BOX_TYPE_INT },
-                { "uint ", VR_BOX_TYPE_UINT },
-                { "int8_t ", VR_BOX_TYPE_INT8 },
-                { "uint8_t ", VR_BOX_TYPE_UINT8 },
-                { "int16_t ", VR_BOX_TYPE_INT16 },
-                { "uint16_t ", VR_BOX_TYPE_UINT16 },
-                { "int64_t ", VR_BOX_TYPE_INT64 },
-                { "uint64_t ", VR_BOX_TYPE_UINT64 },
-                { "float16_t ", VR_BOX_TYPE_FLOAT16 },
-                { "float ", VR_BOX_TYPE_FLOAT },
-                { "double ", VR_BOX_TYPE_DOUBLE },
-                { "f16vec2 ", VR_BOX_TYPE_F16VEC2 },
-                { "f16vec3 ", VR_BOX_TYPE_F16VEC3 },
-                { "f16vec4 ", VR_BOX_TYPE_F16VEC4 },
-                { "vec2 ", VR_BOX_TYPE_VEC2 },
-                { "vec3 ", VR_BOX_TYPE_VEC3 },
-                { "vec4 ", VR_BOX_TYPE_VEC4 },
-                { "dvec2 ", VR_BOX_TYPE_DVEC2 },
-                { "dvec3 ", VR_BOX_TYPE_DVEC3 },
-                { "dvec4 ", VR_BOX_TYPE_DVEC4 },
-                { "ivec2 ", VR_BOX_TYPE_IVEC2 },
-                { "ivec3 ", VR_BOX_TYPE_IVEC3 },
-                { "ivec4 ", VR_BOX_TYPE_IVEC4 },
-                { "uvec2 ", VR_BOX_TYPE_UVEC2 },
-                { "uvec3 ", VR_BOX_TYPE_UVEC3 },
-                { "uvec4 ", VR_BOX_TYPE_UVEC4 },
-                { "i8vec2 ", VR_BOX_TYPE_I8VEC2 },
-                { "i8vec3 ", VR_BOX_TYPE_I8VEC3 },
-                { "i8vec4 ", VR_BOX_TYPE_I8VEC4 },
-                { "u8vec2 ", VR_BOX_TYPE_U8VEC2 },
-                { "u8vec3 ", VR_BOX_TYPE_U8VEC3 },
-                { "u8vec4 ", VR_BOX_TYPE_U8VEC4 },
-                { "i16vec2 ", VR_BOX_TYPE_I16VEC2 },
-                { "i16vec3 ", VR_BOX_TYPE_I16VEC3 },
-                { "i16vec4 ", VR_BOX_TYPE_I16VEC4 },
-                { "u16vec2 ", VR_BOX_TYPE_U16VEC2 },
-                { "u16vec3 ", VR_BOX_TYPE_U16VEC3 },
-                { "u16vec4 ", VR_BOX_TYPE_U16VEC4 },
-                { "i64vec2 ", VR_BOX_TYPE_I64VEC2 },
-                { "i64vec3 ", VR_BOX_TYPE_I64VEC3 },
-                { "i64vec4 ", VR_BOX_TYPE_I64VEC4 },
-                { "u64vec2 ", VR_BOX_TYPE_U64VEC2 },
-                { "u64vec3 ", VR_BOX_TYPE_U64VEC3 },
-                { "u64vec4 ", VR_BOX_TYPE_U64VEC4 },
-                { "mat2 ", VR_BOX_TYPE_MAT2 },
-                { "mat2x2 ", VR_BOX_TYPE_MAT2 },
-                { "mat2x3 ", VR_BOX_TYPE_MAT2X3 },
-                { "mat2x4 ", VR_BOX_TYPE_MAT2X4 },
-                { "mat3x2 ", VR_BOX_TYPE_MAT3X2 },
-                { "mat3 ", VR_BOX_TYPE_MAT3 },
-                { "mat3x3 ", VR_BOX_TYPE_MAT3 },
-                { "mat3x4 ", VR_BOX_TYPE_MAT3X4 },
-                { "mat4x2 ", VR_BOX_TYPE_MAT4X2 },
-                { "mat4x3 ", VR_BOX_TYPE_MAT4X3 },
-                { "mat4 ", VR_BOX_TYPE_MAT4 },
-                { "mat4x4 ", VR_BOX_TYPE_MAT4 },
-                { "dmat2 ", VR_BOX_TYPE_DMAT2 },
-                { "dmat2x2 ", VR_BOX_TYPE_DMAT2 },
-                { "dmat2x3 ", VR_BOX_TYPE_DMAT2X3 },
-                { "dmat2x4 ", VR_BOX_TYPE_DMAT2X4 },
-                { "dmat3x2 ", VR_BOX_TYPE_DMAT3X2 },
-                { "dmat3 ", VR_BOX_TYPE_DMAT3 },
-                { "dmat3x3 ", VR_BOX_TYPE_DMAT3 },
-                { "dmat3x4 ", VR_BOX_TYPE_DMAT3X4 },
-                { "dmat4x2 ", VR_BOX_TYPE_DMAT4X2 },
-                { "dmat4x3 ", VR_BOX_TYPE_DMAT4X3 },
-                { "dmat4 ", VR_BOX_TYPE_DMAT4 },
-                { "dmat4x4 ", VR_BOX_TYPE_DMAT4 },
-        };
-
-        for (int i = 0; i < VR_N_ELEMENTS(types); i++) {
-                if (looking_at(p, types[i].name)) {
-                        *type = types[i].type;
+        for (int i = 0; i < VR_N_ELEMENTS(value_types); i++) {
+                if (looking_at(p, value_types[i].name)) {
+                        *type = value_types[i].type;
                         return true;
                 }
         }
@@ -1018,6 +1055,94 @@ process_none_line(struct load_state *data)
         return true;
 }
 
+static enum parse_result
+process_target_env(struct load_state *data,
+                   const char *p)
+{
+        int stage;
+
+        for (stage = 0; stage < VR_SHADER_STAGE_N_STAGES; stage++) {
+                if (looking_at(&p, stage_names[stage]))
+                        goto found_stage;
+        }
+
+        return PARSE_RESULT_NON_MATCHED;
+
+found_stage:
+
+        if (!looking_at(&p, " targetenv "))
+                return PARSE_RESULT_NON_MATCHED;
+
+        while (*p && vr_char_is_space(*p))
+                p++;
+
+        const char *end = p;
+
+        while (*end && !vr_char_is_space(*end))
+                end++;
+
+        if (end <= p || !is_end(end)) {
+                error_at_line(data, "Invalid targetenv");
+                return PARSE_RESULT_ERROR;
+        }
+
+        vr_free(data->script->target_envs[stage]);
+        data->script->target_envs[stage] = vr_strndup(p, end - p);
+
+        return PARSE_RESULT_OK;
+}
+
+/* Parses a framebuffer requirement which can optionally start with
+ * the index of the color attachment. The attachments have to be
+ * declared in order. */
+static bool
+parse_framebuffer(struct load_state *data,
+                  const char *p)
+{
+        struct vr_window_format *format = &data->script->window_format;
+        unsigned attachment = 0;
+
+        while (vr_char_is_space(*p))
+                p++;
+
+        if (*p >= '0' && *p <= '9') {
+                if (!parse_uints(&p, &attachment, 1, NULL) ||
+                    !vr_char_is_space(*p)) {
+                        error_at_line(data, "Invalid framebuffer attachment");
+                        return false;
+                }
+
+                if (attachment >= VR_WINDOW_MAX_COLOR_ATTACHMENTS) {
+                        error_at_line(data,
+                                      "At most %i color attachments are "
+                                      "supported",
+                                      VR_WINDOW_MAX_COLOR_ATTACHMENTS);
+                        return false;
+                }
+
+                if (attachment > format->n_color_attachments) {
+                        error_at_line(data,
+                                      "Color attachment %u declared before "
+                                      "attachment %i",
+                                      attachment,
+                                      format->n_color_attachments);
+                        return false;
+                }
+        }
+
+        if (!parse_format(data, p, format->color_formats + attachment))
+                return false;
+
+        if (attachment >= format->n_color_attachments) {
+                format->n_color_attachments = attachment + 1;
+                vr_requirements_add_max_color_attachments(data->script->
+                                                          requirements,
+                                                          attachment + 1);
+        }
+
+        return true;
+}
+
 static bool
 process_require_line(struct load_state *data)
 {
@@ -1029,11 +1154,8 @@ process_require_line(struct load_state *data)
         if (*start == '#' || *start == '\0')
                 return true;
 
-        if (looking_at(&p, "framebuffer ")) {
-                struct vr_window_format *format =
-                        &data->script->window_format;
-                return parse_format(data, p, &format->color_format);
-        }
+        if (looking_at(&p, "framebuffer "))
+                return parse_framebuffer(data, p);
 
         if (looking_at(&p, "depthstencil ")) {
                 struct vr_window_format *format =
@@ -1048,6 +1170,129 @@ process_require_line(struct load_state *data)
                 return parse_version(data, p, data->script->requirements);
         }
 
+        if (looking_at(&p, "devicegroup ")) {
+                unsigned size;
+
+                if (!parse_uints(&p, &size, 1, NULL) || !is_end(p)) {
+                        error_at_line(data, "Invalid devicegroup size");
+                        return false;
+                }
+
+                vr_requirements_add_device_group_size(
+                        data->script->requirements,
+                        size);
+                return true;
+        }
+
+        if (looking_at(&p, "maxsharedmemory ")) {
+                unsigned size;
+
+                if (!parse_uints(&p, &size, 1, NULL) || !is_end(p)) {
+                        error_at_line(data, "Invalid maxsharedmemory size");
+                        return false;
+                }
+
+                vr_requirements_add_max_compute_shared_memory_size(
+                        data->script->requirements,
+                        size);
+                return true;
+        }
+
+        if (looking_at(&p, "maxvertexattributes ")) {
+                unsigned n_attributes;
+
+                if (!parse_uints(&p, &n_attributes, 1, NULL) ||
+                    !is_end(p)) {
+                        error_at_line(data,
+                                      "Invalid maxvertexattributes count");
+                        return false;
+                }
+
+                vr_requirements_add_max_vertex_input_attributes(
+                        data->script->requirements,
+                        n_attributes);
+                return true;
+        }
+
+        if (looking_at(&p, "maxuniformbufferrange ")) {
+                unsigned range;
+
+                if (!parse_uints(&p, &range, 1, NULL) || !is_end(p)) {
+                        error_at_line(data,
+                                      "Invalid maxuniformbufferrange size");
+                        return false;
+                }
+
+                vr_requirements_add_max_uniform_buffer_range(
+                        data->script->requirements,
+                        range);
+                return true;
+        }
+
+        if (looking_at(&p, "maxstoragebuffers ")) {
+                unsigned n_buffers;
+
+                if (!parse_uints(&p, &n_buffers, 1, NULL) || !is_end(p)) {
+                        error_at_line(data,
+                                      "Invalid maxstoragebuffers count");
+                        return false;
+                }
+
+                vr_requirements_add_max_storage_buffers(
+                        data->script->requirements,
+                        n_buffers);
+                return true;
+        }
+
+        if (looking_at(&p, "pointsize ")) {
+                float range[2];
+
+                if (!parse_floats(data, &p, range, 2, NULL) ||
+                    !is_end(p) ||
+                    range[0] <= 0.0f ||
+                    range[1] < range[0]) {
+                        error_at_line(data, "Invalid pointsize range");
+                        return false;
+                }
+
+                vr_requirements_add_point_size_range(data->script->requirements,
+                                                     range[0],
+                                                     range[1]);
+
+                /* Any size other than 1.0 needs the largePoints
+                 * feature */
+                if (range[0] != 1.0f || range[1] != 1.0f) {
+                        vr_requirements_add(data->script->requirements,
+                                            "largePoints");
+                }
+
+                return true;
+        }
+
+        switch (process_target_env(data, p)) {
+        case PARSE_RESULT_OK:
+                return true;
+        case PARSE_RESULT_ERROR:
+                return false;
+        case PARSE_RESULT_NON_MATCHED:
+                break;
+        }
+
+        if (looking_at(&p, "maxdrawindirectcount ")) {
+                unsigned count;
+
+                if (!parse_uints(&p, &count, 1, NULL) || !is_end(p)) {
+                        error_at_line(data,
+                                      "Invalid maxdrawindirectcount count");
+                        return false;
+                }
+
+                vr_requirements_add_max_draw_indirect_count(
+                        data->script->requirements,
+                        count);
+                return true;
+        }
+
         int extension_len = 0;
 
         while (true) {
@@ -1062,7 +1307,39 @@ process_require_line(struct load_state *data)
                 extension_len++;
         }
 
-        if (is_end(start + extension_len)) {
+        if (start[extension_len] == '.') {
+                /* A feature qualified with the extension name */
+                extension_len++;
+
+                while (true) {
+                        char ch = start[extension_len];
+
+                        if ((ch < 'A' || ch > 'Z') &&
+                            (ch < 'a' || ch > 'z') &&
+                            (ch < '0' || ch > '9'))
+                                break;
+
+                        extension_len++;
+                }
+
+                if (is_end(start + extension_len)) {
+                        char *name = vr_strndup(start, extension_len);
+                        bool is_feature = vr_requirements_is_feature(name);
+
+                        if (is_feature) {
+                                vr_requirements_add(data->script->requirements,
+                                                    name);
+                        } else {
+                                error_at_line(data,
+                                              "Unknown feature “%s”",
+                                              name);
+                        }
+
+                        vr_free(name);
+
+                        return is_feature;
+                }
+        } else if (is_end(start + extension_len)) {
                 char *ext = vr_strndup(start, extension_len);
                 vr_requirements_add(data->script->requirements, ext);
                 vr_free(ext);
@@ -1117,6 +1394,119 @@ add_command(struct load_state *data)
         return command;
 }
 
+static struct vr_script_buffer *
+get_buffer(struct load_state *data,
+           unsigned desc_set,
+           unsigned binding,
+           enum vr_script_buffer_type type)
+{
+        struct vr_script_buffer *buffer =
+                (struct vr_script_buffer *) data->buffers.data;
+        unsigned n_buffers = (data->buffers.length /
+                              sizeof (struct vr_script_buffer));
+
+        for (unsigned i = 0; i < n_buffers; i++) {
+                if (buffer[i].desc_set == desc_set &&
+                    buffer[i].binding == binding) {
+                        if (buffer[i].type != type) {
+                                error_at_line(data,
+                                              "Buffer binding point "
+                                              "%u:%u used with different "
+                                              "types",
+                                              desc_set,
+                                              binding);
+                                return NULL;
+                        }
+
+                        if (buffer[i].is_copy) {
+                                return get_buffer(data,
+                                                  buffer[i].copy_desc_set,
+                                                  buffer[i].copy_binding,
+                                                  type);
+                        }
+
+                        if (buffer[i].is_null) {
+                                error_at_line(data,
+                                              "Buffer binding point "
+                                              "%u:%u is null",
+                                              desc_set,
+                                              binding);
+                                return NULL;
+                        }
+
+                        return buffer + i;
+                }
+        }
+
+        vr_buffer_set_length(&data->buffers,
+                             data->buffers.length + sizeof *buffer);
+        buffer = ((struct vr_script_buffer *)
+                  (data->buffers.data + data->buffers.length) - 1);
+        buffer->type = type;
+        buffer->size = 0;
+        buffer->desc_set = desc_set;
+        buffer->binding = binding;
+        buffer->is_null = false;
+        buffer->is_copy = false;
+        buffer->is_alias = false;
+
+        return buffer;
+}
+
+static const struct {
+        const char *name;
+        VkPrimitiveTopology topology;
+} topologies[] = {
+        /* GL names used in Piglit */
+        { "GL_POINTS", VK_PRIMITIVE_TOPOLOGY_POINT_LIST },
+        { "GL_LINES", VK_PRIMITIVE_TOPOLOGY_LINE_LIST },
+        { "GL_LINE_STRIP", VK_PRIMITIVE_TOPOLOGY_LINE_STRIP },
+        { "GL_TRIANGLES", VK_PRIMITIVE_TOPOLOGY_TRIANGLE_LIST },
+        { "GL_TRIANGLE_STRIP", VK_PRIMITIVE_TOPOLOGY_TRIANGLE_STRIP },
+        { "GL_TRIANGLE_FAN", VK_PRIMITIVE_TOPOLOGY_TRIANGLE_FAN },
+        { "GL_LINES_ADJACENCY",
+          VK_PRIMITIVE_TOPOLOGY_LINE_LIST_WITH_ADJACENCY },
+        { "GL_LINE_STRIP_ADJACENCY",
+          VK_PRIMITIVE_TOPOLOGY_LINE_STRIP_WITH_ADJACENCY },
+        { "GL_TRIANGLES_ADJACENCY",
+          VK_PRIMITIVE_TOPOLOGY_TRIANGLE_LIST_WITH_ADJACENCY },
+        { "GL_TRIANGLE_STRIP_ADJACENCY",
+          VK_PRIMITIVE_TOPOLOGY_TRIANGLE_STRIP_WITH_ADJACENCY },
+        { "GL_PATCHES", VK_PRIMITIVE_TOPOLOGY_PATCH_LIST },
+        /* Vulkan names */
+#define vkname(x) { VR_STRINGIFY(x), VK_PRIMITIVE_TOPOLOGY_ ## x }
+        vkname(POINT_LIST),
+        vkname(LINE_LIST),
+        vkname(LINE_STRIP),
+        vkname(TRIANGLE_LIST),
+        vkname(TRIANGLE_STRIP),
+        vkname(TRIANGLE_FAN),
+        vkname(LINE_LIST_WITH_ADJACENCY),
+        vkname(LINE_STRIP_WITH_ADJACENCY),
+        vkname(TRIANGLE_LIST_WITH_ADJACENCY),
+        vkname(TRIANGLE_STRIP_WITH_ADJACENCY),
+        vkname(PATCH_LIST),
+#undef vkname
+};
+
+static bool
+lookup_topology(const char **p,
+                VkPrimitiveTopology *topology)
+{
+        for (int i = 0; i < VR_N_ELEMENTS(topologies); i++) {
+                int len = strlen(topologies[i].name);
+
+                if (strncmp(*p, topologies[i].name, len) == 0 &&
+                    ((*p)[len] == '\0' || vr_char_is_space((*p)[len]))) {
+                        *p += len;
+                        *topology = topologies[i].topology;
+                        return true;
+                }
+        }
+
+        return false;
+}
+
 static enum parse_result
 process_draw_rect_command(struct load_state *data,
                           const char *p)
@@ -1136,10 +1526,25 @@ process_draw_rect_command(struct load_state *data,
         key.type = VR_PIPELINE_KEY_TYPE_GRAPHICS;
         key.source = VR_PIPELINE_KEY_SOURCE_RECTANGLE;
 
-        if (looking_at(&p, "patch "))
+        if (looking_at(&p, "patch ")) {
                 key.topology.i = VK_PRIMITIVE_TOPOLOGY_PATCH_LIST;
-        else
+        } else if (looking_at(&p, "topology ")) {
+                VkPrimitiveTopology topology;
+
+                while (vr_char_is_space(*p))
+                        p++;
+
+                if (!lookup_topology(&p, &topology)) {
+                        error_at_line(data,
+                                      "Unknown topology in draw rect command");
+                        vr_pipeline_key_destroy(&key);
+                        return PARSE_RESULT_ERROR;
+                }
+
+                key.topology.i = topology;
+        } else {
                 key.topology.i = VK_PRIMITIVE_TOPOLOGY_TRIANGLE_STRIP;
+        }
         key.patchControlPoints.i = 4;
 
         command->draw_rect.pipeline_key = add_pipeline_key(data, &key);
@@ -1174,29 +1579,205 @@ process_draw_rect_command(struct load_state *data,
         return PARSE_RESULT_OK;
 }
 
-static enum parse_result
-process_probe_command(struct load_state *data,
-                      const char *p)
+/* Parses the values of a tolerance up to the end of the line. This is
+ * used both for the tolerance command and for the tolerance clause at
+ * the end of a probe. */
+static bool
+parse_tolerance(struct load_state *data,
+                const char *p,
+                struct vr_tolerance *tolerance)
 {
-        bool relative = false;
-        enum { POINT, RECT, ALL } region_type = POINT;
-        int n_components;
+        bool parse_percent = false;
+        bool parse_ulp = false;
+        int n_args;
 
-        if (looking_at(&p, "relative "))
-                relative = true;
+        if (looking_at(&p, "ulp "))
+                parse_ulp = true;
 
-        if (!looking_at(&p, "probe "))
-                return PARSE_RESULT_NON_MATCHED;
+        for (n_args = 0; !is_end(p) && n_args < 4; n_args++) {
+                while (vr_char_is_space(*p))
+                        p++;
 
-        struct vr_script_command *command = add_command(data);
+                if (!parse_ulp && looking_at(&p, "ulp")) {
+                        error_at_line(data,
+                                      "a ULP tolerance can’t be mixed with "
+                                      "other tolerance values");
+                        return false;
+                }
+
+                if (!parse_doubles(data,
+                                   &p,
+                                   tolerance->value + n_args,
+                                   1,
+                                   NULL) ||
+                    tolerance->value[n_args] < 0.0) {
+                        error_at_line(data, "invalid tolerance value");
+                        return false;
+                }
+
+                while (vr_char_is_space(*p))
+                        p++;
+
+                if (parse_ulp) {
+                        if (*p == '%') {
+                                error_at_line(data,
+                                              "a ULP tolerance can’t be "
+                                              "mixed with percentages");
+                                return false;
+                        }
+                        if (tolerance->value[n_args] !=
+                            floor(tolerance->value[n_args])) {
+                                error_at_line(data,
+                                              "ULP tolerance values must be "
+                                              "integers");
+                                return false;
+                        }
+                } else if (n_args == 0) {
+                        if (*p == '%') {
+                                parse_percent = true;
+                                p++;
+                        }
+                } else if (parse_percent) {
+                        if (*p != '%') {
+                                error_at_line(data,
+                                              "either all tolerance "
+                                              "values must be a percentage "
+                                              "or none");
+                                return false;
+                        }
+                        p++;
+                }
+        }
+
+        if (n_args == 1) {
+                for (unsigned i = 1; i < 4; i++)
+                        tolerance->value[i] = tolerance->value[0];
+        } else if (n_args != 4) {
+                error_at_line(data,
+                              "there must be either 1 or 4 "
+                              "tolerance values");
+                return false;
+        }
+
+        if (!is_end(p)) {
+                error_at_line(data, "tolerance command has extra arguments");
+                return false;
+        }
+
+        tolerance->is_percent = parse_percent;
+        tolerance->is_ulp = parse_ulp;
+
+        return true;
+}
+
+/* Parses the end of a probe command, which can optionally have a
+ * “relative-tolerance” clause to compare the colors with a tolerance
+ * relative to the expected value or a “tolerance” clause to override
+ * the current tolerance just for this probe. Unlike the other parts of
+ * the probe command, any error is reported here so that a bad
+ * tolerance clause only gets one message. */
+static bool
+parse_probe_end(struct load_state *data,
+                const char *p,
+                struct vr_script_command *command)
+{
+        while (vr_char_is_space(*p))
+                p++;
+
+        if (looking_at(&p, "relative-tolerance ")) {
+                double value;
+
+                if (command->probe_rect.comparison !=
+                    VR_SCRIPT_PROBE_COMPARISON_EQUAL ||
+                    !parse_doubles(data, &p, &value, 1, NULL) ||
+                    value < 0.0)
+                        goto error;
+
+                /* This is the same as a percentage tolerance */
+                for (int i = 0; i < 4; i++)
+                        command->probe_rect.tolerance.value[i] = value * 100.0;
+                command->probe_rect.tolerance.is_percent = true;
+                command->probe_rect.tolerance.is_ulp = false;
+        } else if (looking_at(&p, "tolerance ")) {
+                /* This overrides the current tolerance just for
+                 * this probe. It has to be at the end of the line
+                 * so parse_tolerance checks for extra arguments. */
+                if (command->probe_rect.comparison !=
+                    VR_SCRIPT_PROBE_COMPARISON_EQUAL)
+                        goto error;
+
+                return parse_tolerance(data,
+                                       p,
+                                       &command->probe_rect.tolerance);
+        }
+
+        if (is_end(p))
+                return true;
+
+error:
+        error_at_line(data, "Invalid probe command");
+        return false;
+}
+
+static enum parse_result
+process_probe_command(struct load_state *data,
+                      const char *p)
+{
+        bool relative = false;
+        enum { POINT, RECT, ALL } region_type = POINT;
+        int n_components;
+
+        if (looking_at(&p, "relative "))
+                relative = true;
+
+        if (!looking_at(&p, "probe "))
+                return PARSE_RESULT_NON_MATCHED;
+
+        struct vr_script_command *command = add_command(data);
 
         command->probe_rect.tolerance = data->tolerance;
 
+        if (looking_at(&p, "attachment ")) {
+                unsigned attachment;
+
+                if (!parse_uints(&p, &attachment, 1, NULL) ||
+                    !vr_char_is_space(*p)) {
+                        error_at_line(data, "Invalid attachment in probe "
+                                      "command");
+                        return PARSE_RESULT_ERROR;
+                }
+
+                if (attachment >=
+                    data->script->window_format.n_color_attachments) {
+                        error_at_line(data,
+                                      "Color attachment %u is not declared "
+                                      "in the [require] section",
+                                      attachment);
+                        return PARSE_RESULT_ERROR;
+                }
+
+                command->probe_rect.attachment = attachment;
+
+                while (vr_char_is_space(*p))
+                        p++;
+        }
+
         if (looking_at(&p, "rect "))
                 region_type = RECT;
         else if (looking_at(&p, "all "))
                 region_type = ALL;
 
+        if (looking_at(&p, "greater ")) {
+                command->probe_rect.comparison =
+                        VR_SCRIPT_PROBE_COMPARISON_GREATER;
+        } else if (looking_at(&p, "less ")) {
+                command->probe_rect.comparison =
+                        VR_SCRIPT_PROBE_COMPARISON_LESS;
+        } else {
+                command->probe_rect.comparison =
+                        VR_SCRIPT_PROBE_COMPARISON_EQUAL;
+        }
+
         if (looking_at(&p, "rgb ")) {
                 n_components = 3;
         } else if (looking_at(&p, "rgba ")) {
@@ -1223,9 +1804,10 @@ process_probe_command(struct load_state *data,
                                    &p,
                                    command->probe_rect.color,
                                    n_components,
-                                   NULL) ||
-                    !is_end(p))
+                                   NULL))
                         goto error;
+                if (!parse_probe_end(data, p, command))
+                        return PARSE_RESULT_ERROR;
                 command->probe_rect.x = 0;
                 command->probe_rect.y = 0;
                 command->probe_rect.w = window_width;
@@ -1302,8 +1884,8 @@ process_probe_command(struct load_state *data,
                 goto error;
         p++;
 
-        if (!is_end(p))
-                goto error;
+        if (!parse_probe_end(data, p, command))
+                return PARSE_RESULT_ERROR;
 
         return PARSE_RESULT_OK;
 
@@ -1313,1417 +1895,4545 @@ error:
 }
 
 static enum parse_result
-process_probe_ssbo_command(struct load_state *data,
-                           const char *p)
+process_probe_ssbo_equal_command(struct load_state *data,
+                                 const char *p)
 {
-        if (!looking_at(&p, "probe ssbo "))
+        if (!looking_at(&p, "probe ssbo equal "))
                 return PARSE_RESULT_NON_MATCHED;
 
         struct vr_script_command *command = add_command(data);
 
-        if (!parse_value_type(&p, &command->probe_ssbo.type))
+        command->op = VR_SCRIPT_OP_PROBE_SSBO_EQUAL;
+
+        for (int i = 0; i < 2; i++) {
+                unsigned values[2];
+
+                while (vr_char_is_space(*p))
+                        p++;
+
+                if (!parse_desc_set_and_binding(&p, values))
+                        goto error;
+
+                command->probe_ssbo_equal.buffers[i].desc_set = values[0];
+                command->probe_ssbo_equal.buffers[i].binding = values[1];
+        }
+
+        while (vr_char_is_space(*p))
+                p++;
+
+        if (!parse_size_t(&p, &command->probe_ssbo_equal.size) ||
+            !is_end(p))
                 goto error;
 
-        command->probe_ssbo.layout = data->ssbo_layout;
+        return PARSE_RESULT_OK;
+
+error:
+        error_at_line(data, "Invalid probe ssbo equal command");
+        return PARSE_RESULT_ERROR;
+}
+
+static enum parse_result
+process_probe_ssbo_histogram_command(struct load_state *data,
+                                     const char *p)
+{
+        if (!looking_at(&p, "probe ssbo histogram "))
+                return PARSE_RESULT_NON_MATCHED;
+
+        struct vr_script_command *command = add_command(data);
+
+        command->op = VR_SCRIPT_OP_PROBE_SSBO_HISTOGRAM;
 
         while (vr_char_is_space(*p))
                 p++;
 
-        unsigned values[3];
-        if (!parse_desc_set_and_binding(&p, values) ||
-            !parse_uints(&p, &values[2], 1, NULL)) {
+        if (!parse_value_type(&p, &command->probe_ssbo_histogram.type))
                 goto error;
+
+        const struct vr_box_type_info *type_info =
+                vr_box_type_get_info(command->probe_ssbo_histogram.type);
+
+        if (type_info->columns != 1 || type_info->rows != 1) {
+                error_at_line(data,
+                              "probe ssbo histogram only supports scalar "
+                              "types");
+                return PARSE_RESULT_ERROR;
         }
 
-        command->probe_ssbo.desc_set = values[0];
-        command->probe_ssbo.binding = values[1];
-        command->probe_ssbo.offset = values[2];
+        command->probe_ssbo_histogram.layout = data->ssbo_layout;
 
         while (vr_char_is_space(*p))
                 p++;
 
-        static const char *comparison_names[] =
-        {
-                [VR_BOX_COMPARISON_EQUAL] = "==",
-                [VR_BOX_COMPARISON_FUZZY_EQUAL] = "~=",
-                [VR_BOX_COMPARISON_NOT_EQUAL] = "!=",
-                [VR_BOX_COMPARISON_LESS] = "<",
-                [VR_BOX_COMPARISON_GREATER_EQUAL] = ">=",
-                [VR_BOX_COMPARISON_GREATER] = ">",
-                [VR_BOX_COMPARISON_LESS_EQUAL] = "<=",
-        };
+        unsigned values[4];
+        if (!parse_desc_set_and_binding(&p, values) ||
+            !parse_uints(&p, values + 2, 2, NULL) ||
+            values[3] < 1)
+                goto error;
 
-        for (unsigned i = 0; i < VR_N_ELEMENTS(comparison_names); i++) {
-                if (looking_at(&p, comparison_names[i])) {
-                        command->probe_ssbo.comparison = i;
-                        goto found_comparison;
-                }
-        }
-        goto error;
-found_comparison:
+        command->probe_ssbo_histogram.desc_set = values[0];
+        command->probe_ssbo_histogram.binding = values[1];
+        command->probe_ssbo_histogram.offset = values[2];
+        command->probe_ssbo_histogram.n_values = values[3];
 
         while (vr_char_is_space(*p))
                 p++;
 
-        size_t value_size;
+        unsigned n_buckets;
 
-        size_t type_size = vr_box_type_size(command->probe_ssbo.type,
-                                            &command->probe_ssbo.layout);
-        if (!parse_box_values(data,
-                              &p,
-                              command->probe_ssbo.type,
-                              &command->probe_ssbo.layout,
-                              type_size, /* array_stride (tightly packed) */
-                              &value_size,
-                              &command->probe_ssbo.value))
+        if (!looking_at(&p, "buckets ") ||
+            !parse_uints(&p, &n_buckets, 1, NULL) ||
+            n_buckets < 1)
                 goto error;
 
-        if (!is_end(p)) {
-                vr_free(command->probe_ssbo.value);
+        command->probe_ssbo_histogram.n_buckets = n_buckets;
+
+        while (vr_char_is_space(*p))
+                p++;
+
+        if (!looking_at(&p, "expect "))
+                goto error;
+
+        command->probe_ssbo_histogram.expected =
+                vr_alloc(n_buckets * sizeof (unsigned));
+
+        if (!parse_uints(&p,
+                         command->probe_ssbo_histogram.expected,
+                         n_buckets,
+                         NULL) ||
+            !is_end(p))
                 goto error;
+
+        /* The counts are integers so a ULP distance wouldn’t mean
+         * anything */
+        if (data->tolerance.is_ulp) {
+                error_at_line(data,
+                              "a ULP tolerance can’t be used with a "
+                              "histogram probe");
+                return PARSE_RESULT_ERROR;
         }
 
-        command->probe_ssbo.n_values = value_size / type_size;
-        command->op = VR_SCRIPT_OP_PROBE_SSBO;
-        command->probe_ssbo.tolerance = data->tolerance;
+        command->probe_ssbo_histogram.tolerance = data->tolerance;
 
         return PARSE_RESULT_OK;
 
 error:
-        error_at_line(data, "Invalid probe ssbo command");
+        error_at_line(data, "Invalid probe ssbo histogram command");
         return PARSE_RESULT_ERROR;
 }
 
-static enum parse_result
-process_draw_arrays_command(struct load_state *data,
-                            const char *p)
+static char *
+get_script_relative_path(struct load_state *data,
+                         const char *filename)
 {
-        if (!looking_at(&p, "draw arrays "))
-                return PARSE_RESULT_NON_MATCHED;
+        if (data->source->type != VR_SOURCE_TYPE_FILE || filename[0] == '/')
+                return vr_strdup(filename);
 
-        struct vr_script_command *command = add_command(data);
-
-        int args[3] = { [2] = 1 };
-        int n_args = 2;
+        const char *script_filename = data->source->string;
+        const char *slash = strrchr(script_filename, '/');
 
-        command->draw_arrays.indexed = false;
+        if (slash == NULL)
+                return vr_strdup(filename);
 
-        while (true) {
-                if (looking_at(&p, "instanced ")) {
-                        n_args = 3;
-                        continue;
-                } else if (looking_at(&p, "indexed ")) {
-                        command->draw_arrays.indexed = true;
-                        continue;
-                }
+        struct vr_buffer buf = VR_BUFFER_STATIC_INIT;
 
-                break;
-        }
+        vr_buffer_append(&buf, script_filename, slash + 1 - script_filename);
+        vr_buffer_append_string(&buf, filename);
 
-        VkPrimitiveTopology topology;
+        return (char *) buf.data;
+}
 
-        static const struct {
-                const char *name;
-                VkPrimitiveTopology topology;
-        } topologies[] = {
-                /* GL names used in Piglit */
-                { "GL_POINTS", VK_PRIMITIVE_TOPOLOGY_POINT_LIST },
-                { "GL_LINES", VK_PRIMITIVE_TOPOLOGY_LINE_LIST },
-                { "GL_LINE_STRIP", VK_PRIMITIVE_TOPOLOGY_LINE_STRIP },
-                { "GL_TRIANGLES", VK_PRIMITIVE_TOPOLOGY_TRIANGLE_LIST },
-                { "GL_TRIANGLE_STRIP", VK_PRIMITIVE_TOPOLOGY_TRIANGLE_STRIP },
-                { "GL_TRIANGLE_FAN", VK_PRIMITIVE_TOPOLOGY_TRIANGLE_FAN },
-                { "GL_LINES_ADJACENCY",
-                  VK_PRIMITIVE_TOPOLOGY_LINE_LIST_WITH_ADJACENCY },
-                { "GL_LINE_STRIP_ADJACENCY",
-                  VK_PRIMITIVE_TOPOLOGY_LINE_STRIP_WITH_ADJACENCY },
-                { "GL_TRIANGLES_ADJACENCY",
-                  VK_PRIMITIVE_TOPOLOGY_TRIANGLE_LIST_WITH_ADJACENCY },
-                { "GL_TRIANGLE_STRIP_ADJACENCY",
-                  VK_PRIMITIVE_TOPOLOGY_TRIANGLE_STRIP_WITH_ADJACENCY },
-                { "GL_PATCHES", VK_PRIMITIVE_TOPOLOGY_PATCH_LIST },
-                /* Vulkan names */
-#define vkname(x) { VR_STRINGIFY(x), VK_PRIMITIVE_TOPOLOGY_ ## x }
-                vkname(POINT_LIST),
-                vkname(LINE_LIST),
-                vkname(LINE_STRIP),
-                vkname(TRIANGLE_LIST),
-                vkname(TRIANGLE_STRIP),
-                vkname(TRIANGLE_FAN),
-                vkname(LINE_LIST_WITH_ADJACENCY),
-                vkname(LINE_STRIP_WITH_ADJACENCY),
-                vkname(TRIANGLE_LIST_WITH_ADJACENCY),
-                vkname(TRIANGLE_STRIP_WITH_ADJACENCY),
-                vkname(PATCH_LIST),
-#undef vkname
-        };
+static bool
+load_script_relative_file(struct load_state *data,
+                          const char *filename,
+                          uint8_t **contents_out,
+                          size_t *size_out)
+{
+        char *path = get_script_relative_path(data, filename);
+        bool ret = false;
+        FILE *f = fopen(path, "rb");
 
-        for (int i = 0; i < VR_N_ELEMENTS(topologies); i++) {
-                if (looking_at(&p, topologies[i].name)) {
-                        topology = topologies[i].topology;
-                        goto found_topology;
-                }
+        if (f == NULL) {
+                error_at_line(data, "%s: %s", path, strerror(errno));
+                goto out;
         }
 
-        error_at_line(data, "Unknown topology in draw arrays command");
-        return PARSE_RESULT_ERROR;
+        long size;
 
-found_topology:
-        if (!parse_ints(&p, args, n_args, NULL) ||
-            !is_end(p)) {
-                error_at_line(data, "Invalid draw arrays command");
-                return PARSE_RESULT_ERROR;
+        if (fseek(f, 0, SEEK_END) != 0 ||
+            (size = ftell(f)) < 0 ||
+            fseek(f, 0, SEEK_SET) != 0) {
+                error_at_line(data, "%s: %s", path, strerror(errno));
+                goto out;
         }
 
-        struct vr_pipeline_key key;
-        vr_pipeline_key_copy(&key, &data->current_key);
-        key.type = VR_PIPELINE_KEY_TYPE_GRAPHICS;
-        key.source = VR_PIPELINE_KEY_SOURCE_VERTEX_DATA;
-        key.topology.i = topology;
+        uint8_t *contents = vr_alloc(MAX(size, 1));
 
-        command->op = VR_SCRIPT_OP_DRAW_ARRAYS;
-        command->draw_arrays.first_vertex = args[0];
-        command->draw_arrays.vertex_count = args[1];
-        command->draw_arrays.first_instance = 0;
-        command->draw_arrays.instance_count = args[2];
-        command->draw_arrays.pipeline_key = add_pipeline_key(data, &key);
+        if (fread(contents, 1, size, f) != (size_t) size) {
+                error_at_line(data, "%s: error reading file", path);
+                vr_free(contents);
+                goto out;
+        }
 
-        vr_pipeline_key_destroy(&key);
+        *contents_out = contents;
+        *size_out = size;
 
-        return PARSE_RESULT_OK;
+        ret = true;
+
+out:
+        if (f)
+                fclose(f);
+        vr_free(path);
+        return ret;
 }
 
-static enum parse_result
-process_compute_command(struct load_state *data,
-                        const char *p)
+/* Parses a filename in double quotes. Returns a newly allocated copy
+ * of the filename or NULL if it is invalid.
+ */
+static char *
+parse_quoted_filename(const char **p)
 {
-        if (!looking_at(&p, "compute "))
-                return PARSE_RESULT_NON_MATCHED;
-
-        unsigned parts[3];
-
-        if (!parse_uints(&p, parts, 3, NULL) ||
-            !is_end(p)) {
-                error_at_line(data, "Invalid compute command");
-                return PARSE_RESULT_ERROR;
-        }
+        const char *start = *p;
+        const char *end;
 
-        struct vr_script_command *command = add_command(data);
+        while (vr_char_is_space(*start))
+                start++;
 
-        command->dispatch_compute.x = parts[0];
-        command->dispatch_compute.y = parts[1];
-        command->dispatch_compute.z = parts[2];
+        if (*start != '"' ||
+            (end = strchr(start + 1, '"')) == NULL ||
+            end == start + 1)
+                return NULL;
 
-        data->current_key.type = VR_PIPELINE_KEY_TYPE_COMPUTE;
-        command->op = VR_SCRIPT_OP_DISPATCH_COMPUTE;
-        command->dispatch_compute.pipeline_key =
-                add_pipeline_key(data, &data->current_key);
+        *p = end + 1;
 
-        return PARSE_RESULT_OK;
+        return vr_strndup(start + 1, end - start - 1);
 }
 
-static enum parse_result
-process_push_command(struct load_state *data,
-                     const char *p)
+static const char *
+probe_buffer_name(const struct vr_script_command *command)
 {
-        if (!looking_at(&p, "uniform ") &&
-            !looking_at(&p, "push "))
-                return PARSE_RESULT_NON_MATCHED;
-
-        struct vr_script_command *command = add_command(data);
+        switch (command->probe_ssbo.buffer_type) {
+        case VR_SCRIPT_BUFFER_TYPE_UBO:
+                return "ubo";
+        case VR_SCRIPT_BUFFER_TYPE_SSBO:
+                return "ssbo";
+        }
 
-        while (vr_char_is_space(*p))
-                p++;
+        vr_fatal("Unknown buffer type");
+}
 
-        enum vr_box_type type;
-
-        if (!parse_value_type(&p, &type) ||
-            !parse_size_t(&p, &command->set_push_constant.offset) ||
-            !parse_buffer_subdata(data,
-                                  &p,
-                                  type,
-                                  &data->push_layout,
-                                  &command->set_push_constant.size,
-                                  &command->set_push_constant.data) ||
-            !is_end(p)) {
-                error_at_line(data, "Invalid uniform command");
-                return PARSE_RESULT_ERROR;
+static const struct vr_box_layout *
+probe_buffer_layout(const struct load_state *data,
+                    const struct vr_script_command *command)
+{
+        switch (command->probe_ssbo.buffer_type) {
+        case VR_SCRIPT_BUFFER_TYPE_UBO:
+                return &data->ubo_layout;
+        case VR_SCRIPT_BUFFER_TYPE_SSBO:
+                return &data->ssbo_layout;
         }
 
-        command->op = VR_SCRIPT_OP_SET_PUSH_CONSTANT;
-
-        return PARSE_RESULT_OK;
+        vr_fatal("Unknown buffer type");
 }
 
 static enum parse_result
-process_clear_command(struct load_state *data,
-                      const char *p)
+process_probe_ssbo_from_file(struct load_state *data,
+                             const char *p,
+                             struct vr_script_command *command)
 {
-        if (!looking_at(&p, "clear"))
-                return PARSE_RESULT_NON_MATCHED;
+        char *filename = parse_quoted_filename(&p);
 
-        if (!is_end(p)) {
+        if (filename == NULL)
+                goto error;
+
+        while (vr_char_is_space(*p))
+                p++;
+
+        /* Without a type the file is compared byte by byte. With a
+         * type the values are compared with the tolerance. The bytes
+         * are always tightly packed regardless of the buffer layout
+         * because std140 would otherwise give them an array stride
+         * of 16. */
+        if (parse_value_type(&p, &command->probe_ssbo.type)) {
+                command->probe_ssbo.comparison = VR_BOX_COMPARISON_FUZZY_EQUAL;
+                command->probe_ssbo.layout =
+                        *probe_buffer_layout(data, command);
+        } else {
+                command->probe_ssbo.type = VR_BOX_TYPE_UINT8;
+                command->probe_ssbo.comparison = VR_BOX_COMPARISON_EQUAL;
+                command->probe_ssbo.layout = default_ssbo_layout;
+        }
+
+        while (vr_char_is_space(*p))
+                p++;
+
+        unsigned values[3];
+        if (!parse_desc_set_and_binding(&p, values) ||
+            !parse_uints(&p, &values[2], 1, NULL) ||
+            !is_end(p))
+                goto error;
+
+        uint8_t *contents;
+        size_t size;
+
+        if (!load_script_relative_file(data, filename, &contents, &size)) {
+                vr_free(filename);
+                return PARSE_RESULT_ERROR;
+        }
+
+        size_t stride = vr_box_type_array_stride(command->probe_ssbo.type,
+                                                 &command->probe_ssbo.layout);
+
+        if (size == 0 || size % stride != 0) {
                 error_at_line(data,
-                              "The clear command doesn’t take any arguments");
+                              "%s: size is not a multiple of the stride "
+                              "of the type",
+                              filename);
+                vr_free(contents);
+                vr_free(filename);
                 return PARSE_RESULT_ERROR;
         }
 
-        struct vr_script_command *command = add_command(data);
+        vr_free(filename);
 
-        command->op = VR_SCRIPT_OP_CLEAR;
-        memcpy(command->clear.color,
-               data->clear_color,
-               sizeof data->clear_color);
-        command->clear.depth = data->clear_depth;
-        command->clear.stencil = data->clear_stencil;
+        command->op = VR_SCRIPT_OP_PROBE_SSBO;
+        command->probe_ssbo.desc_set = values[0];
+        command->probe_ssbo.binding = values[1];
+        command->probe_ssbo.offset = values[2];
+        command->probe_ssbo.n_values = size / stride;
+        command->probe_ssbo.value = contents;
+        command->probe_ssbo.tolerance = data->tolerance;
+        command->probe_ssbo.from_file = true;
 
         return PARSE_RESULT_OK;
+
+error:
+        vr_free(filename);
+        error_at_line(data,
+                      "Invalid probe %s fromfile command",
+                      probe_buffer_name(command));
+        return PARSE_RESULT_ERROR;
 }
 
+static const char *
+box_comparison_names[] = {
+        [VR_BOX_COMPARISON_EQUAL] = "==",
+        [VR_BOX_COMPARISON_FUZZY_EQUAL] = "~=",
+        [VR_BOX_COMPARISON_NOT_EQUAL] = "!=",
+        [VR_BOX_COMPARISON_LESS] = "<",
+        [VR_BOX_COMPARISON_GREATER_EQUAL] = ">=",
+        [VR_BOX_COMPARISON_GREATER] = ">",
+        [VR_BOX_COMPARISON_LESS_EQUAL] = "<=",
+};
+
 static bool
-process_indices_line(struct load_state *data)
+parse_box_comparison(const char **p,
+                     enum vr_box_comparison *comparison)
 {
-        const char *p = (char *) data->line.data;
-
-        while (true) {
-                while (*p && vr_char_is_space(*p))
-                        p++;
-
-                if (*p == '\0' || *p == '#')
+        for (unsigned i = 0; i < VR_N_ELEMENTS(box_comparison_names); i++) {
+                if (looking_at(p, box_comparison_names[i])) {
+                        *comparison = i;
                         return true;
-
-                vr_buffer_set_length(&data->indices,
-                                     data->indices.length + sizeof (uint16_t));
-                errno = 0;
-                char *tail;
-                unsigned value = strtoul(p, &tail, 10);
-
-                if (errno || value > UINT16_MAX) {
-                        error_at_line(data, "Invalid index");
-                        return false;
                 }
-
-                uint16_t *index = (uint16_t *) (data->indices.data +
-                                                data->indices.length) - 1;
-                *index = value;
-                p = tail;
         }
 
-        return true;
+        return false;
 }
 
 static enum parse_result
-process_bool_property(struct load_state *data,
-                      union vr_pipeline_key_value *value,
-                      const char *p)
+process_probe_buffer_command(struct load_state *data,
+                             const char *p)
 {
-        if (looking_at(&p, "true"))
-                value->i = true;
-        else if (looking_at(&p, "false"))
-                value->i = false;
-        else if (!parse_ints(&p, &value->i, 1, NULL))
-                goto error;
-
-        if (!is_end(p))
-                goto error;
+        enum vr_script_buffer_type buffer_type;
 
-        return PARSE_RESULT_OK;
+        if (looking_at(&p, "probe ssbo "))
+                buffer_type = VR_SCRIPT_BUFFER_TYPE_SSBO;
+        else if (looking_at(&p, "probe ubo "))
+                buffer_type = VR_SCRIPT_BUFFER_TYPE_UBO;
+        else
+                return PARSE_RESULT_NON_MATCHED;
 
-error:
-        error_at_line(data, "Invalid boolean value");
-        return PARSE_RESULT_ERROR;
-}
+        struct vr_script_command *command = add_command(data);
 
-static enum parse_result
-process_int_property(struct load_state *data,
-                     union vr_pipeline_key_value *value,
-                     const char *p)
-{
-        value->i = 0;
+        command->probe_ssbo.buffer_type = buffer_type;
 
-        while (true) {
-                int this_int;
+        if (looking_at(&p, "into ")) {
+                unsigned result[3];
 
                 while (vr_char_is_space(*p))
                         p++;
 
-                if (parse_ints(&p, &this_int, 1, NULL)) {
-                        value->i |= this_int;
-                } else if (vr_char_is_alnum(*p)) {
-                        const char *end = p + 1;
-                        while (vr_char_is_alnum(*end) || *end == '_')
-                                end++;
-                        char *enum_name = vr_strndup(p, end - p);
-                        bool is_enum = vr_pipeline_key_lookup_enum(enum_name,
-                                                                   &this_int);
-                        free(enum_name);
+                if (!parse_desc_set_and_binding(&p, result) ||
+                    !parse_uints(&p, &result[2], 1, NULL))
+                        goto error;
 
-                        if (!is_enum)
-                                goto error;
+                struct vr_script_buffer *result_buffer =
+                        get_buffer(data,
+                                   result[0],
+                                   result[1],
+                                   VR_SCRIPT_BUFFER_TYPE_SSBO);
+                if (result_buffer == NULL)
+                        return PARSE_RESULT_ERROR;
 
-                        value->i |= this_int;
-                        p = end;
-                } else {
-                        goto error;
-                }
+                size_t result_end = result[2] + sizeof (uint32_t);
 
-                if (is_end(p))
-                        break;
+                if (result_end > result_buffer->size)
+                        result_buffer->size = result_end;
+
+                command->probe_ssbo.write_result = true;
+                command->probe_ssbo.result_desc_set = result[0];
+                command->probe_ssbo.result_binding = result[1];
+                command->probe_ssbo.result_offset = result[2];
 
                 while (vr_char_is_space(*p))
                         p++;
+        }
 
-                if (*p != '|')
-                        goto error;
+        if (looking_at(&p, "fromfile "))
+                return process_probe_ssbo_from_file(data, p, command);
+
+        /* With “each” the binding is followed by a count instead of
+         * an offset and a single value is compared against every
+         * element from the start of the buffer */
+        command->probe_ssbo.each = looking_at(&p, "each ");
+
+        while (vr_char_is_space(*p))
                 p++;
-        }
 
-        return PARSE_RESULT_OK;
+        if (!parse_value_type(&p, &command->probe_ssbo.type))
+                goto error;
 
-error:
-        error_at_line(data, "Invalid int value");
-        return PARSE_RESULT_ERROR;
-}
+        command->probe_ssbo.layout = *probe_buffer_layout(data, command);
 
-static enum parse_result
-process_float_property(struct load_state *data,
-                       union vr_pipeline_key_value *value,
-                       const char *p)
-{
         while (vr_char_is_space(*p))
                 p++;
 
-        if (!parse_floats(data, &p, &value->f, 1, NULL) || !is_end(p)) {
-                error_at_line(data, "Invalid float value");
-                return PARSE_RESULT_ERROR;
+        unsigned values[3];
+        if (!parse_desc_set_and_binding(&p, values) ||
+            !parse_uints(&p, &values[2], 1, NULL)) {
+                goto error;
         }
 
-        return PARSE_RESULT_OK;
-}
+        command->probe_ssbo.desc_set = values[0];
+        command->probe_ssbo.binding = values[1];
 
-static enum parse_result
-process_pipeline_property(struct load_state *data,
-                          const char *p)
-{
-        if (!vr_char_is_alnum(*p))
-                return PARSE_RESULT_NON_MATCHED;
+        if (command->probe_ssbo.each) {
+                if (values[2] == 0)
+                        goto error;
+                command->probe_ssbo.offset = 0;
+        } else {
+                command->probe_ssbo.offset = values[2];
+        }
 
-        const char *end = p + 1;
-        while (vr_char_is_alnum(*end) || *end == '.')
-                end++;
-        char *prop_name = vr_strndup(p, end - p);
+        while (vr_char_is_space(*p))
+                p++;
 
-        enum vr_pipeline_key_value_type key_value_type;
-        union vr_pipeline_key_value *key_value =
-                vr_pipeline_key_lookup(&data->current_key,
-                                       prop_name,
-                                       &key_value_type);
+        if (!parse_box_comparison(&p, &command->probe_ssbo.comparison))
+                goto error;
 
-        vr_free(prop_name);
+        while (vr_char_is_space(*p))
+                p++;
 
-        if (key_value == NULL)
-                return PARSE_RESULT_NON_MATCHED;
+        command->probe_ssbo.tolerance = data->tolerance;
 
-        p = end;
+        /* An optional trailing tolerance clause overrides the
+         * ambient tolerance for just this probe. The values are
+         * parsed from a copy that stops before the clause.
+         */
+        char *values_copy = NULL;
+        const char *tolerance_start = strstr(p, "tolerance ");
+        const char *comment_start = strchr(p, '#');
+
+        if (tolerance_start &&
+            (comment_start == NULL || tolerance_start < comment_start)) {
+                if (!parse_tolerance(data,
+                                     tolerance_start + 10,
+                                     &command->probe_ssbo.tolerance))
+                        return PARSE_RESULT_ERROR;
 
-        while (*p && vr_char_is_space(*p))
-                p++;
+                values_copy = vr_strndup(p, tolerance_start - p);
+                p = values_copy;
+        }
 
-        switch (key_value_type) {
-        case VR_PIPELINE_KEY_VALUE_TYPE_BOOL:
-                return process_bool_property(data, key_value, p);
-        case VR_PIPELINE_KEY_VALUE_TYPE_INT:
-                return process_int_property(data, key_value, p);
-        case VR_PIPELINE_KEY_VALUE_TYPE_FLOAT:
-                return process_float_property(data, key_value, p);
+        size_t value_size;
+
+        size_t type_size = vr_box_type_size(command->probe_ssbo.type,
+                                            &command->probe_ssbo.layout);
+        bool values_ok = parse_box_values(data,
+                                          &p,
+                                          command->probe_ssbo.type,
+                                          &command->probe_ssbo.layout,
+                                          /* array_stride (tightly packed) */
+                                          type_size,
+                                          &value_size,
+                                          &command->probe_ssbo.value);
+
+        if (values_ok && !is_end(p)) {
+                vr_free(command->probe_ssbo.value);
+                values_ok = false;
         }
 
-        vr_fatal("Unknown pipeline property type");
-}
+        vr_free(values_copy);
 
-static struct vr_script_buffer *
-get_buffer(struct load_state *data,
-           unsigned desc_set,
-           unsigned binding,
-           enum vr_script_buffer_type type)
-{
-        struct vr_script_buffer *buffer =
-                (struct vr_script_buffer *) data->buffers.data;
-        unsigned n_buffers = (data->buffers.length /
-                              sizeof (struct vr_script_buffer));
+        if (!values_ok)
+                goto error;
 
-        for (unsigned i = 0; i < n_buffers; i++) {
-                if (buffer[i].desc_set == desc_set &&
-                    buffer[i].binding == binding) {
-                        if (buffer[i].type != type) {
-                                error_at_line(data,
-                                              "Buffer binding point "
-                                              "%u:%u used with different "
-                                              "types",
-                                              desc_set,
-                                              binding);
-                                return NULL;
-                        }
+        if (command->probe_ssbo.each) {
+                bool single_value = value_size == type_size;
 
-                        return buffer + i;
+                if (!single_value) {
+                        vr_free(command->probe_ssbo.value);
+                        goto error;
                 }
+
+                command->probe_ssbo.n_values = values[2];
+        } else {
+                command->probe_ssbo.n_values = value_size / type_size;
         }
 
-        vr_buffer_set_length(&data->buffers,
-                             data->buffers.length + sizeof *buffer);
-        buffer = ((struct vr_script_buffer *)
-                  (data->buffers.data + data->buffers.length) - 1);
-        buffer->type = type;
-        buffer->size = 0;
-        buffer->desc_set = desc_set;
-        buffer->binding = binding;
+        command->op = VR_SCRIPT_OP_PROBE_SSBO;
 
-        return buffer;
+        return PARSE_RESULT_OK;
+
+error:
+        error_at_line(data,
+                      "Invalid probe %s command",
+                      probe_buffer_name(command));
+        return PARSE_RESULT_ERROR;
 }
 
-static const struct vr_box_layout *
-get_layout_for_buffer_type(struct load_state *data,
-                           enum vr_script_buffer_type type)
+static bool
+parse_draw_indirect_count(struct load_state *data,
+                          const char *p,
+                          bool indexed,
+                          struct vr_script_command *command)
 {
-        switch (type) {
-        case VR_SCRIPT_BUFFER_TYPE_UBO:
-                return &data->ubo_layout;
-        case VR_SCRIPT_BUFFER_TYPE_SSBO:
-                return &data->ssbo_layout;
-        }
-
-        vr_fatal("Unknown buffer type");
-}
+        unsigned buffer[2], count_buffer[2];
+        unsigned offset, count_offset, max_draw_count;
 
-static bool
-process_set_buffer_subdata(struct load_state *data,
-                           unsigned desc_set,
-                           unsigned binding,
-                           enum vr_script_buffer_type type,
-                           const char *p,
-                           struct vr_script_command *command)
-{
-        command->set_buffer_subdata.desc_set = desc_set;
-        command->set_buffer_subdata.binding = binding;
+        while (vr_char_is_space(*p))
+                p++;
 
-        struct vr_script_buffer *buffer =
-                get_buffer(data, desc_set, binding, type);
-        if (buffer == NULL)
+        if (!parse_desc_set_and_binding(&p, buffer) ||
+            !parse_uints(&p, &offset, 1, NULL))
                 return false;
 
         while (vr_char_is_space(*p))
                 p++;
-        enum vr_box_type value_type;
-        if (!parse_value_type(&p, &value_type))
-                goto error;
-        if (!parse_size_t(&p, &command->set_buffer_subdata.offset))
-                goto error;
-        const struct vr_box_layout *layout =
-                get_layout_for_buffer_type(data, type);
-        if (!parse_buffer_subdata(data,
-                                  &p,
-                                  value_type,
-                                  layout,
-                                  &command->set_buffer_subdata.size,
-                                  &command->set_buffer_subdata.data))
-                goto error;
-        if (!is_end(p))
-                goto error;
 
-        size_t end = (command->set_buffer_subdata.offset +
-                      command->set_buffer_subdata.size);
-        if (end > buffer->size)
-                buffer->size = end;
+        if (!parse_desc_set_and_binding(&p, count_buffer) ||
+            !parse_uints(&p, &count_offset, 1, NULL) ||
+            !parse_uints(&p, &max_draw_count, 1, NULL) ||
+            !is_end(p))
+                return false;
 
-        command->op = VR_SCRIPT_OP_SET_BUFFER_SUBDATA;
+        size_t stride = (indexed ?
+                         sizeof (VkDrawIndexedIndirectCommand) :
+                         sizeof (VkDrawIndirectCommand));
 
-        return true;
+        struct vr_script_buffer *script_buffer =
+                get_buffer(data,
+                           buffer[0],
+                           buffer[1],
+                           VR_SCRIPT_BUFFER_TYPE_SSBO);
+        if (script_buffer == NULL)
+                return false;
 
-error:
-        error_at_line(data, "Invalid set buffer subdata command");
-        return false;
-}
+        size_t end = offset + stride * max_draw_count;
+        if (end > script_buffer->size)
+                script_buffer->size = end;
 
-static bool
-process_set_buffer_size(struct load_state *data,
-                        unsigned desc_set,
-                        unsigned binding,
-                        enum vr_script_buffer_type buffer_type,
-                        unsigned size)
-{
-        struct vr_script_buffer *buffer =
-                get_buffer(data, desc_set, binding, buffer_type);
-        if (buffer == NULL)
+        script_buffer = get_buffer(data,
+                                   count_buffer[0],
+                                   count_buffer[1],
+                                   VR_SCRIPT_BUFFER_TYPE_SSBO);
+        if (script_buffer == NULL)
                 return false;
 
-        if (size > buffer->size)
-                buffer->size = size;
+        end = count_offset + sizeof (uint32_t);
+        if (end > script_buffer->size)
+                script_buffer->size = end;
+
+        command->draw_indirect_count.indexed = indexed;
+        command->draw_indirect_count.desc_set = buffer[0];
+        command->draw_indirect_count.binding = buffer[1];
+        command->draw_indirect_count.offset = offset;
+        command->draw_indirect_count.count_desc_set = count_buffer[0];
+        command->draw_indirect_count.count_binding = count_buffer[1];
+        command->draw_indirect_count.count_offset = count_offset;
+        command->draw_indirect_count.max_draw_count = max_draw_count;
+
+        vr_requirements_add(data->script->requirements,
+                            "VK_KHR_draw_indirect_count");
+        vr_requirements_add_max_draw_indirect_count(data->script->requirements,
+                                                    max_draw_count);
 
         return true;
 }
 
 static enum parse_result
-process_entrypoint(struct load_state *data,
-                   const char *p)
+process_draw_arrays_command(struct load_state *data,
+                            const char *p)
 {
-        int stage;
-
-        for (stage = 0; stage < VR_SHADER_STAGE_N_STAGES; stage++) {
-                if (looking_at(&p, stage_names[stage]))
-                        goto found_stage;
-        }
+        if (!looking_at(&p, "draw arrays "))
+                return PARSE_RESULT_NON_MATCHED;
 
-        return PARSE_RESULT_NON_MATCHED;
+        struct vr_script_command *command = add_command(data);
 
-found_stage:
+        /* first vertex, vertex count, instance count, first instance */
+        int args[4] = { [2] = 1, [3] = 0 };
+        int n_args = 2;
+        bool indexed = false;
+        bool indirect_count = false;
 
-        if (!looking_at(&p, " entrypoint "))
-                return PARSE_RESULT_NON_MATCHED;
+        while (true) {
+                if (looking_at(&p, "instanced ")) {
+                        n_args = 3;
+                        continue;
+                } else if (looking_at(&p, "indexed ")) {
+                        indexed = true;
+                        continue;
+                } else if (looking_at(&p, "indirect count ")) {
+                        indirect_count = true;
+                        continue;
+                }
 
-        while (*p && vr_char_is_space(*p))
-                p++;
+                break;
+        }
 
-        const char *end = p + strlen(p);
+        VkPrimitiveTopology topology;
 
-        while (end > p && vr_char_is_space(end[-1]))
-                end--;
+        if (!lookup_topology(&p, &topology)) {
+                error_at_line(data, "Unknown topology in draw arrays command");
+                return PARSE_RESULT_ERROR;
+        }
 
-        if (end <= p) {
-                error_at_line(data, "Missing entrypoint name");
+        if (indirect_count) {
+                if (n_args != 2 ||
+                    !parse_draw_indirect_count(data, p, indexed, command)) {
+                        error_at_line(data,
+                                      "Invalid draw arrays indirect count "
+                                      "command");
+                        return PARSE_RESULT_ERROR;
+                }
+        } else if (!parse_ints(&p, args, n_args, NULL) ||
+                   (!is_end(p) &&
+                    (n_args != 3 ||
+                     !parse_ints(&p, args + 3, 1, NULL) ||
+                     !is_end(p)))) {
+                error_at_line(data, "Invalid draw arrays command");
                 return PARSE_RESULT_ERROR;
         }
 
-        char *entrypoint = vr_strndup(p, end - p);
-        vr_pipeline_key_set_entrypoint(&data->current_key, stage, entrypoint);
-        vr_free(entrypoint);
+        struct vr_pipeline_key key;
+        vr_pipeline_key_copy(&key, &data->current_key);
+        key.type = VR_PIPELINE_KEY_TYPE_GRAPHICS;
+        key.source = VR_PIPELINE_KEY_SOURCE_VERTEX_DATA;
+        key.topology.i = topology;
+
+        if (indirect_count) {
+                command->op = VR_SCRIPT_OP_DRAW_INDIRECT_COUNT;
+                command->draw_indirect_count.pipeline_key =
+                        add_pipeline_key(data, &key);
+                vr_pipeline_key_destroy(&key);
+                return PARSE_RESULT_OK;
+        }
+
+        command->op = VR_SCRIPT_OP_DRAW_ARRAYS;
+        command->draw_arrays.indexed = indexed;
+        command->draw_arrays.first_vertex = args[0];
+        command->draw_arrays.vertex_count = args[1];
+        command->draw_arrays.first_instance = args[3];
+        command->draw_arrays.instance_count = args[2];
+        command->draw_arrays.pipeline_key = add_pipeline_key(data, &key);
+
+        vr_pipeline_key_destroy(&key);
 
         return PARSE_RESULT_OK;
 }
 
 static enum parse_result
-process_patch_parameter_vertices(struct load_state *data,
-                                 const char *p)
+process_draw_mesh_command(struct load_state *data,
+                          const char *p)
 {
-        if (!looking_at(&p, "patch parameter vertices "))
+        if (!looking_at(&p, "draw mesh "))
                 return PARSE_RESULT_NON_MATCHED;
 
-        struct vr_pipeline_key *key = &data->current_key;
-        if (!parse_ints(&p, &key->patchControlPoints.i, 1, NULL) ||
+        unsigned parts[3];
+
+        if (!parse_uints(&p, parts, 3, NULL) ||
             !is_end(p)) {
-                error_at_line(data, "Invalid patch parameter vertices command");
+                error_at_line(data, "Invalid draw mesh command");
                 return PARSE_RESULT_ERROR;
         }
 
+        struct vr_script_command *command = add_command(data);
+
+        command->draw_mesh.x = parts[0];
+        command->draw_mesh.y = parts[1];
+        command->draw_mesh.z = parts[2];
+
+        struct vr_pipeline_key key;
+        vr_pipeline_key_copy(&key, &data->current_key);
+        key.type = VR_PIPELINE_KEY_TYPE_GRAPHICS;
+        key.source = VR_PIPELINE_KEY_SOURCE_VERTEX_DATA;
+
+        command->op = VR_SCRIPT_OP_DRAW_MESH;
+        command->draw_mesh.pipeline_key = add_pipeline_key(data, &key);
+
+        vr_pipeline_key_destroy(&key);
+
         return PARSE_RESULT_OK;
 }
 
 static enum parse_result
-process_clear_values(struct load_state *data,
-                     const char *p)
+process_occlusion_command(struct load_state *data,
+                          const char *p)
 {
-        if (!looking_at(&p, "clear "))
-                return PARSE_RESULT_NON_MATCHED;
+        struct vr_script_command *command;
 
-        if (looking_at(&p, "color ")) {
-                if (!parse_floats(data, &p, data->clear_color, 4, NULL) ||
-                    !is_end(p)) {
-                        error_at_line(data, "Invalid clear color command");
-                        return PARSE_RESULT_ERROR;
-                }
-        } else if (looking_at(&p, "depth ")) {
-                if (!parse_floats(data, &p, &data->clear_depth, 1, NULL) ||
-                    !is_end(p)) {
-                        error_at_line(data, "Invalid clear depth command");
+        if (looking_at(&p, "occlusion begin")) {
+                if (!is_end(p)) {
+                        error_at_line(data, "Invalid occlusion begin command");
                         return PARSE_RESULT_ERROR;
                 }
-        } else if (looking_at(&p, "stencil ")) {
-                if (!parse_uints(&p, &data->clear_stencil, 1, NULL) ||
-                    !is_end(p)) {
-                        error_at_line(data, "Invalid clear stencil command");
+
+                if (data->occlusion_query_line) {
+                        error_at_line(data,
+                                      "An occlusion query is already active "
+                                      "since line %i",
+                                      data->occlusion_query_line);
                         return PARSE_RESULT_ERROR;
                 }
-        } else {
-                return PARSE_RESULT_NON_MATCHED;
+
+                data->occlusion_query_line = data->line_num;
+
+                command = add_command(data);
+                command->op = VR_SCRIPT_OP_BEGIN_OCCLUSION_QUERY;
+
+                vr_requirements_add(data->script->requirements,
+                                    "occlusionQueryPrecise");
+
+                return PARSE_RESULT_OK;
         }
 
-        return PARSE_RESULT_OK;
-}
+        if (looking_at(&p, "occlusion end")) {
+                if (!is_end(p)) {
+                        error_at_line(data, "Invalid occlusion end command");
+                        return PARSE_RESULT_ERROR;
+                }
 
-static enum parse_result
-process_buffer_command(struct load_state *data,
-                       const char *p)
-{
-        enum vr_script_buffer_type buffer_type;
+                if (!data->occlusion_query_line) {
+                        error_at_line(data,
+                                      "occlusion end used without an active "
+                                      "occlusion query");
+                        return PARSE_RESULT_ERROR;
+                }
 
-        if (looking_at(&p, "ssbo "))
-                buffer_type = VR_SCRIPT_BUFFER_TYPE_SSBO;
-        else if (looking_at(&p, "ubo "))
-                buffer_type = VR_SCRIPT_BUFFER_TYPE_UBO;
-        else
-                return PARSE_RESULT_NON_MATCHED;
+                data->occlusion_query_line = 0;
 
-        unsigned binding[2];
+                command = add_command(data);
+                command->op = VR_SCRIPT_OP_END_OCCLUSION_QUERY;
 
-        if (!parse_desc_set_and_binding(&p, binding)) {
-                error_at_line(data, "Invalid binding in buffer command");
-                return PARSE_RESULT_ERROR;
+                return PARSE_RESULT_OK;
         }
 
+        if (!looking_at(&p, "probe occlusion "))
+                return PARSE_RESULT_NON_MATCHED;
+
+        command = add_command(data);
+        command->op = VR_SCRIPT_OP_PROBE_OCCLUSION;
+
         while (vr_char_is_space(*p))
                 p++;
 
-        if (looking_at(&p, "subdata ")) {
-                struct vr_script_command *command = add_command(data);
+        unsigned long long value;
+        char *tail;
 
-                if (!process_set_buffer_subdata(data,
-                                                binding[0],
-                                                binding[1],
-                                                buffer_type,
-                                                p,
-                                                command))
-                        return PARSE_RESULT_ERROR;
-        } else {
-                unsigned size;
+        if (!parse_box_comparison(&p,
+                                  &command->probe_occlusion.comparison) ||
+            command->probe_occlusion.comparison ==
+            VR_BOX_COMPARISON_FUZZY_EQUAL)
+                goto error;
 
-                if (!parse_uints(&p, &size, 1, NULL) || !is_end(p)) {
-                        error_at_line(data, "Invalid buffer command");
-                        return PARSE_RESULT_ERROR;
-                }
+        while (vr_char_is_space(*p))
+                p++;
 
-                if (!process_set_buffer_size(data,
-                                             binding[0],
-                                             binding[1],
-                                             buffer_type,
-                                             size))
-                        return PARSE_RESULT_ERROR;
-        }
+        /* strtoull would silently accept a negative number */
+        if (*p < '0' || *p > '9')
+                goto error;
+
+        errno = 0;
+        value = strtoull(p, &tail, 10);
+        if (errno != 0 || !is_end(tail))
+                goto error;
+
+        command->probe_occlusion.value = value;
 
         return PARSE_RESULT_OK;
+
+error:
+        error_at_line(data, "Invalid probe occlusion command");
+        return PARSE_RESULT_ERROR;
 }
 
-static enum parse_result
-process_uniform_ubo_command(struct load_state *data,
-                            const char *p)
+static bool
+check_occlusion_query(struct load_state *data)
 {
-        if (!looking_at(&p, "uniform ubo "))
-                return PARSE_RESULT_NON_MATCHED;
+        if (!data->occlusion_query_line || data->commands.length == 0)
+                return true;
 
-        struct vr_script_command *command = add_command(data);
+        const struct vr_script_command *command =
+                (const struct vr_script_command *)
+                (data->commands.data +
+                 data->commands.length -
+                 sizeof (struct vr_script_command));
 
-        unsigned values[2];
-        if (!parse_desc_set_and_binding(&p, values)) {
-                error_at_line(data, "Invalid binding in uniform ubo command");
-                return PARSE_RESULT_ERROR;
+        switch (command->op) {
+        case VR_SCRIPT_OP_PROBE_RECT:
+        case VR_SCRIPT_OP_PROBE_SSBO:
+        case VR_SCRIPT_OP_PROBE_SSBO_EQUAL:
+        case VR_SCRIPT_OP_PROBE_SSBO_HISTOGRAM:
+        case VR_SCRIPT_OP_PROBE_OCCLUSION:
+                error_at_line(data,
+                              "Probes can’t be used while the occlusion "
+                              "query begun on line %i is active",
+                              data->occlusion_query_line);
+                return false;
+        default:
+                return true;
         }
+}
 
-        if (!process_set_buffer_subdata(data,
-                                        values[0],
-                                        values[1],
-                                        VR_SCRIPT_BUFFER_TYPE_UBO,
-                                        p,
-                                        command))
+static enum parse_result
+process_expect_submissions(struct load_state *data,
+                           const char *p)
+{
+        if (!looking_at(&p, "expect submissions "))
+                return PARSE_RESULT_NON_MATCHED;
+
+        unsigned n_submissions;
+
+        if (!parse_uints(&p, &n_submissions, 1, NULL) ||
+            n_submissions > INT_MAX ||
+            !is_end(p)) {
+                error_at_line(data, "Invalid expect submissions command");
                 return PARSE_RESULT_ERROR;
+        }
+
+        data->script->expected_submissions = n_submissions;
 
         return PARSE_RESULT_OK;
 }
 
 static enum parse_result
-process_tolerance(struct load_state *data,
-                  const char *p)
+process_compute_command(struct load_state *data,
+                        const char *p)
 {
-        if (!looking_at(&p, "tolerance "))
+        if (!looking_at(&p, "compute "))
                 return PARSE_RESULT_NON_MATCHED;
 
-        bool parse_percent = false;
-        int n_args;
-
-        for (n_args = 0; !is_end(p) && n_args < 4; n_args++) {
-                if (!parse_doubles(data,
-                                   &p,
-                                   data->tolerance.value + n_args,
-                                   1,
-                                   NULL) ||
-                    data->tolerance.value[n_args] < 0.0) {
-                        error_at_line(data, "invalid tolerance value");
-                        return PARSE_RESULT_ERROR;
-                }
-
-                while (vr_char_is_space(*p))
-                        p++;
-
-                if (n_args == 0) {
-                        if (*p == '%') {
-                                parse_percent = true;
-                                p++;
-                        }
-                } else if (parse_percent) {
-                        if (*p != '%') {
-                                error_at_line(data,
-                                              "either all tolerance "
-                                              "values must be a percentage "
-                                              "or none");
-                                return PARSE_RESULT_ERROR;
-                        }
-                        p++;
-                }
-        }
+        unsigned parts[3];
 
-        if (n_args == 1) {
-                for (unsigned i = 1; i < 4; i++)
-                        data->tolerance.value[i] = data->tolerance.value[0];
-        } else if (n_args != 4) {
-                error_at_line(data,
-                              "there must be either 1 or 4 "
-                              "tolerance values");
+        if (!parse_uints(&p, parts, 3, NULL) ||
+            !is_end(p)) {
+                error_at_line(data, "Invalid compute command");
                 return PARSE_RESULT_ERROR;
         }
 
-        if (!is_end(p)) {
-                error_at_line(data, "tolerance command has extra arguments");
-                return PARSE_RESULT_ERROR;
-        }
+        struct vr_script_command *command = add_command(data);
+
+        command->dispatch_compute.x = parts[0];
+        command->dispatch_compute.y = parts[1];
+        command->dispatch_compute.z = parts[2];
 
-        data->tolerance.is_percent = parse_percent;
+        data->current_key.type = VR_PIPELINE_KEY_TYPE_COMPUTE;
+        command->op = VR_SCRIPT_OP_DISPATCH_COMPUTE;
+        command->dispatch_compute.pipeline_key =
+                add_pipeline_key(data, &data->current_key);
 
         return PARSE_RESULT_OK;
 }
 
-static bool
-process_test_line(struct load_state *data)
+static enum parse_result
+process_push_command(struct load_state *data,
+                     const char *p)
 {
-        const char *p = (char *) data->line.data;
+        if (!looking_at(&p, "uniform ") &&
+            !looking_at(&p, "push "))
+                return PARSE_RESULT_NON_MATCHED;
 
-        while (*p && vr_char_is_space(*p))
-                p++;
+        struct vr_script_command *command = add_command(data);
 
-        if (*p == '#' || *p == '\0')
-                return true;
+        while (vr_char_is_space(*p))
+                p++;
 
-        static const process_test_line_func funcs[] = {
-                process_patch_parameter_vertices,
-                process_clear_values,
-                process_layout,
-                process_buffer_command,
-                process_tolerance,
-                process_entrypoint,
-                process_probe_ssbo_command,
-                process_probe_command,
-                process_draw_arrays_command,
-                process_compute_command,
-                process_uniform_ubo_command,
-                process_push_command,
-                process_clear_command,
-                process_draw_rect_command,
-                /* This should be last because it is more expensive to check */
-                process_pipeline_property,
-        };
+        enum vr_box_type type;
 
-        for (int i = 0; i < VR_N_ELEMENTS(funcs); i++) {
-                switch (funcs[i](data, p)) {
-                case PARSE_RESULT_NON_MATCHED:
-                        break;
-                case PARSE_RESULT_ERROR:
-                        return false;
-                case PARSE_RESULT_OK:
-                        return true;
-                }
+        if (!parse_value_type(&p, &type) ||
+            !parse_size_t(&p, &command->set_push_constant.offset) ||
+            !parse_buffer_subdata(data,
+                                  &p,
+                                  type,
+                                  &data->push_layout,
+                                  &command->set_push_constant.size,
+                                  &command->set_push_constant.data) ||
+            !is_end(p)) {
+                error_at_line(data, "Invalid uniform command");
+                return PARSE_RESULT_ERROR;
         }
 
-        error_at_line(data, "Invalid test command");
+        command->op = VR_SCRIPT_OP_SET_PUSH_CONSTANT;
 
-        return false;
+        return PARSE_RESULT_OK;
 }
 
-static void
-set_current_section(struct load_state *data,
-                    enum section section)
+static enum parse_result
+process_clear_checkerboard_command(struct load_state *data,
+                                   const char *p)
 {
-        data->current_section = section;
-        data->had_sections |= (1 << section);
+        if (!looking_at(&p, "clear checkerboard "))
+                return PARSE_RESULT_NON_MATCHED;
+
+        struct vr_script_command *command = add_command(data);
+
+        command->op = VR_SCRIPT_OP_CLEAR_CHECKERBOARD;
+
+        if (!parse_floats(data, &p,
+                          command->clear_checkerboard.colors[0],
+                          4,
+                          NULL) ||
+            !parse_floats(data, &p,
+                          command->clear_checkerboard.colors[1],
+                          4,
+                          NULL) ||
+            !parse_uints(&p, &command->clear_checkerboard.size, 1, NULL) ||
+            command->clear_checkerboard.size < 1 ||
+            !is_end(p)) {
+                error_at_line(data, "Invalid clear checkerboard command");
+                return PARSE_RESULT_ERROR;
+        }
+
+        return PARSE_RESULT_OK;
 }
 
+/* Only the layouts that are valid for the usage of the color
+ * image are allowed */
+static const struct {
+        const char *name;
+        VkImageLayout layout;
+} image_layouts[] = {
+        { "UNDEFINED", VK_IMAGE_LAYOUT_UNDEFINED },
+        { "GENERAL", VK_IMAGE_LAYOUT_GENERAL },
+        { "COLOR_ATTACHMENT_OPTIMAL",
+          VK_IMAGE_LAYOUT_COLOR_ATTACHMENT_OPTIMAL },
+        { "TRANSFER_SRC_OPTIMAL",
+          VK_IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL },
+};
+
 static bool
-is_stage_section(struct load_state *data,
-                 const char *start,
-                 const char *end)
+parse_image_layout(const char **p,
+                   bool allow_undefined,
+                   VkImageLayout *layout_out)
 {
-        static const char tail[] = " shader";
-        int stage;
+        while (vr_char_is_space(**p))
+                (*p)++;
 
-        for (stage = 0; stage < VR_SHADER_STAGE_N_STAGES; stage++) {
-                int len = strlen(stage_names[stage]);
+        for (int i = 0; i < VR_N_ELEMENTS(image_layouts); i++) {
+                size_t len = strlen(image_layouts[i].name);
+                const char *end = *p + len;
 
-                if (end - start >= len + (sizeof tail) - 1 &&
-                    !memcmp(start, stage_names[stage], len) &&
-                    !memcmp(start + len, tail, (sizeof tail) - 1)) {
-                        start += len + (sizeof tail) - 1;
-                        goto found_stage;
-                }
+                if (strncmp(*p, image_layouts[i].name, len) ||
+                    (*end && !vr_char_is_space(*end)))
+                        continue;
+
+                if (!allow_undefined &&
+                    image_layouts[i].layout == VK_IMAGE_LAYOUT_UNDEFINED)
+                        return false;
+
+                *layout_out = image_layouts[i].layout;
+                *p = end;
+                return true;
         }
 
         return false;
+}
 
-found_stage:
+static enum parse_result
+process_image_transition_command(struct load_state *data,
+                                 const char *p)
+{
+        if (!looking_at(&p, "image transition "))
+                return PARSE_RESULT_NON_MATCHED;
 
-        if (is_string(" spirv", start, end))
-                data->current_source_type = VR_SCRIPT_SOURCE_TYPE_SPIRV;
-        else if (is_string(" binary", start, end))
-                data->current_source_type = VR_SCRIPT_SOURCE_TYPE_BINARY;
-        else if (start == end)
-                data->current_source_type = VR_SCRIPT_SOURCE_TYPE_GLSL;
-        else
-                return false;
+        struct vr_script_command *command = add_command(data);
 
-        set_current_section(data, SECTION_SHADER);
-        data->current_stage = stage;
-        data->buffer.length = 0;
-        return true;
-}
+        command->op = VR_SCRIPT_OP_IMAGE_TRANSITION;
 
-static bool
-start_spirv_shader(struct load_state *data,
-                   enum vr_shader_stage stage)
-{
-        if (!vr_list_empty(&data->script->stages[stage])) {
-                error_at_line(data,
-                              "SPIR-V source can not be "
-                              "linked with other shaders in the "
-                              "same stage");
-                return false;
+        if (!parse_image_layout(&p,
+                                true, /* allow_undefined */
+                                &command->image_transition.old_layout) ||
+            !parse_image_layout(&p,
+                                false, /* allow_undefined */
+                                &command->image_transition.new_layout) ||
+            !is_end(p)) {
+                error_at_line(data, "Invalid image transition command");
+                return PARSE_RESULT_ERROR;
         }
 
-        return true;
+        return PARSE_RESULT_OK;
 }
 
-static bool
-is_spirv_shader(enum vr_script_source_type type)
+static enum parse_result
+process_clear_command(struct load_state *data,
+                      const char *p)
 {
-        switch (type) {
-        case VR_SCRIPT_SOURCE_TYPE_BINARY:
-        case VR_SCRIPT_SOURCE_TYPE_SPIRV:
-                return true;
-        case VR_SCRIPT_SOURCE_TYPE_GLSL:
-                return false;
+        if (!looking_at(&p, "clear"))
+                return PARSE_RESULT_NON_MATCHED;
+
+        if (!is_end(p)) {
+                error_at_line(data,
+                              "The clear command doesn’t take any arguments");
+                return PARSE_RESULT_ERROR;
         }
 
-        vr_fatal("Unexpected source type");
+        struct vr_script_command *command = add_command(data);
+
+        command->op = VR_SCRIPT_OP_CLEAR;
+        memcpy(command->clear.colors,
+               data->clear_colors,
+               sizeof data->clear_colors);
+        command->clear.depth = data->clear_depth;
+        command->clear.stencil = data->clear_stencil;
+
+        return PARSE_RESULT_OK;
 }
 
 static bool
-process_section_header(struct load_state *data)
+process_indices_line(struct load_state *data)
 {
-        if (!end_section(data))
-                return false;
+        const char *p = (char *) data->line.data;
 
-        const char *start = (char *) data->line.data + 1;
-        const char *end = strchr(start, ']');
-        if (end == NULL) {
-                error_at_line(data, "Missing ']'");
-                return false;
-        }
+        while (true) {
+                while (*p && vr_char_is_space(*p))
+                        p++;
 
-        if (is_stage_section(data, start, end)) {
-                if (is_spirv_shader(data->current_source_type) &&
-                    !start_spirv_shader(data, data->current_stage))
+                if (*p == '\0' || *p == '#')
+                        return true;
+
+                vr_buffer_set_length(&data->indices,
+                                     data->indices.length + sizeof (uint32_t));
+                errno = 0;
+                char *tail;
+                unsigned long value = strtoul(p, &tail, 10);
+
+                if (errno || tail == p || value > UINT32_MAX) {
+                        error_at_line(data, "Invalid index");
                         return false;
+                }
 
-                return true;
+                uint32_t *index = (uint32_t *) (data->indices.data +
+                                                data->indices.length) - 1;
+                *index = value;
+                p = tail;
         }
 
-        if (is_string("vertex shader passthrough", start, end)) {
-                if (!start_spirv_shader(data, VR_SHADER_STAGE_VERTEX))
+        return true;
+}
+
+/* The indices are parsed as 32-bit values. They are only kept like
+ * that if one of them doesn’t fit in 16 bits so that the index buffer
+ * for existing scripts doesn’t change.
+ */
+static void
+set_indices(struct vr_script *script,
+            struct vr_buffer *buffer)
+{
+        uint32_t *indices = (uint32_t *) buffer->data;
+        size_t n_indices = buffer->length / sizeof (uint32_t);
+
+        script->n_indices = n_indices;
+        script->indices = indices;
+        script->index_type = VK_INDEX_TYPE_UINT16;
+
+        for (size_t i = 0; i < n_indices; i++) {
+                if (indices[i] > UINT16_MAX) {
+                        script->index_type = VK_INDEX_TYPE_UINT32;
+                        return;
+                }
+        }
+
+        uint16_t *short_indices = (uint16_t *) indices;
+
+        for (size_t i = 0; i < n_indices; i++)
+                short_indices[i] = indices[i];
+}
+
+static enum parse_result
+process_bool_property(struct load_state *data,
+                      union vr_pipeline_key_value *value,
+                      const char *p)
+{
+        if (looking_at(&p, "true"))
+                value->i = true;
+        else if (looking_at(&p, "false"))
+                value->i = false;
+        else if (!parse_ints(&p, &value->i, 1, NULL))
+                goto error;
+
+        if (!is_end(p))
+                goto error;
+
+        return PARSE_RESULT_OK;
+
+error:
+        error_at_line(data, "Invalid boolean value");
+        return PARSE_RESULT_ERROR;
+}
+
+static enum parse_result
+process_int_property(struct load_state *data,
+                     const char *prop_name,
+                     union vr_pipeline_key_value *value,
+                     const char *p)
+{
+        value->i = 0;
+
+        while (true) {
+                int this_int;
+
+                while (vr_char_is_space(*p))
+                        p++;
+
+                if (parse_ints(&p, &this_int, 1, NULL)) {
+                        value->i |= this_int;
+                } else if (vr_char_is_alnum(*p)) {
+                        const char *end = p + 1;
+                        while (vr_char_is_alnum(*end) || *end == '_')
+                                end++;
+                        char *enum_name = vr_strndup(p, end - p);
+                        bool is_enum =
+                                vr_pipeline_key_lookup_property_enum(prop_name,
+                                                                     enum_name,
+                                                                     &this_int);
+                        free(enum_name);
+
+                        if (!is_enum)
+                                goto error;
+
+                        value->i |= this_int;
+                        p = end;
+                } else {
+                        goto error;
+                }
+
+                if (is_end(p))
+                        break;
+
+                while (vr_char_is_space(*p))
+                        p++;
+
+                if (*p != '|')
+                        goto error;
+                p++;
+        }
+
+        return PARSE_RESULT_OK;
+
+error:
+        error_at_line(data, "Invalid int value");
+        return PARSE_RESULT_ERROR;
+}
+
+static enum parse_result
+process_float_property(struct load_state *data,
+                       union vr_pipeline_key_value *value,
+                       const char *p)
+{
+        while (vr_char_is_space(*p))
+                p++;
+
+        if (!parse_floats(data, &p, &value->f, 1, NULL) || !is_end(p)) {
+                error_at_line(data, "Invalid float value");
+                return PARSE_RESULT_ERROR;
+        }
+
+        return PARSE_RESULT_OK;
+}
+
+static enum parse_result
+process_pipeline_property(struct load_state *data,
+                          const char *p)
+{
+        if (!vr_char_is_alnum(*p))
+                return PARSE_RESULT_NON_MATCHED;
+
+        const char *end = p + 1;
+        while (vr_char_is_alnum(*end) || *end == '.')
+                end++;
+        char *prop_name = vr_strndup(p, end - p);
+
+        enum vr_pipeline_key_value_type key_value_type;
+        union vr_pipeline_key_value *key_value =
+                vr_pipeline_key_lookup(&data->current_key,
+                                       prop_name,
+                                       &key_value_type);
+
+        if (key_value == NULL) {
+                vr_free(prop_name);
+                return PARSE_RESULT_NON_MATCHED;
+        }
+
+        p = end;
+
+        while (*p && vr_char_is_space(*p))
+                p++;
+
+        enum parse_result res;
+
+        switch (key_value_type) {
+        case VR_PIPELINE_KEY_VALUE_TYPE_BOOL:
+                res = process_bool_property(data, key_value, p);
+                break;
+        case VR_PIPELINE_KEY_VALUE_TYPE_INT:
+                res = process_int_property(data, prop_name, key_value, p);
+                break;
+        case VR_PIPELINE_KEY_VALUE_TYPE_FLOAT:
+                res = process_float_property(data, key_value, p);
+                break;
+        default:
+                vr_fatal("Unknown pipeline property type");
+        }
+
+        vr_free(prop_name);
+
+        return res;
+}
+
+static const struct vr_box_layout *
+get_layout_for_buffer_type(struct load_state *data,
+                           enum vr_script_buffer_type type)
+{
+        switch (type) {
+        case VR_SCRIPT_BUFFER_TYPE_UBO:
+                return &data->ubo_layout;
+        case VR_SCRIPT_BUFFER_TYPE_SSBO:
+                return &data->ssbo_layout;
+        }
+
+        vr_fatal("Unknown buffer type");
+}
+
+static bool
+process_set_buffer_subdata(struct load_state *data,
+                           unsigned desc_set,
+                           unsigned binding,
+                           enum vr_script_buffer_type type,
+                           const char *p,
+                           struct vr_script_command *command)
+{
+        command->set_buffer_subdata.desc_set = desc_set;
+        command->set_buffer_subdata.binding = binding;
+
+        struct vr_script_buffer *buffer =
+                get_buffer(data, desc_set, binding, type);
+        if (buffer == NULL)
+                return false;
+
+        while (vr_char_is_space(*p))
+                p++;
+        enum vr_box_type value_type;
+        if (!parse_value_type(&p, &value_type))
+                goto error;
+        if (!parse_size_t(&p, &command->set_buffer_subdata.offset))
+                goto error;
+        const struct vr_box_layout *layout =
+                get_layout_for_buffer_type(data, type);
+        if (!parse_buffer_subdata(data,
+                                  &p,
+                                  value_type,
+                                  layout,
+                                  &command->set_buffer_subdata.size,
+                                  &command->set_buffer_subdata.data))
+                goto error;
+        if (!is_end(p))
+                goto error;
+
+        size_t end = (command->set_buffer_subdata.offset +
+                      command->set_buffer_subdata.size);
+        if (end > buffer->size)
+                buffer->size = end;
+
+        command->op = VR_SCRIPT_OP_SET_BUFFER_SUBDATA;
+
+        return true;
+
+error:
+        error_at_line(data, "Invalid set buffer subdata command");
+        return false;
+}
+
+static bool
+process_set_buffer_size(struct load_state *data,
+                        unsigned desc_set,
+                        unsigned binding,
+                        enum vr_script_buffer_type buffer_type,
+                        unsigned size)
+{
+        struct vr_script_buffer *buffer =
+                get_buffer(data, desc_set, binding, buffer_type);
+        if (buffer == NULL)
+                return false;
+
+        if (size > buffer->size)
+                buffer->size = size;
+
+        return true;
+}
+
+static bool
+process_set_buffer_null(struct load_state *data,
+                        unsigned desc_set,
+                        unsigned binding,
+                        enum vr_script_buffer_type buffer_type)
+{
+        struct vr_script_buffer *buffer =
+                get_buffer(data, desc_set, binding, buffer_type);
+        if (buffer == NULL)
+                return false;
+
+        if (buffer->size > 0) {
+                error_at_line(data,
+                              "Buffer binding point %u:%u has data but is "
+                              "being set to null",
+                              desc_set,
+                              binding);
+                return false;
+        }
+
+        buffer->is_null = true;
+
+        vr_requirements_add(data->script->requirements, "nullDescriptor");
+
+        return true;
+}
+
+static bool
+process_set_buffer_copy(struct load_state *data,
+                        unsigned desc_set,
+                        unsigned binding,
+                        enum vr_script_buffer_type buffer_type,
+                        const unsigned source[2],
+                        bool alias)
+{
+        const struct vr_script_buffer *buffers =
+                (const struct vr_script_buffer *) data->buffers.data;
+        unsigned n_buffers = (data->buffers.length /
+                              sizeof (struct vr_script_buffer));
+        const struct vr_script_buffer *source_buffer = NULL;
+
+        for (unsigned i = 0; i < n_buffers; i++) {
+                if (buffers[i].desc_set == desc_set &&
+                    buffers[i].binding == binding) {
+                        error_at_line(data,
+                                      "Buffer binding point %u:%u is "
+                                      "already used",
+                                      desc_set,
+                                      binding);
                         return false;
-                set_current_section(data, SECTION_NONE);
-                add_shader(data->script,
-                           VR_SHADER_STAGE_VERTEX,
-                           VR_SCRIPT_SOURCE_TYPE_BINARY,
-                           sizeof vertex_shader_passthrough,
+                }
+
+                if (buffers[i].desc_set == source[0] &&
+                    buffers[i].binding == source[1])
+                        source_buffer = buffers + i;
+        }
+
+        if (source_buffer == NULL) {
+                error_at_line(data,
+                              "Buffer binding point %u:%u must be used "
+                              "before it can be copied",
+                              source[0],
+                              source[1]);
+                return false;
+        }
+
+        if (source_buffer->type != buffer_type) {
+                error_at_line(data,
+                              "Buffer binding point %u:%u used with "
+                              "different types",
+                              source[0],
+                              source[1]);
+                return false;
+        }
+
+        /* Always copy from the binding that has the real buffer */
+        unsigned copy_desc_set, copy_binding;
+
+        if (source_buffer->is_copy) {
+                copy_desc_set = source_buffer->copy_desc_set;
+                copy_binding = source_buffer->copy_binding;
+        } else {
+                copy_desc_set = source_buffer->desc_set;
+                copy_binding = source_buffer->binding;
+        }
+
+        struct vr_script_buffer *buffer =
+                get_buffer(data, desc_set, binding, buffer_type);
+
+        buffer->is_copy = true;
+        buffer->is_alias = alias;
+        buffer->copy_desc_set = copy_desc_set;
+        buffer->copy_binding = copy_binding;
+
+        return true;
+}
+
+static bool
+process_set_buffer_from_file(struct load_state *data,
+                             unsigned desc_set,
+                             unsigned binding,
+                             enum vr_script_buffer_type buffer_type,
+                             const char *filename,
+                             struct vr_script_command *command)
+{
+        struct vr_script_buffer *buffer =
+                get_buffer(data, desc_set, binding, buffer_type);
+        if (buffer == NULL)
+                return false;
+
+        uint8_t *contents;
+        size_t size;
+
+        if (!load_script_relative_file(data, filename, &contents, &size))
+                return false;
+
+        /* An empty file would make a buffer with a size of zero,
+         * which Vulkan doesn’t allow */
+        if (size == 0) {
+                vr_free(contents);
+                error_at_line(data, "Buffer file “%s” is empty", filename);
+                return false;
+        }
+
+        command->op = VR_SCRIPT_OP_SET_BUFFER_SUBDATA;
+        command->set_buffer_subdata.desc_set = desc_set;
+        command->set_buffer_subdata.binding = binding;
+        command->set_buffer_subdata.offset = 0;
+        command->set_buffer_subdata.size = size;
+        command->set_buffer_subdata.data = contents;
+
+        if (size > buffer->size)
+                buffer->size = size;
+
+        return true;
+}
+
+static enum parse_result
+process_entrypoint(struct load_state *data,
+                   const char *p)
+{
+        int stage;
+
+        for (stage = 0; stage < VR_SHADER_STAGE_N_STAGES; stage++) {
+                if (looking_at(&p, stage_names[stage]))
+                        goto found_stage;
+        }
+
+        return PARSE_RESULT_NON_MATCHED;
+
+found_stage:
+
+        if (!looking_at(&p, " entrypoint "))
+                return PARSE_RESULT_NON_MATCHED;
+
+        while (*p && vr_char_is_space(*p))
+                p++;
+
+        const char *end = p + strlen(p);
+
+        while (end > p && vr_char_is_space(end[-1]))
+                end--;
+
+        if (end <= p) {
+                error_at_line(data, "Missing entrypoint name");
+                return PARSE_RESULT_ERROR;
+        }
+
+        char *entrypoint = vr_strndup(p, end - p);
+        vr_pipeline_key_set_entrypoint(&data->current_key, stage, entrypoint);
+        vr_free(entrypoint);
+
+        return PARSE_RESULT_OK;
+}
+
+static enum parse_result
+process_patch_parameter_vertices(struct load_state *data,
+                                 const char *p)
+{
+        if (!looking_at(&p, "patch parameter vertices "))
+                return PARSE_RESULT_NON_MATCHED;
+
+        struct vr_pipeline_key *key = &data->current_key;
+        if (!parse_ints(&p, &key->patchControlPoints.i, 1, NULL) ||
+            !is_end(p)) {
+                error_at_line(data, "Invalid patch parameter vertices command");
+                return PARSE_RESULT_ERROR;
+        }
+
+        return PARSE_RESULT_OK;
+}
+
+static enum parse_result
+process_viewports(struct load_state *data,
+                  const char *p)
+{
+        if (!looking_at(&p, "viewports "))
+                return PARSE_RESULT_NON_MATCHED;
+
+        unsigned n_viewports;
+
+        if (!parse_uints(&p, &n_viewports, 1, NULL) ||
+            !is_end(p) ||
+            n_viewports < 1 ||
+            n_viewports > INT_MAX) {
+                error_at_line(data, "Invalid viewports command");
+                return PARSE_RESULT_ERROR;
+        }
+
+        data->current_key.n_viewports = n_viewports;
+
+        vr_requirements_add_max_viewports(data->script->requirements,
+                                          n_viewports);
+
+        return PARSE_RESULT_OK;
+}
+
+static enum parse_result
+process_viewport(struct load_state *data,
+                 const char *p)
+{
+        if (!looking_at(&p, "viewport "))
+                return PARSE_RESULT_NON_MATCHED;
+
+        float rect[4];
+        float depth[2] = { 0.0f, 1.0f };
+
+        if (!parse_floats(data, &p, rect, VR_N_ELEMENTS(rect), NULL) ||
+            rect[2] <= 0.0f ||
+            rect[3] <= 0.0f)
+                goto error;
+
+        /* The depth range is optional */
+        if (!is_end(p) &&
+            (!parse_floats(data, &p, depth, VR_N_ELEMENTS(depth), NULL) ||
+             depth[0] < 0.0f || depth[0] > 1.0f ||
+             depth[1] < 0.0f || depth[1] > 1.0f ||
+             !is_end(p)))
+                goto error;
+
+        data->current_key.viewport = (VkViewport) {
+                .x = rect[0],
+                .y = rect[1],
+                .width = rect[2],
+                .height = rect[3],
+                .minDepth = depth[0],
+                .maxDepth = depth[1]
+        };
+
+        return PARSE_RESULT_OK;
+
+error:
+        error_at_line(data, "Invalid viewport command");
+        return PARSE_RESULT_ERROR;
+}
+
+static enum parse_result
+process_scissor(struct load_state *data,
+                const char *p)
+{
+        if (!looking_at(&p, "scissor "))
+                return PARSE_RESULT_NON_MATCHED;
+
+        int rect[4];
+
+        if (!parse_ints(&p, rect, VR_N_ELEMENTS(rect), NULL) ||
+            !is_end(p) ||
+            rect[0] < 0 ||
+            rect[1] < 0 ||
+            rect[2] <= 0 ||
+            rect[3] <= 0) {
+                error_at_line(data, "Invalid scissor command");
+                return PARSE_RESULT_ERROR;
+        }
+
+        data->current_key.scissor = (VkRect2D) {
+                .offset = { rect[0], rect[1] },
+                .extent = { rect[2], rect[3] }
+        };
+
+        return PARSE_RESULT_OK;
+}
+
+static const struct {
+        const char *name;
+        VkPipelineCreateFlags flag;
+        const char *feature;
+} pipeline_flags[] = {
+        { "disable_optimization",
+          VK_PIPELINE_CREATE_DISABLE_OPTIMIZATION_BIT,
+          NULL },
+        { "allow_derivatives",
+          VK_PIPELINE_CREATE_ALLOW_DERIVATIVES_BIT,
+          NULL },
+        { "capture_statistics",
+          VK_PIPELINE_CREATE_CAPTURE_STATISTICS_BIT_KHR,
+          "pipelineExecutableInfo" },
+        { "capture_internal_representations",
+          VK_PIPELINE_CREATE_CAPTURE_INTERNAL_REPRESENTATIONS_BIT_KHR,
+          "pipelineExecutableInfo" },
+};
+
+static enum parse_result
+process_pipeline_flags(struct load_state *data,
+                       const char *p)
+{
+        if (!looking_at(&p, "pipelineFlags "))
+                return PARSE_RESULT_NON_MATCHED;
+
+        VkPipelineCreateFlags value = 0;
+
+        while (true) {
+                while (vr_char_is_space(*p))
+                        p++;
+
+                const char *end = p;
+                while (vr_char_is_alnum(*end) || *end == '_')
+                        end++;
+
+                for (int i = 0; i < VR_N_ELEMENTS(pipeline_flags); i++) {
+                        if (!is_string(pipeline_flags[i].name, p, end))
+                                continue;
+
+                        value |= pipeline_flags[i].flag;
+
+                        if (pipeline_flags[i].feature) {
+                                vr_requirements_add(data->script->requirements,
+                                                    pipeline_flags[i].feature);
+                        }
+
+                        goto found_flag;
+                }
+
+                error_at_line(data,
+                              "Unknown pipeline flag “%.*s”",
+                              (int) (end - p),
+                              p);
+                return PARSE_RESULT_ERROR;
+
+        found_flag:
+                p = end;
+
+                if (is_end(p))
+                        break;
+
+                while (vr_char_is_space(*p))
+                        p++;
+
+                if (*p != ',') {
+                        error_at_line(data, "Invalid pipelineFlags command");
+                        return PARSE_RESULT_ERROR;
+                }
+                p++;
+        }
+
+        data->current_key.flags = value;
+
+        return PARSE_RESULT_OK;
+}
+
+static enum parse_result
+process_specialize(struct load_state *data,
+                   const char *p)
+{
+        if (!looking_at(&p, "specialize "))
+                return PARSE_RESULT_NON_MATCHED;
+
+        unsigned constant_id;
+        uint32_t value;
+
+        if (!parse_uints(&p, &constant_id, 1, NULL))
+                goto error;
+
+        while (vr_char_is_space(*p))
+                p++;
+
+        if (looking_at(&p, "bool ")) {
+                while (vr_char_is_space(*p))
+                        p++;
+                if (looking_at(&p, "true")) {
+                        value = true;
+                } else if (looking_at(&p, "false")) {
+                        value = false;
+                } else {
+                        int int_value;
+                        if (!parse_ints(&p, &int_value, 1, NULL))
+                                goto error;
+                        value = int_value != 0;
+                }
+        } else if (looking_at(&p, "int ")) {
+                int int_value;
+                if (!parse_ints(&p, &int_value, 1, NULL))
+                        goto error;
+                value = int_value;
+        } else if (looking_at(&p, "uint ")) {
+                unsigned uint_value;
+                if (!parse_uints(&p, &uint_value, 1, NULL))
+                        goto error;
+                value = uint_value;
+        } else if (looking_at(&p, "float ")) {
+                float float_value;
+                if (!parse_floats(data, &p, &float_value, 1, NULL))
+                        goto error;
+                memcpy(&value, &float_value, sizeof value);
+        } else {
+                goto error;
+        }
+
+        if (!is_end(p))
+                goto error;
+
+        vr_pipeline_key_set_specialization(&data->current_key,
+                                           constant_id,
+                                           value);
+
+        return PARSE_RESULT_OK;
+
+error:
+        error_at_line(data, "Invalid specialize command");
+        return PARSE_RESULT_ERROR;
+}
+
+/* Parses the arguments of a clear color command. If there are five
+ * values then the first one is the index of the color attachment. */
+static bool
+parse_clear_color(struct load_state *data,
+                  const char *p)
+{
+        const char *start = p;
+        unsigned attachment = 0;
+        bool explicit_attachment = false;
+        float color[4];
+
+        if (!parse_floats(data, &p, color, 4, NULL) || !is_end(p)) {
+                p = start;
+                explicit_attachment = true;
+
+                if (!parse_uints(&p, &attachment, 1, NULL) ||
+                    !vr_char_is_space(*p) ||
+                    !parse_floats(data, &p, color, 4, NULL) ||
+                    !is_end(p)) {
+                        error_at_line(data, "Invalid clear color command");
+                        return false;
+                }
+
+                if (attachment >=
+                    data->script->window_format.n_color_attachments) {
+                        error_at_line(data,
+                                      "Color attachment %u is not declared "
+                                      "in the [require] section",
+                                      attachment);
+                        return false;
+                }
+        }
+
+        memcpy(data->clear_colors[attachment], color, sizeof color);
+
+        /* Only the form without an attachment number is inherited
+         * by the other attachments. An explicit attachment 0 is
+         * treated like any other attachment. */
+        if (!explicit_attachment) {
+                int n_color_attachments =
+                        data->script->window_format.n_color_attachments;
+
+                for (int i = 1; i < n_color_attachments; i++) {
+                        if (!data->clear_color_set[i]) {
+                                memcpy(data->clear_colors[i],
+                                       color,
+                                       sizeof color);
+                        }
+                }
+        } else {
+                data->clear_color_set[attachment] = true;
+        }
+
+        return true;
+}
+
+static enum parse_result
+process_clear_values(struct load_state *data,
+                     const char *p)
+{
+        if (!looking_at(&p, "clear "))
+                return PARSE_RESULT_NON_MATCHED;
+
+        if (looking_at(&p, "color ")) {
+                if (!parse_clear_color(data, p))
+                        return PARSE_RESULT_ERROR;
+        } else if (looking_at(&p, "depth ")) {
+                if (!parse_floats(data, &p, &data->clear_depth, 1, NULL) ||
+                    !is_end(p)) {
+                        error_at_line(data, "Invalid clear depth command");
+                        return PARSE_RESULT_ERROR;
+                }
+        } else if (looking_at(&p, "stencil ")) {
+                if (!parse_uints(&p, &data->clear_stencil, 1, NULL) ||
+                    !is_end(p)) {
+                        error_at_line(data, "Invalid clear stencil command");
+                        return PARSE_RESULT_ERROR;
+                }
+        } else {
+                return PARSE_RESULT_NON_MATCHED;
+        }
+
+        return PARSE_RESULT_OK;
+}
+
+static enum parse_result
+process_alias_command(struct load_state *data,
+                      const char *p)
+{
+        enum vr_script_buffer_type buffer_type;
+
+        if (!looking_at(&p, "alias "))
+                return PARSE_RESULT_NON_MATCHED;
+
+        if (looking_at(&p, "ssbo "))
+                buffer_type = VR_SCRIPT_BUFFER_TYPE_SSBO;
+        else if (looking_at(&p, "ubo "))
+                buffer_type = VR_SCRIPT_BUFFER_TYPE_UBO;
+        else
+                goto error;
+
+        unsigned source[2], binding[2];
+
+        if (!parse_desc_set_and_binding(&p, source))
+                goto error;
+
+        while (vr_char_is_space(*p))
+                p++;
+
+        if (!looking_at(&p, "as ") ||
+            !parse_desc_set_and_binding(&p, binding) ||
+            !is_end(p))
+                goto error;
+
+        if (!process_set_buffer_copy(data,
+                                     binding[0],
+                                     binding[1],
+                                     buffer_type,
+                                     source,
+                                     true /* alias */))
+                return PARSE_RESULT_ERROR;
+
+        return PARSE_RESULT_OK;
+
+error:
+        error_at_line(data, "Invalid alias command");
+        return PARSE_RESULT_ERROR;
+}
+
+static enum parse_result
+process_persist_command(struct load_state *data,
+                        const char *p)
+{
+        if (!looking_at(&p, "persist "))
+                return PARSE_RESULT_NON_MATCHED;
+
+        unsigned binding[2];
+
+        if (!looking_at(&p, "ssbo ") ||
+            !parse_desc_set_and_binding(&p, binding) ||
+            !is_end(p)) {
+                error_at_line(data, "Invalid persist command");
+                return PARSE_RESULT_ERROR;
+        }
+
+        /* Buffers are allocated once at the start of the test and
+         * are never cleared between submissions so there is nothing
+         * to do here other than make sure the buffer exists.
+         */
+        const struct vr_script_buffer *buffer =
+                get_buffer(data,
+                           binding[0],
+                           binding[1],
+                           VR_SCRIPT_BUFFER_TYPE_SSBO);
+
+        if (buffer == NULL)
+                return PARSE_RESULT_ERROR;
+
+        vr_buffer_set_length(&data->persisted_buffers,
+                             data->persisted_buffers.length +
+                             sizeof (struct persisted_buffer));
+
+        struct persisted_buffer *persisted =
+                (struct persisted_buffer *)
+                (data->persisted_buffers.data +
+                 data->persisted_buffers.length) - 1;
+
+        persisted->desc_set = buffer->desc_set;
+        persisted->binding = buffer->binding;
+        persisted->line_num = data->line_num;
+
+        return PARSE_RESULT_OK;
+}
+
+static enum parse_result
+process_buffer_command(struct load_state *data,
+                       const char *p)
+{
+        enum vr_script_buffer_type buffer_type;
+
+        if (looking_at(&p, "ssbo "))
+                buffer_type = VR_SCRIPT_BUFFER_TYPE_SSBO;
+        else if (looking_at(&p, "ubo "))
+                buffer_type = VR_SCRIPT_BUFFER_TYPE_UBO;
+        else
+                return PARSE_RESULT_NON_MATCHED;
+
+        unsigned binding[2];
+
+        if (!parse_desc_set_and_binding(&p, binding)) {
+                error_at_line(data, "Invalid binding in buffer command");
+                return PARSE_RESULT_ERROR;
+        }
+
+        while (vr_char_is_space(*p))
+                p++;
+
+        if (looking_at(&p, "subdata ")) {
+                struct vr_script_command *command = add_command(data);
+
+                if (!process_set_buffer_subdata(data,
+                                                binding[0],
+                                                binding[1],
+                                                buffer_type,
+                                                p,
+                                                command))
+                        return PARSE_RESULT_ERROR;
+        } else if (looking_at(&p, "fromfile ")) {
+                char *filename = parse_quoted_filename(&p);
+
+                if (filename == NULL || !is_end(p)) {
+                        vr_free(filename);
+                        error_at_line(data, "Invalid buffer fromfile command");
+                        return PARSE_RESULT_ERROR;
+                }
+
+                struct vr_script_command *command = add_command(data);
+                bool res = process_set_buffer_from_file(data,
+                                                        binding[0],
+                                                        binding[1],
+                                                        buffer_type,
+                                                        filename,
+                                                        command);
+                vr_free(filename);
+
+                if (!res)
+                        return PARSE_RESULT_ERROR;
+        } else if (looking_at(&p, "copy ")) {
+                unsigned source[2];
+
+                if (!parse_desc_set_and_binding(&p, source) || !is_end(p)) {
+                        error_at_line(data, "Invalid buffer copy command");
+                        return PARSE_RESULT_ERROR;
+                }
+
+                if (!process_set_buffer_copy(data,
+                                             binding[0],
+                                             binding[1],
+                                             buffer_type,
+                                             source,
+                                             false /* alias */))
+                        return PARSE_RESULT_ERROR;
+        } else if (looking_at(&p, "null")) {
+                if (!is_end(p)) {
+                        error_at_line(data, "Invalid buffer command");
+                        return PARSE_RESULT_ERROR;
+                }
+
+                if (!process_set_buffer_null(data,
+                                             binding[0],
+                                             binding[1],
+                                             buffer_type))
+                        return PARSE_RESULT_ERROR;
+        } else {
+                unsigned size;
+
+                if (!parse_uints(&p, &size, 1, NULL) || !is_end(p)) {
+                        error_at_line(data, "Invalid buffer command");
+                        return PARSE_RESULT_ERROR;
+                }
+
+                if (!process_set_buffer_size(data,
+                                             binding[0],
+                                             binding[1],
+                                             buffer_type,
+                                             size))
+                        return PARSE_RESULT_ERROR;
+        }
+
+        return PARSE_RESULT_OK;
+}
+
+static enum parse_result
+process_uniform_ubo_command(struct load_state *data,
+                            const char *p)
+{
+        if (!looking_at(&p, "uniform ubo "))
+                return PARSE_RESULT_NON_MATCHED;
+
+        struct vr_script_command *command = add_command(data);
+
+        unsigned values[2];
+        if (!parse_desc_set_and_binding(&p, values)) {
+                error_at_line(data, "Invalid binding in uniform ubo command");
+                return PARSE_RESULT_ERROR;
+        }
+
+        if (!process_set_buffer_subdata(data,
+                                        values[0],
+                                        values[1],
+                                        VR_SCRIPT_BUFFER_TYPE_UBO,
+                                        p,
+                                        command))
+                return PARSE_RESULT_ERROR;
+
+        return PARSE_RESULT_OK;
+}
+
+static enum parse_result
+process_tolerance(struct load_state *data,
+                  const char *p)
+{
+        if (!looking_at(&p, "tolerance "))
+                return PARSE_RESULT_NON_MATCHED;
+
+        struct vr_tolerance tolerance;
+
+        if (!parse_tolerance(data, p, &tolerance))
+                return PARSE_RESULT_ERROR;
+
+        data->tolerance = tolerance;
+
+        return PARSE_RESULT_OK;
+}
+
+static bool
+process_test_line(struct load_state *data)
+{
+        const char *p = (char *) data->line.data;
+
+        while (*p && vr_char_is_space(*p))
+                p++;
+
+        if (*p == '#' || *p == '\0')
+                return true;
+
+        static const process_test_line_func funcs[] = {
+                process_patch_parameter_vertices,
+                process_viewports,
+                process_viewport,
+                process_scissor,
+                process_pipeline_flags,
+                process_clear_values,
+                process_layout,
+                process_buffer_command,
+                process_alias_command,
+                process_persist_command,
+                process_tolerance,
+                process_entrypoint,
+                process_specialize,
+                process_occlusion_command,
+                process_probe_ssbo_equal_command,
+                process_probe_ssbo_histogram_command,
+                process_probe_buffer_command,
+                process_probe_command,
+                process_draw_arrays_command,
+                process_draw_mesh_command,
+                process_compute_command,
+                process_expect_submissions,
+                process_uniform_ubo_command,
+                process_push_command,
+                process_clear_checkerboard_command,
+                process_clear_command,
+                process_image_transition_command,
+                process_draw_rect_command,
+                /* This should be last because it is more expensive to check */
+                process_pipeline_property,
+        };
+
+        for (int i = 0; i < VR_N_ELEMENTS(funcs); i++) {
+                switch (funcs[i](data, p)) {
+                case PARSE_RESULT_NON_MATCHED:
+                        break;
+                case PARSE_RESULT_ERROR:
+                        return false;
+                case PARSE_RESULT_OK:
+                        return check_occlusion_query(data);
+                }
+        }
+
+        error_at_line(data, "Invalid test command");
+
+        return false;
+}
+
+static void
+set_current_section(struct load_state *data,
+                    enum section section)
+{
+        data->current_section = section;
+        data->had_sections |= (1 << section);
+}
+
+static bool
+is_stage_section(struct load_state *data,
+                 const char *start,
+                 const char *end)
+{
+        static const char tail[] = " shader";
+        int stage;
+
+        for (stage = 0; stage < VR_SHADER_STAGE_N_STAGES; stage++) {
+                int len = strlen(stage_names[stage]);
+
+                if (end - start >= len + (sizeof tail) - 1 &&
+                    !memcmp(start, stage_names[stage], len) &&
+                    !memcmp(start + len, tail, (sizeof tail) - 1)) {
+                        start += len + (sizeof tail) - 1;
+                        goto found_stage;
+                }
+        }
+
+        return false;
+
+found_stage:
+
+        if (is_string(" spirv", start, end))
+                data->current_source_type = VR_SCRIPT_SOURCE_TYPE_SPIRV;
+        else if (is_string(" binary", start, end))
+                data->current_source_type = VR_SCRIPT_SOURCE_TYPE_BINARY;
+        else if (start == end)
+                data->current_source_type = VR_SCRIPT_SOURCE_TYPE_GLSL;
+        else
+                return false;
+
+        set_current_section(data, SECTION_SHADER);
+        data->current_stage = stage;
+        data->buffer.length = 0;
+        return true;
+}
+
+static bool
+start_spirv_shader(struct load_state *data,
+                   enum vr_shader_stage stage)
+{
+        if (!vr_list_empty(&data->script->stages[stage])) {
+                error_at_line(data,
+                              "SPIR-V source can not be "
+                              "linked with other shaders in the "
+                              "same stage");
+                return false;
+        }
+
+        return true;
+}
+
+static bool
+is_spirv_shader(enum vr_script_source_type type)
+{
+        switch (type) {
+        case VR_SCRIPT_SOURCE_TYPE_BINARY:
+        case VR_SCRIPT_SOURCE_TYPE_SPIRV:
+                return true;
+        case VR_SCRIPT_SOURCE_TYPE_GLSL:
+                return false;
+        }
+
+        vr_fatal("Unexpected source type");
+}
+
+static bool
+load_spirv_file(struct load_state *data,
+                enum vr_shader_stage stage,
+                const char *filename)
+{
+        uint8_t *contents;
+        size_t size;
+
+        if (!load_script_relative_file(data, filename, &contents, &size))
+                return false;
+
+        uint32_t magic;
+
+        if (size < 5 * sizeof (uint32_t) || size % sizeof (uint32_t) != 0) {
+                error_at_line(data, "%s: invalid SPIR-V file size", filename);
+                vr_free(contents);
+                return false;
+        }
+
+        memcpy(&magic, contents, sizeof magic);
+
+        if (magic != 0x07230203) {
+                error_at_line(data,
+                              "%s: file does not begin with the SPIR-V "
+                              "magic number",
+                              filename);
+                vr_free(contents);
+                return false;
+        }
+
+        add_shader(data->script,
+                   stage,
+                   VR_SCRIPT_SOURCE_TYPE_BINARY,
+                   size,
+                   (const char *) contents);
+
+        vr_free(contents);
+
+        return true;
+}
+
+static enum parse_result
+process_shader_file_section(struct load_state *data,
+                            const char *start,
+                            const char *end)
+{
+        static const char tail[] = " shader spirv file ";
+        int stage;
+
+        for (stage = 0; stage < VR_SHADER_STAGE_N_STAGES; stage++) {
+                int len = strlen(stage_names[stage]);
+
+                if (end - start >= len + (sizeof tail) - 1 &&
+                    !memcmp(start, stage_names[stage], len) &&
+                    !memcmp(start + len, tail, (sizeof tail) - 1)) {
+                        start += len + (sizeof tail) - 1;
+                        goto found_stage;
+                }
+        }
+
+        return PARSE_RESULT_NON_MATCHED;
+
+found_stage:
+
+        if (!start_spirv_shader(data, stage))
+                return PARSE_RESULT_ERROR;
+
+        /* Parse the filename from a copy so that it stops at the
+         * end of the section name */
+        char *name = vr_strndup(start, end - start);
+        const char *p = name;
+        char *filename = parse_quoted_filename(&p);
+        bool ret;
+
+        if (filename == NULL || !is_end(p)) {
+                error_at_line(data, "Invalid shader file section");
+                ret = false;
+        } else {
+                ret = load_spirv_file(data, stage, filename);
+        }
+
+        vr_free(filename);
+        vr_free(name);
+
+        if (!ret)
+                return PARSE_RESULT_ERROR;
+
+        set_current_section(data, SECTION_NONE);
+
+        return PARSE_RESULT_OK;
+}
+
+static bool
+start_vertex_data(struct load_state *data,
+                  const char *start,
+                  const char *end)
+{
+        if (data->script->vertex_data) {
+                error_at_line(data, "Duplicate vertex data section");
+                return false;
+        }
+
+        if (start < end) {
+                const char *p = start;
+                unsigned divisor;
+
+                if (!looking_at(&p, " divisor ") ||
+                    !parse_uints(&p, &divisor, 1, NULL)) {
+                        error_at_line(data,
+                                      "Unknown section “vertex data%.*s”",
+                                      (int) (end - start),
+                                      start);
+                        return false;
+                }
+
+                while (p < end && vr_char_is_space(*p))
+                        p++;
+
+                if (p != end) {
+                        error_at_line(data,
+                                      "Invalid vertex data divisor");
+                        return false;
+                }
+
+                data->script->vertex_data_instanced = true;
+                data->script->vertex_data_divisor = divisor;
+
+                /* A divisor of one is the same as the core instance
+                 * input rate so it doesn’t need the extension */
+                if (divisor == 0) {
+                        vr_requirements_add(data->script->requirements,
+                                            "vertexAttributeInstance"
+                                            "RateZeroDivisor");
+                } else if (divisor > 1) {
+                        vr_requirements_add(data->script->requirements,
+                                            "vertexAttributeInstance"
+                                            "RateDivisor");
+                }
+        }
+
+        set_current_section(data, SECTION_VERTEX_DATA);
+        data->buffer.length = 0;
+        return true;
+}
+
+static bool
+process_section_header(struct load_state *data)
+{
+        if (!end_section(data))
+                return false;
+
+        const char *start = (char *) data->line.data + 1;
+        const char *end = strchr(start, ']');
+        if (end == NULL) {
+                error_at_line(data, "Missing ']'");
+                return false;
+        }
+
+        switch (process_shader_file_section(data, start, end)) {
+        case PARSE_RESULT_OK:
+                return true;
+        case PARSE_RESULT_ERROR:
+                return false;
+        case PARSE_RESULT_NON_MATCHED:
+                break;
+        }
+
+        if (is_stage_section(data, start, end)) {
+                if (is_spirv_shader(data->current_source_type) &&
+                    !start_spirv_shader(data, data->current_stage))
+                        return false;
+
+                return true;
+        }
+
+        if (is_string("vertex shader passthrough", start, end)) {
+                if (!start_spirv_shader(data, VR_SHADER_STAGE_VERTEX))
+                        return false;
+                set_current_section(data, SECTION_NONE);
+                add_shader(data->script,
+                           VR_SHADER_STAGE_VERTEX,
+                           VR_SCRIPT_SOURCE_TYPE_BINARY,
+                           sizeof vertex_shader_passthrough,
                            (const char *) vertex_shader_passthrough);
                 return true;
         }
 
-        if (is_string("comment", start, end)) {
-                set_current_section(data, SECTION_COMMENT);
-                return true;
+        if (is_string("comment", start, end)) {
+                set_current_section(data, SECTION_COMMENT);
+                return true;
+        }
+
+        if (is_string("require", start, end)) {
+                /* The “require” section must come first because the
+                 * “test” section uses the window size while parsing
+                 * the commands.
+                 */
+                if ((data->had_sections & ~(1 << SECTION_COMMENT)) != 0) {
+                        error_at_line(data,
+                                      "[require] must be the first section");
+                        return false;
+                }
+                set_current_section(data, SECTION_REQUIRE);
+                return true;
+        }
+
+        if (is_string("test", start, end)) {
+                set_current_section(data, SECTION_TEST);
+                return true;
+        }
+
+        if (is_string("indices", start, end)) {
+                set_current_section(data, SECTION_INDICES);
+                return true;
+        }
+
+        static const char vertex_data_name[] = "vertex data";
+
+        if (end - start >= (sizeof vertex_data_name) - 1 &&
+            !memcmp(start, vertex_data_name, (sizeof vertex_data_name) - 1))
+                return start_vertex_data(data,
+                                         start +
+                                         (sizeof vertex_data_name) - 1,
+                                         end);
+
+        error_at_line(data,
+                      "Unknown section “%.*s”",
+                      (int) (end - start),
+                      start);
+        return false;
+}
+
+static int
+hex_value(char ch)
+{
+        if (ch < '0')
+                return -1;
+        if (ch <= '9')
+                return ch - '0';
+        if (ch < 'A')
+                return -1;
+        if (ch <= 'F')
+                return ch - 'A' + 10;
+        if (ch < 'a')
+                return -1;
+        if (ch <= 'f')
+                return ch - 'a' + 10;
+        return -1;
+}
+
+static bool
+decode_binary(struct load_state *data,
+              const char *line,
+              size_t length)
+{
+        const char *end = line + length;
+
+        while (true) {
+                /* Skip spaces and finish if we encounter the end of
+                 * the line */
+                while (true) {
+                        if (line >= end)
+                                return true;
+                        if (!vr_char_is_space(*line))
+                                break;
+                        line++;
+                }
+
+                /* Skip comments */
+                if (*line == '#')
+                        return true;
+
+                /* If it’s not a space then it must be a hex digit */
+                int digit = hex_value(*(line++));
+
+                if (digit == -1) {
+                        error_at_line(data, "Invalid character in binary data");
+                        return false;
+                }
+
+                uint32_t value = digit;
+
+                while (line < end) {
+                        digit = hex_value(*line);
+                        if (digit == -1)
+                                break;
+                        value = (value << 4) | digit;
+                        line++;
+                }
+
+                vr_buffer_append(&data->buffer, &value, sizeof value);
+        }
+}
+
+static bool
+process_line(struct load_state *data)
+{
+        if (*data->line.data == '[')
+                return process_section_header(data);
+
+        switch (data->current_section) {
+        case SECTION_NONE:
+                return process_none_line(data);
+
+        case SECTION_COMMENT:
+                return true;
+
+        case SECTION_REQUIRE:
+                return process_require_line(data);
+
+        case SECTION_SHADER:
+                if (data->current_source_type == VR_SCRIPT_SOURCE_TYPE_BINARY) {
+                        if (!decode_binary(data,
+                                           (const char *) data->line.data,
+                                           data->line.length))
+                                return false;
+                } else {
+                        vr_buffer_append(&data->buffer,
+                                         data->line.data,
+                                         data->line.length);
+                }
+                return true;
+
+        case SECTION_VERTEX_DATA:
+                vr_buffer_append(&data->buffer,
+                                 data->line.data,
+                                 data->line.length);
+                return true;
+
+        case SECTION_INDICES:
+                return process_indices_line(data);
+
+        case SECTION_TEST:
+                return process_test_line(data);
+        }
+
+        return true;
+}
+
+static int
+compare_buffer_set_and_binding(const void *a,
+                               const void *b)
+{
+        const struct vr_script_buffer *buffer_a =
+                (const struct vr_script_buffer *) a;
+        const struct vr_script_buffer *buffer_b =
+                (const struct vr_script_buffer *) b;
+        if ((int) buffer_a->desc_set == (int) buffer_b->desc_set)
+                return ((int) buffer_a->binding - (int) buffer_b->binding);
+        return ((int) buffer_a->desc_set - (int) buffer_b->desc_set);
+}
+
+static bool
+find_replacement(const struct vr_list *replacements,
+                 struct vr_buffer *line,
+                 int pos)
+{
+        const struct vr_source_token_replacement *tr;
+
+        vr_list_for_each(tr, replacements, link) {
+                int len = strlen(tr->token);
+
+                if (pos + len <= line->length &&
+                    !memcmp(line->data + pos, tr->token, len)) {
+                        int repl_len = strlen(tr->replacement);
+                        int new_line_len = line->length + repl_len - len;
+                        /* The extra “1” is to preserve the null terminator */
+                        vr_buffer_ensure_size(line, new_line_len + 1);
+                        memmove(line->data + pos + repl_len,
+                                line->data + pos + len,
+                                line->length - pos - len + 1);
+                        memcpy(line->data + pos, tr->replacement, repl_len);
+
+                        vr_buffer_set_length(line, new_line_len);
+
+                        return true;
+                }
+        }
+
+        return false;
+}
+
+static bool
+process_token_replacements(struct load_state *data)
+{
+        int count = 0;
+
+        for (int i = 0; i < data->line.length; i++) {
+                while (find_replacement(&data->source->token_replacements,
+                                        &data->line,
+                                        i)) {
+                        count++;
+
+                        if (count > 1000) {
+                                vr_error_message(data->config,
+                                                 "%s:%i: infinite recursion "
+                                                 "suspected while replacing "
+                                                 "tokens",
+                                                 data->filename,
+                                                 data->line_num);
+                                return false;
+                        }
+                }
+        }
+
+        return true;
+}
+
+/* Checks that the draw commands match the type of pipeline that the
+ * shaders make. The vertex draws can’t be used with a mesh shader and
+ * “draw mesh” needs one.
+ */
+static bool
+check_draw_commands(struct load_state *data,
+                    bool has_mesh_shader)
+{
+        const struct vr_script_command *commands =
+                (const struct vr_script_command *) data->commands.data;
+        size_t n_commands = data->commands.length / sizeof *commands;
+
+        for (size_t i = 0; i < n_commands; i++) {
+                const struct vr_script_command *command = commands + i;
+
+                switch (command->op) {
+                case VR_SCRIPT_OP_DRAW_MESH:
+                        if (has_mesh_shader)
+                                break;
+                        vr_error_message(data->config,
+                                         "%s:%i: draw mesh can only be used "
+                                         "with a mesh shader",
+                                         data->filename,
+                                         command->line_num);
+                        return false;
+                case VR_SCRIPT_OP_DRAW_RECT:
+                case VR_SCRIPT_OP_DRAW_ARRAYS:
+                case VR_SCRIPT_OP_DRAW_INDIRECT_COUNT:
+                        if (!has_mesh_shader)
+                                break;
+                        vr_error_message(data->config,
+                                         "%s:%i: Only draw mesh can be used "
+                                         "with a mesh shader",
+                                         data->filename,
+                                         command->line_num);
+                        return false;
+                default:
+                        break;
+                }
+        }
+
+        return true;
+}
+
+static bool
+check_persisted_buffers(struct load_state *data)
+{
+        const struct persisted_buffer *persisted =
+                (const struct persisted_buffer *) data->persisted_buffers.data;
+        size_t n_persisted = (data->persisted_buffers.length /
+                              sizeof (struct persisted_buffer));
+        const struct vr_script_buffer *buffers =
+                (const struct vr_script_buffer *) data->buffers.data;
+        size_t n_buffers = (data->buffers.length /
+                            sizeof (struct vr_script_buffer));
+
+        for (size_t i = 0; i < n_persisted; i++) {
+                for (size_t j = 0; j < n_buffers; j++) {
+                        if (buffers[j].desc_set != persisted[i].desc_set ||
+                            buffers[j].binding != persisted[i].binding ||
+                            buffers[j].size > 0)
+                                continue;
+
+                        vr_error_message(data->config,
+                                         "%s:%i: persist ssbo used for "
+                                         "buffer %u:%u which is never "
+                                         "given any data",
+                                         data->filename,
+                                         persisted[i].line_num,
+                                         persisted[i].desc_set,
+                                         persisted[i].binding);
+                        return false;
+                }
+        }
+
+        return true;
+}
+
+static bool
+check_stages(struct load_state *data)
+{
+        const struct vr_list *stages = data->script->stages;
+
+        if (vr_list_empty(&stages[VR_SHADER_STAGE_MESH])) {
+                if (!vr_list_empty(&stages[VR_SHADER_STAGE_TASK])) {
+                        vr_error_message(data->config,
+                                         "%s: A task shader can only be "
+                                         "used with a mesh shader",
+                                         data->filename);
+                        return false;
+                }
+
+                return check_draw_commands(data, false);
+        }
+
+        if (!vr_list_empty(&stages[VR_SHADER_STAGE_VERTEX]) ||
+            !vr_list_empty(&stages[VR_SHADER_STAGE_TESS_CTRL]) ||
+            !vr_list_empty(&stages[VR_SHADER_STAGE_TESS_EVAL]) ||
+            !vr_list_empty(&stages[VR_SHADER_STAGE_GEOMETRY])) {
+                vr_error_message(data->config,
+                                 "%s: A mesh shader can not be combined "
+                                 "with vertex, tessellation or geometry "
+                                 "shaders",
+                                 data->filename);
+                return false;
+        }
+
+        return check_draw_commands(data, true);
+}
+
+static void
+add_stage_requirements(struct load_state *data)
+{
+        static const struct {
+                enum vr_shader_stage stage;
+                const char *feature;
+        } stage_features[] = {
+                { VR_SHADER_STAGE_TESS_CTRL, "tessellationShader" },
+                { VR_SHADER_STAGE_TESS_EVAL, "tessellationShader" },
+                { VR_SHADER_STAGE_GEOMETRY, "geometryShader" },
+                /* The NV extension also has features with the same
+                 * names but the pipeline is drawn with the EXT
+                 * extension */
+                { VR_SHADER_STAGE_TASK, "VK_EXT_mesh_shader.taskShader" },
+                { VR_SHADER_STAGE_MESH, "VK_EXT_mesh_shader.meshShader" },
+        };
+
+        for (int i = 0; i < VR_N_ELEMENTS(stage_features); i++) {
+                enum vr_shader_stage stage = stage_features[i].stage;
+
+                if (vr_list_empty(&data->script->stages[stage]))
+                        continue;
+
+                vr_requirements_add(data->script->requirements,
+                                    stage_features[i].feature);
+        }
+
+        /* The fragment shader might write to any of the SSBOs. We
+         * can’t tell without compiling the shader so assume that
+         * it does.
+         */
+        if (vr_list_empty(&data->script->stages[VR_SHADER_STAGE_FRAGMENT]))
+                return;
+
+        const struct vr_script_buffer *buffers =
+                (const struct vr_script_buffer *) data->buffers.data;
+        size_t n_buffers = data->buffers.length / sizeof *buffers;
+
+        for (size_t i = 0; i < n_buffers; i++) {
+                if (buffers[i].type == VR_SCRIPT_BUFFER_TYPE_SSBO &&
+                    !buffers[i].is_null) {
+                        vr_requirements_add(data->script->requirements,
+                                            "fragmentStoresAndAtomics");
+                        break;
+                }
+        }
+}
+
+static void
+add_pipeline_key_requirements(struct load_state *data)
+{
+        const struct vr_pipeline_key *keys =
+                (const struct vr_pipeline_key *) data->pipeline_keys.data;
+        size_t n_keys = data->pipeline_keys.length / sizeof *keys;
+
+        for (size_t i = 0; i < n_keys; i++) {
+                if (keys[i].type != VR_PIPELINE_KEY_TYPE_GRAPHICS)
+                        continue;
+
+                if (keys[i].depthClampEnable.i) {
+                        vr_requirements_add(data->script->requirements,
+                                            "depthClamp");
+                }
+        }
+}
+
+static bool
+load_script_from_stream(struct load_state *data,
+                        struct vr_stream *stream)
+{
+        bool res = true;
+
+        do {
+                int lines_consumed = vr_stream_read_line(stream, &data->line);
+
+                if (lines_consumed == 0)
+                        break;
+
+                if (!process_token_replacements(data)) {
+                        res = false;
+                        break;
+                }
+
+                res = process_line(data);
+
+                data->line_num += lines_consumed;
+        } while (res);
+
+        if (res)
+                res = end_section(data);
+
+        if (res)
+                res = check_stages(data);
+
+        if (res)
+                res = check_persisted_buffers(data);
+
+        if (res) {
+                add_stage_requirements(data);
+                add_pipeline_key_requirements(data);
+        }
+
+        return res;
+}
+
+static bool
+load_script_from_file_stream(struct load_state *data,
+                             FILE *file)
+{
+        struct vr_stream stream;
+
+        vr_stream_init_file(&stream, file);
+        bool res = load_script_from_stream(data, &stream);
+
+        return res;
+}
+
+static bool
+load_script_from_file(struct load_state *data,
+                      const char *filename)
+{
+        FILE *f = fopen(filename, "r");
+
+        if (f == NULL) {
+                vr_error_message(data->config,
+                                 "%s: %s",
+                                 filename,
+                                 strerror(errno));
+                return false;
+        }
+
+        bool res = load_script_from_file_stream(data, f);
+        fclose(f);
+
+        return res;
+}
+
+static bool
+load_script_from_string(struct load_state *data,
+                        const char *string)
+{
+        struct vr_stream stream;
+
+        vr_stream_init_string(&stream, string);
+        bool res = load_script_from_stream(data, &stream);
+
+        return res;
+}
+
+struct vr_script *
+vr_script_load(const struct vr_config *config,
+               const struct vr_source *source)
+{
+        struct vr_script *script = vr_calloc(sizeof (struct vr_script));
+        struct load_state data = {
+                .config = config,
+                .source = source,
+                .line_num = 1,
+                .script = script,
+                .current_stage = -1,
+                .current_section = SECTION_NONE,
+                .clear_depth = 1.0f,
+                .line = VR_BUFFER_STATIC_INIT,
+                .buffer = VR_BUFFER_STATIC_INIT,
+                .commands = VR_BUFFER_STATIC_INIT,
+                .pipeline_keys = VR_BUFFER_STATIC_INIT,
+                .buffers = VR_BUFFER_STATIC_INIT,
+                .persisted_buffers = VR_BUFFER_STATIC_INIT,
+                .tolerance = {
+                        .value = {
+                                DEFAULT_TOLERANCE,
+                                DEFAULT_TOLERANCE,
+                                DEFAULT_TOLERANCE,
+                                DEFAULT_TOLERANCE,
+                        },
+                        .is_percent = false,
+                },
+                .push_layout = default_push_layout,
+                .ubo_layout = default_ubo_layout,
+                .ssbo_layout = default_ssbo_layout,
+        };
+
+        vr_pipeline_key_init(&data.current_key);
+
+        script->window_format.width = 250;
+        script->window_format.height = 250;
+        script->window_format.color_formats[0] =
+                vr_format_lookup_by_vk_format(VK_FORMAT_B8G8R8A8_UNORM);
+        assert(script->window_format.color_formats[0] != NULL);
+        script->window_format.n_color_attachments = 1;
+
+        for (int stage = 0; stage < VR_SHADER_STAGE_N_STAGES; stage++)
+                vr_list_init(&script->stages[stage]);
+
+        script->requirements = vr_requirements_new();
+        script->expected_submissions = -1;
+
+        bool res = false;
+
+        switch (source->type) {
+        case VR_SOURCE_TYPE_FILE:
+                data.filename = source->string;
+                script->filename = vr_strdup(data.filename);
+                res = load_script_from_file(&data, source->string);
+                break;
+
+        case VR_SOURCE_TYPE_STRING:
+                data.filename = "(string script)";
+                script->filename = vr_strdup(data.filename);
+                res = load_script_from_string(&data, source->string);
+                break;
+
+        case VR_SOURCE_TYPE_STREAM:
+                data.filename = source->string;
+                script->filename = vr_strdup(data.filename);
+                res = load_script_from_file_stream(&data, source->file);
+                break;
+        }
+
+        script->commands = (struct vr_script_command *) data.commands.data;
+        script->n_commands = (data.commands.length /
+                              sizeof (struct vr_script_command));
+        script->pipeline_keys =
+                (struct vr_pipeline_key *) data.pipeline_keys.data;
+        script->n_pipeline_keys = (data.pipeline_keys.length /
+                                   sizeof (struct vr_pipeline_key));
+        set_indices(script, &data.indices);
+
+        script->buffers = (struct vr_script_buffer *) data.buffers.data;
+        script->n_buffers = (data.buffers.length /
+                             sizeof (struct vr_script_buffer));
+        qsort(script->buffers,
+              script->n_buffers,
+              sizeof script->buffers[0],
+              compare_buffer_set_and_binding);
+
+        vr_buffer_destroy(&data.buffer);
+        vr_buffer_destroy(&data.line);
+        vr_buffer_destroy(&data.persisted_buffers);
+        vr_pipeline_key_destroy(&data.current_key);
+
+        if (res) {
+                return script;
+        } else {
+                vr_script_free(script);
+                return NULL;
+        }
+}
+
+void
+vr_script_free(struct vr_script *script)
+{
+        int stage;
+        struct vr_script_shader *shader, *tmp;
+
+        for (int i = 0; i < script->n_commands; i++) {
+                struct vr_script_command *command = script->commands + i;
+                if (command->op == VR_SCRIPT_OP_SET_BUFFER_SUBDATA)
+                        vr_free(command->set_buffer_subdata.data);
+                else if (command->op == VR_SCRIPT_OP_SET_PUSH_CONSTANT)
+                        vr_free(command->set_push_constant.data);
+                else if (command->op == VR_SCRIPT_OP_PROBE_SSBO)
+                        vr_free(command->probe_ssbo.value);
+                else if (command->op == VR_SCRIPT_OP_PROBE_SSBO_HISTOGRAM)
+                        vr_free(command->probe_ssbo_histogram.expected);
+        }
+
+        for (stage = 0; stage < VR_SHADER_STAGE_N_STAGES; stage++) {
+                vr_list_for_each_safe(shader,
+                                      tmp,
+                                      &script->stages[stage],
+                                      link) {
+                        vr_free(shader);
+                }
+
+                vr_free(script->target_envs[stage]);
+        }
+
+        if (script->vertex_data)
+                vr_vbo_free(script->vertex_data);
+
+        vr_free(script->indices);
+
+        vr_free(script->filename);
+
+        vr_free(script->commands);
+
+        for (int i = 0; i < script->n_pipeline_keys; i++)
+                vr_pipeline_key_destroy(script->pipeline_keys + i);
+        vr_free(script->pipeline_keys);
+
+        vr_free(script->buffers);
+
+        vr_requirements_free(script->requirements);
+
+        vr_free(script);
+}
+
+int
+vr_script_get_shaders(const struct vr_script *script,
+                      const struct vr_source *source,
+                      struct vr_script_shader_code *shaders)
+{
+        int shaders_number = 0;
+        int stage;
+        struct vr_script_shader *shader, *tmp;
+
+        for (stage = 0; stage < VR_SHADER_STAGE_N_STAGES; stage++) {
+                vr_list_for_each_safe(shader,
+                                      tmp,
+                                      &script->stages[stage],
+                                      link) {
+                        shaders[shaders_number].source_type =
+                                shader->source_type;
+                        shaders[shaders_number].source_length = shader->length;
+                        shaders[shaders_number].stage = stage;
+                        shaders[shaders_number].source =
+                                malloc(shader->length + 1);
+                        memcpy(shaders[shaders_number].source,
+                               shader->source, shader->length);
+                        shaders[shaders_number].source[shader->length] = '\0';
+                        shaders_number++;
+                }
+        }
+
+        return shaders_number;
+}
+
+int
+vr_script_get_num_shaders(const struct vr_script* script)
+{
+        int stage, num_shaders = 0;
+
+        for (stage = 0; stage < VR_SHADER_STAGE_N_STAGES; stage++) {
+                num_shaders += vr_list_length(&script->stages[stage]);
+        }
+        return num_shaders;
+}
+
+size_t
+vr_script_get_n_pipelines(const struct vr_script *script)
+{
+        return script->n_pipeline_keys;
+}
+
+size_t
+vr_script_get_n_buffers(const struct vr_script *script)
+{
+        return script->n_buffers;
+}
+
+void
+vr_script_get_buffer_info(const struct vr_script *script,
+                          size_t index,
+                          struct vr_script_buffer_info *info)
+{
+        assert(index < script->n_buffers);
+
+        const struct vr_script_buffer *buffer = script->buffers + index;
+
+        info->desc_set = buffer->desc_set;
+        info->binding = buffer->binding;
+        info->size = buffer->size;
+}
+
+size_t
+vr_script_get_n_required_extensions(const struct vr_script *script)
+{
+        return vr_requirements_get_n_extensions(script->requirements);
+}
+
+const char *
+vr_script_get_required_extension(const struct vr_script *script,
+                                 size_t index)
+{
+        assert(index < vr_script_get_n_required_extensions(script));
+
+        return vr_requirements_get_extensions(script->requirements)[index];
+}
+
+size_t
+vr_script_get_n_required_features(const struct vr_script *script)
+{
+        return vr_requirements_get_n_features(script->requirements);
+}
+
+const char *
+vr_script_get_required_feature(const struct vr_script *script,
+                               size_t index)
+{
+        return vr_requirements_get_feature(script->requirements, index);
+}
+
+void
+vr_script_get_required_version(const struct vr_script *script,
+                               unsigned *major,
+                               unsigned *minor,
+                               unsigned *patch)
+{
+        uint32_t version = vr_requirements_get_version(script->requirements);
+
+        *major = VK_VERSION_MAJOR(version);
+        *minor = VK_VERSION_MINOR(version);
+        *patch = VK_VERSION_PATCH(version);
+}
+
+void
+vr_script_replace_shaders_stage_binary(struct vr_script *script,
+                                       enum vr_shader_stage stage,
+                                       size_t source_length,
+                                       const uint32_t *source)
+{
+        struct vr_script_shader *shader, *tmp;
+
+        /* Remove all the shaders of the stage */
+        vr_list_for_each_safe(shader,
+                              tmp,
+                              &script->stages[stage],
+                              link) {
+                vr_list_remove(&shader->link);
+                vr_free(shader);
+        }
+        /* Add the new shader for the stage */
+        add_shader(script,
+                   stage,
+                   VR_SCRIPT_SOURCE_TYPE_BINARY,
+                   source_length,
+                   (const char *) source);
+}
+
+/* The state that the parser would have after reading the commands
+ * written so far. Before writing a command that depends on any of
+ * this state, the commands needed to make it match are written
+ * first.
+ */
+struct write_state {
+        const struct vr_script *script;
+        struct vr_buffer *buf;
+        struct vr_pipeline_key key;
+        struct vr_tolerance tolerance;
+        struct vr_box_layout push_layout;
+        struct vr_box_layout ubo_layout;
+        struct vr_box_layout ssbo_layout;
+        /* The clear colors as the parser will see them, following
+         * the same rules as in struct load_state */
+        float clear_colors[VR_WINDOW_MAX_COLOR_ATTACHMENTS][4];
+        bool clear_color_set[VR_WINDOW_MAX_COLOR_ATTACHMENTS];
+        float clear_depth;
+        uint32_t clear_stencil;
+};
+
+static const struct {
+        const char *name;
+        size_t offset;
+        enum vr_pipeline_key_value_type type;
+} key_properties[] = {
+#define VR_PIPELINE_STRUCT_BEGIN(m)
+#define VR_PIPELINE_STRUCT_BEGIN2(m1, s2, m2)
+#define VR_PIPELINE_PROP(t, s, n)                                       \
+        { #n,                                                           \
+          offsetof(struct vr_pipeline_key, n),                          \
+          VR_PIPELINE_KEY_VALUE_TYPE_ ## t },
+#define VR_PIPELINE_PROP_NAME(t, s, m, n)                               \
+        { #m,                                                           \
+          offsetof(struct vr_pipeline_key, n),                          \
+          VR_PIPELINE_KEY_VALUE_TYPE_ ## t },
+#define VR_PIPELINE_STRUCT_END()
+#include "vr-pipeline-properties.h"
+#undef VR_PIPELINE_STRUCT_BEGIN
+#undef VR_PIPELINE_STRUCT_BEGIN2
+#undef VR_PIPELINE_PROP
+#undef VR_PIPELINE_PROP_NAME
+#undef VR_PIPELINE_STRUCT_END
+};
+
+static const char *
+get_value_type_name(enum vr_box_type type)
+{
+        for (int i = 0; i < VR_N_ELEMENTS(value_types); i++) {
+                if (value_types[i].type == type)
+                        return value_types[i].name;
+        }
+
+        vr_fatal("Unknown value type");
+}
+
+static const char *
+get_topology_name(VkPrimitiveTopology topology)
+{
+        /* Search backwards so that the Vulkan name is used instead
+         * of the GL name */
+        for (int i = VR_N_ELEMENTS(topologies) - 1; i >= 0; i--) {
+                if (topologies[i].topology == topology)
+                        return topologies[i].name;
+        }
+
+        vr_fatal("Unknown topology");
+}
+
+static const char *
+get_image_layout_name(VkImageLayout layout)
+{
+        for (int i = 0; i < VR_N_ELEMENTS(image_layouts); i++) {
+                if (image_layouts[i].layout == layout)
+                        return image_layouts[i].name;
+        }
+
+        vr_fatal("Unknown image layout");
+}
+
+static const char *
+get_buffer_type_name(enum vr_script_buffer_type type)
+{
+        switch (type) {
+        case VR_SCRIPT_BUFFER_TYPE_UBO:
+                return "ubo";
+        case VR_SCRIPT_BUFFER_TYPE_SSBO:
+                return "ssbo";
+        }
+
+        vr_fatal("Unknown buffer type");
+}
+
+static enum vr_script_buffer_type
+get_script_buffer_type(const struct vr_script *script,
+                       unsigned desc_set,
+                       unsigned binding)
+{
+        for (size_t i = 0; i < script->n_buffers; i++) {
+                const struct vr_script_buffer *buffer = script->buffers + i;
+
+                if (buffer->desc_set == desc_set && buffer->binding == binding)
+                        return buffer->type;
+        }
+
+        vr_fatal("Unknown buffer binding");
+}
+
+static bool
+write_value_cb(enum vr_box_base_type base_type,
+               size_t offset,
+               void *user_data)
+{
+        struct vr_buffer *buf = ((void **) user_data)[0];
+        const uint8_t *p = (const uint8_t *) ((void **) user_data)[1] + offset;
+
+        vr_buffer_append_c(buf, ' ');
+
+        /* The floating-point values are written with enough digits
+         * to be parsed back to exactly the same value */
+        switch (base_type) {
+        case VR_BOX_BASE_TYPE_INT:
+                vr_buffer_append_printf(buf, "%i", *(const int *) p);
+                break;
+        case VR_BOX_BASE_TYPE_UINT:
+                vr_buffer_append_printf(buf, "%u", *(const unsigned *) p);
+                break;
+        case VR_BOX_BASE_TYPE_INT8:
+                vr_buffer_append_printf(buf, "%" PRIi8, *(const int8_t *) p);
+                break;
+        case VR_BOX_BASE_TYPE_UINT8:
+                vr_buffer_append_printf(buf, "%" PRIu8, *(const uint8_t *) p);
+                break;
+        case VR_BOX_BASE_TYPE_INT16:
+                vr_buffer_append_printf(buf,
+                                        "%" PRIi16,
+                                        *(const int16_t *) p);
+                break;
+        case VR_BOX_BASE_TYPE_UINT16:
+                vr_buffer_append_printf(buf,
+                                        "%" PRIu16,
+                                        *(const uint16_t *) p);
+                break;
+        case VR_BOX_BASE_TYPE_INT64:
+                vr_buffer_append_printf(buf,
+                                        "%" PRIi64,
+                                        *(const int64_t *) p);
+                break;
+        case VR_BOX_BASE_TYPE_UINT64:
+                vr_buffer_append_printf(buf,
+                                        "%" PRIu64,
+                                        *(const uint64_t *) p);
+                break;
+        case VR_BOX_BASE_TYPE_FLOAT16:
+                vr_buffer_append_printf(buf,
+                                        "%.17g",
+                                        vr_half_float_to_double(
+                                                *(const uint16_t *) p));
+                break;
+        case VR_BOX_BASE_TYPE_FLOAT:
+                vr_buffer_append_printf(buf, "%.9g", *(const float *) p);
+                break;
+        case VR_BOX_BASE_TYPE_DOUBLE:
+                vr_buffer_append_printf(buf, "%.17g", *(const double *) p);
+                break;
+        }
+
+        return true;
+}
+
+static void
+write_box_values(struct vr_buffer *buf,
+                 enum vr_box_type type,
+                 const struct vr_box_layout *layout,
+                 size_t n_values,
+                 size_t stride,
+                 const void *values)
+{
+        for (size_t i = 0; i < n_values; i++) {
+                void *closure[] = {
+                        buf,
+                        (uint8_t *) values + i * stride
+                };
+
+                vr_box_for_each_component(type, layout, write_value_cb, closure);
+        }
+}
+
+static void
+write_vertex_datum(struct vr_buffer *buf,
+                   enum vr_format_mode mode,
+                   int bit_size,
+                   const uint8_t *p)
+{
+        switch (mode) {
+        case VR_FORMAT_MODE_SFLOAT:
+                switch (bit_size) {
+                case 16:
+                        /* The parser accepts the bit pattern in hex */
+                        vr_buffer_append_printf(buf,
+                                                "0x%04" PRIx16,
+                                                *(const uint16_t *) p);
+                        return;
+                case 32:
+                        vr_buffer_append_printf(buf,
+                                                "%.9g",
+                                                *(const float *) p);
+                        return;
+                case 64:
+                        vr_buffer_append_printf(buf,
+                                                "%.17g",
+                                                *(const double *) p);
+                        return;
+                }
+                break;
+        case VR_FORMAT_MODE_UNORM:
+        case VR_FORMAT_MODE_USCALED:
+        case VR_FORMAT_MODE_UINT:
+        case VR_FORMAT_MODE_SRGB:
+                switch (bit_size) {
+                case 8:
+                        vr_buffer_append_printf(buf,
+                                                "%" PRIu8,
+                                                *(const uint8_t *) p);
+                        return;
+                case 16:
+                        vr_buffer_append_printf(buf,
+                                                "%" PRIu16,
+                                                *(const uint16_t *) p);
+                        return;
+                case 32:
+                        vr_buffer_append_printf(buf,
+                                                "%" PRIu32,
+                                                *(const uint32_t *) p);
+                        return;
+                case 64:
+                        vr_buffer_append_printf(buf,
+                                                "%" PRIu64,
+                                                *(const uint64_t *) p);
+                        return;
+                }
+                break;
+        case VR_FORMAT_MODE_SNORM:
+        case VR_FORMAT_MODE_SSCALED:
+        case VR_FORMAT_MODE_SINT:
+                switch (bit_size) {
+                case 8:
+                        vr_buffer_append_printf(buf,
+                                                "%" PRIi8,
+                                                *(const int8_t *) p);
+                        return;
+                case 16:
+                        vr_buffer_append_printf(buf,
+                                                "%" PRIi16,
+                                                *(const int16_t *) p);
+                        return;
+                case 32:
+                        vr_buffer_append_printf(buf,
+                                                "%" PRIi32,
+                                                *(const int32_t *) p);
+                        return;
+                case 64:
+                        vr_buffer_append_printf(buf,
+                                                "%" PRIi64,
+                                                *(const int64_t *) p);
+                        return;
+                }
+                break;
+        case VR_FORMAT_MODE_UFLOAT:
+                break;
+        }
+
+        vr_fatal("Unexpected format");
+}
+
+static void
+write_require_section(struct write_state *state)
+{
+        const struct vr_script *script = state->script;
+        const struct vr_requirements *reqs = script->requirements;
+        const struct vr_window_format *window_format = &script->window_format;
+        struct vr_buffer *buf = state->buf;
+        unsigned major, minor, patch;
+
+        vr_buffer_append_string(buf, "[require]\n");
+
+        vr_script_get_required_version(script, &major, &minor, &patch);
+        vr_buffer_append_printf(buf, "vulkan %u.%u.%u\n", major, minor, patch);
+
+        if (window_format->color_formats[0]->vk_format !=
+            VK_FORMAT_B8G8R8A8_UNORM) {
+                vr_buffer_append_printf(buf,
+                                        "framebuffer %s\n",
+                                        window_format->color_formats[0]->name);
+        }
+
+        for (int i = 1; i < window_format->n_color_attachments; i++) {
+                vr_buffer_append_printf(buf,
+                                        "framebuffer %i %s\n",
+                                        i,
+                                        window_format->color_formats[i]->name);
+        }
+
+        if (window_format->depth_stencil_format) {
+                vr_buffer_append_printf(buf,
+                                        "depthstencil %s\n",
+                                        window_format->
+                                        depth_stencil_format->name);
+        }
+
+        if (window_format->width != 250 || window_format->height != 250) {
+                vr_buffer_append_printf(buf,
+                                        "fbsize %lu %lu\n",
+                                        (unsigned long) window_format->width,
+                                        (unsigned long) window_format->height);
+        }
+
+        uint32_t value;
+
+        value = vr_requirements_get_device_group_size(reqs);
+        if (value)
+                vr_buffer_append_printf(buf, "devicegroup %" PRIu32 "\n", value);
+
+        value = vr_requirements_get_max_compute_shared_memory_size(reqs);
+        if (value) {
+                vr_buffer_append_printf(buf,
+                                        "maxsharedmemory %" PRIu32 "\n",
+                                        value);
         }
 
-        if (is_string("require", start, end)) {
-                /* The “require” section must come first because the
-                 * “test” section uses the window size while parsing
-                 * the commands.
-                 */
-                if ((data->had_sections & ~(1 << SECTION_COMMENT)) != 0) {
-                        error_at_line(data,
-                                      "[require] must be the first section");
-                        return false;
-                }
-                set_current_section(data, SECTION_REQUIRE);
-                return true;
+        value = vr_requirements_get_max_vertex_input_attributes(reqs);
+        if (value) {
+                vr_buffer_append_printf(buf,
+                                        "maxvertexattributes %" PRIu32 "\n",
+                                        value);
         }
 
-        if (is_string("test", start, end)) {
-                set_current_section(data, SECTION_TEST);
-                return true;
+        value = vr_requirements_get_max_uniform_buffer_range(reqs);
+        if (value) {
+                vr_buffer_append_printf(buf,
+                                        "maxuniformbufferrange %" PRIu32 "\n",
+                                        value);
         }
 
-        if (is_string("indices", start, end)) {
-                set_current_section(data, SECTION_INDICES);
-                return true;
+        value = vr_requirements_get_max_storage_buffers(reqs);
+        if (value) {
+                vr_buffer_append_printf(buf,
+                                        "maxstoragebuffers %" PRIu32 "\n",
+                                        value);
         }
 
-        if (is_string("vertex data", start, end)) {
-                if (data->script->vertex_data) {
-                        error_at_line(data, "Duplicate vertex data section");
-                        return false;
-                }
-                set_current_section(data, SECTION_VERTEX_DATA);
-                data->buffer.length = 0;
-                return true;
+        value = vr_requirements_get_max_draw_indirect_count(reqs);
+        if (value) {
+                vr_buffer_append_printf(buf,
+                                        "maxdrawindirectcount %" PRIu32 "\n",
+                                        value);
         }
 
-        error_at_line(data,
-                      "Unknown section “%.*s”",
-                      (int) (end - start),
-                      start);
-        return false;
+        float point_size_range[2];
+
+        vr_requirements_get_point_size_range(reqs, point_size_range);
+        if (point_size_range[0] != 1.0f || point_size_range[1] != 1.0f) {
+                vr_buffer_append_printf(buf,
+                                        "pointsize %.9g %.9g\n",
+                                        point_size_range[0],
+                                        point_size_range[1]);
+        }
+
+        for (int stage = 0; stage < VR_SHADER_STAGE_N_STAGES; stage++) {
+                if (script->target_envs[stage] == NULL)
+                        continue;
+
+                vr_buffer_append_printf(buf,
+                                        "%s targetenv %s\n",
+                                        stage_names[stage],
+                                        script->target_envs[stage]);
+        }
+
+        size_t n_features = vr_requirements_get_n_features(reqs);
+
+        for (size_t i = 0; i < n_features; i++) {
+                const char *extension =
+                        vr_requirements_get_feature_extension(reqs, i);
+
+                if (extension)
+                        vr_buffer_append_printf(buf, "%s.", extension);
+
+                vr_buffer_append_printf(buf,
+                                        "%s\n",
+                                        vr_requirements_get_feature(reqs, i));
+        }
+
+        const char *const *extensions = vr_requirements_get_extensions(reqs);
+        size_t n_extensions = vr_requirements_get_n_extensions(reqs);
+
+        for (size_t i = 0; i < n_extensions; i++)
+                vr_buffer_append_printf(buf, "%s\n", extensions[i]);
 }
 
-static int
-hex_value(char ch)
+/* Adds a blank line before a section header unless the previous
+ * section already ended with one. The source of a shader includes
+ * any blank lines at the end so this avoids adding another one each
+ * time the script is written.
+ */
+static void
+start_section(struct vr_buffer *buf)
 {
-        if (ch < '0')
-                return -1;
-        if (ch <= '9')
-                return ch - '0';
-        if (ch < 'A')
-                return -1;
-        if (ch <= 'F')
-                return ch - 'A' + 10;
-        if (ch < 'a')
-                return -1;
-        if (ch <= 'f')
-                return ch - 'a' + 10;
-        return -1;
+        if (buf->length >= 2 &&
+            buf->data[buf->length - 1] == '\n' &&
+            buf->data[buf->length - 2] == '\n')
+                return;
+
+        vr_buffer_append_c(buf, '\n');
 }
 
-static bool
-decode_binary(struct load_state *data,
-              const char *line,
-              size_t length)
+static void
+write_binary_shader(struct vr_buffer *buf,
+                    const struct vr_script_shader *shader)
 {
-        const char *end = line + length;
+        size_t n_words = shader->length / sizeof (uint32_t);
 
-        while (true) {
-                /* Skip spaces and finish if we encounter the end of
-                 * the line */
-                while (true) {
-                        if (line >= end)
-                                return true;
-                        if (!vr_char_is_space(*line))
+        for (size_t i = 0; i < n_words; i++) {
+                uint32_t word;
+
+                memcpy(&word, shader->source + i * sizeof word, sizeof word);
+
+                vr_buffer_append_printf(buf,
+                                        "%08" PRIx32 "%c",
+                                        word,
+                                        (i % 8 == 7 || i == n_words - 1) ?
+                                        '\n' : ' ');
+        }
+}
+
+static void
+write_shader_sections(struct write_state *state)
+{
+        const struct vr_script *script = state->script;
+        struct vr_buffer *buf = state->buf;
+        const struct vr_script_shader *shader;
+
+        for (int stage = 0; stage < VR_SHADER_STAGE_N_STAGES; stage++) {
+                vr_list_for_each(shader, &script->stages[stage], link) {
+                        switch (shader->source_type) {
+                        case VR_SCRIPT_SOURCE_TYPE_GLSL:
+                        case VR_SCRIPT_SOURCE_TYPE_SPIRV:
+                                start_section(buf);
+                                vr_buffer_append_printf(buf,
+                                                        "[%s shader%s]\n",
+                                                        stage_names[stage],
+                                                        shader->source_type ==
+                                                        VR_SCRIPT_SOURCE_TYPE_SPIRV ?
+                                                        " spirv" : "");
+                                vr_buffer_append(buf,
+                                                 shader->source,
+                                                 shader->length);
+                                if (shader->length > 0 &&
+                                    shader->source[shader->length - 1] != '\n')
+                                        vr_buffer_append_c(buf, '\n');
                                 break;
-                        line++;
+                        case VR_SCRIPT_SOURCE_TYPE_BINARY:
+                                if (stage == VR_SHADER_STAGE_VERTEX &&
+                                    shader->length ==
+                                    sizeof vertex_shader_passthrough &&
+                                    !memcmp(shader->source,
+                                            vertex_shader_passthrough,
+                                            shader->length)) {
+                                        start_section(buf);
+                                        vr_buffer_append_string(buf,
+                                                                "[vertex "
+                                                                "shader "
+                                                                "passthrough]"
+                                                                "\n");
+                                        break;
+                                }
+
+                                start_section(buf);
+                                vr_buffer_append_printf(buf,
+                                                        "[%s shader binary]"
+                                                        "\n",
+                                                        stage_names[stage]);
+                                write_binary_shader(buf, shader);
+                                break;
+                        }
                 }
+        }
+}
 
-                /* Skip comments */
-                if (*line == '#')
-                        return true;
+static void
+write_vertex_data_section(struct write_state *state)
+{
+        const struct vr_script *script = state->script;
+        const struct vr_vbo *vbo = script->vertex_data;
+        const struct vr_vbo_attrib *attrib;
+        struct vr_buffer *buf = state->buf;
 
-                /* If it’s not a space then it must be a hex digit */
-                int digit = hex_value(*(line++));
+        if (vbo == NULL)
+                return;
 
-                if (digit == -1) {
-                        error_at_line(data, "Invalid character in binary data");
-                        return false;
-                }
+        start_section(buf);
 
-                uint32_t value = digit;
+        if (script->vertex_data_instanced) {
+                vr_buffer_append_printf(buf,
+                                        "[vertex data divisor %" PRIu32 "]\n",
+                                        script->vertex_data_divisor);
+        } else {
+                vr_buffer_append_string(buf, "[vertex data]\n");
+        }
 
-                while (line < end) {
-                        digit = hex_value(*line);
-                        if (digit == -1)
-                                break;
-                        value = (value << 4) | digit;
-                        line++;
+        vr_list_for_each(attrib, &vbo->attribs, link) {
+                if (attrib->link.prev != &vbo->attribs)
+                        vr_buffer_append_c(buf, ' ');
+                vr_buffer_append_printf(buf,
+                                        "%u/%s",
+                                        attrib->location,
+                                        attrib->format->name);
+        }
+
+        vr_buffer_append_c(buf, '\n');
+
+        for (size_t row = 0; row < vbo->num_rows; row++) {
+                const uint8_t *row_data = vbo->raw_data + row * vbo->stride;
+
+                vr_list_for_each(attrib, &vbo->attribs, link) {
+                        const struct vr_format *format = attrib->format;
+                        const uint8_t *p = row_data + attrib->offset;
+
+                        if (attrib->link.prev != &vbo->attribs)
+                                vr_buffer_append_string(buf, "  ");
+
+                        if (format->packed_size) {
+                                write_vertex_datum(buf,
+                                                   VR_FORMAT_MODE_UINT,
+                                                   format->packed_size,
+                                                   p);
+                                continue;
+                        }
+
+                        for (int i = 0; i < format->n_parts; i++) {
+                                if (i > 0)
+                                        vr_buffer_append_c(buf, ' ');
+                                write_vertex_datum(buf,
+                                                   format->parts[i].mode,
+                                                   format->parts[i].bits,
+                                                   p);
+                                p += format->parts[i].bits / 8;
+                        }
                 }
 
-                vr_buffer_append(&data->buffer, &value, sizeof value);
+                vr_buffer_append_c(buf, '\n');
         }
 }
 
-static bool
-process_line(struct load_state *data)
+static void
+write_indices_section(struct write_state *state)
 {
-        if (*data->line.data == '[')
-                return process_section_header(data);
+        const struct vr_script *script = state->script;
+        struct vr_buffer *buf = state->buf;
 
-        switch (data->current_section) {
-        case SECTION_NONE:
-                return process_none_line(data);
+        if (script->n_indices == 0)
+                return;
 
-        case SECTION_COMMENT:
-                return true;
+        start_section(buf);
+        vr_buffer_append_string(buf, "[indices]\n");
 
-        case SECTION_REQUIRE:
-                return process_require_line(data);
+        for (size_t i = 0; i < script->n_indices; i++) {
+                uint32_t index;
 
-        case SECTION_SHADER:
-                if (data->current_source_type == VR_SCRIPT_SOURCE_TYPE_BINARY) {
-                        if (!decode_binary(data,
-                                           (const char *) data->line.data,
-                                           data->line.length))
-                                return false;
+                if (script->index_type == VK_INDEX_TYPE_UINT32)
+                        index = ((const uint32_t *) script->indices)[i];
+                else
+                        index = ((const uint16_t *) script->indices)[i];
+
+                vr_buffer_append_printf(buf,
+                                        "%" PRIu32 "%c",
+                                        index,
+                                        (i % 16 == 15 ||
+                                         i == script->n_indices - 1) ?
+                                        '\n' : ' ');
+        }
+}
+
+static void
+write_buffers(struct write_state *state)
+{
+        const struct vr_script *script = state->script;
+        struct vr_buffer *buf = state->buf;
+
+        /* Copies are written after all of the real buffers because
+         * the source needs to exist first */
+        for (size_t i = 0; i < script->n_buffers; i++) {
+                const struct vr_script_buffer *buffer = script->buffers + i;
+
+                if (buffer->is_copy)
+                        continue;
+
+                vr_buffer_append_printf(buf,
+                                        "%s %u:%u ",
+                                        get_buffer_type_name(buffer->type),
+                                        buffer->desc_set,
+                                        buffer->binding);
+
+                if (buffer->is_null) {
+                        vr_buffer_append_string(buf, "null\n");
                 } else {
-                        vr_buffer_append(&data->buffer,
-                                         data->line.data,
-                                         data->line.length);
+                        vr_buffer_append_printf(buf,
+                                                "%lu\n",
+                                                (unsigned long) buffer->size);
                 }
-                return true;
+        }
 
-        case SECTION_VERTEX_DATA:
-                vr_buffer_append(&data->buffer,
-                                 data->line.data,
-                                 data->line.length);
-                return true;
+        for (size_t i = 0; i < script->n_buffers; i++) {
+                const struct vr_script_buffer *buffer = script->buffers + i;
 
-        case SECTION_INDICES:
-                return process_indices_line(data);
+                if (!buffer->is_copy)
+                        continue;
 
-        case SECTION_TEST:
-                return process_test_line(data);
+                if (buffer->is_alias) {
+                        vr_buffer_append_printf(buf,
+                                                "alias %s %u:%u as %u:%u\n",
+                                                get_buffer_type_name(buffer->
+                                                                     type),
+                                                buffer->copy_desc_set,
+                                                buffer->copy_binding,
+                                                buffer->desc_set,
+                                                buffer->binding);
+                } else {
+                        vr_buffer_append_printf(buf,
+                                                "%s %u:%u copy %u:%u\n",
+                                                get_buffer_type_name(buffer->
+                                                                     type),
+                                                buffer->desc_set,
+                                                buffer->binding,
+                                                buffer->copy_desc_set,
+                                                buffer->copy_binding);
+                }
         }
+}
 
-        return true;
+static void
+sync_tolerance(struct write_state *state,
+               const struct vr_tolerance *tolerance)
+{
+        if (tolerance->is_percent == state->tolerance.is_percent &&
+            tolerance->is_ulp == state->tolerance.is_ulp &&
+            !memcmp(tolerance->value,
+                    state->tolerance.value,
+                    sizeof tolerance->value))
+                return;
+
+        vr_buffer_append_string(state->buf, "tolerance");
+
+        if (tolerance->is_ulp)
+                vr_buffer_append_string(state->buf, " ulp");
+
+        for (int i = 0; i < VR_N_ELEMENTS(tolerance->value); i++) {
+                vr_buffer_append_printf(state->buf,
+                                        " %.17g%s",
+                                        tolerance->value[i],
+                                        tolerance->is_percent ? "%" : "");
+        }
+
+        vr_buffer_append_c(state->buf, '\n');
+
+        state->tolerance = *tolerance;
 }
 
-static int
-compare_buffer_set_and_binding(const void *a,
-                               const void *b)
+static void
+sync_layout(struct write_state *state,
+            const char *name,
+            struct vr_box_layout *current,
+            const struct vr_box_layout *layout)
 {
-        const struct vr_script_buffer *buffer_a =
-                (const struct vr_script_buffer *) a;
-        const struct vr_script_buffer *buffer_b =
-                (const struct vr_script_buffer *) b;
-        if ((int) buffer_a->desc_set == (int) buffer_b->desc_set)
-                return ((int) buffer_a->binding - (int) buffer_b->binding);
-        return ((int) buffer_a->desc_set - (int) buffer_b->desc_set);
+        if (current->std == layout->std && current->major == layout->major)
+                return;
+
+        vr_buffer_append_printf(state->buf,
+                                "%s layout %s %s\n",
+                                name,
+                                layout->std == VR_BOX_LAYOUT_STD_140 ?
+                                "std140" : "std430",
+                                layout->major == VR_BOX_MAJOR_AXIS_ROW ?
+                                "row_major" : "column_major");
+
+        *current = *layout;
 }
 
-static bool
-find_replacement(const struct vr_list *replacements,
-                 struct vr_buffer *line,
-                 int pos)
+static void
+sync_entrypoint(struct write_state *state,
+                const struct vr_pipeline_key *key,
+                enum vr_shader_stage stage)
 {
-        const struct vr_source_token_replacement *tr;
+        const char *entrypoint = vr_pipeline_key_get_entrypoint(key, stage);
 
-        vr_list_for_each(tr, replacements, link) {
-                int len = strlen(tr->token);
+        if (!strcmp(entrypoint,
+                    vr_pipeline_key_get_entrypoint(&state->key, stage)))
+                return;
 
-                if (pos + len <= line->length &&
-                    !memcmp(line->data + pos, tr->token, len)) {
-                        int repl_len = strlen(tr->replacement);
-                        int new_line_len = line->length + repl_len - len;
-                        /* The extra “1” is to preserve the null terminator */
-                        vr_buffer_ensure_size(line, new_line_len + 1);
-                        memmove(line->data + pos + repl_len,
-                                line->data + pos + len,
-                                line->length - pos - len + 1);
-                        memcpy(line->data + pos, tr->replacement, repl_len);
+        vr_buffer_append_printf(state->buf,
+                                "%s entrypoint %s\n",
+                                stage_names[stage],
+                                entrypoint);
 
-                        vr_buffer_set_length(line, new_line_len);
+        vr_pipeline_key_set_entrypoint(&state->key, stage, entrypoint);
+}
 
-                        return true;
+static void
+sync_specializations(struct write_state *state,
+                     const struct vr_pipeline_key *key)
+{
+        /* Specialization constants can’t be removed from the key by
+         * the script so only the ones that are missing or different
+         * need to be written */
+        for (int i = 0; i < key->n_specializations; i++) {
+                const struct vr_pipeline_key_specialization *spec =
+                        key->specializations + i;
+                int j;
+
+                for (j = 0; j < state->key.n_specializations; j++) {
+                        if (state->key.specializations[j].constant_id ==
+                            spec->constant_id)
+                                break;
                 }
-        }
 
-        return false;
+                if (j < state->key.n_specializations &&
+                    state->key.specializations[j].value == spec->value)
+                        continue;
+
+                vr_buffer_append_printf(state->buf,
+                                        "specialize %" PRIu32
+                                        " uint %" PRIu32 "\n",
+                                        spec->constant_id,
+                                        spec->value);
+
+                vr_pipeline_key_set_specialization(&state->key,
+                                                   spec->constant_id,
+                                                   spec->value);
+        }
 }
 
-static bool
-process_token_replacements(struct load_state *data)
+static void
+sync_pipeline_flags(struct write_state *state,
+                    const struct vr_pipeline_key *key)
 {
-        int count = 0;
+        /* The flags can’t be set back to zero by the script once
+         * they have been set so a key like that can’t occur */
+        if (key->flags == state->key.flags || key->flags == 0)
+                return;
 
-        for (int i = 0; i < data->line.length; i++) {
-                while (find_replacement(&data->source->token_replacements,
-                                        &data->line,
-                                        i)) {
-                        count++;
+        vr_buffer_append_string(state->buf, "pipelineFlags");
 
-                        if (count > 1000) {
-                                fprintf(stderr,
-                                        "%s:%i: infinite recursion suspected "
-                                        "while replacing tokens\n",
-                                        data->filename,
-                                        data->line_num);
-                                return false;
-                        }
-                }
+        const char *separator = " ";
+
+        for (int i = 0; i < VR_N_ELEMENTS(pipeline_flags); i++) {
+                if ((key->flags & pipeline_flags[i].flag) == 0)
+                        continue;
+
+                vr_buffer_append_printf(state->buf,
+                                        "%s%s",
+                                        separator,
+                                        pipeline_flags[i].name);
+                separator = ", ";
         }
 
-        return true;
+        vr_buffer_append_c(state->buf, '\n');
+
+        state->key.flags = key->flags;
 }
 
-static bool
-load_script_from_stream(struct load_state *data,
-                        struct vr_stream *stream)
+static void
+sync_graphics_pipeline_key(struct write_state *state,
+                           const struct vr_pipeline_key *key)
 {
-        bool res = true;
+        struct vr_buffer *buf = state->buf;
 
-        do {
-                int lines_consumed = vr_stream_read_line(stream, &data->line);
+        if (key->n_viewports != state->key.n_viewports) {
+                vr_buffer_append_printf(buf,
+                                        "viewports %i\n",
+                                        key->n_viewports);
+                state->key.n_viewports = key->n_viewports;
+        }
 
-                if (lines_consumed == 0)
-                        break;
+        /* The viewport and scissor can’t be set back to the
+         * default by the script so they only need to be written if
+         * they have been set */
+        if (memcmp(&key->viewport,
+                   &state->key.viewport,
+                   sizeof key->viewport)) {
+                vr_buffer_append_printf(buf,
+                                        "viewport %.9g %.9g %.9g %.9g "
+                                        "%.9g %.9g\n",
+                                        key->viewport.x,
+                                        key->viewport.y,
+                                        key->viewport.width,
+                                        key->viewport.height,
+                                        key->viewport.minDepth,
+                                        key->viewport.maxDepth);
+                state->key.viewport = key->viewport;
+        }
 
-                if (!process_token_replacements(data)) {
-                        res = false;
-                        break;
-                }
+        if (memcmp(&key->scissor,
+                   &state->key.scissor,
+                   sizeof key->scissor)) {
+                vr_buffer_append_printf(buf,
+                                        "scissor %" PRIi32 " %" PRIi32
+                                        " %" PRIu32 " %" PRIu32 "\n",
+                                        key->scissor.offset.x,
+                                        key->scissor.offset.y,
+                                        key->scissor.extent.width,
+                                        key->scissor.extent.height);
+                state->key.scissor = key->scissor;
+        }
 
-                res = process_line(data);
+        sync_pipeline_flags(state, key);
+
+        for (int i = 0; i < VR_N_ELEMENTS(key_properties); i++) {
+                const union vr_pipeline_key_value *value =
+                        (const union vr_pipeline_key_value *)
+                        ((const uint8_t *) key + key_properties[i].offset);
+                union vr_pipeline_key_value *current =
+                        (union vr_pipeline_key_value *)
+                        ((uint8_t *) &state->key + key_properties[i].offset);
+
+                if (!memcmp(value, current, sizeof *value))
+                        continue;
+
+                vr_buffer_append_printf(buf, "%s ", key_properties[i].name);
+
+                switch (key_properties[i].type) {
+                case VR_PIPELINE_KEY_VALUE_TYPE_BOOL:
+                        if (value->i == 0 || value->i == 1) {
+                                vr_buffer_append_string(buf,
+                                                        value->i ?
+                                                        "true\n" :
+                                                        "false\n");
+                                break;
+                        }
+                        /* flow through */
+                case VR_PIPELINE_KEY_VALUE_TYPE_INT:
+                        vr_buffer_append_printf(buf, "%i\n", value->i);
+                        break;
+                case VR_PIPELINE_KEY_VALUE_TYPE_FLOAT:
+                        vr_buffer_append_printf(buf, "%.9g\n", value->f);
+                        break;
+                }
 
-                data->line_num += lines_consumed;
-        } while (res);
+                *current = *value;
+        }
 
-        if (res)
-                res = end_section(data);
+        for (int stage = 0; stage < VR_SHADER_STAGE_N_STAGES; stage++) {
+                if (stage != VR_SHADER_STAGE_COMPUTE)
+                        sync_entrypoint(state, key, stage);
+        }
 
-        return res;
+        sync_specializations(state, key);
 }
 
-static bool
-load_script_from_file(struct load_state *data,
-                      const char *filename)
+static void
+sync_compute_pipeline_key(struct write_state *state,
+                          const struct vr_pipeline_key *key)
 {
-        FILE *f = fopen(filename, "r");
+        /* Compute keys are shared between commands that only have
+         * the same compute state so the graphics properties of the
+         * key might not be the ones that were set when the command
+         * was parsed. */
+        sync_pipeline_flags(state, key);
+        sync_entrypoint(state, key, VR_SHADER_STAGE_COMPUTE);
+        sync_specializations(state, key);
+}
 
-        if (f == NULL) {
-                vr_error_message(data->config,
-                                 "%s: %s",
-                                 filename,
-                                 strerror(errno));
-                return false;
+static void
+sync_clear_values(struct write_state *state,
+                  const struct vr_script_command *command)
+{
+        struct vr_buffer *buf = state->buf;
+        const float *color = command->clear.colors[0];
+
+        if (memcmp(color, state->clear_colors[0], sizeof (float) * 4)) {
+                vr_buffer_append_printf(buf,
+                                        "clear color %.9g %.9g %.9g %.9g\n",
+                                        color[0],
+                                        color[1],
+                                        color[2],
+                                        color[3]);
+
+                for (int i = 0; i < VR_WINDOW_MAX_COLOR_ATTACHMENTS; i++) {
+                        if (!state->clear_color_set[i]) {
+                                memcpy(state->clear_colors[i],
+                                       color,
+                                       sizeof (float) * 4);
+                        }
+                }
         }
 
-        struct vr_stream stream;
+        int n_color_attachments =
+                state->script->window_format.n_color_attachments;
 
-        vr_stream_init_file(&stream, f);
-        bool res = load_script_from_stream(data, &stream);
-        fclose(f);
+        for (int i = 1; i < n_color_attachments; i++) {
+                color = command->clear.colors[i];
 
-        return res;
+                if (!memcmp(color,
+                            state->clear_colors[i],
+                            sizeof (float) * 4))
+                        continue;
+
+                vr_buffer_append_printf(buf,
+                                        "clear color %i %.9g %.9g %.9g %.9g\n",
+                                        i,
+                                        color[0],
+                                        color[1],
+                                        color[2],
+                                        color[3]);
+                memcpy(state->clear_colors[i], color, sizeof (float) * 4);
+                state->clear_color_set[i] = true;
+        }
+
+        if (memcmp(&command->clear.depth,
+                   &state->clear_depth,
+                   sizeof state->clear_depth)) {
+                vr_buffer_append_printf(buf,
+                                        "clear depth %.9g\n",
+                                        command->clear.depth);
+                state->clear_depth = command->clear.depth;
+        }
+
+        if (command->clear.stencil != state->clear_stencil) {
+                vr_buffer_append_printf(buf,
+                                        "clear stencil %" PRIu32 "\n",
+                                        command->clear.stencil);
+                state->clear_stencil = command->clear.stencil;
+        }
 }
 
-static bool
-load_script_from_string(struct load_state *data,
-                        const char *string)
+/* Writes the arguments for a push or subdata command to set the raw
+ * bytes. This needs a std430 layout so that the array stride is the
+ * size of the type. */
+static void
+write_raw_data(struct vr_buffer *buf,
+               size_t offset,
+               size_t size,
+               const uint8_t *data)
 {
-        struct vr_stream stream;
+        static const struct vr_box_layout layout = {
+                .std = VR_BOX_LAYOUT_STD_430,
+                .major = VR_BOX_MAJOR_AXIS_COLUMN
+        };
+        enum vr_box_type type = (size % sizeof (uint32_t) == 0 ?
+                                 VR_BOX_TYPE_UINT :
+                                 VR_BOX_TYPE_UINT8);
+        size_t type_size = vr_box_type_size(type, &layout);
 
-        vr_stream_init_string(&stream, string);
-        bool res = load_script_from_stream(data, &stream);
+        vr_buffer_append_printf(buf,
+                                "%s%lu",
+                                get_value_type_name(type),
+                                (unsigned long) offset);
 
-        return res;
+        write_box_values(buf, type, &layout, size / type_size, type_size, data);
+
+        vr_buffer_append_c(buf, '\n');
 }
 
-struct vr_script *
-vr_script_load(const struct vr_config *config,
-               const struct vr_source *source)
+static void
+sync_raw_data_layout(struct write_state *state,
+                     const char *name,
+                     struct vr_box_layout *current)
 {
-        struct vr_script *script = vr_calloc(sizeof (struct vr_script));
-        struct load_state data = {
-                .config = config,
-                .source = source,
-                .line_num = 1,
-                .script = script,
-                .current_stage = -1,
-                .current_section = SECTION_NONE,
-                .clear_depth = 1.0f,
-                .line = VR_BUFFER_STATIC_INIT,
-                .buffer = VR_BUFFER_STATIC_INIT,
-                .commands = VR_BUFFER_STATIC_INIT,
-                .pipeline_keys = VR_BUFFER_STATIC_INIT,
-                .buffers = VR_BUFFER_STATIC_INIT,
-                .tolerance = {
-                        .value = {
-                                DEFAULT_TOLERANCE,
-                                DEFAULT_TOLERANCE,
-                                DEFAULT_TOLERANCE,
-                                DEFAULT_TOLERANCE,
-                        },
-                        .is_percent = false,
-                },
-                .push_layout = default_push_layout,
-                .ubo_layout = default_ubo_layout,
-                .ssbo_layout = default_ssbo_layout,
+        struct vr_box_layout layout = {
+                .std = VR_BOX_LAYOUT_STD_430,
+                .major = current->major
         };
 
-        vr_pipeline_key_init(&data.current_key);
+        sync_layout(state, name, current, &layout);
+}
 
-        script->window_format.width = 250;
-        script->window_format.height = 250;
-        script->window_format.color_format =
-                vr_format_lookup_by_vk_format(VK_FORMAT_B8G8R8A8_UNORM);
-        assert(script->window_format.color_format != NULL);
+static void
+write_probe_rect(struct write_state *state,
+                 const struct vr_script_command *command)
+{
+        struct vr_buffer *buf = state->buf;
 
-        for (int stage = 0; stage < VR_SHADER_STAGE_N_STAGES; stage++)
-                vr_list_init(&script->stages[stage]);
+        sync_tolerance(state, &command->probe_rect.tolerance);
 
-        script->requirements = vr_requirements_new();
+        vr_buffer_append_string(buf, "probe ");
 
-        bool res = false;
+        if (command->probe_rect.attachment > 0) {
+                vr_buffer_append_printf(buf,
+                                        "attachment %i ",
+                                        command->probe_rect.attachment);
+        }
 
-        switch (source->type) {
-        case VR_SOURCE_TYPE_FILE:
-                data.filename = source->string;
-                script->filename = vr_strdup(data.filename);
-                res = load_script_from_file(&data, source->string);
-                break;
+        vr_buffer_append_string(buf, "rect ");
 
-        case VR_SOURCE_TYPE_STRING:
-                data.filename = "(string script)";
-                script->filename = vr_strdup(data.filename);
-                res = load_script_from_string(&data, source->string);
+        switch (command->probe_rect.comparison) {
+        case VR_SCRIPT_PROBE_COMPARISON_EQUAL:
+                break;
+        case VR_SCRIPT_PROBE_COMPARISON_GREATER:
+                vr_buffer_append_string(buf, "greater ");
+                break;
+        case VR_SCRIPT_PROBE_COMPARISON_LESS:
+                vr_buffer_append_string(buf, "less ");
                 break;
         }
 
-        script->commands = (struct vr_script_command *) data.commands.data;
-        script->n_commands = (data.commands.length /
-                              sizeof (struct vr_script_command));
-        script->pipeline_keys =
-                (struct vr_pipeline_key *) data.pipeline_keys.data;
-        script->n_pipeline_keys = (data.pipeline_keys.length /
-                                   sizeof (struct vr_pipeline_key));
-        script->indices = (uint16_t *) data.indices.data;
-        script->n_indices =
-                data.indices.length / sizeof (uint16_t);
-
-        script->buffers = (struct vr_script_buffer *) data.buffers.data;
-        script->n_buffers = (data.buffers.length /
-                             sizeof (struct vr_script_buffer));
-        qsort(script->buffers,
-              script->n_buffers,
-              sizeof script->buffers[0],
-              compare_buffer_set_and_binding);
-
-        vr_buffer_destroy(&data.buffer);
-        vr_buffer_destroy(&data.line);
-        vr_pipeline_key_destroy(&data.current_key);
-
-        if (res) {
-                return script;
-        } else {
-                vr_script_free(script);
-                return NULL;
+        vr_buffer_append_printf(buf,
+                                "%s (%i, %i, %i, %i) (",
+                                command->probe_rect.n_components == 3 ?
+                                "rgb" : "rgba",
+                                command->probe_rect.x,
+                                command->probe_rect.y,
+                                command->probe_rect.w,
+                                command->probe_rect.h);
+
+        for (int i = 0; i < command->probe_rect.n_components; i++) {
+                vr_buffer_append_printf(buf,
+                                        "%s%.17g",
+                                        i > 0 ? ", " : "",
+                                        command->probe_rect.color[i]);
         }
+
+        vr_buffer_append_string(buf, ")\n");
 }
 
-void
-vr_script_free(struct vr_script *script)
+static void
+write_probe_buffer(struct write_state *state,
+                   const struct vr_script_command *command)
 {
-        int stage;
-        struct vr_script_shader *shader, *tmp;
+        struct vr_buffer *buf = state->buf;
+        enum vr_box_type type = command->probe_ssbo.type;
+        const struct vr_box_layout *layout = &command->probe_ssbo.layout;
 
-        for (int i = 0; i < script->n_commands; i++) {
-                struct vr_script_command *command = script->commands + i;
-                if (command->op == VR_SCRIPT_OP_SET_BUFFER_SUBDATA)
-                        vr_free(command->set_buffer_subdata.data);
-                else if (command->op == VR_SCRIPT_OP_SET_PUSH_CONSTANT)
-                        vr_free(command->set_push_constant.data);
-                else if (command->op == VR_SCRIPT_OP_PROBE_SSBO)
-                        vr_free(command->probe_ssbo.value);
+        switch (command->probe_ssbo.buffer_type) {
+        case VR_SCRIPT_BUFFER_TYPE_UBO:
+                sync_layout(state, "ubo", &state->ubo_layout, layout);
+                break;
+        case VR_SCRIPT_BUFFER_TYPE_SSBO:
+                sync_layout(state, "ssbo", &state->ssbo_layout, layout);
+                break;
         }
 
-        for (stage = 0; stage < VR_SHADER_STAGE_N_STAGES; stage++) {
-                vr_list_for_each_safe(shader,
-                                      tmp,
-                                      &script->stages[stage],
-                                      link) {
-                        vr_free(shader);
-                }
+        sync_tolerance(state, &command->probe_ssbo.tolerance);
+
+        vr_buffer_append_printf(buf,
+                                "probe %s ",
+                                probe_buffer_name(command));
+
+        if (command->probe_ssbo.write_result) {
+                vr_buffer_append_printf(buf,
+                                        "into %u:%u %lu ",
+                                        command->probe_ssbo.result_desc_set,
+                                        command->probe_ssbo.result_binding,
+                                        (unsigned long)
+                                        command->probe_ssbo.result_offset);
         }
 
-        if (script->vertex_data)
-                vr_vbo_free(script->vertex_data);
+        if (command->probe_ssbo.each)
+                vr_buffer_append_string(buf, "each ");
+
+        vr_buffer_append_printf(buf,
+                                "%s%u:%u %lu %s",
+                                get_value_type_name(type),
+                                command->probe_ssbo.desc_set,
+                                command->probe_ssbo.binding,
+                                (unsigned long)
+                                (command->probe_ssbo.each ?
+                                 command->probe_ssbo.n_values :
+                                 command->probe_ssbo.offset),
+                                box_comparison_names[command->probe_ssbo.
+                                                     comparison]);
+
+        /* Values loaded from a file use the array stride of the
+         * buffer but they are written inline so the parser will
+         * pack them tightly */
+        size_t stride = (command->probe_ssbo.from_file ?
+                         vr_box_type_array_stride(type, layout) :
+                         vr_box_type_size(type, layout));
+
+        write_box_values(buf,
+                         type,
+                         layout,
+                         (command->probe_ssbo.each ?
+                          1 :
+                          command->probe_ssbo.n_values),
+                         stride,
+                         command->probe_ssbo.value);
+
+        vr_buffer_append_c(buf, '\n');
+}
 
-        vr_free(script->indices);
+static void
+write_probe_ssbo_histogram(struct write_state *state,
+                           const struct vr_script_command *command)
+{
+        struct vr_buffer *buf = state->buf;
+
+        sync_layout(state,
+                    "ssbo",
+                    &state->ssbo_layout,
+                    &command->probe_ssbo_histogram.layout);
+        sync_tolerance(state, &command->probe_ssbo_histogram.tolerance);
+
+        vr_buffer_append_printf(buf,
+                                "probe ssbo histogram %s%u:%u %lu %lu "
+                                "buckets %u expect",
+                                get_value_type_name(command->
+                                                    probe_ssbo_histogram.type),
+                                command->probe_ssbo_histogram.desc_set,
+                                command->probe_ssbo_histogram.binding,
+                                (unsigned long)
+                                command->probe_ssbo_histogram.offset,
+                                (unsigned long)
+                                command->probe_ssbo_histogram.n_values,
+                                command->probe_ssbo_histogram.n_buckets);
+
+        for (unsigned i = 0; i < command->probe_ssbo_histogram.n_buckets; i++) {
+                vr_buffer_append_printf(buf,
+                                        " %u",
+                                        command->probe_ssbo_histogram.
+                                        expected[i]);
+        }
 
-        vr_free(script->filename);
+        vr_buffer_append_c(buf, '\n');
+}
 
-        vr_free(script->commands);
+static void
+write_draw_rect(struct write_state *state,
+                const struct vr_script_command *command)
+{
+        const struct vr_pipeline_key *command_key =
+                state->script->pipeline_keys + command->draw_rect.pipeline_key;
+        struct vr_buffer *buf = state->buf;
+
+        /* The topology and patch size are set by the command */
+        struct vr_pipeline_key key = *command_key;
+        key.topology = state->key.topology;
+        key.patchControlPoints = state->key.patchControlPoints;
+        sync_graphics_pipeline_key(state, &key);
+
+        vr_buffer_append_string(buf, "draw rect ");
+
+        if (command_key->topology.i == VK_PRIMITIVE_TOPOLOGY_PATCH_LIST) {
+                vr_buffer_append_string(buf, "patch ");
+        } else if (command_key->topology.i !=
+                   VK_PRIMITIVE_TOPOLOGY_TRIANGLE_STRIP) {
+                vr_buffer_append_printf(buf,
+                                        "topology %s ",
+                                        get_topology_name(command_key->
+                                                          topology.i));
+        }
 
-        for (int i = 0; i < script->n_pipeline_keys; i++)
-                vr_pipeline_key_destroy(script->pipeline_keys + i);
-        vr_free(script->pipeline_keys);
+        vr_buffer_append_printf(buf,
+                                "%.9g %.9g %.9g %.9g\n",
+                                command->draw_rect.x,
+                                command->draw_rect.y,
+                                command->draw_rect.w,
+                                command->draw_rect.h);
+}
 
-        vr_free(script->buffers);
+static void
+write_draw_arrays(struct write_state *state,
+                  const struct vr_script_command *command)
+{
+        const struct vr_pipeline_key *command_key =
+                state->script->pipeline_keys +
+                command->draw_arrays.pipeline_key;
+        struct vr_buffer *buf = state->buf;
+
+        /* The topology is set by the command */
+        struct vr_pipeline_key key = *command_key;
+        key.topology = state->key.topology;
+        sync_graphics_pipeline_key(state, &key);
+
+        bool instanced = (command->draw_arrays.instance_count != 1 ||
+                          command->draw_arrays.first_instance != 0);
+
+        vr_buffer_append_printf(buf,
+                                "draw arrays %s%s%s %" PRIu32 " %" PRIu32,
+                                command->draw_arrays.indexed ?
+                                "indexed " : "",
+                                instanced ? "instanced " : "",
+                                get_topology_name(command_key->topology.i),
+                                command->draw_arrays.first_vertex,
+                                command->draw_arrays.vertex_count);
+
+        if (instanced) {
+                vr_buffer_append_printf(buf,
+                                        " %" PRIu32 " %" PRIu32,
+                                        command->draw_arrays.instance_count,
+                                        command->draw_arrays.first_instance);
+        }
 
-        vr_requirements_free(script->requirements);
+        vr_buffer_append_c(buf, '\n');
+}
 
-        vr_free(script);
+static void
+write_draw_indirect_count(struct write_state *state,
+                          const struct vr_script_command *command)
+{
+        const struct vr_pipeline_key *command_key =
+                state->script->pipeline_keys +
+                command->draw_indirect_count.pipeline_key;
+
+        /* The topology is set by the command */
+        struct vr_pipeline_key key = *command_key;
+        key.topology = state->key.topology;
+        sync_graphics_pipeline_key(state, &key);
+
+        vr_buffer_append_printf(state->buf,
+                                "draw arrays %sindirect count %s "
+                                "%u:%u %lu %u:%u %lu %" PRIu32 "\n",
+                                command->draw_indirect_count.indexed ?
+                                "indexed " : "",
+                                get_topology_name(command_key->topology.i),
+                                command->draw_indirect_count.desc_set,
+                                command->draw_indirect_count.binding,
+                                (unsigned long)
+                                command->draw_indirect_count.offset,
+                                command->draw_indirect_count.count_desc_set,
+                                command->draw_indirect_count.count_binding,
+                                (unsigned long)
+                                command->draw_indirect_count.count_offset,
+                                command->draw_indirect_count.max_draw_count);
 }
 
-int
-vr_script_get_shaders(const struct vr_script *script,
-                      const struct vr_source *source,
-                      struct vr_script_shader_code *shaders)
+static void
+write_command(struct write_state *state,
+              const struct vr_script_command *command)
 {
-        int shaders_number = 0;
-        int stage;
-        struct vr_script_shader *shader, *tmp;
+        const struct vr_script *script = state->script;
+        struct vr_buffer *buf = state->buf;
+
+        switch (command->op) {
+        case VR_SCRIPT_OP_DRAW_RECT:
+                write_draw_rect(state, command);
+                return;
+        case VR_SCRIPT_OP_DRAW_ARRAYS:
+                write_draw_arrays(state, command);
+                return;
+        case VR_SCRIPT_OP_DRAW_INDIRECT_COUNT:
+                write_draw_indirect_count(state, command);
+                return;
+        case VR_SCRIPT_OP_DRAW_MESH:
+                sync_graphics_pipeline_key(state,
+                                           script->pipeline_keys +
+                                           command->draw_mesh.pipeline_key);
+                vr_buffer_append_printf(buf,
+                                        "draw mesh %u %u %u\n",
+                                        command->draw_mesh.x,
+                                        command->draw_mesh.y,
+                                        command->draw_mesh.z);
+                return;
+        case VR_SCRIPT_OP_DISPATCH_COMPUTE:
+                sync_compute_pipeline_key(state,
+                                          script->pipeline_keys +
+                                          command->dispatch_compute.
+                                          pipeline_key);
+                vr_buffer_append_printf(buf,
+                                        "compute %u %u %u\n",
+                                        command->dispatch_compute.x,
+                                        command->dispatch_compute.y,
+                                        command->dispatch_compute.z);
+                return;
+        case VR_SCRIPT_OP_PROBE_RECT:
+                write_probe_rect(state, command);
+                return;
+        case VR_SCRIPT_OP_PROBE_SSBO:
+                write_probe_buffer(state, command);
+                return;
+        case VR_SCRIPT_OP_PROBE_SSBO_EQUAL:
+                vr_buffer_append_printf(buf,
+                                        "probe ssbo equal %u:%u %u:%u %lu\n",
+                                        command->probe_ssbo_equal.buffers[0].
+                                        desc_set,
+                                        command->probe_ssbo_equal.buffers[0].
+                                        binding,
+                                        command->probe_ssbo_equal.buffers[1].
+                                        desc_set,
+                                        command->probe_ssbo_equal.buffers[1].
+                                        binding,
+                                        (unsigned long)
+                                        command->probe_ssbo_equal.size);
+                return;
+        case VR_SCRIPT_OP_PROBE_SSBO_HISTOGRAM:
+                write_probe_ssbo_histogram(state, command);
+                return;
+        case VR_SCRIPT_OP_SET_PUSH_CONSTANT:
+                sync_raw_data_layout(state, "push", &state->push_layout);
+                vr_buffer_append_string(buf, "push ");
+                write_raw_data(buf,
+                               command->set_push_constant.offset,
+                               command->set_push_constant.size,
+                               command->set_push_constant.data);
+                return;
+        case VR_SCRIPT_OP_SET_BUFFER_SUBDATA: {
+                enum vr_script_buffer_type type =
+                        get_script_buffer_type(script,
+                                               command->set_buffer_subdata.
+                                               desc_set,
+                                               command->set_buffer_subdata.
+                                               binding);
+
+                switch (type) {
+                case VR_SCRIPT_BUFFER_TYPE_UBO:
+                        sync_raw_data_layout(state, "ubo", &state->ubo_layout);
+                        break;
+                case VR_SCRIPT_BUFFER_TYPE_SSBO:
+                        sync_raw_data_layout(state,
+                                             "ssbo",
+                                             &state->ssbo_layout);
+                        break;
+                }
 
-        for (stage = 0; stage < VR_SHADER_STAGE_N_STAGES; stage++) {
-                vr_list_for_each_safe(shader,
-                                      tmp,
-                                      &script->stages[stage],
-                                      link) {
-                        shaders[shaders_number].source_type =
-                                shader->source_type;
-                        shaders[shaders_number].source_length = shader->length;
-                        shaders[shaders_number].stage = stage;
-                        shaders[shaders_number].source =
-                                malloc(shader->length + 1);
-                        memcpy(shaders[shaders_number].source,
-                               shader->source, shader->length);
-                        shaders[shaders_number].source[shader->length] = '\0';
-                        shaders_number++;
+                vr_buffer_append_printf(buf,
+                                        "%s %u:%u subdata ",
+                                        get_buffer_type_name(type),
+                                        command->set_buffer_subdata.desc_set,
+                                        command->set_buffer_subdata.binding);
+                write_raw_data(buf,
+                               command->set_buffer_subdata.offset,
+                               command->set_buffer_subdata.size,
+                               command->set_buffer_subdata.data);
+                return;
+        }
+        case VR_SCRIPT_OP_CLEAR:
+                sync_clear_values(state, command);
+                vr_buffer_append_string(buf, "clear\n");
+                return;
+        case VR_SCRIPT_OP_CLEAR_CHECKERBOARD:
+                vr_buffer_append_string(buf, "clear checkerboard");
+                for (int i = 0; i < 2; i++) {
+                        for (int j = 0; j < 4; j++) {
+                                vr_buffer_append_printf(buf,
+                                                        " %.9g",
+                                                        command->
+                                                        clear_checkerboard.
+                                                        colors[i][j]);
+                        }
                 }
+                vr_buffer_append_printf(buf,
+                                        " %u\n",
+                                        command->clear_checkerboard.size);
+                return;
+        case VR_SCRIPT_OP_IMAGE_TRANSITION:
+                vr_buffer_append_printf(buf,
+                                        "image transition %s %s\n",
+                                        get_image_layout_name(command->
+                                                              image_transition.
+                                                              old_layout),
+                                        get_image_layout_name(command->
+                                                              image_transition.
+                                                              new_layout));
+                return;
+        case VR_SCRIPT_OP_BEGIN_OCCLUSION_QUERY:
+                vr_buffer_append_string(buf, "occlusion begin\n");
+                return;
+        case VR_SCRIPT_OP_END_OCCLUSION_QUERY:
+                vr_buffer_append_string(buf, "occlusion end\n");
+                return;
+        case VR_SCRIPT_OP_PROBE_OCCLUSION:
+                vr_buffer_append_printf(buf,
+                                        "probe occlusion %s %" PRIu64 "\n",
+                                        box_comparison_names[command->
+                                                             probe_occlusion.
+                                                             comparison],
+                                        command->probe_occlusion.value);
+                return;
         }
 
-        return shaders_number;
+        vr_fatal("Unknown op");
 }
 
-int
-vr_script_get_num_shaders(const struct vr_script* script)
+static void
+write_test_section(struct write_state *state)
 {
-        int stage, num_shaders = 0;
+        const struct vr_script *script = state->script;
+        struct vr_buffer *buf = state->buf;
 
-        for (stage = 0; stage < VR_SHADER_STAGE_N_STAGES; stage++) {
-                num_shaders += vr_list_length(&script->stages[stage]);
+        start_section(buf);
+        vr_buffer_append_string(buf, "[test]\n");
+
+        if (script->expected_submissions >= 0) {
+                vr_buffer_append_printf(buf,
+                                        "expect submissions %i\n",
+                                        script->expected_submissions);
         }
-        return num_shaders;
+
+        write_buffers(state);
+
+        for (size_t i = 0; i < script->n_commands; i++)
+                write_command(state, script->commands + i);
 }
 
-void
-vr_script_replace_shaders_stage_binary(struct vr_script *script,
-                                       enum vr_shader_stage stage,
-                                       size_t source_length,
-                                       const uint32_t *source)
+char *
+vr_script_to_string(const struct vr_script *script)
 {
-        struct vr_script_shader *shader, *tmp;
+        struct vr_buffer buf = VR_BUFFER_STATIC_INIT;
+        struct write_state state = {
+                .script = script,
+                .buf = &buf,
+                .tolerance = {
+                        .value = {
+                                DEFAULT_TOLERANCE,
+                                DEFAULT_TOLERANCE,
+                                DEFAULT_TOLERANCE,
+                                DEFAULT_TOLERANCE,
+                        },
+                        .is_percent = false,
+                },
+                .push_layout = default_push_layout,
+                .ubo_layout = default_ubo_layout,
+                .ssbo_layout = default_ssbo_layout,
+                .clear_depth = 1.0f,
+        };
 
-        /* Remove all the shaders of the stage */
-        vr_list_for_each_safe(shader,
-                              tmp,
-                              &script->stages[stage],
-                              link) {
-                vr_list_remove(&shader->link);
-                vr_free(shader);
-        }
-        /* Add the new shader for the stage */
-        add_shader(script,
-                   stage,
-                   VR_SCRIPT_SOURCE_TYPE_BINARY,
-                   source_length,
-                   (const char *) source);
+        vr_pipeline_key_init(&state.key);
+
+        write_require_section(&state);
+        write_shader_sections(&state);
+        write_vertex_data_section(&state);
+        write_indices_section(&state);
+        write_test_section(&state);
+
+        vr_pipeline_key_destroy(&state.key);
+
+        vr_buffer_append_c(&buf, '\0');
+
+        return (char *) buf.data;
 }
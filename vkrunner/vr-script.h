@@ -44,6 +44,12 @@ enum vr_script_source_type {
 
 struct vr_script;
 
+struct vr_script_buffer_info {
+        unsigned desc_set;
+        unsigned binding;
+        size_t size;
+};
+
 struct vr_script_shader_code {
         enum vr_script_source_type source_type;
         enum vr_shader_stage stage;
@@ -74,6 +80,66 @@ vr_script_replace_shaders_stage_binary(struct vr_script *script,
                                        size_t source_length,
                                        const uint32_t *source);
 
+/* Returns the number of pipelines that will be created to run the
+ * script.
+ */
+size_t
+vr_script_get_n_pipelines(const struct vr_script *script);
+
+/* Returns the number of UBO and SSBO bindings used by the script */
+size_t
+vr_script_get_n_buffers(const struct vr_script *script);
+
+/* Fills in info with the details of the buffer at index. The size is
+ * zero for a binding that shares the buffer of another binding or
+ * that uses a null descriptor.
+ */
+void
+vr_script_get_buffer_info(const struct vr_script *script,
+                          size_t index,
+                          struct vr_script_buffer_info *info);
+
+/* Returns the number of device extensions that the script requires.
+ * This includes the extensions that provide any of the required
+ * features.
+ */
+size_t
+vr_script_get_n_required_extensions(const struct vr_script *script);
+
+/* Returns the name of the required extension at index. The string is
+ * owned by the script.
+ */
+const char *
+vr_script_get_required_extension(const struct vr_script *script,
+                                 size_t index);
+
+/* Returns the number of device features that the script requires */
+size_t
+vr_script_get_n_required_features(const struct vr_script *script);
+
+/* Returns the name of the required feature at index, such as
+ * “shaderFloat64”.
+ */
+const char *
+vr_script_get_required_feature(const struct vr_script *script,
+                               size_t index);
+
+/* Stores the minimum Vulkan API version that the script requires */
+void
+vr_script_get_required_version(const struct vr_script *script,
+                               unsigned *major,
+                               unsigned *minor,
+                               unsigned *patch);
+
+/* Returns a newly allocated string containing the script in the
+ * shader_test format. This won’t be byte-for-byte identical to the
+ * original file but it will be parsed back into an equivalent
+ * script. Any values that were loaded from other files are written
+ * inline. The string should be freed with free().
+ */
+char *
+vr_script_to_string(const struct vr_script *script);
+
 void
 vr_script_free(struct vr_script *script);
 
@@ -26,15 +26,21 @@
 #ifndef VR_SHADER_STAGE_H
 #define VR_SHADER_STAGE_H
 
+/* The stages are in the same order as the bits of
+ * VkShaderStageFlagBits so that the flag for a stage can be
+ * calculated by shifting VK_SHADER_STAGE_VERTEX_BIT.
+ */
 enum vr_shader_stage {
         VR_SHADER_STAGE_VERTEX,
         VR_SHADER_STAGE_TESS_CTRL,
         VR_SHADER_STAGE_TESS_EVAL,
         VR_SHADER_STAGE_GEOMETRY,
         VR_SHADER_STAGE_FRAGMENT,
-        VR_SHADER_STAGE_COMPUTE
+        VR_SHADER_STAGE_COMPUTE,
+        VR_SHADER_STAGE_TASK,
+        VR_SHADER_STAGE_MESH
 };
 
-#define VR_SHADER_STAGE_N_STAGES 6
+#define VR_SHADER_STAGE_N_STAGES 8
 
 #endif /* VR_SHADER_STAGE_H */
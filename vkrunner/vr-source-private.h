@@ -26,12 +26,15 @@
 #ifndef VR_SOURCE_PRIVATE_H
 #define VR_SOURCE_PRIVATE_H
 
+#include <stdio.h>
+
 #include "vr-source.h"
 #include "vr-list.h"
 
 enum vr_source_type {
         VR_SOURCE_TYPE_FILE,
-        VR_SOURCE_TYPE_STRING
+        VR_SOURCE_TYPE_STRING,
+        VR_SOURCE_TYPE_STREAM
 };
 
 struct vr_source_token_replacement {
@@ -43,6 +46,9 @@ struct vr_source_token_replacement {
 struct vr_source {
         enum vr_source_type type;
         struct vr_list token_replacements;
+        /* The stream to read for VR_SOURCE_TYPE_STREAM. The string
+         * is then the name used in error messages. */
+        FILE *file;
         char string[];
 };
 
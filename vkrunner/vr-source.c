@@ -37,6 +37,7 @@ source_new_with_type(enum vr_source_type type,
         struct vr_source *source = vr_alloc(sizeof *source + length + 1);
 
         source->type = type;
+        source->file = NULL;
         vr_list_init(&source->token_replacements);
         memcpy(source->string, string, length + 1);
 
@@ -55,6 +56,18 @@ vr_source_from_file(const char *filename)
         return source_new_with_type(VR_SOURCE_TYPE_FILE, filename);
 }
 
+struct vr_source *
+vr_source_from_stream(FILE *file,
+                      const char *name)
+{
+        struct vr_source *source =
+                source_new_with_type(VR_SOURCE_TYPE_STREAM, name);
+
+        source->file = file;
+
+        return source;
+}
+
 void
 vr_source_add_token_replacement(struct vr_source *source,
                                 const char *token,
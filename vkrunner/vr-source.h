@@ -26,6 +26,8 @@
 #ifndef VR_SOURCE_H
 #define VR_SOURCE_H
 
+#include <stdio.h>
+
 struct vr_source;
 
 #ifdef  __cplusplus
@@ -38,6 +40,19 @@ vr_source_from_string(const char *string);
 struct vr_source *
 vr_source_from_file(const char *filename);
 
+/* Creates a source that reads the script from an already open
+ * stream, for example one created with fdopen on a socket. The lines
+ * are read as they are needed so errors can be reported before the
+ * whole script has arrived. The name is only used in error messages
+ * and files referenced by the script are looked up relative to the
+ * current directory. The stream isn’t closed when the source is freed
+ * and it must stay open until the script is loaded. The source can
+ * only be used to load a script once.
+ */
+struct vr_source *
+vr_source_from_stream(FILE *file,
+                      const char *name);
+
 void
 vr_source_add_token_replacement(struct vr_source *source,
                                 const char *token,
@@ -35,6 +35,7 @@ vr_stream_init_string(struct vr_stream *stream,
                       const char *string)
 {
         stream->type = VR_STREAM_TYPE_STRING;
+        stream->at_start = true;
         stream->string = string;
         stream->end = string + strlen(string);
 }
@@ -44,6 +45,7 @@ vr_stream_init_file(struct vr_stream *stream,
                     FILE *file)
 {
         stream->type = VR_STREAM_TYPE_FILE;
+        stream->at_start = true;
         stream->file = file;
 }
 
@@ -54,9 +56,7 @@ read_line_from_string(struct vr_stream *stream,
         if (stream->string >= stream->end)
                 return false;
 
-        const char *end = memchr(stream->string,
-                                 '\n',
-                                 stream->end - stream->string);
+        const char *end = strpbrk(stream->string, "\r\n");
 
         if (end == NULL) {
                 vr_buffer_append(buffer,
@@ -66,7 +66,12 @@ read_line_from_string(struct vr_stream *stream,
         } else {
                 vr_buffer_append(buffer,
                                  stream->string,
-                                 end - stream->string + 1);
+                                 end - stream->string);
+                vr_buffer_append_c(buffer, '\n');
+
+                if (end[0] == '\r' && end[1] == '\n')
+                        end++;
+
                 stream->string = end + 1;
         }
 
@@ -78,20 +83,23 @@ read_line_from_file(FILE *f,
                     struct vr_buffer *buffer)
 {
         bool got_something = false;
+        int c;
 
-        while (true) {
-                vr_buffer_ensure_size(buffer, buffer->length + 128);
+        while ((c = getc(f)) != EOF) {
+                got_something = true;
 
-                char *read_start = (char *) buffer->data + buffer->length;
+                if (c == '\r') {
+                        int next = getc(f);
 
-                if (!fgets(read_start, buffer->size - buffer->length, f))
-                        break;
+                        if (next != '\n' && next != EOF)
+                                ungetc(next, f);
 
-                got_something = true;
+                        c = '\n';
+                }
 
-                buffer->length += strlen(read_start);
+                vr_buffer_append_c(buffer, c);
 
-                if (strchr(read_start, '\n'))
+                if (c == '\n')
                         break;
         }
 
@@ -112,6 +120,21 @@ raw_read_line(struct vr_stream *stream,
         vr_fatal("Unexpected stream type");
 }
 
+static void
+skip_bom(struct vr_buffer *buffer)
+{
+        static const char bom[] = "\xef\xbb\xbf";
+        size_t bom_length = sizeof bom - 1;
+
+        if (buffer->length >= bom_length &&
+            !memcmp(buffer->data, bom, bom_length)) {
+                memmove(buffer->data,
+                        buffer->data + bom_length,
+                        buffer->length - bom_length);
+                buffer->length -= bom_length;
+        }
+}
+
 int
 vr_stream_read_line(struct vr_stream *stream,
                     struct vr_buffer *buffer)
@@ -128,20 +151,15 @@ vr_stream_read_line(struct vr_stream *stream,
 
                 lines_consumed++;
 
-                if (buffer->length >= old_length + 2) {
-                        if (!memcmp(buffer->data + buffer->length - 2,
-                                    "\\\n",
-                                    2)) {
-                                buffer->length -= 2;
-                                continue;
-                        }
-                        if (buffer->length >= old_length + 3 &&
-                            !memcmp(buffer->data + buffer->length - 3,
-                                    "\\\r\n",
-                                    3)) {
-                                buffer->length -= 3;
-                                continue;
-                        }
+                if (stream->at_start) {
+                        skip_bom(buffer);
+                        stream->at_start = false;
+                }
+
+                if (buffer->length >= old_length + 2 &&
+                    !memcmp(buffer->data + buffer->length - 2, "\\\n", 2)) {
+                        buffer->length -= 2;
+                        continue;
                 }
 
                 break;
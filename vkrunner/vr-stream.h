@@ -38,6 +38,9 @@ enum vr_stream_type {
 
 struct vr_stream {
         enum vr_stream_type type;
+        /* Whether nothing has been read yet so that a byte order
+         * mark can be skipped */
+        bool at_start;
         union {
                 FILE *file;
                 struct {
@@ -57,7 +60,9 @@ vr_stream_init_file(struct vr_stream *stream,
 
 /* Returns the number of physical lines conusmed or 0 at the end of
  * the stream. Each returned line can consume multiple physical lines
- * if backslashes are used to combine them into one.
+ * if backslashes are used to combine them into one. A UTF-8 byte
+ * order mark at the start of the stream is skipped and CRLF or lone CR
+ * line endings are converted to LF.
  */
 int
 vr_stream_read_line(struct vr_stream *stream,
@@ -31,8 +31,15 @@
 #include <errno.h>
 #include <string.h>
 
+/* Function used to report each line of the output. This is either
+ * vr_error_message_string or vr_info_message_string */
+typedef void
+(* print_func)(const struct vr_config *config,
+               const char *str);
+
 static void
 print_lines_in_buffer(const struct vr_config *config,
+                      print_func print,
                       struct vr_buffer *buf)
 {
         uint8_t *p = buf->data;
@@ -48,7 +55,7 @@ print_lines_in_buffer(const struct vr_config *config,
                 if (end > p && end[-1] == '\r')
                         end[-1] = '\0';
 
-                vr_error_message_string(config, (const char *) p);
+                print(config, (const char *) p);
 
                 p = end + 1;
         }
@@ -60,13 +67,14 @@ print_lines_in_buffer(const struct vr_config *config,
 
 static void
 print_remaining_output(const struct vr_config *config,
+                       print_func print,
                        struct vr_buffer *buf)
 {
         if (buf->length <= 0)
                 return;
 
         vr_buffer_append_c(buf, '\0');
-        vr_error_message_string(config, (const char *) buf->data);
+        print(config, (const char *) buf->data);
 }
 
 #ifdef WIN32
@@ -178,6 +186,7 @@ error:
 
 static bool
 process_output(const struct vr_config *config,
+               print_func print,
                HANDLE pipe_fd)
 {
         struct vr_buffer buf = VR_BUFFER_STATIC_INIT;
@@ -205,19 +214,20 @@ process_output(const struct vr_config *config,
 
                 buf.length += got;
 
-                print_lines_in_buffer(config, &buf);
+                print_lines_in_buffer(config, print, &buf);
         }
 
-        print_remaining_output(config, &buf);
+        print_remaining_output(config, print, &buf);
 
         vr_buffer_destroy(&buf);
 
         return ret;
 }
 
-bool
-vr_subprocess_command(const struct vr_config *config,
-                      char * const *arguments)
+static bool
+run_command(const struct vr_config *config,
+            char * const *arguments,
+            print_func print)
 {
         BOOL res;
         bool result = true;
@@ -282,7 +292,7 @@ vr_subprocess_command(const struct vr_config *config,
                 goto out_pipes;
         }
 
-        if (!process_output(config, output_fd))
+        if (!process_output(config, print, output_fd))
                 result = false;
 
         DWORD exit_code;
@@ -311,6 +321,7 @@ out_pipes:
 
 static bool
 process_output(const struct vr_config *config,
+               print_func print,
                int *pipe_fd,
                struct vr_buffer *buf)
 {
@@ -340,13 +351,16 @@ process_output(const struct vr_config *config,
 
         buf->length += res;
 
-        print_lines_in_buffer(config, buf);
+        print_lines_in_buffer(config, print, buf);
 
         return true;
 }
 
+/* The output from stdout is reported with print. Anything on stderr
+ * is always reported as an error. */
 static bool
 stream_data(const struct vr_config *config,
+            print_func print,
             int stdout_pipe,
             int stderr_pipe)
 {
@@ -391,6 +405,7 @@ stream_data(const struct vr_config *config,
 
                         if (pollfds[i].fd == stdout_pipe) {
                                 if (!process_output(config,
+                                                    print,
                                                     &stdout_pipe,
                                                     &stdout_buf)) {
                                         ret = false;
@@ -398,6 +413,7 @@ stream_data(const struct vr_config *config,
                                 }
                         } else if (pollfds[i].fd == stderr_pipe) {
                                 if (!process_output(config,
+                                                    vr_error_message_string,
                                                     &stderr_pipe,
                                                     &stderr_buf)) {
                                         ret = false;
@@ -408,8 +424,8 @@ stream_data(const struct vr_config *config,
         }
 
 done:
-        print_remaining_output(config, &stdout_buf);
-        print_remaining_output(config, &stderr_buf);
+        print_remaining_output(config, print, &stdout_buf);
+        print_remaining_output(config, vr_error_message_string, &stderr_buf);
 
         vr_buffer_destroy(&stdout_buf);
         vr_buffer_destroy(&stderr_buf);
@@ -417,9 +433,10 @@ done:
         return ret;
 }
 
-bool
-vr_subprocess_command(const struct vr_config *config,
-                      char * const *arguments)
+static bool
+run_command(const struct vr_config *config,
+            char * const *arguments,
+            print_func print)
 {
         pid_t pid;
         int stdout_pipe[2];
@@ -457,6 +474,7 @@ vr_subprocess_command(const struct vr_config *config,
 
                 int status;
                 bool ret = stream_data(config,
+                                       print,
                                        stdout_pipe[0],
                                        stderr_pipe[0]);
 
@@ -473,3 +491,17 @@ vr_subprocess_command(const struct vr_config *config,
 }
 
 #endif /* WIN32 */
+
+bool
+vr_subprocess_command(const struct vr_config *config,
+                      char * const *arguments)
+{
+        return run_command(config, arguments, vr_error_message_string);
+}
+
+bool
+vr_subprocess_command_info(const struct vr_config *config,
+                           char * const *arguments)
+{
+        return run_command(config, arguments, vr_info_message_string);
+}
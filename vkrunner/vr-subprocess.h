@@ -29,8 +29,16 @@
 #include <stdlib.h>
 #include "vr-config.h"
 
+/* Runs the command and reports its output as errors */
 bool
 vr_subprocess_command(const struct vr_config *config,
                       char * const *arguments);
 
+/* Runs the command and reports its standard output with the info
+ * callback instead. Anything written to stderr is still reported as
+ * an error except on Windows where the two streams are merged. */
+bool
+vr_subprocess_command_info(const struct vr_config *config,
+                           char * const *arguments);
+
 #endif /* __VR_SUBPROCESS_H__ */
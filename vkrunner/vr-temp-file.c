@@ -116,3 +116,115 @@ vr_temp_file_create_named(const struct vr_config *config,
 
         return true;
 }
+
+bool
+vr_temp_file_create_beside(const struct vr_config *config,
+                           const char *filename,
+                           FILE **stream_out,
+                           char **filename_out)
+{
+#ifdef WIN32
+
+        const char *sep = NULL;
+
+        for (const char *p = filename; *p; p++) {
+                if (*p == '\\' || *p == '/')
+                        sep = p;
+        }
+
+        char *dir = (sep ?
+                     vr_strndup(filename, sep - filename + 1) :
+                     vr_strdup("."));
+        char temp_filename[MAX_PATH + 1];
+
+        DWORD res = GetTempFileNameA(dir,
+                                     "vkr",
+                                     0, /* unique */
+                                     temp_filename);
+
+        vr_free(dir);
+
+        if (res == 0) {
+                vr_error_message(config, "GetTempFileName failed");
+                return false;
+        }
+
+        FILE *stream = fopen(temp_filename, "r+b");
+        if (stream == NULL) {
+                vr_error_message(config,
+                                 "%s: %s",
+                                 temp_filename,
+                                 strerror(errno));
+                DeleteFileA(temp_filename);
+                return false;
+        }
+
+        *filename_out = vr_strdup(temp_filename);
+        *stream_out = stream;
+
+#else
+
+        struct vr_buffer temp_filename = VR_BUFFER_STATIC_INIT;
+
+        vr_buffer_append_printf(&temp_filename, "%s.XXXXXX", filename);
+
+        int fd = mkstemp((char *) temp_filename.data);
+        FILE *stream;
+
+        if (fd == -1) {
+                vr_error_message(config,
+                                 "%s: %s",
+                                 (char *) temp_filename.data,
+                                 strerror(errno));
+                vr_buffer_destroy(&temp_filename);
+                return false;
+        }
+
+        stream = fdopen(fd, "wb");
+        if (stream == NULL) {
+                vr_error_message(config,
+                                 "%s: %s",
+                                 (char *) temp_filename.data,
+                                 strerror(errno));
+                close(fd);
+                unlink((char *) temp_filename.data);
+                vr_buffer_destroy(&temp_filename);
+                return false;
+        }
+
+        *filename_out = (char *) temp_filename.data;
+        *stream_out = stream;
+
+#endif
+
+        return true;
+}
+
+bool
+vr_temp_file_replace(const struct vr_config *config,
+                     const char *temp_filename,
+                     const char *filename)
+{
+#ifdef WIN32
+        if (!MoveFileExA(temp_filename,
+                         filename,
+                         MOVEFILE_REPLACE_EXISTING)) {
+                vr_error_message(config,
+                                 "%s: error replacing the file",
+                                 filename);
+                DeleteFileA(temp_filename);
+                return false;
+        }
+#else
+        if (rename(temp_filename, filename) == -1) {
+                vr_error_message(config,
+                                 "%s: %s",
+                                 filename,
+                                 strerror(errno));
+                unlink(temp_filename);
+                return false;
+        }
+#endif
+
+        return true;
+}
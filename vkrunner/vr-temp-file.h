@@ -35,4 +35,21 @@ vr_temp_file_create_named(const struct vr_config *config,
                           FILE **stream_out,
                           char **filename_out);
 
+/* Creates a new uniquely named file in the same directory as
+ * filename. This can be written and then moved over filename with
+ * vr_temp_file_replace so that other processes never see a partially
+ * written file. */
+bool
+vr_temp_file_create_beside(const struct vr_config *config,
+                           const char *filename,
+                           FILE **stream_out,
+                           char **filename_out);
+
+/* Renames temp_filename to filename, replacing it if it already
+ * exists. On failure the temporary file is removed. */
+bool
+vr_temp_file_replace(const struct vr_config *config,
+                     const char *temp_filename,
+                     const char *filename);
+
 #endif /* VR_TEMP_FILE_H */
@@ -81,6 +81,19 @@ struct test_data {
         unsigned bound_pipeline;
         enum test_state test_state;
         bool first_render;
+        int n_submissions;
+        /* Whether this is a warmup run where the probes are skipped */
+        bool warmup;
+        /* Time from vr_get_time_ns after which waiting for the
+         * commands should be aborted or zero for no limit */
+        uint64_t deadline;
+        /* Whether to record the barriers with
+         * vkCmdPipelineBarrier2KHR */
+        bool use_synchronization2;
+        /* Query pool for the occlusion commands. This is created
+         * the first time a query is begun. */
+        VkQueryPool occlusion_query_pool;
+        bool occlusion_query_active;
 };
 
 static struct test_buffer *
@@ -190,15 +203,123 @@ begin_command_buffer(struct test_data *data)
         return true;
 }
 
+#ifdef VK_KHR_SYNCHRONIZATION_2_EXTENSION_NAME
+
 static void
-add_ssbo_barriers(struct test_data *data)
+pipeline_barrier2(struct test_data *data,
+                  VkPipelineStageFlags src_stages,
+                  VkPipelineStageFlags dst_stages,
+                  uint32_t n_buffer_barriers,
+                  const VkBufferMemoryBarrier *buffer_barriers,
+                  uint32_t n_image_barriers,
+                  const VkImageMemoryBarrier *image_barriers)
+{
+        struct vr_vk *vkfn = &data->window->vkfn;
+        VkBufferMemoryBarrier2KHR *buffer_barriers2 =
+                alloca(sizeof *buffer_barriers2 * n_buffer_barriers);
+        VkImageMemoryBarrier2KHR *image_barriers2 =
+                alloca(sizeof *image_barriers2 * n_image_barriers);
+
+        /* The stage and access bits of the original API have the
+         * same values in the synchronization2 flags */
+        for (uint32_t i = 0; i < n_buffer_barriers; i++) {
+                const VkBufferMemoryBarrier *b = buffer_barriers + i;
+
+                buffer_barriers2[i] = (VkBufferMemoryBarrier2KHR) {
+                        .sType = VK_STRUCTURE_TYPE_BUFFER_MEMORY_BARRIER_2_KHR,
+                        .srcStageMask = src_stages,
+                        .srcAccessMask = b->srcAccessMask,
+                        .dstStageMask = dst_stages,
+                        .dstAccessMask = b->dstAccessMask,
+                        .srcQueueFamilyIndex = b->srcQueueFamilyIndex,
+                        .dstQueueFamilyIndex = b->dstQueueFamilyIndex,
+                        .buffer = b->buffer,
+                        .offset = b->offset,
+                        .size = b->size
+                };
+        }
+
+        for (uint32_t i = 0; i < n_image_barriers; i++) {
+                const VkImageMemoryBarrier *b = image_barriers + i;
+
+                image_barriers2[i] = (VkImageMemoryBarrier2KHR) {
+                        .sType = VK_STRUCTURE_TYPE_IMAGE_MEMORY_BARRIER_2_KHR,
+                        .srcStageMask = src_stages,
+                        .srcAccessMask = b->srcAccessMask,
+                        .dstStageMask = dst_stages,
+                        .dstAccessMask = b->dstAccessMask,
+                        .oldLayout = b->oldLayout,
+                        .newLayout = b->newLayout,
+                        .srcQueueFamilyIndex = b->srcQueueFamilyIndex,
+                        .dstQueueFamilyIndex = b->dstQueueFamilyIndex,
+                        .image = b->image,
+                        .subresourceRange = b->subresourceRange
+                };
+        }
+
+        VkDependencyInfoKHR dependency_info = {
+                .sType = VK_STRUCTURE_TYPE_DEPENDENCY_INFO_KHR,
+                .bufferMemoryBarrierCount = n_buffer_barriers,
+                .pBufferMemoryBarriers = buffer_barriers2,
+                .imageMemoryBarrierCount = n_image_barriers,
+                .pImageMemoryBarriers = image_barriers2
+        };
+
+        vkfn->vkCmdPipelineBarrier2KHR(data->window->context->command_buffer,
+                                       &dependency_info);
+}
+
+#endif /* VK_KHR_SYNCHRONIZATION_2_EXTENSION_NAME */
+
+/* Records a pipeline barrier. If the script requires the
+ * synchronization2 feature then this uses vkCmdPipelineBarrier2KHR
+ * instead so that the newer API is exercised. */
+static void
+pipeline_barrier(struct test_data *data,
+                 VkPipelineStageFlags src_stages,
+                 VkPipelineStageFlags dst_stages,
+                 uint32_t n_buffer_barriers,
+                 const VkBufferMemoryBarrier *buffer_barriers,
+                 uint32_t n_image_barriers,
+                 const VkImageMemoryBarrier *image_barriers)
 {
         struct vr_vk *vkfn = &data->window->vkfn;
+
+#ifdef VK_KHR_SYNCHRONIZATION_2_EXTENSION_NAME
+        if (data->use_synchronization2) {
+                pipeline_barrier2(data,
+                                  src_stages,
+                                  dst_stages,
+                                  n_buffer_barriers,
+                                  buffer_barriers,
+                                  n_image_barriers,
+                                  image_barriers);
+                return;
+        }
+#endif
+
+        vkfn->vkCmdPipelineBarrier(data->window->context->command_buffer,
+                                   src_stages,
+                                   dst_stages,
+                                   (VkDependencyFlags) 0,
+                                   0, /* memoryBarrierCount */
+                                   NULL, /* pMemoryBarriers */
+                                   n_buffer_barriers,
+                                   buffer_barriers,
+                                   n_image_barriers,
+                                   image_barriers);
+}
+
+static void
+add_ssbo_barriers(struct test_data *data)
+{
         size_t n_barriers = 0;
         VkBufferMemoryBarrier *barriers = NULL;
 
         for (unsigned i = 0; i < data->script->n_buffers; i++) {
-                if (data->script->buffers[i].type != VR_SCRIPT_BUFFER_TYPE_SSBO)
+                if (data->script->buffers[i].type !=
+                    VR_SCRIPT_BUFFER_TYPE_SSBO ||
+                    data->ubo_buffers[i] == NULL)
                         continue;
 
                 if (barriers == NULL) {
@@ -219,17 +340,13 @@ add_ssbo_barriers(struct test_data *data)
         }
 
         if (n_barriers > 0) {
-                vkfn->vkCmdPipelineBarrier(
-                        data->window->context->command_buffer,
-                        VK_PIPELINE_STAGE_ALL_COMMANDS_BIT,
-                        VK_PIPELINE_STAGE_HOST_BIT,
-                        (VkDependencyFlags) 0,
-                        0, /* memoryBarrierCount */
-                        NULL, /* pMemoryBarriers */
-                        n_barriers,
-                        barriers,
-                        0, /* imageMemoryBarrierCount */
-                        NULL /* pImageMemoryBarriers */);
+                pipeline_barrier(data,
+                                 VK_PIPELINE_STAGE_ALL_COMMANDS_BIT,
+                                 VK_PIPELINE_STAGE_HOST_BIT,
+                                 n_barriers,
+                                 barriers,
+                                 0, /* n_image_barriers */
+                                 NULL /* image_barriers */);
         }
 
         vr_free(barriers);
@@ -241,11 +358,13 @@ invalidate_ssbos(struct test_data *data)
         struct vr_vk *vkfn = &data->window->vkfn;
 
         for (unsigned i = 0; i < data->script->n_buffers; i++) {
-                if (data->script->buffers[i].type != VR_SCRIPT_BUFFER_TYPE_SSBO)
-                        continue;
-
                 const struct test_buffer *buffer = data->ubo_buffers[i];
 
+                if (data->script->buffers[i].type !=
+                    VR_SCRIPT_BUFFER_TYPE_SSBO ||
+                    buffer == NULL)
+                        continue;
+
                 const VkMemoryType *memory_type =
                         (data->window->context->memory_properties.memoryTypes +
                          buffer->memory_type_index);
@@ -258,7 +377,7 @@ invalidate_ssbos(struct test_data *data)
 
                 VkMappedMemoryRange memory_range = {
                         .sType = VK_STRUCTURE_TYPE_MAPPED_MEMORY_RANGE,
-                        .memory = data->ubo_buffers[i]->memory,
+                        .memory = buffer->memory,
                         .offset = 0,
                         .size = VK_WHOLE_SIZE
                 };
@@ -274,7 +393,7 @@ flush_buffers(struct test_data *data)
         for (unsigned i = 0; i < data->script->n_buffers; i++) {
                 struct test_buffer *buffer = data->ubo_buffers[i];
 
-                if (!buffer->pending_write)
+                if (buffer == NULL || !buffer->pending_write)
                         continue;
 
                 vr_flush_memory(data->window->context,
@@ -287,6 +406,40 @@ flush_buffers(struct test_data *data)
         }
 }
 
+static VkResult
+wait_for_fence(struct test_data *data)
+{
+        struct vr_context *context = data->window->context;
+        struct vr_vk *vkfn = &context->vkfn;
+        VkResult res;
+
+        if (context->config->wait_mode == VR_CONFIG_WAIT_MODE_SPIN) {
+                while (true) {
+                        res = vkfn->vkGetFenceStatus(context->device,
+                                                     context->vk_fence);
+                        if (res != VK_NOT_READY)
+                                return res;
+
+                        if (data->deadline &&
+                            vr_get_time_ns() >= data->deadline)
+                                return VK_TIMEOUT;
+                }
+        }
+
+        uint64_t timeout = UINT64_MAX;
+
+        if (data->deadline) {
+                uint64_t now = vr_get_time_ns();
+                timeout = now < data->deadline ? data->deadline - now : 0;
+        }
+
+        return vkfn->vkWaitForFences(context->device,
+                                     1, /* fenceCount */
+                                     &context->vk_fence,
+                                     VK_TRUE, /* waitAll */
+                                     timeout);
+}
+
 static bool
 end_command_buffer(struct test_data *data)
 {
@@ -326,12 +479,16 @@ end_command_buffer(struct test_data *data)
                 return false;
         }
 
-        res = vkfn->vkWaitForFences(context->device,
-                                    1, /* fenceCount */
-                                    &context->vk_fence,
-                                    VK_TRUE, /* waitAll */
-                                    UINT64_MAX);
-        if (res != VK_SUCCESS) {
+        data->n_submissions++;
+
+        res = wait_for_fence(data);
+        if (res == VK_TIMEOUT) {
+                vr_error_message(context->config,
+                                 "Test timed out after %i seconds",
+                                 context->config->timeout);
+                context->timed_out = true;
+                return false;
+        } else if (res != VK_SUCCESS) {
                 vr_error_message(context->config, "vkWaitForFences failed");
                 return false;
         }
@@ -381,75 +538,79 @@ begin_render_pass(struct test_data *data)
         return true;
 }
 
-static bool
-end_render_pass(struct test_data *data)
+/* Records the commands to copy the color attachments into the
+ * linear buffer so that they can be read once the command buffer has
+ * completed. The images are expected to be in the color attachment
+ * layout and they are put back into it afterwards. */
+static void
+add_readback_commands(struct test_data *data)
 {
         struct vr_window *window = data->window;
         struct vr_vk *vkfn = &window->vkfn;
-
-        vkfn->vkCmdEndRenderPass(window->context->command_buffer);
+        int n_attachments = window->format.n_color_attachments;
+        VkImageMemoryBarrier image_barriers[VR_WINDOW_MAX_COLOR_ATTACHMENTS];
 
         /* Image barrier: transition the layout but also ensure:
          * - rendering is complete before vkCmdCopyImageToBuffer (below) and
          * before any future color attachment accesses
          * - the color attachment writes are visible to vkCmdCopyImageToBuffer
          * and to any future color attachment accesses */
-        VkImageMemoryBarrier render_finish_barrier = {
-                .sType = VK_STRUCTURE_TYPE_IMAGE_MEMORY_BARRIER,
-                .srcAccessMask = VK_ACCESS_COLOR_ATTACHMENT_WRITE_BIT,
-                .dstAccessMask = (VK_ACCESS_TRANSFER_READ_BIT |
-                                  VK_ACCESS_COLOR_ATTACHMENT_WRITE_BIT |
-                                  VK_ACCESS_COLOR_ATTACHMENT_READ_BIT),
-                .oldLayout = VK_IMAGE_LAYOUT_COLOR_ATTACHMENT_OPTIMAL,
-                .newLayout = VK_IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL,
-                .srcQueueFamilyIndex = VK_QUEUE_FAMILY_IGNORED,
-                .dstQueueFamilyIndex = VK_QUEUE_FAMILY_IGNORED,
-                .image = window->color_image,
-                .subresourceRange = {
-                        .aspectMask = VK_IMAGE_ASPECT_COLOR_BIT,
-                        .baseMipLevel = 0,
-                        .levelCount = 1,
-                        .baseArrayLayer = 0,
-                        .layerCount = 1
-                }
-        };
+        for (int i = 0; i < n_attachments; i++) {
+                image_barriers[i] = (VkImageMemoryBarrier) {
+                        .sType = VK_STRUCTURE_TYPE_IMAGE_MEMORY_BARRIER,
+                        .srcAccessMask = VK_ACCESS_COLOR_ATTACHMENT_WRITE_BIT,
+                        .dstAccessMask = (VK_ACCESS_TRANSFER_READ_BIT |
+                                          VK_ACCESS_COLOR_ATTACHMENT_WRITE_BIT |
+                                          VK_ACCESS_COLOR_ATTACHMENT_READ_BIT),
+                        .oldLayout = VK_IMAGE_LAYOUT_COLOR_ATTACHMENT_OPTIMAL,
+                        .newLayout = VK_IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL,
+                        .srcQueueFamilyIndex = VK_QUEUE_FAMILY_IGNORED,
+                        .dstQueueFamilyIndex = VK_QUEUE_FAMILY_IGNORED,
+                        .image = window->color_images[i],
+                        .subresourceRange = {
+                                .aspectMask = VK_IMAGE_ASPECT_COLOR_BIT,
+                                .baseMipLevel = 0,
+                                .levelCount = 1,
+                                .baseArrayLayer = 0,
+                                .layerCount = 1
+                        }
+                };
+        }
 
-        vkfn->vkCmdPipelineBarrier(
-                window->context->command_buffer,
-                VK_PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT,
-                VK_PIPELINE_STAGE_TRANSFER_BIT |
-                VK_PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT,
-                (VkDependencyFlags) 0,
-                0, /* memoryBarrierCount */
-                NULL, /* pMemoryBarriers */
-                0, /* bufferMemoryBarrierCount */
-                NULL, /* pBufferMemoryBarriers */
-                1, /* imageMemoryBarrierCount */
-                &render_finish_barrier);
-
-        VkBufferImageCopy copy_region = {
-                .bufferOffset = 0,
-                .bufferRowLength = data->window->format.width,
-                .bufferImageHeight = data->window->format.height,
-                .imageSubresource = {
-                        .aspectMask = VK_IMAGE_ASPECT_COLOR_BIT,
-                        .mipLevel = 0,
-                        .baseArrayLayer = 0,
-                        .layerCount = 1
-                },
-                .imageOffset = { 0, 0, 0 },
-                .imageExtent = {
-                        data->window->format.width,
-                        data->window->format.height,
-                        1
-                }
-        };
-        vkfn->vkCmdCopyImageToBuffer(window->context->command_buffer,
-                                     window->color_image,
-                                     VK_IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL,
-                                     window->linear_buffer,
-                                     1, /* regionCount */
-                                     &copy_region);
+        pipeline_barrier(data,
+                         VK_PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT,
+                         VK_PIPELINE_STAGE_TRANSFER_BIT |
+                         VK_PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT,
+                         0, /* n_buffer_barriers */
+                         NULL, /* buffer_barriers */
+                         n_attachments,
+                         image_barriers);
+
+        for (int i = 0; i < n_attachments; i++) {
+                VkBufferImageCopy copy_region = {
+                        .bufferOffset = window->linear_memory_offsets[i],
+                        .bufferRowLength = data->window->format.width,
+                        .bufferImageHeight = data->window->format.height,
+                        .imageSubresource = {
+                                .aspectMask = VK_IMAGE_ASPECT_COLOR_BIT,
+                                .mipLevel = 0,
+                                .baseArrayLayer = 0,
+                                .layerCount = 1
+                        },
+                        .imageOffset = { 0, 0, 0 },
+                        .imageExtent = {
+                                data->window->format.width,
+                                data->window->format.height,
+                                1
+                        }
+                };
+                vkfn->vkCmdCopyImageToBuffer(window->context->command_buffer,
+                                             window->color_images[i],
+                                             VK_IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL,
+                                             window->linear_buffer,
+                                             1, /* regionCount */
+                                             &copy_region);
+        }
 
 
         /* Image barrier: transition the layout back but also ensure:
@@ -457,35 +618,33 @@ end_render_pass(struct test_data *data)
          * attachment operations
          * No memory dependencies are needed because the first set of operations
          * are reads. */
-        VkImageMemoryBarrier copy_finish_barrier = {
-                .sType = VK_STRUCTURE_TYPE_IMAGE_MEMORY_BARRIER,
-                .srcAccessMask = 0,
-                .dstAccessMask = 0,
-                .oldLayout = VK_IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL,
-                .newLayout = VK_IMAGE_LAYOUT_COLOR_ATTACHMENT_OPTIMAL,
-                .srcQueueFamilyIndex = VK_QUEUE_FAMILY_IGNORED,
-                .dstQueueFamilyIndex = VK_QUEUE_FAMILY_IGNORED,
-                .image = window->color_image,
-                .subresourceRange = {
-                        .aspectMask = VK_IMAGE_ASPECT_COLOR_BIT,
-                        .baseMipLevel = 0,
-                        .levelCount = 1,
-                        .baseArrayLayer = 0,
-                        .layerCount = 1
-                }
-        };
+        for (int i = 0; i < n_attachments; i++) {
+                image_barriers[i] = (VkImageMemoryBarrier) {
+                        .sType = VK_STRUCTURE_TYPE_IMAGE_MEMORY_BARRIER,
+                        .srcAccessMask = 0,
+                        .dstAccessMask = 0,
+                        .oldLayout = VK_IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL,
+                        .newLayout = VK_IMAGE_LAYOUT_COLOR_ATTACHMENT_OPTIMAL,
+                        .srcQueueFamilyIndex = VK_QUEUE_FAMILY_IGNORED,
+                        .dstQueueFamilyIndex = VK_QUEUE_FAMILY_IGNORED,
+                        .image = window->color_images[i],
+                        .subresourceRange = {
+                                .aspectMask = VK_IMAGE_ASPECT_COLOR_BIT,
+                                .baseMipLevel = 0,
+                                .levelCount = 1,
+                                .baseArrayLayer = 0,
+                                .layerCount = 1
+                        }
+                };
+        }
 
-        vkfn->vkCmdPipelineBarrier(
-                        window->context->command_buffer,
-                        VK_PIPELINE_STAGE_TRANSFER_BIT,
-                        VK_PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT,
-                        (VkDependencyFlags) 0,
-                        0, /* memoryBarrierCount */
-                        NULL, /* pMemoryBarriers */
-                        0, /* bufferMemoryBarrierCount */
-                        NULL, /* pBufferMemoryBarriers */
-                        1, /* imageMemoryBarrierCount */
-                        &copy_finish_barrier);
+        pipeline_barrier(data,
+                         VK_PIPELINE_STAGE_TRANSFER_BIT,
+                         VK_PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT,
+                         0, /* n_buffer_barriers */
+                         NULL, /* buffer_barriers */
+                         n_attachments,
+                         image_barriers);
 
 
         /* Buffer barrier: ensure the device transfer writes have completed
@@ -501,16 +660,24 @@ end_render_pass(struct test_data *data)
                 .size = VK_WHOLE_SIZE
         };
 
-        vkfn->vkCmdPipelineBarrier(window->context->command_buffer,
-                                   VK_PIPELINE_STAGE_TRANSFER_BIT,
-                                   VK_PIPELINE_STAGE_HOST_BIT,
-                                   (VkDependencyFlags) 0,
-                                   0, /* memoryBarrierCount */
-                                   NULL, /* pMemoryBarriers */
-                                   1, /* bufferMemoryBarrierCount */
-                                   &write_finish_buffer_memory_barrier,
-                                   0, /* imageMemoryBarrierCount */
-                                   NULL); /* pImageMemoryBarriers */
+        pipeline_barrier(data,
+                         VK_PIPELINE_STAGE_TRANSFER_BIT,
+                         VK_PIPELINE_STAGE_HOST_BIT,
+                         1, /* n_buffer_barriers */
+                         &write_finish_buffer_memory_barrier,
+                         0, /* n_image_barriers */
+                         NULL /* image_barriers */);
+}
+
+static bool
+end_render_pass(struct test_data *data)
+{
+        struct vr_window *window = data->window;
+        struct vr_vk *vkfn = &window->vkfn;
+
+        vkfn->vkCmdEndRenderPass(window->context->command_buffer);
+
+        add_readback_commands(data);
 
         return true;
 }
@@ -544,6 +711,13 @@ set_state(struct test_data *data,
                                 return false;
                         break;
                 case TEST_STATE_RENDER_PASS:
+                        if (data->occlusion_query_active) {
+                                vr_error_message(data->window->config,
+                                                 "The render pass can’t be "
+                                                 "ended while an occlusion "
+                                                 "query is active");
+                                return false;
+                        }
                         if (!end_render_pass(data))
                                 return false;
                         break;
@@ -712,17 +886,19 @@ ensure_index_buffer(struct test_data *data)
         if (data->index_buffer)
                 return true;
 
+        size_t index_size = (data->script->index_type == VK_INDEX_TYPE_UINT32 ?
+                             sizeof (uint32_t) :
+                             sizeof (uint16_t));
+        size_t size = data->script->n_indices * index_size;
+
         data->index_buffer =
                 allocate_test_buffer(data,
-                                     data->script->n_indices *
-                                     sizeof data->script->indices[0],
+                                     size,
                                      VK_BUFFER_USAGE_INDEX_BUFFER_BIT);
         if (data->index_buffer == NULL)
                 return false;
 
-        memcpy(data->index_buffer->memory_map,
-               data->script->indices,
-               data->script->n_indices * sizeof data->script->indices[0]);
+        memcpy(data->index_buffer->memory_map, data->script->indices, size);
 
         vr_flush_memory(data->window->context,
                         data->index_buffer->memory_type_index,
@@ -763,39 +939,63 @@ ensure_vbo_buffer(struct test_data *data)
 }
 
 static bool
-draw_arrays(struct test_data *data,
-            const struct vr_script_command *command)
+bind_vbo(struct test_data *data)
 {
         struct vr_vk *vkfn = &data->window->vkfn;
         struct vr_context *context = data->window->context;
+        struct vr_vbo *vbo = data->script->vertex_data;
 
-        if (!set_state(data, TEST_STATE_RENDER_PASS))
+        if (vbo == NULL)
+                return true;
+
+        if (!ensure_vbo_buffer(data))
                 return false;
 
-        struct vr_vbo *vbo = data->script->vertex_data;
+        vkfn->vkCmdBindVertexBuffers(context->command_buffer,
+                                     0, /* firstBinding */
+                                     1, /* bindingCount */
+                                     &data->vbo_buffer->buffer,
+                                     (VkDeviceSize[]) { 0 });
 
-        if (vbo) {
-                if (!ensure_vbo_buffer(data))
-                        return false;
+        return true;
+}
 
-                vkfn->vkCmdBindVertexBuffers(context->command_buffer,
-                                             0, /* firstBinding */
-                                             1, /* bindingCount */
-                                             &data->vbo_buffer->buffer,
-                                             (VkDeviceSize[]) { 0 });
+static bool
+bind_index_buffer(struct test_data *data)
+{
+        struct vr_vk *vkfn = &data->window->vkfn;
+        struct vr_context *context = data->window->context;
 
-        }
+        if (!ensure_index_buffer(data))
+                return false;
+
+        vkfn->vkCmdBindIndexBuffer(context->command_buffer,
+                                   data->index_buffer->buffer,
+                                   0, /* offset */
+                                   data->script->index_type);
+
+        return true;
+}
+
+static bool
+draw_arrays(struct test_data *data,
+            const struct vr_script_command *command)
+{
+        struct vr_vk *vkfn = &data->window->vkfn;
+        struct vr_context *context = data->window->context;
+
+        if (!set_state(data, TEST_STATE_RENDER_PASS))
+                return false;
+
+        if (!bind_vbo(data))
+                return false;
 
         bind_ubo_descriptor_set(data);
         bind_pipeline(data, command->draw_arrays.pipeline_key);
 
         if (command->draw_arrays.indexed) {
-                if (!ensure_index_buffer(data))
+                if (!bind_index_buffer(data))
                         return false;
-                vkfn->vkCmdBindIndexBuffer(context->command_buffer,
-                                           data->index_buffer->buffer,
-                                           0, /* offset */
-                                           VK_INDEX_TYPE_UINT16);
                 vkfn->vkCmdDrawIndexed(context->command_buffer,
                                        command->draw_arrays.vertex_count,
                                        command->draw_arrays.instance_count,
@@ -813,6 +1013,165 @@ draw_arrays(struct test_data *data,
         return true;
 }
 
+/* Makes any shader writes to the buffers, such as from an earlier
+ * compute dispatch, visible to the indirect draw. Barriers can’t be
+ * recorded in the render pass so this has to be called outside of
+ * it. */
+static void
+add_indirect_barrier(struct test_data *data,
+                     const struct test_buffer *buffer,
+                     const struct test_buffer *count_buffer)
+{
+        VkBufferMemoryBarrier barriers[2];
+        uint32_t n_barriers = 0;
+
+        for (int i = 0; i < 2; i++) {
+                const struct test_buffer *b = i == 0 ? buffer : count_buffer;
+
+                /* The same buffer can hold the count */
+                if (i == 1 && count_buffer == buffer)
+                        break;
+
+                barriers[n_barriers++] = (VkBufferMemoryBarrier) {
+                        .sType = VK_STRUCTURE_TYPE_BUFFER_MEMORY_BARRIER,
+                        .srcAccessMask = VK_ACCESS_SHADER_WRITE_BIT,
+                        .dstAccessMask = VK_ACCESS_INDIRECT_COMMAND_READ_BIT,
+                        .srcQueueFamilyIndex = VK_QUEUE_FAMILY_IGNORED,
+                        .dstQueueFamilyIndex = VK_QUEUE_FAMILY_IGNORED,
+                        .buffer = b->buffer,
+                        .offset = 0,
+                        .size = VK_WHOLE_SIZE,
+                };
+        }
+
+        pipeline_barrier(data,
+                         VK_PIPELINE_STAGE_ALL_COMMANDS_BIT,
+                         VK_PIPELINE_STAGE_DRAW_INDIRECT_BIT,
+                         n_barriers,
+                         barriers,
+                         0, /* n_image_barriers */
+                         NULL /* image_barriers */);
+}
+
+static bool
+draw_indirect_count(struct test_data *data,
+                    const struct vr_script_command *command)
+{
+#ifdef VK_KHR_DRAW_INDIRECT_COUNT_EXTENSION_NAME
+        struct vr_vk *vkfn = &data->window->vkfn;
+        struct vr_context *context = data->window->context;
+        bool indexed = command->draw_indirect_count.indexed;
+
+        if ((indexed ?
+             vkfn->vkCmdDrawIndexedIndirectCountKHR == NULL :
+             vkfn->vkCmdDrawIndirectCountKHR == NULL)) {
+                print_command_fail(data->window->config, command);
+                vr_error_message(data->window->config,
+                                 "%s is not available",
+                                 indexed ?
+                                 "vkCmdDrawIndexedIndirectCountKHR" :
+                                 "vkCmdDrawIndirectCountKHR");
+                return false;
+        }
+
+        struct test_buffer *buffer =
+                get_ubo_buffer(data,
+                               command->draw_indirect_count.desc_set,
+                               command->draw_indirect_count.binding);
+        struct test_buffer *count_buffer =
+                get_ubo_buffer(data,
+                               command->draw_indirect_count.count_desc_set,
+                               command->draw_indirect_count.count_binding);
+
+        if (buffer == NULL || count_buffer == NULL) {
+                print_command_fail(data->window->config, command);
+                vr_error_message(data->window->config,
+                                 "Invalid buffer binding");
+                return false;
+        }
+
+        /* End any render pass so that the barrier can be recorded */
+        if (!set_state(data, TEST_STATE_COMMAND_BUFFER))
+                return false;
+
+        add_indirect_barrier(data, buffer, count_buffer);
+
+        if (!set_state(data, TEST_STATE_RENDER_PASS))
+                return false;
+
+        if (!bind_vbo(data))
+                return false;
+
+        bind_ubo_descriptor_set(data);
+        bind_pipeline(data, command->draw_indirect_count.pipeline_key);
+
+        if (indexed) {
+                if (!bind_index_buffer(data))
+                        return false;
+                vkfn->vkCmdDrawIndexedIndirectCountKHR(
+                        context->command_buffer,
+                        buffer->buffer,
+                        command->draw_indirect_count.offset,
+                        count_buffer->buffer,
+                        command->draw_indirect_count.count_offset,
+                        command->draw_indirect_count.max_draw_count,
+                        sizeof (VkDrawIndexedIndirectCommand));
+        } else {
+                vkfn->vkCmdDrawIndirectCountKHR(
+                        context->command_buffer,
+                        buffer->buffer,
+                        command->draw_indirect_count.offset,
+                        count_buffer->buffer,
+                        command->draw_indirect_count.count_offset,
+                        command->draw_indirect_count.max_draw_count,
+                        sizeof (VkDrawIndirectCommand));
+        }
+
+        return true;
+#else
+        print_command_fail(data->window->config, command);
+        vr_error_message(data->window->config,
+                         "VkRunner was built with Vulkan headers that "
+                         "are missing VK_KHR_draw_indirect_count");
+        return false;
+#endif
+}
+
+static bool
+draw_mesh(struct test_data *data,
+          const struct vr_script_command *command)
+{
+#ifdef VK_EXT_MESH_SHADER_EXTENSION_NAME
+        struct vr_vk *vkfn = &data->window->vkfn;
+
+        if (vkfn->vkCmdDrawMeshTasksEXT == NULL) {
+                print_command_fail(data->window->config, command);
+                vr_error_message(data->window->config,
+                                 "vkCmdDrawMeshTasksEXT is not available");
+                return false;
+        }
+
+        if (!set_state(data, TEST_STATE_RENDER_PASS))
+                return false;
+
+        bind_ubo_descriptor_set(data);
+        bind_pipeline(data, command->draw_mesh.pipeline_key);
+
+        vkfn->vkCmdDrawMeshTasksEXT(data->window->context->command_buffer,
+                                    command->draw_mesh.x,
+                                    command->draw_mesh.y,
+                                    command->draw_mesh.z);
+
+        return true;
+#else
+        print_command_fail(data->window->config, command);
+        vr_error_message(data->window->config,
+                         "VkRunner was built with Vulkan headers that "
+                         "are missing VK_EXT_mesh_shader");
+        return false;
+#endif
+}
+
 static bool
 dispatch_compute(struct test_data *data,
                  const struct vr_script_command *command)
@@ -834,17 +1193,32 @@ dispatch_compute(struct test_data *data,
 }
 
 static bool
-compare_pixels(const double *color1,
+compare_pixels(enum vr_script_probe_comparison comparison,
+               const double *color1,
                const double *color2,
                const struct vr_tolerance *tolerance,
+               enum vr_tolerance_precision precision,
                int n_components)
 {
         for (int p = 0; p < n_components; ++p) {
-                if (!vr_tolerance_equal(tolerance,
-                                        p,
-                                        color1[p],
-                                        color2[p]))
-                        return false;
+                switch (comparison) {
+                case VR_SCRIPT_PROBE_COMPARISON_EQUAL:
+                        if (!vr_tolerance_equal_precision(tolerance,
+                                                          precision,
+                                                          p,
+                                                          color1[p],
+                                                          color2[p]))
+                                return false;
+                        break;
+                case VR_SCRIPT_PROBE_COMPARISON_GREATER:
+                        if (!(color1[p] > color2[p]))
+                                return false;
+                        break;
+                case VR_SCRIPT_PROBE_COMPARISON_LESS:
+                        if (!(color1[p] < color2[p]))
+                                return false;
+                        break;
+                }
         }
 
         return true;
@@ -862,16 +1236,30 @@ print_components_double(struct vr_buffer *buf,
 static void
 print_bad_pixel(const struct vr_config *config,
                 int x, int y,
+                enum vr_script_probe_comparison comparison,
                 int n_components,
                 const double *expected,
                 const double *observed)
 {
         struct vr_buffer buf = VR_BUFFER_STATIC_INIT;
+        const char *expected_label = "Expected";
+
+        switch (comparison) {
+        case VR_SCRIPT_PROBE_COMPARISON_EQUAL:
+                break;
+        case VR_SCRIPT_PROBE_COMPARISON_GREATER:
+                expected_label = "Expected greater than";
+                break;
+        case VR_SCRIPT_PROBE_COMPARISON_LESS:
+                expected_label = "Expected less than";
+                break;
+        }
 
         vr_buffer_append_printf(&buf,
                                 "Probe color at (%i,%i)\n"
-                                "  Expected:",
-                                x, y);
+                                "  %s:",
+                                x, y,
+                                expected_label);
         print_components_double(&buf, expected, n_components);
         vr_buffer_append_string(&buf,
                                 "\n"
@@ -883,14 +1271,59 @@ print_bad_pixel(const struct vr_config *config,
         vr_buffer_destroy(&buf);
 }
 
+/* Gets the precision to measure a ULP tolerance in for the
+ * framebuffer format. Only floating-point formats that have a
+ * matching floating-point type are supported because the distance
+ * wouldn’t mean anything for the other formats.
+ */
+static bool
+get_framebuffer_precision(const struct vr_format *format,
+                          enum vr_tolerance_precision *precision)
+{
+        switch (vr_format_get_float_bits(format)) {
+        case 16:
+                *precision = VR_TOLERANCE_PRECISION_HALF;
+                return true;
+        case 32:
+                *precision = VR_TOLERANCE_PRECISION_SINGLE;
+                return true;
+        case 64:
+                *precision = VR_TOLERANCE_PRECISION_DOUBLE;
+                return true;
+        }
+
+        return false;
+}
+
 static bool
 probe_rect(struct test_data *data,
            const struct vr_script_command *command)
 {
+        struct vr_window *window = data->window;
+        int attachment = command->probe_rect.attachment;
         int n_components = command->probe_rect.n_components;
         const struct vr_format *format =
-                data->window->format.color_format;
+                window->format.color_formats[attachment];
         int format_size = vr_format_get_size(format);
+        const uint8_t *attachment_data =
+                ((const uint8_t *) window->linear_memory_map +
+                 window->linear_memory_offsets[attachment]);
+
+        enum vr_tolerance_precision precision = VR_TOLERANCE_PRECISION_SINGLE;
+
+        if (command->probe_rect.comparison ==
+            VR_SCRIPT_PROBE_COMPARISON_EQUAL &&
+            command->probe_rect.tolerance.is_ulp &&
+            !get_framebuffer_precision(format, &precision)) {
+                print_command_fail(window->config, command);
+                vr_error_message(window->config,
+                                 "A ULP tolerance can only be used with a "
+                                 "framebuffer that has a 16, 32 or 64-bit "
+                                 "floating-point format but the format is "
+                                 "%s",
+                                 vr_format_get_name(format));
+                return false;
+        }
 
         /* End the paint to copy the framebuffer into the linear buffer */
         if (!set_state(data, TEST_STATE_IDLE))
@@ -899,23 +1332,26 @@ probe_rect(struct test_data *data,
         for (int y = 0; y < command->probe_rect.h; y++) {
                 const uint8_t *p =
                         ((y + command->probe_rect.y) *
-                         data->window->linear_memory_stride +
+                         window->linear_memory_strides[attachment] +
                          command->probe_rect.x * format_size +
-                         (uint8_t *) data->window->linear_memory_map);
+                         attachment_data);
                 for (int x = 0; x < command->probe_rect.w; x++) {
                         double pixel[4];
                         vr_format_load_pixel(format, p, pixel);
                         p += format_size;
 
-                        if (!compare_pixels(pixel,
+                        if (!compare_pixels(command->probe_rect.comparison,
+                                            pixel,
                                             command->probe_rect.color,
                                             &command->probe_rect.tolerance,
+                                            precision,
                                             n_components)) {
                                 print_command_fail(data->window->config,
                                                    command);
                                 print_bad_pixel(data->window->config,
                                                 x + command->probe_rect.x,
                                                 y + command->probe_rect.y,
+                                                command->probe_rect.comparison,
                                                 n_components,
                                                 command->probe_rect.color,
                                                 pixel);
@@ -1016,10 +1452,30 @@ append_box(struct vr_buffer *buf,
         }
 }
 
+static void
+write_probe_result(struct test_buffer *buffer,
+                   size_t offset,
+                   bool result)
+{
+        uint32_t value = result;
+
+        memcpy((uint8_t *) buffer->memory_map + offset,
+               &value,
+               sizeof value);
+
+        buffer->pending_write = true;
+}
+
 static bool
 probe_ssbo(struct test_data *data,
            const struct vr_script_command *command)
 {
+        const char *buffer_name =
+                (command->probe_ssbo.buffer_type ==
+                 VR_SCRIPT_BUFFER_TYPE_UBO ?
+                 "UBO" :
+                 "SSBO");
+
         if (!set_state(data, TEST_STATE_IDLE))
                 return false;
 
@@ -1041,6 +1497,15 @@ probe_ssbo(struct test_data *data,
         size_t observed_stride =
                 vr_box_type_array_stride(command->probe_ssbo.type,
                                          &command->probe_ssbo.layout);
+        /* Values from a file have the same layout as the buffer */
+        size_t expected_stride;
+
+        if (command->probe_ssbo.each)
+                expected_stride = 0;
+        else if (command->probe_ssbo.from_file)
+                expected_stride = observed_stride;
+        else
+                expected_stride = type_size;
 
         if (command->probe_ssbo.offset +
             (command->probe_ssbo.n_values - 1) * observed_stride +
@@ -1051,6 +1516,16 @@ probe_ssbo(struct test_data *data,
                 return false;
         }
 
+        struct test_buffer *result_buffer = NULL;
+
+        if (command->probe_ssbo.write_result) {
+                result_buffer =
+                        get_ubo_buffer(data,
+                                       command->probe_ssbo.result_desc_set,
+                                       command->probe_ssbo.result_binding);
+                assert(result_buffer);
+        }
+
         const uint8_t *observed = ((const uint8_t *) buffer->memory_map +
                                    command->probe_ssbo.offset);
 
@@ -1060,15 +1535,67 @@ probe_ssbo(struct test_data *data,
                                    command->probe_ssbo.type,
                                    &command->probe_ssbo.layout,
                                    observed + observed_stride * i,
-                                   expected + type_size * i))
+                                   expected + expected_stride * i))
                         continue;
 
+                if (result_buffer) {
+                        write_probe_result(result_buffer,
+                                           command->probe_ssbo.result_offset,
+                                           false);
+                        return true;
+                }
+
                 print_command_fail(data->window->config, command);
 
                 struct vr_buffer buf = VR_BUFFER_STATIC_INIT;
-                vr_buffer_append_string(&buf,
-                                        "SSBO probe failed\n"
-                                        "  Reference:");
+
+                if (command->probe_ssbo.each ||
+                    command->probe_ssbo.from_file) {
+                        if (command->probe_ssbo.each) {
+                                vr_buffer_append_printf(&buf,
+                                                        "%s probe failed at "
+                                                        "index %lu\n"
+                                                        "  Reference:",
+                                                        buffer_name,
+                                                        (unsigned long) i);
+                        } else {
+                                unsigned long offset =
+                                        (command->probe_ssbo.offset +
+                                         observed_stride * i);
+                                vr_buffer_append_printf(&buf,
+                                                        "%s probe failed at "
+                                                        "offset %lu\n"
+                                                        "  Reference:",
+                                                        buffer_name,
+                                                        offset);
+                        }
+                        append_box(&buf,
+                                   command->probe_ssbo.type,
+                                   &command->probe_ssbo.layout,
+                                   1, /* n_values */
+                                   expected_stride,
+                                   expected + expected_stride * i);
+                        vr_buffer_append_string(&buf,
+                                                "\n"
+                                                "  Observed: ");
+                        append_box(&buf,
+                                   command->probe_ssbo.type,
+                                   &command->probe_ssbo.layout,
+                                   1, /* n_values */
+                                   observed_stride,
+                                   observed + observed_stride * i);
+                        vr_error_message(data->window->config,
+                                         "%s",
+                                         (const char *) buf.data);
+                        vr_buffer_destroy(&buf);
+
+                        return false;
+                }
+
+                vr_buffer_append_printf(&buf,
+                                        "%s probe failed\n"
+                                        "  Reference:",
+                                        buffer_name);
                 append_box(&buf,
                            command->probe_ssbo.type,
                            &command->probe_ssbo.layout,
@@ -1092,9 +1619,229 @@ probe_ssbo(struct test_data *data,
                 return false;
         }
 
+        if (result_buffer) {
+                write_probe_result(result_buffer,
+                                   command->probe_ssbo.result_offset,
+                                   true);
+        }
+
         return true;
 }
 
+static bool
+probe_ssbo_equal(struct test_data *data,
+                 const struct vr_script_command *command)
+{
+        if (!set_state(data, TEST_STATE_IDLE))
+                return false;
+
+        const struct test_buffer *buffers[2];
+        size_t size = command->probe_ssbo_equal.size;
+
+        for (int i = 0; i < 2; i++) {
+                buffers[i] =
+                        get_ubo_buffer(data,
+                                       command->probe_ssbo_equal.
+                                       buffers[i].desc_set,
+                                       command->probe_ssbo_equal.
+                                       buffers[i].binding);
+
+                if (buffers[i] == NULL) {
+                        print_command_fail(data->window->config, command);
+                        vr_error_message(data->window->config,
+                                         "Invalid binding in probe command");
+                        return false;
+                }
+
+                if (size > buffers[i]->size) {
+                        print_command_fail(data->window->config, command);
+                        vr_error_message(data->window->config,
+                                         "Invalid size in probe command");
+                        return false;
+                }
+        }
+
+        const uint8_t *a = buffers[0]->memory_map;
+        const uint8_t *b = buffers[1]->memory_map;
+
+        for (size_t offset = 0; offset < size; offset++) {
+                if (a[offset] == b[offset])
+                        continue;
+
+                print_command_fail(data->window->config, command);
+                vr_error_message(data->window->config,
+                                 "SSBO equal probe failed\n"
+                                 "  Buffers differ at offset %lu: "
+                                 "0x%02x != 0x%02x",
+                                 (unsigned long) offset,
+                                 a[offset],
+                                 b[offset]);
+                return false;
+        }
+
+        return true;
+}
+
+static double
+get_scalar_value(enum vr_box_base_type type,
+                 const uint8_t *p)
+{
+        switch (type) {
+        case VR_BOX_BASE_TYPE_INT:
+                return *(const int32_t *) p;
+        case VR_BOX_BASE_TYPE_UINT:
+                return *(const uint32_t *) p;
+        case VR_BOX_BASE_TYPE_INT8:
+                return *(const int8_t *) p;
+        case VR_BOX_BASE_TYPE_UINT8:
+                return *(const uint8_t *) p;
+        case VR_BOX_BASE_TYPE_INT16:
+                return *(const int16_t *) p;
+        case VR_BOX_BASE_TYPE_UINT16:
+                return *(const uint16_t *) p;
+        case VR_BOX_BASE_TYPE_INT64:
+                return *(const int64_t *) p;
+        case VR_BOX_BASE_TYPE_UINT64:
+                return *(const uint64_t *) p;
+        case VR_BOX_BASE_TYPE_FLOAT16:
+                return vr_half_float_to_double(*(const uint16_t *) p);
+        case VR_BOX_BASE_TYPE_FLOAT:
+                return *(const float *) p;
+        case VR_BOX_BASE_TYPE_DOUBLE:
+                return *(const double *) p;
+        }
+
+        vr_fatal("Unexpected base type");
+}
+
+static void
+append_bucket_counts(struct vr_buffer *buf,
+                     unsigned n_buckets,
+                     const unsigned *counts)
+{
+        for (unsigned i = 0; i < n_buckets; i++)
+                vr_buffer_append_printf(buf, " %u", counts[i]);
+}
+
+static bool
+probe_ssbo_histogram(struct test_data *data,
+                     const struct vr_script_command *command)
+{
+        if (!set_state(data, TEST_STATE_IDLE))
+                return false;
+
+        struct test_buffer *buffer =
+                get_ubo_buffer(data,
+                               command->probe_ssbo_histogram.desc_set,
+                               command->probe_ssbo_histogram.binding);
+
+        if (buffer == NULL) {
+                print_command_fail(data->window->config, command);
+                vr_error_message(data->window->config,
+                                 "Invalid binding in probe command");
+                return false;
+        }
+
+        enum vr_box_type type = command->probe_ssbo_histogram.type;
+        const struct vr_box_layout *layout =
+                &command->probe_ssbo_histogram.layout;
+        size_t n_values = command->probe_ssbo_histogram.n_values;
+        size_t type_size = vr_box_type_size(type, layout);
+        size_t stride = vr_box_type_array_stride(type, layout);
+
+        if (command->probe_ssbo_histogram.offset +
+            (n_values - 1) * stride +
+            type_size > buffer->size) {
+                print_command_fail(data->window->config, command);
+                vr_error_message(data->window->config,
+                                 "Invalid offset in probe command");
+                return false;
+        }
+
+        const uint8_t *observed = ((const uint8_t *) buffer->memory_map +
+                                   command->probe_ssbo_histogram.offset);
+        enum vr_box_base_type base_type =
+                vr_box_type_get_info(type)->base_type;
+        double *values = vr_alloc(n_values * sizeof (double));
+        double min_value = INFINITY, max_value = -INFINITY;
+
+        for (size_t i = 0; i < n_values; i++) {
+                values[i] = get_scalar_value(base_type,
+                                             observed + i * stride);
+
+                /* NaN and infinite values aren’t in any bucket */
+                if (!isfinite(values[i]))
+                        continue;
+
+                min_value = MIN(min_value, values[i]);
+                max_value = MAX(max_value, values[i]);
+        }
+
+        unsigned n_buckets = command->probe_ssbo_histogram.n_buckets;
+        unsigned *counts = vr_calloc(n_buckets * sizeof (unsigned));
+        double range = max_value - min_value;
+
+        for (size_t i = 0; i < n_values; i++) {
+                unsigned bucket = 0;
+
+                if (!isfinite(values[i]))
+                        continue;
+
+                /* The largest value would be one past the last
+                 * bucket so it is clamped into it */
+                if (range > 0.0) {
+                        double pos = ((values[i] - min_value) / range *
+                                      n_buckets);
+                        bucket = MIN((unsigned) pos, n_buckets - 1);
+                }
+
+                counts[bucket]++;
+        }
+
+        vr_free(values);
+
+        const unsigned *expected = command->probe_ssbo_histogram.expected;
+        bool ret = true;
+
+        for (unsigned i = 0; i < n_buckets; i++) {
+                if (vr_tolerance_equal(&command->probe_ssbo_histogram.
+                                       tolerance,
+                                       0, /* component */
+                                       expected[i],
+                                       counts[i]))
+                        continue;
+
+                print_command_fail(data->window->config, command);
+
+                struct vr_buffer buf = VR_BUFFER_STATIC_INIT;
+
+                vr_buffer_append_printf(&buf,
+                                        "SSBO histogram probe failed at "
+                                        "bucket %u\n"
+                                        "  Range: %f to %f\n"
+                                        "  Reference:",
+                                        i,
+                                        min_value,
+                                        max_value);
+                append_bucket_counts(&buf, n_buckets, expected);
+                vr_buffer_append_string(&buf,
+                                        "\n"
+                                        "  Observed: ");
+                append_bucket_counts(&buf, n_buckets, counts);
+                vr_error_message(data->window->config,
+                                 "%s",
+                                 (const char *) buf.data);
+                vr_buffer_destroy(&buf);
+
+                ret = false;
+                break;
+        }
+
+        vr_free(counts);
+
+        return ret;
+}
+
 static bool
 set_push_constant(struct test_data *data,
                   const struct vr_script_command *command)
@@ -1115,6 +1862,49 @@ set_push_constant(struct test_data *data,
         return true;
 }
 
+static void
+write_buffer_descriptor(struct test_data *data,
+                        const struct vr_script_buffer *script_buffer,
+                        const struct test_buffer *test_buffer)
+{
+        struct vr_vk *vkfn = &data->window->vkfn;
+
+        VkDescriptorType descriptor_type;
+        switch (script_buffer->type) {
+        case VR_SCRIPT_BUFFER_TYPE_UBO:
+                descriptor_type = VK_DESCRIPTOR_TYPE_UNIFORM_BUFFER;
+                goto found_type;
+        case VR_SCRIPT_BUFFER_TYPE_SSBO:
+                descriptor_type = VK_DESCRIPTOR_TYPE_STORAGE_BUFFER;
+                goto found_type;
+        }
+        vr_fatal("Unexpected buffer type");
+found_type:
+        ((void) 0);
+
+        VkDescriptorBufferInfo buffer_info = {
+                .buffer = (test_buffer ?
+                           test_buffer->buffer :
+                           VK_NULL_HANDLE),
+                .offset = 0,
+                .range = VK_WHOLE_SIZE
+        };
+        VkWriteDescriptorSet write = {
+                .sType = VK_STRUCTURE_TYPE_WRITE_DESCRIPTOR_SET,
+                .dstSet = data->ubo_descriptor_set[script_buffer->desc_set],
+                .dstBinding = script_buffer->binding,
+                .dstArrayElement = 0,
+                .descriptorCount = 1,
+                .descriptorType = descriptor_type,
+                .pBufferInfo = &buffer_info
+        };
+        vkfn->vkUpdateDescriptorSets(data->window->device,
+                                     1, /* descriptorWriteCount */
+                                     &write,
+                                     0, /* descriptorCopyCount */
+                                     NULL /* pDescriptorCopies */);
+}
+
 static bool
 allocate_ubo_buffers(struct test_data *data)
 {
@@ -1149,51 +1939,79 @@ allocate_ubo_buffers(struct test_data *data)
                 const struct vr_script_buffer *script_buffer =
                         data->script->buffers + i;
 
-                unsigned desc_set = script_buffer->desc_set;
+                /* Copies are handled after all of the buffers are
+                 * written */
+                if (script_buffer->is_copy)
+                        continue;
 
                 enum VkBufferUsageFlagBits usage;
-                VkDescriptorType descriptor_type;
                 switch (script_buffer->type) {
                 case VR_SCRIPT_BUFFER_TYPE_UBO:
                         usage = VK_BUFFER_USAGE_UNIFORM_BUFFER_BIT;
-                        descriptor_type = VK_DESCRIPTOR_TYPE_UNIFORM_BUFFER;
                         goto found_type;
                 case VR_SCRIPT_BUFFER_TYPE_SSBO:
-                        usage = VK_BUFFER_USAGE_STORAGE_BUFFER_BIT;
-                        descriptor_type = VK_DESCRIPTOR_TYPE_STORAGE_BUFFER;
+                        usage = (VK_BUFFER_USAGE_STORAGE_BUFFER_BIT |
+                                 VK_BUFFER_USAGE_INDIRECT_BUFFER_BIT);
                         goto found_type;
                 }
                 vr_fatal("Unexpected buffer type");
         found_type:
                 ((void) 0);
 
-                struct test_buffer *test_buffer =
-                        allocate_test_buffer(data, script_buffer->size, usage);
+                struct test_buffer *test_buffer;
 
-                if (test_buffer == NULL)
-                        return false;
+                if (script_buffer->is_null) {
+                        test_buffer = NULL;
+                } else {
+                        test_buffer = allocate_test_buffer(data,
+                                                           script_buffer->size,
+                                                           usage);
+                        if (test_buffer == NULL)
+                                return false;
+                }
 
                 data->ubo_buffers[i] = test_buffer;
 
-                VkDescriptorBufferInfo buffer_info = {
-                        .buffer = test_buffer->buffer,
-                        .offset = 0,
-                        .range = VK_WHOLE_SIZE
-                };
-                VkWriteDescriptorSet write = {
-                        .sType = VK_STRUCTURE_TYPE_WRITE_DESCRIPTOR_SET,
-                        .dstSet = data->ubo_descriptor_set[desc_set],
+                write_buffer_descriptor(data, script_buffer, test_buffer);
+        }
+
+        for (unsigned i = 0; i < data->script->n_buffers; i++) {
+                const struct vr_script_buffer *script_buffer =
+                        data->script->buffers + i;
+
+                if (!script_buffer->is_copy)
+                        continue;
+
+                data->ubo_buffers[i] =
+                        get_ubo_buffer(data,
+                                       script_buffer->copy_desc_set,
+                                       script_buffer->copy_binding);
+
+                /* Aliases get a new descriptor for the same buffer */
+                if (script_buffer->is_alias) {
+                        write_buffer_descriptor(data,
+                                                script_buffer,
+                                                data->ubo_buffers[i]);
+                        continue;
+                }
+
+                VkCopyDescriptorSet copy = {
+                        .sType = VK_STRUCTURE_TYPE_COPY_DESCRIPTOR_SET,
+                        .srcSet = data->ubo_descriptor_set[
+                                script_buffer->copy_desc_set],
+                        .srcBinding = script_buffer->copy_binding,
+                        .srcArrayElement = 0,
+                        .dstSet = data->ubo_descriptor_set[
+                                script_buffer->desc_set],
                         .dstBinding = script_buffer->binding,
                         .dstArrayElement = 0,
-                        .descriptorCount = 1,
-                        .descriptorType = descriptor_type,
-                        .pBufferInfo = &buffer_info
+                        .descriptorCount = 1
                 };
                 vkfn->vkUpdateDescriptorSets(data->window->device,
-                                             1, /* descriptorWriteCount */
-                                             &write,
-                                             0, /* descriptorCopyCount */
-                                             NULL /* pDescriptorCopies */);
+                                             0, /* descriptorWriteCount */
+                                             NULL, /* pDescriptorWrites */
+                                             1, /* descriptorCopyCount */
+                                             &copy);
         }
 
         return true;
@@ -1249,18 +2067,32 @@ clear(struct test_data *data,
                 }
         }
 
-        VkClearAttachment clear_attachments[] = {
-                {
+        VkClearAttachment
+                clear_attachments[VR_WINDOW_MAX_COLOR_ATTACHMENTS + 1];
+        int n_attachments = 0;
+
+        for (int i = 0; i < data->window->format.n_color_attachments; i++) {
+                VkClearAttachment *clear_attachment =
+                        clear_attachments + n_attachments++;
+
+                *clear_attachment = (VkClearAttachment) {
                         .aspectMask = VK_IMAGE_ASPECT_COLOR_BIT,
-                        .colorAttachment = 0,
-                },
-                {
+                        .colorAttachment = i,
+                };
+                memcpy(clear_attachment->clearValue.color.float32,
+                       command->clear.colors[i],
+                       sizeof command->clear.colors[i]);
+        }
+
+        if (depth_stencil_flags) {
+                clear_attachments[n_attachments++] = (VkClearAttachment) {
                         .aspectMask = depth_stencil_flags,
                         .clearValue = { .depthStencil =
                                         { .depth = command->clear.depth,
                                           .stencil = command->clear.stencil } }
-                },
-        };
+                };
+        }
+
         VkClearRect clear_rect = {
                 .rect = {
                         .offset = { 0, 0 },
@@ -1272,16 +2104,6 @@ clear(struct test_data *data,
                 .baseArrayLayer = 0,
                 .layerCount = 1
         };
-        memcpy(clear_attachments[0].clearValue.color.float32,
-               command->clear.color,
-               sizeof command->clear.color);
-
-        int n_attachments;
-
-        if (depth_stencil_flags)
-                n_attachments = 2;
-        else
-                n_attachments = 1;
 
         vkfn->vkCmdClearAttachments(data->window->context->command_buffer,
                                     n_attachments,
@@ -1292,55 +2114,308 @@ clear(struct test_data *data,
 }
 
 static bool
-run_commands(struct test_data *data)
+clear_checkerboard(struct test_data *data,
+                   const struct vr_script_command *command)
 {
-        const struct vr_script *script = data->script;
-        bool ret = true;
+        struct vr_vk *vkfn = &data->window->vkfn;
+        unsigned width = data->window->format.width;
+        unsigned height = data->window->format.height;
+        unsigned size = command->clear_checkerboard.size;
 
-        for (int i = 0; i < script->n_commands; i++) {
-                const struct vr_script_command *command = script->commands + i;
+        if (!set_state(data, TEST_STATE_RENDER_PASS))
+                return false;
 
-                switch (command->op) {
-                case VR_SCRIPT_OP_DRAW_RECT:
-                        if (!draw_rect(data, command))
-                                ret = false;
-                        break;
-                case VR_SCRIPT_OP_DRAW_ARRAYS:
-                        if (!draw_arrays(data, command))
-                                ret = false;
-                        break;
-                case VR_SCRIPT_OP_DISPATCH_COMPUTE:
-                        if (!dispatch_compute(data, command))
-                                ret = false;
-                        break;
-                case VR_SCRIPT_OP_PROBE_RECT:
-                        if (!probe_rect(data, command))
-                                ret = false;
-                        break;
-                case VR_SCRIPT_OP_PROBE_SSBO:
-                        if (!probe_ssbo(data, command))
-                                ret = false;
-                        break;
-                case VR_SCRIPT_OP_SET_PUSH_CONSTANT:
-                        if (!set_push_constant(data, command))
-                                ret = false;
-                        break;
-                case VR_SCRIPT_OP_SET_BUFFER_SUBDATA:
-                        if (!set_buffer_subdata(data, command))
-                                ret = false;
-                        break;
-                case VR_SCRIPT_OP_CLEAR:
-                        if (!clear(data, command))
-                                ret = false;
-                        break;
+        VkClearAttachment clear_attachment = {
+                .aspectMask = VK_IMAGE_ASPECT_COLOR_BIT,
+                .colorAttachment = 0,
+        };
+        VkClearRect clear_rect = {
+                .rect = {
+                        .offset = { 0, 0 },
+                        .extent = { width, height }
+                },
+                .baseArrayLayer = 0,
+                .layerCount = 1
+        };
+
+        /* Clear everything to the first color and then clear every
+         * other square to the second color.
+         */
+        memcpy(clear_attachment.clearValue.color.float32,
+               command->clear_checkerboard.colors[0],
+               sizeof command->clear_checkerboard.colors[0]);
+        vkfn->vkCmdClearAttachments(data->window->context->command_buffer,
+                                    1, /* attachmentCount */
+                                    &clear_attachment,
+                                    1, /* rectCount */
+                                    &clear_rect);
+
+        struct vr_buffer rects = VR_BUFFER_STATIC_INIT;
+
+        for (unsigned y = 0; y < height; y += size) {
+                for (unsigned x = (y / size & 1) * size;
+                     x < width;
+                     x += size * 2) {
+                        clear_rect.rect.offset.x = x;
+                        clear_rect.rect.offset.y = y;
+                        clear_rect.rect.extent.width = MIN(size, width - x);
+                        clear_rect.rect.extent.height = MIN(size, height - y);
+                        vr_buffer_append(&rects,
+                                         &clear_rect,
+                                         sizeof clear_rect);
                 }
         }
 
-        return ret;
+        memcpy(clear_attachment.clearValue.color.float32,
+               command->clear_checkerboard.colors[1],
+               sizeof command->clear_checkerboard.colors[1]);
+
+        if (rects.length > 0) {
+                vkfn->vkCmdClearAttachments(
+                        data->window->context->command_buffer,
+                        1, /* attachmentCount */
+                        &clear_attachment,
+                        rects.length / sizeof clear_rect,
+                        (const VkClearRect *) rects.data);
+        }
+
+        vr_buffer_destroy(&rects);
+
+        return true;
+}
+
+static bool
+image_transition(struct test_data *data,
+                 const struct vr_script_command *command)
+{
+        struct vr_window *window = data->window;
+
+        if (!set_state(data, TEST_STATE_COMMAND_BUFFER))
+                return false;
+
+        VkImageMemoryBarrier barrier = {
+                .sType = VK_STRUCTURE_TYPE_IMAGE_MEMORY_BARRIER,
+                .srcAccessMask = VK_ACCESS_MEMORY_WRITE_BIT,
+                .dstAccessMask = (VK_ACCESS_MEMORY_READ_BIT |
+                                  VK_ACCESS_MEMORY_WRITE_BIT),
+                .oldLayout = command->image_transition.old_layout,
+                .newLayout = command->image_transition.new_layout,
+                .srcQueueFamilyIndex = VK_QUEUE_FAMILY_IGNORED,
+                .dstQueueFamilyIndex = VK_QUEUE_FAMILY_IGNORED,
+                .image = window->color_images[0],
+                .subresourceRange = {
+                        .aspectMask = VK_IMAGE_ASPECT_COLOR_BIT,
+                        .baseMipLevel = 0,
+                        .levelCount = 1,
+                        .baseArrayLayer = 0,
+                        .layerCount = 1
+                }
+        };
+
+        pipeline_barrier(data,
+                         VK_PIPELINE_STAGE_ALL_COMMANDS_BIT,
+                         VK_PIPELINE_STAGE_ALL_COMMANDS_BIT,
+                         0, /* n_buffer_barriers */
+                         NULL, /* buffer_barriers */
+                         1, /* n_image_barriers */
+                         &barrier);
+
+        return true;
+}
+
+static bool
+begin_occlusion_query(struct test_data *data,
+                      const struct vr_script_command *command)
+{
+        struct vr_window *window = data->window;
+        struct vr_vk *vkfn = &window->vkfn;
+        VkResult res;
+
+        if (data->occlusion_query_pool == VK_NULL_HANDLE) {
+                VkQueryPoolCreateInfo query_pool_create_info = {
+                        .sType = VK_STRUCTURE_TYPE_QUERY_POOL_CREATE_INFO,
+                        .queryType = VK_QUERY_TYPE_OCCLUSION,
+                        .queryCount = 1
+                };
+                res = vkfn->vkCreateQueryPool(window->device,
+                                              &query_pool_create_info,
+                                              NULL, /* allocator */
+                                              &data->occlusion_query_pool);
+                if (res != VK_SUCCESS) {
+                        data->occlusion_query_pool = VK_NULL_HANDLE;
+                        print_command_fail(window->config, command);
+                        vr_error_message(window->config,
+                                         "Error creating query pool");
+                        return false;
+                }
+        }
+
+        /* The query has to be reset outside of the render pass but
+         * it has to begin and end within the same one */
+        if (!set_state(data, TEST_STATE_COMMAND_BUFFER))
+                return false;
+
+        vkfn->vkCmdResetQueryPool(window->context->command_buffer,
+                                  data->occlusion_query_pool,
+                                  0, /* firstQuery */
+                                  1 /* queryCount */);
+
+        if (!set_state(data, TEST_STATE_RENDER_PASS))
+                return false;
+
+        vkfn->vkCmdBeginQuery(window->context->command_buffer,
+                              data->occlusion_query_pool,
+                              0, /* query */
+                              VK_QUERY_CONTROL_PRECISE_BIT);
+
+        data->occlusion_query_active = true;
+
+        return true;
+}
+
+static bool
+end_occlusion_query(struct test_data *data,
+                    const struct vr_script_command *command)
+{
+        struct vr_window *window = data->window;
+
+        /* If the query isn’t active then beginning it must have
+         * failed and the error has already been reported */
+        if (!data->occlusion_query_active)
+                return false;
+
+        window->vkfn.vkCmdEndQuery(window->context->command_buffer,
+                                   data->occlusion_query_pool,
+                                   0 /* query */);
+
+        data->occlusion_query_active = false;
+
+        return true;
+}
+
+static bool
+compare_occlusion(enum vr_box_comparison comparison,
+                  uint64_t observed,
+                  uint64_t expected)
+{
+        switch (comparison) {
+        case VR_BOX_COMPARISON_EQUAL:
+        case VR_BOX_COMPARISON_FUZZY_EQUAL:
+                return observed == expected;
+        case VR_BOX_COMPARISON_NOT_EQUAL:
+                return observed != expected;
+        case VR_BOX_COMPARISON_LESS:
+                return observed < expected;
+        case VR_BOX_COMPARISON_GREATER_EQUAL:
+                return observed >= expected;
+        case VR_BOX_COMPARISON_GREATER:
+                return observed > expected;
+        case VR_BOX_COMPARISON_LESS_EQUAL:
+                return observed <= expected;
+        }
+
+        vr_fatal("Unexpected comparison");
+}
+
+static bool
+probe_occlusion(struct test_data *data,
+                const struct vr_script_command *command)
+{
+        struct vr_window *window = data->window;
+        struct vr_vk *vkfn = &window->vkfn;
+        uint64_t observed;
+        VkResult res;
+
+        if (data->occlusion_query_pool == VK_NULL_HANDLE) {
+                print_command_fail(window->config, command);
+                vr_error_message(window->config,
+                                 "probe occlusion used before any "
+                                 "occlusion query");
+                return false;
+        }
+
+        if (!set_state(data, TEST_STATE_IDLE))
+                return false;
+
+        res = vkfn->vkGetQueryPoolResults(window->device,
+                                          data->occlusion_query_pool,
+                                          0, /* firstQuery */
+                                          1, /* queryCount */
+                                          sizeof observed,
+                                          &observed,
+                                          sizeof observed,
+                                          VK_QUERY_RESULT_64_BIT |
+                                          VK_QUERY_RESULT_WAIT_BIT);
+        if (res != VK_SUCCESS) {
+                print_command_fail(window->config, command);
+                vr_error_message(window->config,
+                                 "Error getting the occlusion query result");
+                return false;
+        }
+
+        if (compare_occlusion(command->probe_occlusion.comparison,
+                              observed,
+                              command->probe_occlusion.value))
+                return true;
+
+        print_command_fail(window->config, command);
+        vr_error_message(window->config,
+                         "Occlusion probe failed\n"
+                         "  Reference: %llu\n"
+                         "  Observed:  %llu",
+                         (unsigned long long) command->probe_occlusion.value,
+                         (unsigned long long) observed);
+
+        return false;
+}
+
+static bool
+run_command(struct test_data *data,
+            const struct vr_script_command *command)
+{
+        switch (command->op) {
+        case VR_SCRIPT_OP_DRAW_RECT:
+                return draw_rect(data, command);
+        case VR_SCRIPT_OP_DRAW_ARRAYS:
+                return draw_arrays(data, command);
+        case VR_SCRIPT_OP_DRAW_INDIRECT_COUNT:
+                return draw_indirect_count(data, command);
+        case VR_SCRIPT_OP_DRAW_MESH:
+                return draw_mesh(data, command);
+        case VR_SCRIPT_OP_DISPATCH_COMPUTE:
+                return dispatch_compute(data, command);
+        case VR_SCRIPT_OP_PROBE_RECT:
+                return probe_rect(data, command);
+        case VR_SCRIPT_OP_PROBE_SSBO:
+                return probe_ssbo(data, command);
+        case VR_SCRIPT_OP_PROBE_SSBO_EQUAL:
+                return probe_ssbo_equal(data, command);
+        case VR_SCRIPT_OP_PROBE_SSBO_HISTOGRAM:
+                return probe_ssbo_histogram(data, command);
+        case VR_SCRIPT_OP_SET_PUSH_CONSTANT:
+                return set_push_constant(data, command);
+        case VR_SCRIPT_OP_SET_BUFFER_SUBDATA:
+                return set_buffer_subdata(data, command);
+        case VR_SCRIPT_OP_CLEAR:
+                return clear(data, command);
+        case VR_SCRIPT_OP_CLEAR_CHECKERBOARD:
+                return clear_checkerboard(data, command);
+        case VR_SCRIPT_OP_IMAGE_TRANSITION:
+                return image_transition(data, command);
+        case VR_SCRIPT_OP_BEGIN_OCCLUSION_QUERY:
+                return begin_occlusion_query(data, command);
+        case VR_SCRIPT_OP_END_OCCLUSION_QUERY:
+                return end_occlusion_query(data, command);
+        case VR_SCRIPT_OP_PROBE_OCCLUSION:
+                return probe_occlusion(data, command);
+        }
+
+        vr_fatal("Unexpected command op");
 }
 
 static void
-call_inspect(struct test_data *data)
+call_inspect(struct test_data *data,
+             vr_callback_inspect cb)
 {
         struct vr_inspect_data inspect_data;
 
@@ -1355,8 +2430,18 @@ call_inspect(struct test_data *data)
 
                 for (size_t i = 0; i < inspect_data.n_buffers; i++) {
                         buffers[i].binding = data->script->buffers[i].binding;
-                        buffers[i].size = data->ubo_buffers[i]->size;
-                        buffers[i].data = data->ubo_buffers[i]->memory_map;
+                        buffers[i].desc_set =
+                                data->script->buffers[i].desc_set;
+                        const struct test_buffer *buffer =
+                                data->ubo_buffers[i];
+
+                        if (buffer) {
+                                buffers[i].size = buffer->size;
+                                buffers[i].data = buffer->memory_map;
+                        } else {
+                                buffers[i].size = 0;
+                                buffers[i].data = NULL;
+                        }
                 }
 
                 inspect_data.buffers = buffers;
@@ -1366,18 +2451,247 @@ call_inspect(struct test_data *data)
 
         color_buffer->width = data->window->format.width;
         color_buffer->height = data->window->format.height;
-        color_buffer->stride = data->window->linear_memory_stride;
-        color_buffer->format = data->window->format.color_format;
+        color_buffer->stride = data->window->linear_memory_strides[0];
+        color_buffer->format = data->window->format.color_formats[0];
         color_buffer->data = data->window->linear_memory_map;
 
-        data->window->config->inspect_cb(&inspect_data,
-                                         data->window->config->user_data);
+        cb(&inspect_data, data->window->config->user_data);
+}
+
+static bool
+is_draw_command(const struct vr_script_command *command)
+{
+        switch (command->op) {
+        case VR_SCRIPT_OP_DRAW_RECT:
+        case VR_SCRIPT_OP_DRAW_ARRAYS:
+        case VR_SCRIPT_OP_DRAW_INDIRECT_COUNT:
+        case VR_SCRIPT_OP_DRAW_MESH:
+                return true;
+        default:
+                return false;
+        }
+}
+
+/* Waits for the commands so far to complete and then calls the draw
+ * inspect callback if there is one */
+static bool
+inspect_draw(struct test_data *data)
+{
+        vr_callback_inspect cb = data->window->config->draw_inspect_cb;
+
+        /* The commands can’t be flushed in the middle of an
+         * occlusion query so the draws within one aren’t inspected */
+        if (cb == NULL || data->warmup || data->occlusion_query_active)
+                return true;
+
+        if (!set_state(data, TEST_STATE_IDLE))
+                return false;
+
+        call_inspect(data, cb);
+
+        return true;
+}
+
+static bool
+is_probe_command(const struct vr_script_command *command)
+{
+        switch (command->op) {
+        case VR_SCRIPT_OP_PROBE_RECT:
+        case VR_SCRIPT_OP_PROBE_SSBO:
+        case VR_SCRIPT_OP_PROBE_SSBO_EQUAL:
+        case VR_SCRIPT_OP_PROBE_SSBO_HISTOGRAM:
+        case VR_SCRIPT_OP_PROBE_OCCLUSION:
+                return true;
+        default:
+                return false;
+        }
+}
+
+static const char *
+get_op_name(const struct vr_script_command *command)
+{
+        switch (command->op) {
+        case VR_SCRIPT_OP_DRAW_RECT:
+                return "draw rect";
+        case VR_SCRIPT_OP_DRAW_ARRAYS:
+                return "draw arrays";
+        case VR_SCRIPT_OP_DRAW_INDIRECT_COUNT:
+                return "draw indirect count";
+        case VR_SCRIPT_OP_DRAW_MESH:
+                return "draw mesh";
+        case VR_SCRIPT_OP_DISPATCH_COMPUTE:
+                return "compute";
+        case VR_SCRIPT_OP_PROBE_RECT:
+                return "probe";
+        case VR_SCRIPT_OP_PROBE_SSBO:
+                if (command->probe_ssbo.buffer_type ==
+                    VR_SCRIPT_BUFFER_TYPE_UBO)
+                        return "probe ubo";
+                return "probe ssbo";
+        case VR_SCRIPT_OP_PROBE_SSBO_EQUAL:
+                return "probe ssbo equal";
+        case VR_SCRIPT_OP_PROBE_SSBO_HISTOGRAM:
+                return "probe ssbo histogram";
+        case VR_SCRIPT_OP_SET_PUSH_CONSTANT:
+                return "push";
+        case VR_SCRIPT_OP_SET_BUFFER_SUBDATA:
+                return "subdata";
+        case VR_SCRIPT_OP_CLEAR:
+                return "clear";
+        case VR_SCRIPT_OP_CLEAR_CHECKERBOARD:
+                return "clear checkerboard";
+        case VR_SCRIPT_OP_IMAGE_TRANSITION:
+                return "image transition";
+        case VR_SCRIPT_OP_BEGIN_OCCLUSION_QUERY:
+                return "occlusion begin";
+        case VR_SCRIPT_OP_END_OCCLUSION_QUERY:
+                return "occlusion end";
+        case VR_SCRIPT_OP_PROBE_OCCLUSION:
+                return "probe occlusion";
+        }
+
+        vr_fatal("Unexpected command op");
+}
+
+static void
+report_command_result(struct test_data *data,
+                      const struct vr_script_command *command,
+                      bool passed)
+{
+        const struct vr_config *config = data->window->config;
+
+        if (config->command_cb == NULL || data->warmup)
+                return;
+
+        struct vr_command_result command_result = {
+                .line_num = command->line_num,
+                .op = get_op_name(command),
+                .result = passed ? VR_RESULT_PASS : VR_RESULT_FAIL,
+        };
+
+        config->command_cb(&command_result, config->user_data);
+}
+
+static bool
+run_commands(struct test_data *data)
+{
+        const struct vr_script *script = data->script;
+        bool ret = true;
+
+        for (int i = 0; i < script->n_commands; i++) {
+                const struct vr_script_command *command = script->commands + i;
+
+                if (data->warmup && is_probe_command(command))
+                        continue;
+
+                if (run_command(data, command) &&
+                    (!is_draw_command(command) || inspect_draw(data))) {
+                        report_command_result(data, command, true);
+                        continue;
+                }
+
+                report_command_result(data, command, false);
+
+                ret = false;
+
+                /* The command buffer is still in use so nothing else
+                 * can be submitted */
+                if (data->window->context->timed_out)
+                        break;
+
+                if (data->window->config->stop_on_probe_failure &&
+                    is_probe_command(command))
+                        break;
+        }
+
+        return ret;
+}
+
+static bool
+check_submissions(struct test_data *data)
+{
+        int expected = data->script->expected_submissions;
+
+        if (expected < 0 || expected == data->n_submissions)
+                return true;
+
+        vr_error_message(data->window->config,
+                         "Expected %i queue submissions but there were %i",
+                         expected,
+                         data->n_submissions);
+
+        return false;
+}
+
+/* Times an extra copy of the color buffer to the linear buffer
+ * including waiting for it and reading it back on the CPU. This is
+ * done after the commands have run so that it doesn’t change what
+ * the probes see. */
+static bool
+measure_readback(struct test_data *data)
+{
+        struct vr_window *window = data->window;
+        size_t size = window->linear_memory_size;
+
+        /* If nothing was rendered then the image doesn’t have any
+         * contents to read */
+        if (data->first_render)
+                return true;
+
+        uint64_t start = vr_get_time_ns();
+
+        if (!set_state(data, TEST_STATE_COMMAND_BUFFER))
+                return false;
+
+        add_readback_commands(data);
+
+        if (!set_state(data, TEST_STATE_IDLE))
+                return false;
+
+        /* Touch every word so that the time includes reading the
+         * memory. The result is stored in a volatile so that the
+         * compiler can’t skip the reads. */
+        const uint32_t *words = window->linear_memory_map;
+        uint32_t sum = 0;
+
+        for (size_t i = 0; i < size / sizeof *words; i++)
+                sum += words[i];
+
+        volatile uint32_t sink = sum;
+        (void) sink;
+
+        uint64_t elapsed = vr_get_time_ns() - start;
+
+        if (elapsed == 0)
+                elapsed = 1;
+
+        vr_info_message(window->config,
+                        "Readback: %lu bytes in %.3f ms (%.1f MB/s)",
+                        (unsigned long) size,
+                        elapsed / 1e6,
+                        size * 1e3 / elapsed);
+
+        return true;
+}
+
+static bool
+should_use_synchronization2(const struct vr_window *window,
+                            const struct vr_script *script)
+{
+#ifdef VK_KHR_SYNCHRONIZATION_2_EXTENSION_NAME
+        return (window->vkfn.vkCmdPipelineBarrier2KHR &&
+                vr_requirements_has(script->requirements,
+                                    "synchronization2"));
+#else
+        return false;
+#endif
 }
 
 bool
 vr_test_run(struct vr_window *window,
             struct vr_pipeline *pipeline,
-            const struct vr_script *script)
+            const struct vr_script *script,
+            bool warmup)
 {
         struct vr_vk *vkfn = &window->vkfn;
 
@@ -1385,34 +2699,75 @@ vr_test_run(struct vr_window *window,
                 .window = window,
                 .pipeline = pipeline,
                 .script = script,
+                .warmup = warmup,
                 .test_state = TEST_STATE_IDLE,
                 .first_render = true,
-                .bound_pipeline = UINT_MAX
+                .bound_pipeline = UINT_MAX,
+                .use_synchronization2 =
+                should_use_synchronization2(window, script),
         };
         bool ret = true;
 
         vr_list_init(&data.buffers);
 
+        if (window->config->timeout > 0) {
+                data.deadline = (vr_get_time_ns() +
+                                 window->config->timeout *
+                                 UINT64_C(1000000000));
+        }
+
         if (script->n_buffers > 0 && !allocate_ubo_buffers(&data)) {
                 ret = false;
         } else {
                 if (!run_commands(&data))
                         ret = false;
 
-                if (!set_state(&data, TEST_STATE_IDLE))
+                if (!window->context->timed_out &&
+                    !set_state(&data, TEST_STATE_IDLE))
                         ret = false;
 
-                if (window->config->inspect_cb)
-                        call_inspect(&data);
+                if (!warmup && !window->context->timed_out) {
+                        if (!check_submissions(&data))
+                                ret = false;
+
+                        if (window->config->inspect_cb)
+                                call_inspect(&data, window->config->inspect_cb);
+
+                        if (window->config->measure_readback &&
+                            !measure_readback(&data))
+                                ret = false;
+                }
         }
 
         struct test_buffer *buffer, *tmp;
+
+        /* If the fence was never signalled then the device might
+         * still be using the objects so only the host memory is
+         * freed. The executor leaks the whole device in that case. */
+        if (window->context->timed_out) {
+                vr_list_for_each_safe(buffer, tmp, &data.buffers, link) {
+                        vr_list_remove(&buffer->link);
+                        vr_free(buffer);
+                }
+
+                vr_free(data.ubo_buffers);
+                vr_free(data.ubo_descriptor_set);
+
+                return ret;
+        }
+
         vr_list_for_each_safe(buffer, tmp, &data.buffers, link) {
                 free_test_buffer(&data, buffer);
         }
 
         vr_free(data.ubo_buffers);
 
+        if (data.occlusion_query_pool) {
+                vkfn->vkDestroyQueryPool(window->device,
+                                         data.occlusion_query_pool,
+                                         NULL /* allocator */);
+        }
+
         if (data.ubo_descriptor_set) {
                 for (unsigned i = 0; i < pipeline->n_desc_sets; i++) {
                         if (data.ubo_descriptor_set[i]) {
@@ -25,16 +25,122 @@
 
 #include "config.h"
 
+#include <float.h>
 #include <math.h>
+#include <stdint.h>
+#include <string.h>
 
 #include "vr-tolerance.h"
+#include "vr-util.h"
+
+/* Maps the sign-magnitude bits of a float onto an integer that
+ * increases monotonically with the value so that the difference
+ * between two of them is the number of representable values in
+ * between. Converting a double that is outside the range of a float
+ * is undefined so those values become infinity like for half
+ * floats. */
+static int64_t
+float_ordered_bits(double value)
+{
+        float f = (fabs(value) > FLT_MAX ?
+                   copysignf(INFINITY, value) :
+                   (float) value);
+        int32_t bits;
+
+        memcpy(&bits, &f, sizeof bits);
+
+        return bits < 0 ? (int64_t) INT32_MIN - bits : bits;
+}
+
+/* Half floats are only stored as doubles so this converts the value
+ * to the nearest half float and returns the same mapping as for the
+ * other precisions. Values outside the range become infinity. */
+static int64_t
+half_ordered_bits(double value)
+{
+        double magnitude = fabs(value);
+        int64_t bits;
+
+        if (isinf(magnitude)) {
+                bits = 0x7c00;
+        } else if (magnitude < 0x1p-14) {
+                /* Denormal */
+                bits = llround(magnitude * 0x1p24);
+        } else {
+                int exponent;
+                double mantissa = frexp(magnitude, &exponent);
+
+                /* The mantissa is in the range [0.5,1) so the
+                 * implicit one bit is removed after doubling it. A
+                 * mantissa that rounds up carries into the
+                 * exponent. */
+                bits = ((exponent + 14) * 1024 +
+                        llround((mantissa * 2.0 - 1.0) * 1024.0));
+
+                if (bits > 0x7c00)
+                        bits = 0x7c00;
+        }
+
+        return value < 0.0 ? -bits : bits;
+}
+
+static int64_t
+double_ordered_bits(double value)
+{
+        int64_t bits;
+
+        memcpy(&bits, &value, sizeof bits);
+
+        return bits < 0 ? INT64_MIN - bits : bits;
+}
+
+static uint64_t
+ordered_distance(int64_t a,
+                 int64_t b)
+{
+        /* Unsigned arithmetic so that the distance between values
+         * of opposite signs can’t overflow */
+        if (a >= b)
+                return (uint64_t) a - (uint64_t) b;
+        else
+                return (uint64_t) b - (uint64_t) a;
+}
+
+static uint64_t
+ulp_distance(enum vr_tolerance_precision precision,
+             double a,
+             double b)
+{
+        switch (precision) {
+        case VR_TOLERANCE_PRECISION_HALF:
+                return ordered_distance(half_ordered_bits(a),
+                                        half_ordered_bits(b));
+        case VR_TOLERANCE_PRECISION_SINGLE:
+                return ordered_distance(float_ordered_bits(a),
+                                        float_ordered_bits(b));
+        case VR_TOLERANCE_PRECISION_DOUBLE:
+                return ordered_distance(double_ordered_bits(a),
+                                        double_ordered_bits(b));
+        }
+
+        vr_fatal("Unexpected precision");
+}
 
 bool
-vr_tolerance_equal(const struct vr_tolerance *tolerance,
-                   int component,
-                   const double a,
-                   const double b)
+vr_tolerance_equal_precision(const struct vr_tolerance *tolerance,
+                             enum vr_tolerance_precision precision,
+                             int component,
+                             const double a,
+                             const double b)
 {
+        if (tolerance->is_ulp) {
+                if (isnan(a) || isnan(b))
+                        return false;
+
+                return (ulp_distance(precision, a, b) <=
+                        tolerance->value[component]);
+        }
+
         if (tolerance->is_percent) {
                 if (fabs(a - b) > fabs(tolerance->value[component] / 100.0 * b))
                         return false;
@@ -44,3 +150,16 @@ vr_tolerance_equal(const struct vr_tolerance *tolerance,
         }
         return true;
 }
+
+bool
+vr_tolerance_equal(const struct vr_tolerance *tolerance,
+                   int component,
+                   const double a,
+                   const double b)
+{
+        return vr_tolerance_equal_precision(tolerance,
+                                            VR_TOLERANCE_PRECISION_SINGLE,
+                                            component,
+                                            a,
+                                            b);
+}
@@ -31,12 +31,33 @@
 struct vr_tolerance {
         double value[4];
         bool is_percent;
+        /* If true then the values are the maximum distance in units
+         * in the last place between the two values */
+        bool is_ulp;
 };
 
+/* The floating-point format that a ULP distance is measured in */
+enum vr_tolerance_precision {
+        VR_TOLERANCE_PRECISION_HALF,
+        VR_TOLERANCE_PRECISION_SINGLE,
+        VR_TOLERANCE_PRECISION_DOUBLE,
+};
+
+/* Compares two values with the tolerance. In ULP mode the distance
+ * is measured in single precision. */
 bool
 vr_tolerance_equal(const struct vr_tolerance *tolerance,
                    int component,
                    const double a,
                    const double b);
 
+/* The same as vr_tolerance_equal except that in ULP mode the distance
+ * is measured in the given precision */
+bool
+vr_tolerance_equal_precision(const struct vr_tolerance *tolerance,
+                             enum vr_tolerance_precision precision,
+                             int component,
+                             const double a,
+                             const double b);
+
 #endif /* VR_TOLERANCE_H */
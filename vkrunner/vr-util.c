@@ -31,6 +31,7 @@
 #include <stdarg.h>
 #include <stdlib.h>
 #include <stdbool.h>
+#include <time.h>
 
 #include "vr-util.h"
 
@@ -191,6 +192,25 @@ vr_env_var_as_boolean(const char *var_name, bool default_value)
         }
 }
 
+/**
+ * Returns a monotonic timestamp in nanoseconds. If clock_gettime is
+ * not available then this falls back to the wall clock with a
+ * resolution of one second.
+ */
+uint64_t
+vr_get_time_ns(void)
+{
+#ifdef HAVE_CLOCK_GETTIME
+        struct timespec ts;
+
+        clock_gettime(CLOCK_MONOTONIC, &ts);
+
+        return ts.tv_sec * UINT64_C(1000000000) + ts.tv_nsec;
+#else
+        return time(NULL) * UINT64_C(1000000000);
+#endif
+}
+
 #ifndef HAVE_FFS
 
 int
@@ -146,6 +146,9 @@ vr_free(void *ptr);
 bool
 vr_env_var_as_boolean(const char *var_name, bool default_value);
 
+uint64_t
+vr_get_time_ns(void);
+
 VR_NO_RETURN
 VR_PRINTF_FORMAT(1, 2)
 void
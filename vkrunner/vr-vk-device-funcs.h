@@ -4,6 +4,7 @@ VR_VK_FUNC(vkAllocateMemory)
 VR_VK_FUNC(vkBeginCommandBuffer)
 VR_VK_FUNC(vkBindBufferMemory)
 VR_VK_FUNC(vkBindImageMemory)
+VR_VK_FUNC(vkCmdBeginQuery)
 VR_VK_FUNC(vkCmdBeginRenderPass)
 VR_VK_FUNC(vkCmdBindDescriptorSets)
 VR_VK_FUNC(vkCmdBindIndexBuffer)
@@ -16,9 +17,21 @@ VR_VK_FUNC(vkCmdDispatch)
 VR_VK_FUNC(vkCmdDraw)
 VR_VK_FUNC(vkCmdDrawIndexed)
 VR_VK_FUNC(vkCmdDrawIndexedIndirect)
+#ifdef VK_EXT_MESH_SHADER_EXTENSION_NAME
+VR_VK_FUNC(vkCmdDrawMeshTasksEXT)
+#endif
+#ifdef VK_KHR_DRAW_INDIRECT_COUNT_EXTENSION_NAME
+VR_VK_FUNC(vkCmdDrawIndexedIndirectCountKHR)
+VR_VK_FUNC(vkCmdDrawIndirectCountKHR)
+#endif
+VR_VK_FUNC(vkCmdEndQuery)
 VR_VK_FUNC(vkCmdEndRenderPass)
 VR_VK_FUNC(vkCmdPipelineBarrier)
+#ifdef VK_KHR_SYNCHRONIZATION_2_EXTENSION_NAME
+VR_VK_FUNC(vkCmdPipelineBarrier2KHR)
+#endif
 VR_VK_FUNC(vkCmdPushConstants)
+VR_VK_FUNC(vkCmdResetQueryPool)
 VR_VK_FUNC(vkCmdSetScissor)
 VR_VK_FUNC(vkCmdSetViewport)
 VR_VK_FUNC(vkCreateBuffer)
@@ -33,6 +46,7 @@ VR_VK_FUNC(vkCreateImage)
 VR_VK_FUNC(vkCreateImageView)
 VR_VK_FUNC(vkCreatePipelineCache)
 VR_VK_FUNC(vkCreatePipelineLayout)
+VR_VK_FUNC(vkCreateQueryPool)
 VR_VK_FUNC(vkCreateRenderPass)
 VR_VK_FUNC(vkCreateSampler)
 VR_VK_FUNC(vkCreateSemaphore)
@@ -49,6 +63,7 @@ VR_VK_FUNC(vkDestroyImageView)
 VR_VK_FUNC(vkDestroyPipeline)
 VR_VK_FUNC(vkDestroyPipelineCache)
 VR_VK_FUNC(vkDestroyPipelineLayout)
+VR_VK_FUNC(vkDestroyQueryPool)
 VR_VK_FUNC(vkDestroyRenderPass)
 VR_VK_FUNC(vkDestroySampler)
 VR_VK_FUNC(vkDestroySemaphore)
@@ -60,8 +75,15 @@ VR_VK_FUNC(vkFreeDescriptorSets)
 VR_VK_FUNC(vkFreeMemory)
 VR_VK_FUNC(vkGetBufferMemoryRequirements)
 VR_VK_FUNC(vkGetDeviceQueue)
+VR_VK_FUNC(vkGetFenceStatus)
 VR_VK_FUNC(vkGetImageMemoryRequirements)
 VR_VK_FUNC(vkGetImageSubresourceLayout)
+VR_VK_FUNC(vkGetPipelineCacheData)
+#ifdef VK_KHR_PIPELINE_EXECUTABLE_PROPERTIES_EXTENSION_NAME
+VR_VK_FUNC(vkGetPipelineExecutablePropertiesKHR)
+VR_VK_FUNC(vkGetPipelineExecutableStatisticsKHR)
+#endif
+VR_VK_FUNC(vkGetQueryPoolResults)
 VR_VK_FUNC(vkInvalidateMappedMemoryRanges)
 VR_VK_FUNC(vkMapMemory)
 VR_VK_FUNC(vkQueueSubmit)
@@ -31,8 +31,15 @@ bool
 vr_window_format_equal(const struct vr_window_format *a,
                        const struct vr_window_format *b)
 {
-        return (a->color_format == b->color_format &&
-                a->depth_stencil_format == b->depth_stencil_format &&
+        if (a->n_color_attachments != b->n_color_attachments)
+                return false;
+
+        for (int i = 0; i < a->n_color_attachments; i++) {
+                if (a->color_formats[i] != b->color_formats[i])
+                        return false;
+        }
+
+        return (a->depth_stencil_format == b->depth_stencil_format &&
                 a->width == b->width &&
                 a->height == b->height);
 }
@@ -30,8 +30,15 @@
 #include <stdbool.h>
 #include <stdlib.h>
 
+/* The maximum number of color attachments that a script can use */
+#define VR_WINDOW_MAX_COLOR_ATTACHMENTS 8
+
 struct vr_window_format {
-        const struct vr_format *color_format;
+        /* Format of each color attachment. Only the first
+         * n_color_attachments are used. Attachment 0 is the one
+         * that is reported to the inspect callback. */
+        const struct vr_format *color_formats[VR_WINDOW_MAX_COLOR_ATTACHMENTS];
+        int n_color_attachments;
         const struct vr_format *depth_stencil_format;
         size_t width, height;
 };
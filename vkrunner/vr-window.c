@@ -41,11 +41,13 @@ destroy_framebuffer_resources(struct vr_window *window)
 {
         struct vr_vk *vkfn = &window->vkfn;
 
-        if (window->color_image_view) {
-                vkfn->vkDestroyImageView(window->device,
-                                         window->color_image_view,
-                                         NULL /* allocator */);
-                window->color_image_view = VK_NULL_HANDLE;
+        for (int i = 0; i < VR_N_ELEMENTS(window->color_image_views); i++) {
+                if (window->color_image_views[i]) {
+                        vkfn->vkDestroyImageView(window->device,
+                                                 window->color_image_views[i],
+                                                 NULL /* allocator */);
+                        window->color_image_views[i] = VK_NULL_HANDLE;
+                }
         }
         if (window->framebuffer) {
                 vkfn->vkDestroyFramebuffer(window->device,
@@ -76,11 +78,13 @@ destroy_framebuffer_resources(struct vr_window *window)
                                       NULL /* allocator */);
                 window->linear_buffer = VK_NULL_HANDLE;
         }
-        if (window->color_image) {
-                vkfn->vkDestroyImage(window->device,
-                                     window->color_image,
-                                     NULL /* allocator */);
-                window->color_image = VK_NULL_HANDLE;
+        for (int i = 0; i < VR_N_ELEMENTS(window->color_images); i++) {
+                if (window->color_images[i]) {
+                        vkfn->vkDestroyImage(window->device,
+                                             window->color_images[i],
+                                             NULL /* allocator */);
+                        window->color_images[i] = VK_NULL_HANDLE;
+                }
         }
         if (window->depth_image_view) {
                 vkfn->vkDestroyImageView(window->device,
@@ -224,9 +228,20 @@ create_render_pass(struct vr_window *window,
                 }
         }
 
-        VkAttachmentDescription attachment_descriptions[] = {
-                {
-                        .format = window->format.color_format->vk_format,
+        int n_color_attachments = window->format.n_color_attachments;
+        /* The color attachments come first followed by the optional
+         * depth/stencil attachment */
+        VkAttachmentDescription
+                attachment_descriptions[VR_WINDOW_MAX_COLOR_ATTACHMENTS + 1];
+        VkAttachmentReference
+                color_references[VR_WINDOW_MAX_COLOR_ATTACHMENTS];
+
+        for (int i = 0; i < n_color_attachments; i++) {
+                const struct vr_format *format =
+                        window->format.color_formats[i];
+
+                attachment_descriptions[i] = (VkAttachmentDescription) {
+                        .format = format->vk_format,
                         .samples = VK_SAMPLE_COUNT_1_BIT,
                         .loadOp = (first_render ?
                                    VK_ATTACHMENT_LOAD_OP_DONT_CARE :
@@ -239,8 +254,15 @@ create_render_pass(struct vr_window *window,
                          VK_IMAGE_LAYOUT_UNDEFINED :
                          VK_IMAGE_LAYOUT_COLOR_ATTACHMENT_OPTIMAL),
                         .finalLayout = VK_IMAGE_LAYOUT_COLOR_ATTACHMENT_OPTIMAL,
-                },
-                {
+                };
+                color_references[i] = (VkAttachmentReference) {
+                        .attachment = i,
+                        .layout = VK_IMAGE_LAYOUT_COLOR_ATTACHMENT_OPTIMAL
+                };
+        }
+
+        attachment_descriptions[n_color_attachments] =
+                (VkAttachmentDescription) {
                         .format = (depth_stencil_format ?
                                    depth_stencil_format->vk_format :
                                    0),
@@ -261,19 +283,14 @@ create_render_pass(struct vr_window *window,
                          VK_IMAGE_LAYOUT_DEPTH_STENCIL_ATTACHMENT_OPTIMAL),
                         .finalLayout =
                         VK_IMAGE_LAYOUT_DEPTH_STENCIL_ATTACHMENT_OPTIMAL
-                },
-        };
+                };
         VkSubpassDescription subpass_descriptions[] = {
                 {
                         .pipelineBindPoint = VK_PIPELINE_BIND_POINT_GRAPHICS,
-                        .colorAttachmentCount = 1,
-                        .pColorAttachments = &(VkAttachmentReference) {
-                                .attachment = 0,
-                                .layout =
-                                VK_IMAGE_LAYOUT_COLOR_ATTACHMENT_OPTIMAL
-                        },
+                        .colorAttachmentCount = n_color_attachments,
+                        .pColorAttachments = color_references,
                         .pDepthStencilAttachment = &(VkAttachmentReference) {
-                                .attachment = 1,
+                                .attachment = n_color_attachments,
                                 .layout =
                                 VK_IMAGE_LAYOUT_DEPTH_STENCIL_ATTACHMENT_OPTIMAL
                         },
@@ -281,7 +298,7 @@ create_render_pass(struct vr_window *window,
         };
         VkRenderPassCreateInfo render_pass_create_info = {
                 .sType = VK_STRUCTURE_TYPE_RENDER_PASS_CREATE_INFO,
-                .attachmentCount = VR_N_ELEMENTS(attachment_descriptions),
+                .attachmentCount = n_color_attachments + 1,
                 .pAttachments = attachment_descriptions,
                 .subpassCount = VR_N_ELEMENTS(subpass_descriptions),
                 .pSubpasses = subpass_descriptions
@@ -319,46 +336,74 @@ init_framebuffer_resources(struct vr_window *window)
                                 window->render_pass + 1))
                 return false;
 
-        VkImageCreateInfo image_create_info = {
-                .sType = VK_STRUCTURE_TYPE_IMAGE_CREATE_INFO,
-                .imageType = VK_IMAGE_TYPE_2D,
-                .format = window->format.color_format->vk_format,
-                .extent = {
-                        .width = window->format.width,
-                        .height = window->format.height,
-                        .depth = 1
-                },
-                .mipLevels = 1,
-                .arrayLayers = 1,
-                .samples = VK_SAMPLE_COUNT_1_BIT,
-                .tiling = VK_IMAGE_TILING_OPTIMAL,
-                .usage = (VK_IMAGE_USAGE_TRANSFER_SRC_BIT |
-                          VK_IMAGE_USAGE_COLOR_ATTACHMENT_BIT),
-                .sharingMode = VK_SHARING_MODE_EXCLUSIVE,
-                .initialLayout = VK_IMAGE_LAYOUT_UNDEFINED
-        };
-        res = vkfn->vkCreateImage(window->device,
-                                  &image_create_info,
-                                  NULL, /* allocator */
-                                  &window->color_image);
-        if (res != VK_SUCCESS) {
-                vr_error_message(window->config, "Error creating VkImage");
-                return false;
+        int n_color_attachments = window->format.n_color_attachments;
+
+        for (int i = 0; i < n_color_attachments; i++) {
+                VkImageCreateInfo image_create_info = {
+                        .sType = VK_STRUCTURE_TYPE_IMAGE_CREATE_INFO,
+                        .imageType = VK_IMAGE_TYPE_2D,
+                        .format = window->format.color_formats[i]->vk_format,
+                        .extent = {
+                                .width = window->format.width,
+                                .height = window->format.height,
+                                .depth = 1
+                        },
+                        .mipLevels = 1,
+                        .arrayLayers = 1,
+                        .samples = VK_SAMPLE_COUNT_1_BIT,
+                        .tiling = VK_IMAGE_TILING_OPTIMAL,
+                        .usage = (VK_IMAGE_USAGE_TRANSFER_SRC_BIT |
+                                  VK_IMAGE_USAGE_COLOR_ATTACHMENT_BIT),
+                        .sharingMode = VK_SHARING_MODE_EXCLUSIVE,
+                        .initialLayout = VK_IMAGE_LAYOUT_UNDEFINED
+                };
+                res = vkfn->vkCreateImage(window->device,
+                                          &image_create_info,
+                                          NULL, /* allocator */
+                                          window->color_images + i);
+                if (res != VK_SUCCESS) {
+                        window->color_images[i] = VK_NULL_HANDLE;
+                        vr_error_message(window->config,
+                                         "Error creating VkImage");
+                        return false;
+                }
         }
 
         res = vr_allocate_store_image(window->context,
                                       0, /* memory_type_flags */
-                                      1, /* n_images */
-                                      (VkImage[]) { window->color_image },
+                                      n_color_attachments,
+                                      window->color_images,
                                       &window->memory,
                                       NULL /* memory_type_index */);
+        if (res != VK_SUCCESS) {
+                vr_error_message(window->config,
+                                 "Error allocating color image memory");
+                return false;
+        }
+
+        VkDeviceSize linear_memory_size = 0;
+
+        for (int i = 0; i < n_color_attachments; i++) {
+                int format_size =
+                        vr_format_get_size(window->format.color_formats[i]);
+                /* The offset for vkCmdCopyImageToBuffer must be a
+                 * multiple of the texel size and of four */
+                VkDeviceSize alignment = format_size * 4;
+
+                linear_memory_size = ((linear_memory_size + alignment - 1) /
+                                      alignment * alignment);
+                window->linear_memory_offsets[i] = linear_memory_size;
+                window->linear_memory_strides[i] =
+                        format_size * window->format.width;
+                linear_memory_size += (window->linear_memory_strides[i] *
+                                       window->format.height);
+        }
 
-        int format_size = vr_format_get_size(window->format.color_format);
-        window->linear_memory_stride = format_size * window->format.width;
+        window->linear_memory_size = linear_memory_size;
 
         struct VkBufferCreateInfo buffer_create_info = {
                 .sType = VK_STRUCTURE_TYPE_BUFFER_CREATE_INFO,
-                .size = window->linear_memory_stride * window->format.height,
+                .size = linear_memory_size,
                 .usage = VK_BUFFER_USAGE_TRANSFER_DST_BIT,
                 .sharingMode = VK_SHARING_MODE_EXCLUSIVE,
         };
@@ -403,49 +448,55 @@ init_framebuffer_resources(struct vr_window *window)
                 return false;
         }
 
-        VkImageViewCreateInfo image_view_create_info = {
-                .sType = VK_STRUCTURE_TYPE_IMAGE_VIEW_CREATE_INFO,
-                .image = window->color_image,
-                .viewType = VK_IMAGE_VIEW_TYPE_2D,
-                .format = window->format.color_format->vk_format,
-                .components = {
-                        .r = VK_COMPONENT_SWIZZLE_R,
-                        .g = VK_COMPONENT_SWIZZLE_G,
-                        .b = VK_COMPONENT_SWIZZLE_B,
-                        .a = VK_COMPONENT_SWIZZLE_A
-                },
-                .subresourceRange = {
-                        .aspectMask = VK_IMAGE_ASPECT_COLOR_BIT,
-                        .baseMipLevel = 0,
-                        .levelCount = 1,
-                        .baseArrayLayer = 0,
-                        .layerCount = 1
+        for (int i = 0; i < n_color_attachments; i++) {
+                VkImageViewCreateInfo image_view_create_info = {
+                        .sType = VK_STRUCTURE_TYPE_IMAGE_VIEW_CREATE_INFO,
+                        .image = window->color_images[i],
+                        .viewType = VK_IMAGE_VIEW_TYPE_2D,
+                        .format = window->format.color_formats[i]->vk_format,
+                        .components = {
+                                .r = VK_COMPONENT_SWIZZLE_R,
+                                .g = VK_COMPONENT_SWIZZLE_G,
+                                .b = VK_COMPONENT_SWIZZLE_B,
+                                .a = VK_COMPONENT_SWIZZLE_A
+                        },
+                        .subresourceRange = {
+                                .aspectMask = VK_IMAGE_ASPECT_COLOR_BIT,
+                                .baseMipLevel = 0,
+                                .levelCount = 1,
+                                .baseArrayLayer = 0,
+                                .layerCount = 1
+                        }
+                };
+                res = vkfn->vkCreateImageView(window->device,
+                                              &image_view_create_info,
+                                              NULL, /* allocator */
+                                              window->color_image_views + i);
+                if (res != VK_SUCCESS) {
+                        window->color_image_views[i] = VK_NULL_HANDLE;
+                        vr_error_message(window->config,
+                                         "Error creating image view");
+                        return false;
                 }
-        };
-        res = vkfn->vkCreateImageView(window->device,
-                                      &image_view_create_info,
-                                      NULL, /* allocator */
-                                      &window->color_image_view);
-        if (res != VK_SUCCESS) {
-                vr_error_message(window->config,
-                                 "Error creating image view");
-                return false;
         }
 
         if (window->format.depth_stencil_format &&
             !init_depth_stencil_resources(window))
                 return false;
 
-        VkImageView attachments[] = {
-                window->color_image_view,
-                window->depth_image_view,
-        };
+        VkImageView attachments[VR_WINDOW_MAX_COLOR_ATTACHMENTS + 1];
+        int n_attachments = 0;
+
+        for (int i = 0; i < n_color_attachments; i++)
+                attachments[n_attachments++] = window->color_image_views[i];
+
+        if (window->depth_image_view)
+                attachments[n_attachments++] = window->depth_image_view;
+
         VkFramebufferCreateInfo framebuffer_create_info = {
                 .sType = VK_STRUCTURE_TYPE_FRAMEBUFFER_CREATE_INFO,
                 .renderPass = window->render_pass[0],
-                .attachmentCount = (window->depth_image_view ?
-                                    VR_N_ELEMENTS(attachments) :
-                                    VR_N_ELEMENTS(attachments) - 1),
+                .attachmentCount = n_attachments,
                 .pAttachments = attachments,
                 .width = window->format.width,
                 .height = window->format.height,
@@ -481,16 +532,18 @@ vr_window_new(struct vr_context *context,
 
         window->format = *format;
 
-        if (!check_format(window,
-                          format->color_format,
-                          VK_FORMAT_FEATURE_COLOR_ATTACHMENT_BIT |
-                          VK_FORMAT_FEATURE_BLIT_SRC_BIT)) {
-                vr_error_message(window->config,
-                                 "Format %s is not supported as a color "
-                                 "attachment and blit source",
-                                 format->color_format->name);
-                vres = VR_RESULT_SKIP;
-                goto error;
+        for (int i = 0; i < format->n_color_attachments; i++) {
+                if (!check_format(window,
+                                  format->color_formats[i],
+                                  VK_FORMAT_FEATURE_COLOR_ATTACHMENT_BIT |
+                                  VK_FORMAT_FEATURE_BLIT_SRC_BIT)) {
+                        vr_error_message(window->config,
+                                         "Format %s is not supported as a "
+                                         "color attachment and blit source",
+                                         format->color_formats[i]->name);
+                        vres = VR_RESULT_SKIP;
+                        goto error;
+                }
         }
 
         if (format->depth_stencil_format &&
@@ -48,14 +48,21 @@ struct vr_window {
          */
         VkRenderPass render_pass[2];
 
-        VkImage color_image;
+        /* One image and view for each color attachment. The images
+         * are all allocated in the same memory. */
+        VkImage color_images[VR_WINDOW_MAX_COLOR_ATTACHMENTS];
+        VkImageView color_image_views[VR_WINDOW_MAX_COLOR_ATTACHMENTS];
         VkBuffer linear_buffer;
         VkDeviceMemory memory;
         VkDeviceMemory linear_memory;
         bool need_linear_memory_invalidate;
         void *linear_memory_map;
-        VkDeviceSize linear_memory_stride;
-        VkImageView color_image_view;
+        /* The color attachments are copied one after the other into
+         * the linear buffer. These are the offset and the row
+         * stride of each one within it. */
+        VkDeviceSize linear_memory_offsets[VR_WINDOW_MAX_COLOR_ATTACHMENTS];
+        VkDeviceSize linear_memory_strides[VR_WINDOW_MAX_COLOR_ATTACHMENTS];
+        VkDeviceSize linear_memory_size;
         VkImage depth_image;
         VkDeviceMemory depth_image_memory;
         VkImageView depth_image_view;